@@ -2,10 +2,22 @@
 
 export type Procedures = {
     queries: 
+        { key: "assets.artifacts.audio.summary", input: SummaryRequestPayload, result: string } | 
         { key: "assets.artifacts.image.description", input: ImageRequestPayload, result: string } | 
+        { key: "assets.artifacts.image.objects", input: ImageRequestPayload, result: DetectedObjectData[] } | 
+        { key: "assets.artifacts.image.ocr", input: ImageOcrRequestPayload, result: OcrTextBlockData[] } | 
         { key: "assets.artifacts.raw_text.chunk.content", input: RawTextRequestPayload, result: string } | 
         { key: "assets.artifacts.raw_text.chunk.summarization", input: RawTextRequestPayload, result: string } | 
+        { key: "assets.artifacts.raw_text.summary", input: SummaryRequestPayload, result: string } | 
+        { key: "assets.artifacts.tags", input: SummaryRequestPayload, result: string[] } | 
+        { key: "assets.artifacts.video.chapters", input: SummaryRequestPayload, result: VideoChapterData[] } | 
+        { key: "assets.artifacts.video.chapters.export", input: ChapterExportRequestPayload, result: string } | 
+        { key: "assets.artifacts.video.objects", input: VideoObjectsRequestPayload, result: FrameObjectsData[] } | 
+        { key: "assets.artifacts.video.ocr", input: VideoOcrRequestPayload, result: OcrSegmentData[] } | 
+        { key: "assets.artifacts.video.summary", input: SummaryRequestPayload, result: string } | 
         { key: "assets.artifacts.video.transcript", input: TranscriptRequestPayload, result: TranscriptResponse } | 
+        { key: "assets.artifacts.web_page.summary", input: SummaryRequestPayload, result: string } | 
+        { key: "assets.duplicates", input: DuplicatesRequestPayload, result: DuplicateClusterData[] } | 
         { key: "assets.get", input: FilePathGetPayload, result: FilePathWithAssetObjectData } | 
         { key: "assets.list", input: FilePathQueryPayload, result: FilePathWithAssetObjectData[] } | 
         { key: "audio.find_by_hash", input: string, result: AudioResp[] } | 
@@ -13,11 +25,22 @@ export type Procedures = {
         { key: "libraries.list", input: never, result: LibrariesListResult[] } | 
         { key: "libraries.models.get_model", input: string, result: AIModelResult } | 
         { key: "libraries.models.list", input: never, result: ModelsListResult[] } | 
+        { key: "libraries.models.reindex_status", input: never, result: ReindexStatusResult | null } | 
         { key: "libraries.status", input: never, result: LibraryStatusResult } | 
         { key: "p2p.state", input: never, result: any } | 
-        { key: "search.all", input: SearchRequestPayload, result: SearchResultData[] } | 
+        { key: "search.all", input: SearchRequestPayload, result: SearchResultPage } | 
+        { key: "search.by_image", input: ImageSearchRequestPayload, result: SearchResultPage } | 
+        { key: "search.grouped", input: SearchRequestPayload, result: SearchGroupedResultPage } | 
+        { key: "search.in_asset", input: InAssetSearchRequestPayload, result: SearchHitData[] } | 
+        { key: "search.rag_sessions.list", input: never, result: ChatSessionData[] } | 
+        { key: "search.rag_sessions.messages", input: number, result: ChatMessageData[] } | 
         { key: "search.recommend", input: RecommendRequestPayload, result: SearchResultData[] } | 
-        { key: "search.suggestions", input: never, result: string[] } | 
+        { key: "search.saved.evaluate", input: SavedSearchEvaluatePayload, result: SearchResultPage } | 
+        { key: "search.saved.get", input: number, result: SavedSearchData } | 
+        { key: "search.saved.list", input: never, result: SavedSearchData[] } | 
+        { key: "search.saved.smart_folders", input: SmartFoldersQueryPayload, result: SavedSearchData[] } | 
+        { key: "search.suggestions", input: SearchSuggestionsRequestPayload, result: SearchSuggestion[] } | 
+        { key: "search.tags", input: any | null, result: TagData[] } | 
         { key: "tasks.get_assets_in_process", input: never, result: FilePath[] } | 
         { key: "tasks.list", input: TaskListRequestPayload, result: FileHandlerTask[] } | 
         { key: "users.get", input: never, result: Auth | null } | 
@@ -29,6 +52,7 @@ export type Procedures = {
         { key: "assets.create_web_page_object", input: WebPageCreatePayload, result: FilePathWithAssetObjectData } | 
         { key: "assets.delete_file_path", input: FilePathDeletePayload, result: null } | 
         { key: "assets.export_video_segment", input: VideoSegmentExportPayload, result: null } | 
+        { key: "assets.merge_duplicates", input: MergeDuplicatesPayload, result: string } | 
         { key: "assets.move_file_path", input: FilePathMovePayload, result: null } | 
         { key: "assets.process_asset_metadata", input: string, result: null } | 
         { key: "assets.rebuild_content_index", input: RebuildIndexRequestPayload, result: null } | 
@@ -47,10 +71,17 @@ export type Procedures = {
         { key: "p2p.finish_file_share", input: string, result: string[] } | 
         { key: "p2p.reject_file_share", input: string, result: any } | 
         { key: "p2p.share", input: SharePayload, result: any } | 
+        { key: "search.clear_history", input: any | null, result: null } | 
+        { key: "search.rag_sessions.delete", input: number, result: null } | 
+        { key: "search.rag_sessions.rename", input: ChatSessionRenamePayload, result: ChatSessionData } | 
+        { key: "search.saved.create", input: SavedSearchPayload, result: SavedSearchData } | 
+        { key: "search.saved.delete", input: number, result: null } | 
+        { key: "search.saved.update", input: SavedSearchUpdatePayload, result: SavedSearchData } | 
         { key: "storage.upload_to_s3", input: UploadPayload, result: null } | 
         { key: "tasks.cancel", input: TaskCancelRequestPayload, result: null } | 
         { key: "users.set", input: Auth, result: Auth },
     subscriptions: 
+        { key: "assets.ask", input: AssetAskRequestPayload, result: AssetAskResult } | 
         { key: "p2p.events", input: never, result: any } | 
         { key: "search.rag", input: RAGRequestPayload, result: RAGResult }
 };
@@ -67,6 +98,8 @@ export type ModelsListResult = { category: AIModelCategory; models: AIModelResul
 
 export type RecommendRequestPayload = { assetObjectHash: string; timestamp: number }
 
+export type InAssetSearchRequestPayload = { assetObjectHash: string; text: string }
+
 export type AssetObjectWithMediaData = { id: number; hash: string; size: number; mimeType: string | null; createdAt: string; updatedAt: string; mediaData: ContentMetadata | null }
 
 export type TranscriptType = "Original" | "Summarization"
@@ -75,9 +108,11 @@ export type ModelArtifact = { url: string; checksum: string }
 
 export type AudioType = "txt" | "srt" | "json" | "vtt" | "csv" | "ale" | "docx"
 
-export type RAGResult = { resultType: "Reference"; data: RetrievalResultData } | { resultType: "Response"; data: string } | { resultType: "Error"; data: string } | { resultType: "Done" }
+export type RAGResult = { resultType: "Session"; data: number } | { resultType: "Reference"; data: RetrievalResultData } | { resultType: "Response"; data: string } | { resultType: "Citation"; data: CitationData } | { resultType: "Error"; data: string } | { resultType: "Done" }
+
+export type ImageMetadata = { width: number; height: number; color: string; exif: ImageExif | null }
 
-export type ImageMetadata = { width: number; height: number; color: string }
+export type ImageExif = { make: string | null; model: string | null; takenAt: string | null }
 
 export type LibrarySettingsThemeEnum = "light" | "dark"
 
@@ -87,7 +122,13 @@ export type DownloadModelPayload = { modelId: string }
 
 export type ImageRequestPayload = { hash: string }
 
-export type LibrarySettings = { title: string; appearanceTheme: LibrarySettingsThemeEnum; explorer: LibrarySettingsExplorer; models: LibraryModels; alwaysDeleteLocalFileAfterUpload: boolean; s3Config: S3Config | null }
+export type SummaryRequestPayload = { hash: string }
+
+export type AssetAskRequestPayload = { hash: string; question: string }
+
+export type AssetAskResult = { resultType: "Response"; data: string } | { resultType: "Error"; data: string } | { resultType: "Done" }
+
+export type LibrarySettings = { title: string; appearanceTheme: LibrarySettingsThemeEnum; explorer: LibrarySettingsExplorer; models: LibraryModels; alwaysDeleteLocalFileAfterUpload: boolean; s3Config: S3Config | null; rankWeights: RankWeights; tagging: LibrarySettingsTagging }
 
 export type FileHandlerTask = { id: number; assetObjectId: number; taskType: string; exitCode: number | null; exitMessage: string | null; startsAt: string | null; endsAt: string | null; createdAt: string; updatedAt: string }
 
@@ -109,7 +150,15 @@ export type LibrariesListResult = { id: string; dir: string; title: string }
 
 export type VideoMetadata = { width: number; height: number; duration: number; bitRate: number; avgFrameRate: VideoAvgFrameRate; audio: AudioMetadata | null }
 
-export type SearchResultData = { filePath: FilePathWithAssetObjectData; metadata: ContentIndexMetadata; score: number; hitReason: ContentQueryHitReason; referenceContent: string; searchHint: string }
+export type SearchResultPage = { items: SearchResultData[]; total: number; offset: number }
+
+export type SearchResultData = { filePath: FilePathWithAssetObjectData; metadata: ContentIndexMetadata; score: number; hitReason: ContentQueryHitReason; referenceContent: string; searchHint: string; explain: ContentQueryExplain | null }
+
+export type SearchGroupedResultPage = { items: SearchGroupedResultData[]; total: number; offset: number }
+
+export type SearchGroupedResultData = { filePath: FilePathWithAssetObjectData; score: number; hits: SearchHitData[] }
+
+export type SearchHitData = { metadata: ContentIndexMetadata; score: number; hitReason: ContentQueryHitReason; referenceContent: string; searchHint: string; explain: ContentQueryExplain | null }
 
 export type LibraryStatusResult = { id: string | null; loaded: boolean; isBusy: boolean }
 
@@ -141,9 +190,15 @@ export type RawTextMetadata = { textCount: number }
 
 export type ModelDownloadStatus = { totalBytes: string; downloadedBytes: string }
 
-export type LibraryModels = { MultiModalEmbedding: string; TextEmbedding: string; ImageCaption: string; AudioTranscript: string; Llm: string }
+export type RankWeights = { fullText: number; textVector: number; visionVector: number; transcript: number; caption: number; recency: number }
+
+export type ContentQuerySignalExplain = { rank: number; value: number; weight: number }
+
+export type ContentQueryExplain = { fullText: ContentQuerySignalExplain | null; fullTextTokens: [string, number][]; textVector: ContentQuerySignalExplain | null; visionVector: ContentQuerySignalExplain | null; fusedScore: number; sourceWeight: number; recencyBoost: number; rerankScore: number | null; finalScore: number }
 
-export type ContentQueryHitReason = { reason: "TextMatch"; text: string } | { reason: "TranscriptMatch"; text: string } | { reason: "CaptionMatch"; text: string } | { reason: "SemanticTextMatch"; text: string } | { reason: "SemanticTranscriptMatch"; text: string } | { reason: "SemanticCaptionMatch"; text: string } | { reason: "VisionMatch" }
+export type LibraryModels = { MultiModalEmbedding: string; TextEmbedding: string; ImageCaption: string; AudioTranscript: string; Llm: string; TextRerank: string | null; ObjectDetection: string | null; Ocr: string | null }
+
+export type ContentQueryHitReason = { reason: "TextMatch"; text: string } | { reason: "TranscriptMatch"; text: string } | { reason: "CaptionMatch"; text: string } | { reason: "SemanticTextMatch"; text: string } | { reason: "SemanticTranscriptMatch"; text: string } | { reason: "SemanticCaptionMatch"; text: string } | { reason: "VisionMatch" } | { reason: "OcrMatch"; text: string } | { reason: "ChapterMatch"; text: string }
 
 export type VideoPlayerTsRequestPayload = { hash: string; index: number; size: number }
 
@@ -151,11 +206,13 @@ export type LibraryLoadResult = { id: string; dir: string }
 
 export type RebuildIndexRequestPayload = { assetObjectHash: string; withExistingArtifacts: boolean }
 
+export type ReindexStatusResult = { total: number; scheduled: number; failed: number; running: boolean; processing: number }
+
 export type AIModelResult = { info: AIModel; status: AIModelStatus }
 
 export type AssetObjectCreatePayload = { materializedPath: string; name: string; localFullPath: string }
 
-export type ConcreteModelType = "BLIP" | "CLIP" | "Moondream" | "OrtTextEmbedding" | "Whisper" | "Yolo" | "Qwen2" | "OpenAI" | "AzureOpenAI" | "LLaVAPhi3Mini"
+export type ConcreteModelType = "BLIP" | "CLIP" | "Moondream" | "OrtTextEmbedding" | "OrtTextRerank" | "Whisper" | "Yolo" | "Qwen2" | "OpenAI" | "AzureOpenAI" | "LLaVAPhi3Mini"
 
 export type AudioSliceType = "Transcript"
 
@@ -171,7 +228,7 @@ export type RawTextRequestPayload = { hash: string; index: number }
 
 export type UploadPayload = { materializedPaths: string[]; hashes: string[] }
 
-export type AIModelCategory = "MultiModalEmbedding" | "ImageCaption" | "AudioTranscript" | "TextEmbedding" | "LLM"
+export type AIModelCategory = "MultiModalEmbedding" | "ImageCaption" | "AudioTranscript" | "TextEmbedding" | "LLM" | "TextRerank" | "ObjectDetection" | "Ocr"
 
 export type WebPageChunkType = "Content"
 
@@ -191,7 +248,53 @@ export type ContentIndexMetadata = ({ contentType: "Video" } & VideoIndexMetadat
 
 export type AudioIndexMetadata = { sliceType: AudioSliceType; startTimestamp: number; endTimestamp: number }
 
-export type SearchRequestPayload = { text: string }
+export type SearchRequestPayload = { text: string; filters?: SearchFilters | null; offset?: number | null; limit?: number | null; explain?: boolean | null; expand?: boolean | null; sort?: SearchSort | null }
+
+export type SearchSort = { key: SearchSortKey; descending?: boolean | null }
+
+export type SearchSortKey = "CreatedAt" | "Size" | "Duration" | "Resolution" | "BitRate" | "FrameRate"
+
+export type ImageSearchRequestPayload = { source: ImageSearchSource; filters?: SearchFilters | null; offset?: number | null; limit?: number | null }
+
+export type ImageSearchSource = ({ localFullPath: string }) & { sourceType: "LocalFile" } | ({ assetObjectHash: string; timestamp?: number | null }) & { sourceType: "Asset" }
+
+export type SearchSuggestionsRequestPayload = { text: string; limit?: number | null }
+
+export type SearchSuggestionSource = "History" | "Term"
+
+export type SearchSuggestion = { text: string; source: SearchSuggestionSource; count: number }
+
+export type TagData = { tag: string; assetCount: number }
+
+export type SavedSearchData = { id: number; name: string; query: string; filters: SearchFilters | null; imageAssetObjectHash: string | null; pinnedMaterializedPath: string | null; createdAt: string; updatedAt: string }
+
+export type SavedSearchPayload = { name: string; query: string; filters?: SearchFilters | null; imageAssetObjectHash?: string | null; pinnedMaterializedPath?: string | null }
+
+export type SavedSearchUpdatePayload = { id: number; data: SavedSearchPayload }
+
+export type SavedSearchEvaluatePayload = { id: number; offset?: number | null; limit?: number | null }
+
+export type SmartFoldersQueryPayload = { materializedPath: string }
+
+export type ChatSessionData = { id: number; title: string; createdAt: string; updatedAt: string }
+
+export type ChatRole = "user" | "assistant"
+
+export type ChatMessageData = { id: number; role: ChatRole; content: string; retrievalQuery: string | null; references: RetrievalResultData[]; citations: CitationData[]; createdAt: string }
+
+export type CitationData = { index: number; fileIdentifier: string; metadata: ContentIndexMetadata }
+
+export type ChatSessionRenamePayload = { id: number; title: string }
+
+export type SearchFilters = { contentTypes: ContentType[] | null; materializedPath: string | null; createdAt: DateRangeFilter | null; updatedAt: DateRangeFilter | null; size: NumberRangeFilter<number> | null; duration: NumberRangeFilter<number> | null; resolution: number | null; orientation: MediaOrientation | null; bitRate: NumberRangeFilter<number> | null; frameRate: NumberRangeFilter<number> | null; hasAudio: boolean | null; camera: string | null; objects: string[] | null; tags: string[] | null }
+
+export type MediaOrientation = "Landscape" | "Portrait" | "Square"
+
+export type DateRangeFilter = { from: string | null; to: string | null }
+
+export type NumberRangeFilter<T> = { min: T | null; max: T | null }
+
+export type ContentType = "Audio" | "Video" | "Image" | "RawText" | "WebPage" | "Unknown"
 
 export type FilePathMovePayload = { active: FilePathRequestPayload; target: FilePathRequestPayload | null }
 
@@ -205,4 +308,40 @@ export type SetModelPayload = { category: AIModelCategory; modelId: string }
 
 export type LibrarySettingsExplorer = { layout: LibrarySettingsLayoutEnum; inspectorSize: number; inspectorShow: boolean }
 
-export type RAGRequestPayload = { query: string }
+export type LibrarySettingsTagging = { prompt: string; maxTags: number }
+
+export type RAGRequestPayload = { query: string; sessionId?: number | null; scope?: RAGScope | null }
+
+export type RAGScope = ({ assetObjectHashes: string[] }) & { scopeType: "Assets" } | ({ materializedPath: string }) & { scopeType: "Folder" }
+
+export type DuplicatesRequestPayload = { threshold?: number | null }
+
+export type DuplicateClusterData = { contentType: ContentType; similarity: number; bestAssetObjectHash: string; items: DuplicateAssetData[]; pairs: DuplicatePairData[] }
+
+export type DuplicateAssetData = { filePath: FilePathWithAssetObjectData; similarity: number }
+
+export type DuplicatePairData = { assetObjectHashes: [string, string]; similarity: number; embeddingSimilarity: number; hashSimilarity: number | null }
+
+export type MergeDuplicatesPayload = { assetObjectHashes: string[]; keepAssetObjectHash?: string | null; threshold?: number | null }
+
+export type BoundingBoxData = { xMin: number; yMin: number; xMax: number; yMax: number }
+
+export type DetectedObjectData = { label: string; confidence: number; bbox: BoundingBoxData }
+
+export type VideoObjectsRequestPayload = { hash: string; startTimestamp?: number | null; endTimestamp?: number | null }
+
+export type FrameObjectsData = { timestamp: number; objects: DetectedObjectData[] }
+
+export type OcrTextBlockData = { text: string; confidence: number; bbox: BoundingBoxData }
+
+export type ImageOcrRequestPayload = { hash: string }
+
+export type VideoOcrRequestPayload = { hash: string }
+
+export type OcrSegmentData = { startTimestamp: number; endTimestamp: number; text: string }
+
+export type VideoChapterData = { title: string; startTimestamp: number; endTimestamp: number }
+
+export type ChapterExportRequestPayload = { hash: string; format: ChapterExportFormat }
+
+export type ChapterExportFormat = "youtube" | "webVtt"
@@ -6,10 +6,13 @@ use ai::{
     clip::{CLIPModel, CLIP},
     llava_phi3_mini::LLaVAPhi3Mini,
     llm::{openai::OpenAI, qllama::Qllama, qwen2::Qwen2, LLM},
+    ocr::OCR,
     text_embedding::OrtTextEmbedding,
+    text_rerank::OrtTextRerank,
     whisper::Whisper,
+    yolo::YOLO,
     AIModel, AudioTranscriptModel, ImageCaptionModel, LLMModel, MultiModalEmbeddingModel,
-    TextEmbeddingModel,
+    ObjectDetectionModel, OcrModel, TextEmbeddingModel, TextRerankModel,
 };
 use serde_json::Value;
 use std::{fmt, time::Duration};
@@ -37,8 +40,16 @@ pub struct AIHandler {
     pub audio_transcript: (AudioTranscriptModel, String),
     pub text_embedding: (TextEmbeddingModel, String),
     pub llm: (LLMModel, String),
+    /// LLM 的上下文长度，RAG 按这个分配历史消息和参考文档的 token
+    pub llm_context: LLMContextConfig,
     /// 目前这个是专门给 audio transcript 和 raw text 的 chunking 用的
     pub text_tokenizer: (ai::tokenizers::Tokenizer, String),
+    /// 搜索结果的二次排序，没有设置模型时为 None
+    pub text_rerank: Option<(TextRerankModel, String)>,
+    /// 图片和视频帧的物体检测，没有设置模型时为 None，也不会创建物体检测的任务
+    pub object_detection: Option<(ObjectDetectionModel, String)>,
+    /// 图片和视频帧的文字识别，没有设置模型时为 None，也不会创建 OCR 的任务
+    pub ocr: Option<(OcrModel, String)>,
 }
 
 impl fmt::Debug for AIHandler {
@@ -47,6 +58,25 @@ impl fmt::Debug for AIHandler {
     }
 }
 
+/// LLM 的上下文长度配置，来自 model_list.json 里模型的 params
+/// 没有配置的时候使用默认值，默认值按本地小模型设置，比较保守
+#[derive(Clone, Copy, Debug)]
+pub struct LLMContextConfig {
+    /// 上下文窗口的 token 数，包括输入和输出
+    pub context_window: usize,
+    /// 给回答预留的 token 数，也是生成时的 max_tokens
+    pub max_output_tokens: usize,
+}
+
+impl Default for LLMContextConfig {
+    fn default() -> Self {
+        Self {
+            context_window: 4096,
+            max_output_tokens: 512,
+        }
+    }
+}
+
 fn get_str_from_params<'a>(params: &'a Value, name: &str) -> anyhow::Result<&'a str> {
     match params[name].as_str() {
         Some(s) => Ok(s),
@@ -62,9 +92,13 @@ impl AIHandler {
             (&multi_modal_embedding.0, &multi_modal_embedding.1),
         )?;
         let llm = Self::build_llm_model(ctx)?;
+        let llm_context = Self::build_llm_context_config(ctx)?;
         let text_tokenizer = Self::build_text_tokenizer(ctx)?;
         let image_caption = Self::build_image_caption_model(ctx)?;
         let audio_transcript = Self::build_audio_transcript_model(ctx)?;
+        let text_rerank = Self::build_text_rerank_model(ctx)?;
+        let object_detection = Self::build_object_detection_model(ctx)?;
+        let ocr = Self::build_ocr_model(ctx)?;
 
         Ok(Self {
             multi_modal_embedding,
@@ -72,7 +106,11 @@ impl AIHandler {
             audio_transcript,
             text_embedding,
             llm,
+            llm_context,
             text_tokenizer,
+            text_rerank,
+            object_detection,
+            ocr,
         })
     }
 
@@ -317,6 +355,135 @@ impl AIHandler {
         Ok((handler, model_id))
     }
 
+    fn build_text_rerank_model(
+        ctx: &dyn CtxWithLibrary,
+    ) -> anyhow::Result<Option<(TextRerankModel, String)>> {
+        let resources_dir = ctx.get_resources_dir().to_path_buf();
+        let library = ctx.library()?;
+        let settings = get_library_settings(&library.dir);
+
+        let Some(model_id) = settings.models.text_rerank else {
+            return Ok(None);
+        };
+        let model = get_model_info_by_id(ctx, &model_id)?;
+        let model_id = model.id.clone();
+
+        let handler = AIModel::new(
+            model_id.clone(),
+            move || {
+                let resources_dir_clone = resources_dir.clone();
+                let model_clone = model.clone();
+                async move {
+                    let params = model_clone.params;
+                    match model_clone.model_type {
+                        ConcreteModelType::OrtTextRerank => {
+                            let model_path = resources_dir_clone
+                                .join(get_str_from_params(&params, "model_path")?);
+                            let tokenizer_config_path = resources_dir_clone
+                                .join(get_str_from_params(&params, "tokenizer_config_path")?);
+                            OrtTextRerank::new(model_path, tokenizer_config_path).await
+                        }
+                        _ => {
+                            anyhow::bail!(
+                                "unsupported model {} for text rerank",
+                                model_clone.model_type.as_ref()
+                            )
+                        }
+                    }
+                }
+            },
+            Some(Duration::from_secs(600)),
+        )?;
+
+        Ok(Some((handler, model_id)))
+    }
+
+    fn build_object_detection_model(
+        ctx: &dyn CtxWithLibrary,
+    ) -> anyhow::Result<Option<(ObjectDetectionModel, String)>> {
+        let resources_dir = ctx.get_resources_dir().to_path_buf();
+        let library = ctx.library()?;
+        let settings = get_library_settings(&library.dir);
+
+        let Some(model_id) = settings.models.object_detection else {
+            return Ok(None);
+        };
+        let model = get_model_info_by_id(ctx, &model_id)?;
+        let model_id = model.id.clone();
+
+        let handler = AIModel::new(
+            model_id.clone(),
+            move || {
+                let resources_dir_clone = resources_dir.clone();
+                let model_clone = model.clone();
+                async move {
+                    let params = model_clone.params;
+                    match model_clone.model_type {
+                        ConcreteModelType::Yolo => {
+                            let model_path = resources_dir_clone
+                                .join(get_str_from_params(&params, "model_path")?);
+                            YOLO::from_model_path(model_path)
+                        }
+                        _ => {
+                            anyhow::bail!(
+                                "unsupported model {} for object detection",
+                                model_clone.model_type.as_ref()
+                            )
+                        }
+                    }
+                }
+            },
+            Some(Duration::from_secs(600)),
+        )?;
+
+        Ok(Some((handler, model_id)))
+    }
+
+    fn build_ocr_model(ctx: &dyn CtxWithLibrary) -> anyhow::Result<Option<(OcrModel, String)>> {
+        let resources_dir = ctx.get_resources_dir().to_path_buf();
+        let library = ctx.library()?;
+        let settings = get_library_settings(&library.dir);
+
+        let Some(model_id) = settings.models.ocr else {
+            return Ok(None);
+        };
+        let model = get_model_info_by_id(ctx, &model_id)?;
+        let model_id = model.id.clone();
+
+        let handler = AIModel::new(
+            model_id.clone(),
+            move || {
+                let resources_dir_clone = resources_dir.clone();
+                let model_clone = model.clone();
+                async move {
+                    let params = model_clone.params;
+                    match model_clone.model_type {
+                        ConcreteModelType::PaddleOcr => {
+                            let det_model_path = resources_dir_clone
+                                .join(get_str_from_params(&params, "det_model_path")?);
+                            let rec_model_path = resources_dir_clone
+                                .join(get_str_from_params(&params, "rec_model_path")?);
+                            // 字典是可选的，没有的话从识别模型的 metadata 里读取
+                            let dict_path = get_str_from_params(&params, "dict_path")
+                                .ok()
+                                .map(|v| resources_dir_clone.join(v));
+                            OCR::new(det_model_path, rec_model_path, dict_path)
+                        }
+                        _ => {
+                            anyhow::bail!(
+                                "unsupported model {} for ocr",
+                                model_clone.model_type.as_ref()
+                            )
+                        }
+                    }
+                }
+            },
+            Some(Duration::from_secs(600)),
+        )?;
+
+        Ok(Some((handler, model_id)))
+    }
+
     /// 目前这个是专门给 audio transcript 和 raw text 的 chunking 用的
     fn build_text_tokenizer(
         ctx: &dyn CtxWithLibrary,
@@ -345,6 +512,25 @@ impl AIHandler {
         Ok((tokenizer, name.to_string()))
     }
 
+    fn build_llm_context_config(ctx: &dyn CtxWithLibrary) -> anyhow::Result<LLMContextConfig> {
+        let library = ctx.library()?;
+        let settings = get_library_settings(&library.dir);
+
+        let model = get_model_info_by_id(ctx, &settings.models.llm)?;
+        let default_config = LLMContextConfig::default();
+        let get_usize = |name: &str, default: usize| {
+            model.params[name]
+                .as_u64()
+                .map(|v| v as usize)
+                .unwrap_or(default)
+        };
+
+        Ok(LLMContextConfig {
+            context_window: get_usize("context_window", default_config.context_window),
+            max_output_tokens: get_usize("max_output_tokens", default_config.max_output_tokens),
+        })
+    }
+
     fn build_llm_model(ctx: &dyn CtxWithLibrary) -> anyhow::Result<(LLMModel, String)> {
         let resources_dir = ctx.get_resources_dir().to_path_buf();
         let library = ctx.library()?;
@@ -431,6 +617,7 @@ impl AIHandler {
 
     pub fn rebuild_llm_model(&mut self, ctx: &dyn CtxWithLibrary) -> anyhow::Result<()> {
         self.llm = Self::build_llm_model(ctx)?;
+        self.llm_context = Self::build_llm_context_config(ctx)?;
         Ok(())
     }
 
@@ -439,6 +626,24 @@ impl AIHandler {
         Ok(())
     }
 
+    pub fn rebuild_text_rerank_model(&mut self, ctx: &dyn CtxWithLibrary) -> anyhow::Result<()> {
+        self.text_rerank = Self::build_text_rerank_model(ctx)?;
+        Ok(())
+    }
+
+    pub fn rebuild_object_detection_model(
+        &mut self,
+        ctx: &dyn CtxWithLibrary,
+    ) -> anyhow::Result<()> {
+        self.object_detection = Self::build_object_detection_model(ctx)?;
+        Ok(())
+    }
+
+    pub fn rebuild_ocr_model(&mut self, ctx: &dyn CtxWithLibrary) -> anyhow::Result<()> {
+        self.ocr = Self::build_ocr_model(ctx)?;
+        Ok(())
+    }
+
     pub fn rebuild_audio_transcript_model(
         &mut self,
         ctx: &dyn CtxWithLibrary,
@@ -446,4 +651,13 @@ impl AIHandler {
         self.audio_transcript = Self::build_audio_transcript_model(ctx)?;
         Ok(())
     }
+
+    /// 用 text_tokenizer 计算 token 数，和 LLM 实际使用的 tokenizer 可能有差别
+    pub fn count_text_tokens(&self, text: &str) -> usize {
+        self.text_tokenizer
+            .0
+            .encode(text, false)
+            .map(|encoding| encoding.len())
+            .unwrap_or_else(|_| text.chars().count())
+    }
 }
@@ -3,6 +3,7 @@ use crate::{
     download::{
         DownloadReporter, {file_name_from_url, SimpleReporter},
     },
+    library::get_library_settings,
 };
 use downloader::{Download, Downloader};
 use serde::{Deserialize, Serialize};
@@ -26,6 +27,9 @@ pub enum AIModelCategory {
     AudioTranscript,
     TextEmbedding,
     LLM,
+    TextRerank,
+    ObjectDetection,
+    Ocr,
 }
 
 #[derive(AsRefStr, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, EnumString, Type)]
@@ -34,8 +38,10 @@ pub enum ConcreteModelType {
     CLIP,
     Moondream,
     OrtTextEmbedding,
+    OrtTextRerank,
     Whisper,
     Yolo,
+    PaddleOcr,
     Qwen2,
     OpenAI,
     AzureOpenAI,
@@ -105,6 +111,23 @@ pub fn get_model_info_by_id(ctx: &dyn CtxWithLibrary, model_id: &str) -> anyhow:
     Ok(model.to_owned())
 }
 
+/// 当前 library 的 text embedding 和 multi modal embedding 模型的向量维度，用来生成向量索引
+pub fn get_embedding_dimensions(ctx: &dyn CtxWithLibrary) -> anyhow::Result<(usize, usize)> {
+    let library = ctx.library()?;
+    let settings = get_library_settings(&library.dir);
+    let model_dim = |model_id: &str| -> anyhow::Result<usize> {
+        let model = get_model_info_by_id(ctx, model_id)?;
+        model
+            .dim
+            .map(|dim| dim as usize)
+            .ok_or_else(|| anyhow::anyhow!("dim is not set for model: {}", model_id))
+    };
+    Ok((
+        model_dim(&settings.models.text_embedding)?,
+        model_dim(&settings.models.multi_modal_embedding)?,
+    ))
+}
+
 pub fn get_model_status(ctx: &dyn CtxWithLibrary, model: &AIModel) -> AIModelStatus {
     if let Ok(download_status) = ctx.download_status() {
         let mut total_bytes: u64 = 0;
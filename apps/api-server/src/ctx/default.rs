@@ -1,23 +1,30 @@
 // Ctx 和 Store 的默认实现，主要给 api_server/main 用，不过目前 CtxWithLibrary 的实现也是可以给 tauri 用的，就先用着
 use super::traits::{
-    CtxError, CtxStore, CtxWithAI, CtxWithDownload, CtxWithLibrary, CtxWithP2P, StoreError,
+    CtxError, CtxStore, CtxWithAI, CtxWithDownload, CtxWithLibrary, CtxWithP2P, ReindexStatus,
+    StoreError,
 };
 use crate::cron_jobs::delete_unlinked_assets;
 use crate::{
-    ai::AIHandler,
+    ai::{models::get_embedding_dimensions, AIHandler},
     download::{DownloadHub, DownloadReporter, DownloadStatus},
-    routes::{assets::process::build_content_index, p2p::info::ShareInfo},
+    library::get_library_settings,
+    routes::{
+        assets::{media_index::backfill_media_index, process::build_content_index},
+        p2p::info::ShareInfo,
+    },
 };
 use async_trait::async_trait;
-use content_base::{ContentBase, ContentBaseCtx};
+use content_base::{db::ReindexScope, ContentBase, ContentBaseCtx};
 use content_library::{load_library, Library};
 use futures::FutureExt;
 use p2p::Node;
 use std::{
     boxed::Box,
+    collections::HashSet,
     path::PathBuf,
     sync::{atomic::AtomicBool, Arc, Mutex},
 };
+use tracing::Instrument;
 
 /**
  * default impl of a store for rspc Ctx
@@ -68,6 +75,12 @@ impl CtxStore for Store {
     }
 }
 
+/// 后台重建索引的任务，开始新的任务或者 unload library 以后，旧的任务会停止提交素材
+struct ReindexJob {
+    id: uuid::Uuid,
+    status: ReindexStatus,
+}
+
 /**
  * default impl of a rspc Ctx
  */
@@ -85,6 +98,7 @@ pub struct Ctx<S: CtxStore> {
     download_hub: Arc<Mutex<Option<DownloadHub>>>,
     node: Arc<Mutex<Node<ShareInfo>>>,
     cron: Arc<tokio::sync::Mutex<cron::Instance>>,
+    reindex_job: Arc<Mutex<Option<ReindexJob>>>,
 }
 
 impl<S: CtxStore> Clone for Ctx<S> {
@@ -102,6 +116,7 @@ impl<S: CtxStore> Clone for Ctx<S> {
             cache_dir: self.cache_dir.clone(),
             node: Arc::clone(&self.node),
             cron: Arc::clone(&self.cron),
+            reindex_job: Arc::clone(&self.reindex_job),
         }
     }
 }
@@ -146,6 +161,7 @@ impl<S: CtxStore> Ctx<S> {
             download_hub: Arc::new(Mutex::new(None)),
             node,
             cron: Arc::new(tokio::sync::Mutex::new(cron::Instance::init())),
+            reindex_job: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -196,8 +212,176 @@ impl<T> From<std::sync::PoisonError<T>> for CtxError {
     }
 }
 
-impl<S: CtxStore + Send> Ctx<S> {
-    async fn trigger_unfinished_tasks(&self) -> () {
+impl<S: CtxStore + Send + 'static> Ctx<S> {
+    fn build_content_base_ctx(&self, library: &Library, ai_handler: AIHandler) -> ContentBaseCtx {
+        let cb_ctx = ContentBaseCtx::new(&library.artifacts_dir_name(), &self.temp_dir)
+            .with_audio_transcript(
+                Arc::new(ai_handler.audio_transcript.0),
+                &ai_handler.audio_transcript.1,
+            )
+            .with_llm(
+                Arc::new(ai_handler.llm.0),
+                &ai_handler.llm.1, // this comment is just for for alignment and better readability
+            )
+            .with_text_tokenizer(
+                Arc::new(ai_handler.text_tokenizer.0),
+                &ai_handler.text_tokenizer.1,
+            )
+            .with_multi_modal_embedding(
+                Arc::new(ai_handler.multi_modal_embedding.0),
+                &ai_handler.multi_modal_embedding.1,
+            )
+            .with_text_embedding(
+                Arc::new(ai_handler.text_embedding.0),
+                &ai_handler.text_embedding.1,
+            )
+            .with_image_caption(
+                Arc::new(ai_handler.image_caption.0),
+                &ai_handler.image_caption.1,
+            );
+        let cb_ctx = match ai_handler.text_rerank {
+            Some((text_rerank, model_id)) => {
+                cb_ctx.with_text_rerank(Arc::new(text_rerank), &model_id)
+            }
+            None => cb_ctx,
+        };
+        let cb_ctx = match ai_handler.object_detection {
+            Some((object_detection, model_id)) => {
+                cb_ctx.with_object_detection(Arc::new(object_detection), &model_id)
+            }
+            None => cb_ctx,
+        };
+        let cb_ctx = match ai_handler.ocr {
+            Some((ocr, model_id)) => cb_ctx.with_ocr(Arc::new(ocr), &model_id),
+            None => cb_ctx,
+        };
+        // 后面不再使用 ai_handler 了，上面 with 函数里不需要 clone 直接 move 就行
+        cb_ctx.with_tagging(get_library_settings(&library.dir).tagging.into())
+    }
+
+    fn build_content_base(
+        &self,
+        library: &Library,
+        ai_handler: AIHandler,
+    ) -> Result<ContentBase, CtxError> {
+        let cb_ctx = self.build_content_base_ctx(library, ai_handler);
+        ContentBase::new(&cb_ctx, library.surrealdb_client()).map_err(|e| {
+            tracing::error!(task = "init content base", "Failed: {}", e);
+            CtxError::Internal(format!("Failed to init content base: {}", e))
+        })
+    }
+
+    /// 向量索引的维度要和当前的 embedding 模型一致
+    /// 如果模型变了，content base 会清空受影响的向量，这里重新处理对应的素材
+    /// 加载 library 的时候，重新处理的素材之外，继续处理上次没有完成的任务
+    async fn run_vector_index_sync(&self, trigger_unfinished_tasks: bool) {
+        let scope = match self.sync_vector_index_schema().await {
+            Ok(scope) => scope,
+            Err(e) => {
+                tracing::error!(task = "sync vector indexes", "Failed: {}", e);
+                ReindexScope::None
+            }
+        };
+        let hashes = match scope {
+            ReindexScope::None => {
+                tracing::info!(task = "sync vector indexes", "Success");
+                vec![]
+            }
+            ReindexScope::All => {
+                tracing::warn!(
+                    task = "sync vector indexes",
+                    "Text embedding model changed, rebuild all content indexes"
+                );
+                self.all_asset_hashes().await
+            }
+            ReindexScope::Assets(hashes) => {
+                tracing::warn!(
+                    task = "sync vector indexes",
+                    "Multi modal embedding model changed, rebuild content indexes of {} assets",
+                    hashes.len()
+                );
+                hashes
+            }
+        };
+        if !hashes.is_empty() {
+            self.rebuild_content_indexes(&hashes).await;
+        }
+        if trigger_unfinished_tasks {
+            self.trigger_unfinished_tasks(hashes.into_iter().collect())
+                .await;
+            tracing::info!(task = "trigger unfinished tasks", "Success");
+        }
+    }
+
+    async fn sync_vector_index_schema(&self) -> anyhow::Result<ReindexScope> {
+        let content_base = self.content_base()?;
+        let (text_dim, multi_modal_dim) = get_embedding_dimensions(self)?;
+        content_base
+            .sync_vector_indexes(text_dim, multi_modal_dim)
+            .await
+    }
+
+    async fn all_asset_hashes(&self) -> Vec<String> {
+        let Ok(library) = self.library() else {
+            return vec![];
+        };
+        match library
+            .prisma_client()
+            .asset_object()
+            .find_many(vec![])
+            .exec()
+            .await
+        {
+            Ok(asset_object_data_list) => {
+                asset_object_data_list.into_iter().map(|v| v.hash).collect()
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch assets: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// 重新处理素材，已经完成的任务如果参数（比如模型）没有变化会直接跳过
+    /// 提交的进度记录在 reindex_status 里，每个素材的处理进度和平时一样记录在 file_handler_task 里
+    async fn rebuild_content_indexes(&self, hashes: &[String]) {
+        let Ok(library) = self.library() else {
+            return;
+        };
+        let job_id = uuid::Uuid::new_v4();
+        self.reindex_job.lock().unwrap().replace(ReindexJob {
+            id: job_id,
+            status: ReindexStatus {
+                total: hashes.len(),
+                running: true,
+                hashes: hashes.to_vec(),
+                ..Default::default()
+            },
+        });
+        tracing::info!("Rebuilding content indexes for {} assets", hashes.len());
+        for hash in hashes {
+            let result = build_content_index(&library, self, hash, true).await;
+            if let Err(e) = &result {
+                tracing::error!(error = ?e, "Failed trigger content index rebuild for asset {}", hash);
+            }
+            let mut reindex_job = self.reindex_job.lock().unwrap();
+            match reindex_job.as_mut() {
+                Some(job) if job.id == job_id => match result {
+                    Ok(_) => job.status.scheduled += 1,
+                    Err(_) => job.status.failed += 1,
+                },
+                // 已经有新的重建任务，或者 library 已经 unload 了
+                _ => return,
+            }
+        }
+        if let Some(job) = self.reindex_job.lock().unwrap().as_mut() {
+            if job.id == job_id {
+                job.status.running = false;
+            }
+        }
+    }
+
+    async fn trigger_unfinished_tasks(&self, skip_hashes: HashSet<String>) -> () {
         let Ok(library) = self.library() else {
             return;
         };
@@ -221,6 +405,9 @@ impl<S: CtxStore + Send> Ctx<S> {
             asset_object_data_list.len()
         );
         for asset_object_data in asset_object_data_list {
+            if skip_hashes.contains(&asset_object_data.hash) {
+                continue;
+            }
             if let Err(e) = build_content_index(&library, self, &asset_object_data.hash, true).await
             {
                 tracing::error!(error = ?e, "Failed trigger content index rebuild for asset {}", asset_object_data.hash);
@@ -230,7 +417,7 @@ impl<S: CtxStore + Send> Ctx<S> {
 }
 
 #[async_trait]
-impl<S: CtxStore + Send> CtxWithLibrary for Ctx<S> {
+impl<S: CtxStore + Send + 'static> CtxWithLibrary for Ctx<S> {
     fn is_busy(&self) -> Arc<Mutex<AtomicBool>> {
         self.is_busy.clone()
     }
@@ -296,6 +483,12 @@ impl<S: CtxStore + Send> CtxWithLibrary for Ctx<S> {
             tracing::info!(task = "shutdown ai handler", "Success");
         }
 
+        /* stop rebuilding indexes */
+        {
+            let mut reindex_job = self.reindex_job.lock()?;
+            *reindex_job = None;
+        }
+
         /* update ctx */
         {
             let mut current_library = self.current_library.lock()?;
@@ -414,36 +607,7 @@ impl<S: CtxStore + Send> CtxWithLibrary for Ctx<S> {
 
         /* init content base */
         let content_base = {
-            let cb_ctx = ContentBaseCtx::new(&library.artifacts_dir_name(), &self.temp_dir)
-                .with_audio_transcript(
-                    Arc::new(ai_handler.audio_transcript.0),
-                    &ai_handler.audio_transcript.1,
-                )
-                .with_llm(
-                    Arc::new(ai_handler.llm.0),
-                    &ai_handler.llm.1, // this comment is just for for alignment and better readability
-                )
-                .with_text_tokenizer(
-                    Arc::new(ai_handler.text_tokenizer.0),
-                    &ai_handler.text_tokenizer.1,
-                )
-                .with_multi_modal_embedding(
-                    Arc::new(ai_handler.multi_modal_embedding.0),
-                    &ai_handler.multi_modal_embedding.1,
-                )
-                .with_text_embedding(
-                    Arc::new(ai_handler.text_embedding.0),
-                    &ai_handler.text_embedding.1,
-                )
-                .with_image_caption(
-                    Arc::new(ai_handler.image_caption.0),
-                    &ai_handler.image_caption.1,
-                );
-            // 这个 block 后面不再使用 ai_handler 了，上面 with 函数里不需要 clone 直接 move 就行
-            let cb = ContentBase::new(&cb_ctx, library.surrealdb_client()).map_err(|e| {
-                tracing::error!(task = "init content base", "Failed: {}", e);
-                CtxError::Internal(format!("Failed to init content base: {}", e))
-            })?;
+            let cb = self.build_content_base(&library, ai_handler)?;
             tracing::info!(task = "init task pool", "Success");
             let mut current_cb = self.content_base.lock()?;
             current_cb.replace(cb.clone());
@@ -451,10 +615,16 @@ impl<S: CtxStore + Send> CtxWithLibrary for Ctx<S> {
             cb
         };
 
-        /* trigger unfinished tasks */
+        /* sync vector indexes and trigger unfinished tasks */
+        // 索引重建可能需要重新处理很多素材，在后台执行，不阻塞 library 的加载
+        self.sync_vector_indexes(true);
+
+        /* backfill media index */
         {
-            self.trigger_unfinished_tasks().await;
-            tracing::info!(task = "trigger unfinished tasks", "Success");
+            let library = library.clone();
+            tokio::spawn(async move {
+                backfill_media_index(&library).await;
+            });
         }
 
         // init cron
@@ -492,6 +662,38 @@ impl<S: CtxStore + Send> CtxWithLibrary for Ctx<S> {
             None => Err(CtxError::BadRequest("No content base is set".into())),
         }
     }
+
+    #[tracing::instrument(level = "info", skip_all)]
+    async fn reload_content_base(&self) -> Result<ContentBase, CtxError> {
+        let library = self.library()?;
+        let ai_handler = self.ai_handler()?;
+        let cb_ctx = self.build_content_base_ctx(&library, ai_handler);
+        let content_base = self.content_base()?.with_ctx(&cb_ctx).await;
+        {
+            let mut current_cb = self.content_base.lock()?;
+            current_cb.replace(content_base.clone());
+            tracing::info!(task = "reload content base", "Success");
+        }
+        Ok(content_base)
+    }
+
+    fn sync_vector_indexes(&self, trigger_unfinished_tasks: bool) {
+        let ctx = self.clone();
+        tokio::spawn(
+            async move {
+                ctx.run_vector_index_sync(trigger_unfinished_tasks).await;
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    fn reindex_status(&self) -> Option<ReindexStatus> {
+        self.reindex_job
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|job| job.status.clone())
+    }
 }
 
 impl<S: CtxStore + Send> Ctx<S> {
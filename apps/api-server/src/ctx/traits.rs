@@ -51,6 +51,21 @@ impl From<CtxError> for anyhow::Error {
     }
 }
 
+/// embedding 模型变化以后在后台重建索引的进度
+#[derive(Clone, Debug, Default)]
+pub struct ReindexStatus {
+    /// 需要重新处理的素材数量
+    pub total: usize,
+    /// 已经提交处理的素材数量，处理完成的进度需要结合素材的任务状态
+    pub scheduled: usize,
+    /// 提交处理失败的素材数量
+    pub failed: usize,
+    /// 是否还在提交素材
+    pub running: bool,
+    /// 需要重新处理的素材 hash
+    pub hashes: Vec<String>,
+}
+
 #[async_trait]
 pub trait CtxWithLibrary: Sync + CtxWithP2P + CtxWithAI + CtxWithDownload {
     fn is_busy(&self) -> Arc<Mutex<std::sync::atomic::AtomicBool>>;
@@ -67,6 +82,13 @@ pub trait CtxWithLibrary: Sync + CtxWithP2P + CtxWithAI + CtxWithDownload {
 
     fn library(&self) -> Result<Library, CtxError>;
     fn content_base(&self) -> Result<ContentBase, CtxError>;
+    /// 用当前的 ai handler 更新 content base 里的模型，切换模型以后需要调用，任务池不会重新创建
+    async fn reload_content_base(&self) -> Result<ContentBase, CtxError>;
+    /// 在后台同步向量索引，embedding 模型变化以后需要调用，受影响的素材会重新处理
+    /// trigger_unfinished_tasks 为 true 的时候，确定了重新处理的素材以后再继续处理上次没有完成的任务，只在加载 library 的时候需要
+    fn sync_vector_indexes(&self, trigger_unfinished_tasks: bool);
+    /// 最近一次重建索引的进度，没有重建过的时候为 None
+    fn reindex_status(&self) -> Option<ReindexStatus>;
 }
 
 pub trait CtxWithP2P {
@@ -1,3 +1,4 @@
+use content_base::{query::RankWeights, TaggingConfig};
 use content_library::Library;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -46,6 +47,34 @@ impl Default for LibrarySettingsExplorer {
     }
 }
 
+/// 素材打标签的提示词和标签数量上限
+/// 重新加载素材库以后生效，已经处理过的素材需要重新处理才会更新标签
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySettingsTagging {
+    pub prompt: String,
+    pub max_tags: u32,
+}
+
+impl Default for LibrarySettingsTagging {
+    fn default() -> Self {
+        let config = TaggingConfig::default();
+        LibrarySettingsTagging {
+            prompt: config.prompt,
+            max_tags: config.max_tags as u32,
+        }
+    }
+}
+
+impl From<LibrarySettingsTagging> for TaggingConfig {
+    fn from(settings: LibrarySettingsTagging) -> Self {
+        TaggingConfig {
+            prompt: settings.prompt,
+            max_tags: (settings.max_tags as usize).max(1),
+        }
+    }
+}
+
 #[derive(Type, Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct LibraryModels {
@@ -54,6 +83,16 @@ pub struct LibraryModels {
     pub image_caption: String,
     pub audio_transcript: String,
     pub llm: String,
+    /// 搜索结果二次排序的模型，None 表示不做二次排序
+    /// 旧的 settings.json 里没有这个字段，需要 default，否则整个 models 都会被重置
+    #[serde(default)]
+    pub text_rerank: Option<String>,
+    /// 物体检测的模型，None 表示不做物体检测
+    #[serde(default)]
+    pub object_detection: Option<String>,
+    /// 文字识别的模型，None 表示不做 OCR
+    #[serde(default)]
+    pub ocr: Option<String>,
 }
 
 impl Default for LibraryModels {
@@ -64,6 +103,9 @@ impl Default for LibraryModels {
             image_caption: "llava-phi3-mini".to_string(),
             audio_transcript: "whisper-small".to_string(),
             llm: "qwen2-7b-instruct".to_string(),
+            text_rerank: None,
+            object_detection: None,
+            ocr: None,
         }
     }
 }
@@ -77,6 +119,9 @@ pub struct LibrarySettings {
     pub models: LibraryModels,
     pub always_delete_local_file_after_upload: bool,
     pub s3_config: Option<S3Config>,
+    /// 搜索排序各路召回和来源的权重
+    pub rank_weights: RankWeights,
+    pub tagging: LibrarySettingsTagging,
 }
 
 impl<'de> Deserialize<'de> for LibrarySettings {
@@ -110,6 +155,10 @@ impl<'de> Deserialize<'de> for LibrarySettings {
                 .unwrap_or(false),
             s3_config: serde_json::from_value::<Option<S3Config>>(value["s3Config"].to_owned())
                 .unwrap_or(None),
+            rank_weights: serde_json::from_value::<RankWeights>(value["rankWeights"].to_owned())
+                .unwrap_or_default(),
+            tagging: serde_json::from_value::<LibrarySettingsTagging>(value["tagging"].to_owned())
+                .unwrap_or_default(),
         };
         Ok(settings)
     }
@@ -124,6 +173,8 @@ impl Default for LibrarySettings {
             models: Default::default(),
             always_delete_local_file_after_upload: false,
             s3_config: None,
+            rank_weights: Default::default(),
+            tagging: Default::default(),
         }
     }
 }
@@ -1,12 +1,27 @@
+use super::chapters::{export_chapters, ChapterExportFormat, VideoChapterData};
 use crate::CtxWithLibrary;
 use content_base_task::{
-    audio::{trans_chunk_sum::AudioTransChunkSumTrait, transcript::AudioTranscriptTrait},
-    image::description::ImageDescriptionTask,
+    audio::{
+        trans_chunk_sum::AudioTransChunkSumTrait,
+        trans_sum::{AudioTransSumTask, AudioTransSumTrait},
+        transcript::AudioTranscriptTrait,
+    },
+    image::{
+        description::ImageDescriptionTask,
+        object_detection::{DetectedObject, ImageObjectDetectionTask},
+        ocr::{ImageOcrTask, OcrTextBlock},
+    },
     raw_text::{
         chunk::{DocumentChunkTrait, RawTextChunkTask},
         chunk_sum::{DocumentChunkSumTrait, RawTextChunkSumTask},
+        sum::{DocumentSumTrait, RawTextSumTask},
+    },
+    video::{
+        chapter::VideoChapterTask, frame_object_detection::VideoFrameObjectDetectionTask,
+        frame_ocr::VideoFrameOcrTask, trans_chunk_sum::VideoTransChunkSumTask,
+        trans_sum::VideoTransSumTask, transcript::VideoTranscriptTask,
     },
-    video::{trans_chunk_sum::VideoTransChunkSumTask, transcript::VideoTranscriptTask},
+    web_page::sum::WebPageSumTask,
 };
 use rspc::{Router, RouterBuilder};
 use serde::{Deserialize, Serialize};
@@ -19,6 +34,89 @@ struct RawTextRequestPayload {
     index: u32,
 }
 
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SummaryRequestPayload {
+    hash: String,
+}
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ChapterExportRequestPayload {
+    hash: String,
+    format: ChapterExportFormat,
+}
+
+fn chapters_error(e: anyhow::Error) -> rspc::Error {
+    rspc::Error::new(
+        rspc::ErrorCode::InternalServerError,
+        format!("failed to get chapters: {}", e),
+    )
+}
+
+fn summary_error(e: anyhow::Error) -> rspc::Error {
+    rspc::Error::new(
+        rspc::ErrorCode::InternalServerError,
+        format!("failed to get summary: {}", e),
+    )
+}
+
+/// 坐标是相对于画面宽高的比例，范围是 0 到 1
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BoundingBoxData {
+    x_min: f32,
+    y_min: f32,
+    x_max: f32,
+    y_max: f32,
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DetectedObjectData {
+    label: String,
+    confidence: f32,
+    bbox: BoundingBoxData,
+}
+
+impl From<DetectedObject> for DetectedObjectData {
+    fn from(object: DetectedObject) -> Self {
+        Self {
+            label: object.label,
+            confidence: object.confidence,
+            bbox: BoundingBoxData {
+                x_min: object.bbox.x_min,
+                y_min: object.bbox.y_min,
+                x_max: object.bbox.x_max,
+                y_max: object.bbox.y_max,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OcrTextBlockData {
+    text: String,
+    confidence: f32,
+    bbox: BoundingBoxData,
+}
+
+impl From<OcrTextBlock> for OcrTextBlockData {
+    fn from(block: OcrTextBlock) -> Self {
+        Self {
+            text: block.text,
+            confidence: block.confidence,
+            bbox: BoundingBoxData {
+                x_min: block.bbox.x_min,
+                y_min: block.bbox.y_min,
+                x_max: block.bbox.x_max,
+                y_max: block.bbox.y_max,
+            },
+        }
+    }
+}
+
 pub fn get_routes<TCtx>() -> RouterBuilder<TCtx>
 where
     TCtx: CtxWithLibrary + Clone + Send + Sync + 'static,
@@ -138,6 +236,90 @@ where
                     })?)
             })
         })
+        .query("raw_text.summary", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                RawTextSumTask
+                    .document_sum_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(summary_error)
+            })
+        })
+        .query("web_page.summary", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                WebPageSumTask
+                    .document_sum_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(summary_error)
+            })
+        })
+        .query("video.summary", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                VideoTransSumTask
+                    .trans_sum_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(summary_error)
+            })
+        })
+        .query("video.chapters", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let chapters = VideoChapterTask
+                    .chapters_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(chapters_error)?;
+                Ok(chapters
+                    .into_iter()
+                    .map(VideoChapterData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("video.chapters.export", |t| {
+            t(|ctx, input: ChapterExportRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let chapters = VideoChapterTask
+                    .chapters_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(chapters_error)?;
+                Ok(export_chapters(&chapters, &input.format))
+            })
+        })
+        .query("audio.summary", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                AudioTransSumTask
+                    .trans_sum_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(summary_error)
+            })
+        })
+        .query("tags", |t| {
+            t(|ctx, input: SummaryRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                Ok(content_base.asset_tags(&input.hash).await.map_err(|e| {
+                    rspc::Error::new(
+                        rspc::ErrorCode::InternalServerError,
+                        format!("failed to get tags: {}", e),
+                    )
+                })?)
+            })
+        })
         .query("image.description", |t| {
             #[derive(Deserialize, Type, Debug)]
             #[serde(rename_all = "camelCase")]
@@ -159,4 +341,141 @@ where
                     })?)
             })
         })
+        .query("image.objects", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct ImageRequestPayload {
+                hash: String,
+            }
+            t(|ctx, input: ImageRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let objects = ImageObjectDetectionTask
+                    .objects_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(|e| {
+                        rspc::Error::new(
+                            rspc::ErrorCode::InternalServerError,
+                            format!("failed to get objects: {}", e),
+                        )
+                    })?;
+                Ok(objects
+                    .into_iter()
+                    .map(DetectedObjectData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("video.objects", |t| {
+            /// 不传时间范围的时候返回所有帧
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct VideoObjectsRequestPayload {
+                hash: String,
+                #[specta(optional, type = u32)]
+                start_timestamp: Option<i64>,
+                #[specta(optional, type = u32)]
+                end_timestamp: Option<i64>,
+            }
+            #[derive(Serialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct FrameObjectsData {
+                #[specta(type = u32)]
+                timestamp: i64,
+                objects: Vec<DetectedObjectData>,
+            }
+            t(|ctx, input: VideoObjectsRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let frames = VideoFrameObjectDetectionTask
+                    .frame_objects_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(|e| {
+                        rspc::Error::new(
+                            rspc::ErrorCode::InternalServerError,
+                            format!("failed to get objects: {}", e),
+                        )
+                    })?;
+                Ok(frames
+                    .into_iter()
+                    .filter(|v| {
+                        !input.start_timestamp.is_some_and(|t| v.timestamp < t)
+                            && !input.end_timestamp.is_some_and(|t| v.timestamp > t)
+                    })
+                    .map(|v| FrameObjectsData {
+                        timestamp: v.timestamp,
+                        objects: v
+                            .objects
+                            .into_iter()
+                            .map(DetectedObjectData::from)
+                            .collect(),
+                    })
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("image.ocr", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct ImageOcrRequestPayload {
+                hash: String,
+            }
+            t(|ctx, input: ImageOcrRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let blocks = ImageOcrTask
+                    .ocr_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(|e| {
+                        rspc::Error::new(
+                            rspc::ErrorCode::InternalServerError,
+                            format!("failed to get ocr text: {}", e),
+                        )
+                    })?;
+                Ok(blocks
+                    .into_iter()
+                    .map(OcrTextBlockData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("video.ocr", |t| {
+            /// 相邻帧上一样的文字合并成一段，和索引里的 ocr_frame 一致
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct VideoOcrRequestPayload {
+                hash: String,
+            }
+            #[derive(Serialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct OcrSegmentData {
+                #[specta(type = u32)]
+                start_timestamp: i64,
+                #[specta(type = u32)]
+                end_timestamp: i64,
+                text: String,
+            }
+            t(|ctx, input: VideoOcrRequestPayload| async move {
+                let _library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+
+                let segments = VideoFrameOcrTask
+                    .ocr_segments_content(&input.hash, content_base.ctx())
+                    .await
+                    .map_err(|e| {
+                        rspc::Error::new(
+                            rspc::ErrorCode::InternalServerError,
+                            format!("failed to get ocr text: {}", e),
+                        )
+                    })?;
+                Ok(segments
+                    .into_iter()
+                    .map(|v| OcrSegmentData {
+                        start_timestamp: v.start_timestamp,
+                        end_timestamp: v.end_timestamp,
+                        text: v.text,
+                    })
+                    .collect::<Vec<_>>())
+            })
+        })
 }
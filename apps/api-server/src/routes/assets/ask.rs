@@ -0,0 +1,267 @@
+use crate::ai::AIHandler;
+use ai::llm::{LLMInferenceParams, LLMMessage};
+use content_base::{
+    query::{
+        context::AssetContextSegment,
+        payload::{video::VideoSliceType, ContentIndexMetadata},
+    },
+    ContentBase,
+};
+use content_base_task::video::chapter::format_timestamp;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::sync::mpsc::Sender;
+
+/// 每条消息在 chat template 里的额外开销，比如角色标记
+const MESSAGE_OVERHEAD_TOKENS: usize = 16;
+
+const ASK_SYSTEM_PROMPT: &str = r#"You are an assistant good at answering questions about a single file.
+You will be given the CONTENT of the file, which may contain transcript segments, visual descriptions of video clips and text paragraphs.
+Answer the QUESTION only according to the CONTENT, do not use any other knowledge.
+If the CONTENT doesn't contain the facts to answer the QUESTION, return "I don't know" in the question's language.
+
+Segments of audio and video start with a time range like [01:05 - 01:30].
+When you use facts from these segments, mention the time range right after the sentence, for example [01:05 - 01:30].
+
+You should answer in the language of the QUESTION.
+"#;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetAskRequestPayload {
+    pub hash: String,
+    pub question: String,
+}
+
+#[derive(Serialize, Type)]
+#[serde(tag = "resultType", content = "data")]
+pub enum AssetAskResult {
+    Response(String),
+    Error(String),
+    Done,
+}
+
+fn format_segment(segment: &AssetContextSegment) -> String {
+    let time_range = |start: i64, end: i64| {
+        format!(
+            "[{} - {}]",
+            format_timestamp(start, false),
+            format_timestamp(end, false)
+        )
+    };
+    let title = match &segment.metadata {
+        ContentIndexMetadata::Video(metadata) => match metadata.slice_type {
+            VideoSliceType::Audio => format!(
+                "{} Transcript",
+                time_range(metadata.start_timestamp, metadata.end_timestamp)
+            ),
+            VideoSliceType::Visual => format!(
+                "{} Visual",
+                time_range(metadata.start_timestamp, metadata.end_timestamp)
+            ),
+        },
+        ContentIndexMetadata::Audio(metadata) => format!(
+            "{} Transcript",
+            time_range(metadata.start_timestamp, metadata.end_timestamp)
+        ),
+        ContentIndexMetadata::RawText(metadata) => {
+            format!("Paragraph {}", metadata.start_index + 1)
+        }
+        ContentIndexMetadata::WebPage(metadata) => {
+            format!("Paragraph {}", metadata.start_index + 1)
+        }
+        ContentIndexMetadata::Image(_) => "Image description".to_string(),
+    };
+    format!("{}:\n{}\n\n", title, segment.content)
+}
+
+/// 搜索命中的片段和素材内容是否是同一种来源，并且位置有重叠
+fn hit_overlaps(segment: &ContentIndexMetadata, hit: &ContentIndexMetadata) -> bool {
+    let same_source = match (segment, hit) {
+        (ContentIndexMetadata::Video(a), ContentIndexMetadata::Video(b)) => matches!(
+            (&a.slice_type, &b.slice_type),
+            (VideoSliceType::Audio, VideoSliceType::Audio)
+                | (VideoSliceType::Visual, VideoSliceType::Visual)
+        ),
+        (ContentIndexMetadata::Audio(_), ContentIndexMetadata::Audio(_))
+        | (ContentIndexMetadata::RawText(_), ContentIndexMetadata::RawText(_))
+        | (ContentIndexMetadata::WebPage(_), ContentIndexMetadata::WebPage(_))
+        | (ContentIndexMetadata::Image(_), ContentIndexMetadata::Image(_)) => true,
+        _ => false,
+    };
+    same_source
+        && match (segment.segment_range(), hit.segment_range()) {
+            (Some((start_a, end_a)), Some((start_b, end_b))) => {
+                start_a <= end_b && start_b <= end_a
+            }
+            _ => true,
+        }
+}
+
+/// 内容放不下的时候，按和问题的相关度从高到低选择片段，相关度一样的按位置先后
+/// 返回选中的片段的序号，按片段原来的顺序排列
+fn select_segments(tokens: &[usize], scores: &[f32], budget: usize) -> Vec<usize> {
+    let mut order = (0..tokens.len()).collect::<Vec<_>>();
+    // sort_by 是稳定排序，分数一样的保持原来的顺序
+    order.sort_by(|a, b| {
+        scores[*b]
+            .partial_cmp(&scores[*a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut used = 0;
+    let mut selected = vec![];
+    for idx in order {
+        if used + tokens[idx] <= budget {
+            used += tokens[idx];
+            selected.push(idx);
+        }
+    }
+    selected.sort();
+    selected
+}
+
+fn ask_user_prompt(content: &str, question: &str) -> String {
+    format!(
+        r#"CONTENT:
+{}
+
+QUESTION:
+{}
+"#,
+        content, question
+    )
+}
+
+/// 只根据一个素材的转录、文本分段和画面描述回答问题
+pub async fn ask(
+    content_base: &ContentBase,
+    ai_handler: &AIHandler,
+    input: AssetAskRequestPayload,
+    tx: Sender<AssetAskResult>,
+) -> anyhow::Result<()> {
+    let segments = content_base.asset_context(&input.hash).await?;
+    if segments.is_empty() {
+        anyhow::bail!("no processed content found in asset: {}", &input.hash);
+    }
+
+    let context = ai_handler.llm_context;
+    let count_tokens = |text: &str| ai_handler.count_text_tokens(text);
+    let content_budget = context
+        .context_window
+        .saturating_sub(context.max_output_tokens)
+        .saturating_sub(
+            count_tokens(ASK_SYSTEM_PROMPT)
+                + count_tokens(&ask_user_prompt("", &input.question))
+                + MESSAGE_OVERHEAD_TOKENS * 2,
+        );
+
+    let formatted = segments.iter().map(format_segment).collect::<Vec<_>>();
+    let tokens = formatted
+        .iter()
+        .map(|text| count_tokens(text))
+        .collect::<Vec<_>>();
+    let selected = if tokens.iter().sum::<usize>() <= content_budget {
+        (0..formatted.len()).collect::<Vec<_>>()
+    } else {
+        // 整个素材放不下的时候，用素材内搜索的结果给片段打分，优先放和问题相关的片段
+        let hits = content_base
+            .query_in_asset(&input.hash, &input.question)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("failed to search in asset: {}", e);
+                vec![]
+            });
+        let scores = segments
+            .iter()
+            .map(|segment| {
+                hits.iter()
+                    .filter(|hit| hit_overlaps(&segment.metadata, &hit.metadata))
+                    .map(|hit| hit.score)
+                    .fold(0.0, f32::max)
+            })
+            .collect::<Vec<_>>();
+        select_segments(&tokens, &scores, content_budget)
+    };
+
+    let content = selected
+        .into_iter()
+        .map(|idx| formatted[idx].as_str())
+        .collect::<String>();
+    let messages = vec![
+        LLMMessage::new_system(ASK_SYSTEM_PROMPT),
+        LLMMessage::new_user(ask_user_prompt(&content, &input.question).as_str()),
+    ];
+
+    let mut response = ai_handler
+        .llm
+        .0
+        .process_single((
+            messages,
+            LLMInferenceParams::default().with_max_tokens(context.max_output_tokens),
+        ))
+        .await?;
+
+    while let Some(content) = response.next().await {
+        match content {
+            Ok(Some(data)) => {
+                tx.send(AssetAskResult::Response(data)).await?;
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                tx.send(AssetAskResult::Error(e.to_string())).await?;
+            }
+        }
+    }
+
+    tx.send(AssetAskResult::Done).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hit_overlaps, select_segments};
+    use content_base::query::payload::{
+        video::{VideoIndexMetadata, VideoSliceType},
+        ContentIndexMetadata,
+    };
+
+    fn video(slice_type: VideoSliceType, start: i64, end: i64) -> ContentIndexMetadata {
+        VideoIndexMetadata {
+            slice_type,
+            start_timestamp: start,
+            end_timestamp: end,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_hit_overlaps() {
+        let segment = video(VideoSliceType::Audio, 1000, 3000);
+        assert!(hit_overlaps(
+            &segment,
+            &video(VideoSliceType::Audio, 2000, 5000)
+        ));
+        // 画面和转录的时间有重叠，但不是同一种内容
+        assert!(!hit_overlaps(
+            &segment,
+            &video(VideoSliceType::Visual, 2000, 2000)
+        ));
+        assert!(!hit_overlaps(
+            &segment,
+            &video(VideoSliceType::Audio, 4000, 5000)
+        ));
+    }
+
+    #[test]
+    fn test_select_segments() {
+        let tokens = vec![10, 10, 10, 10];
+        let scores = vec![0.0, 0.5, 0.0, 0.9];
+        assert_eq!(select_segments(&tokens, &scores, 25), vec![1, 3]);
+        // 分数一样的按位置先后
+        assert_eq!(select_segments(&tokens, &scores, 30), vec![0, 1, 3]);
+        assert_eq!(select_segments(&tokens, &scores, 5), Vec::<usize>::new());
+    }
+}
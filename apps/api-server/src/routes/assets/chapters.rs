@@ -0,0 +1,144 @@
+use crate::routes::audio::reader::AudioData;
+use content_base_task::video::chapter::{format_timestamp, VideoChapter};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ChapterExportFormat {
+    /// 贴在 YouTube 视频简介里的章节列表，每行是开始时间和标题
+    Youtube,
+    /// WebVTT 章节轨道，可以作为 `<track kind="chapters">` 使用
+    WebVtt,
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoChapterData {
+    pub title: String,
+    #[specta(type = u32)]
+    pub start_timestamp: i64,
+    #[specta(type = u32)]
+    pub end_timestamp: i64,
+}
+
+impl From<VideoChapter> for VideoChapterData {
+    fn from(chapter: VideoChapter) -> Self {
+        Self {
+            title: chapter.title,
+            start_timestamp: chapter.start_timestamp,
+            end_timestamp: chapter.end_timestamp,
+        }
+    }
+}
+
+/// LLM 生成的标题可能有换行，也可能包含 `-->`，这两种都会破坏 WebVTT 的 cue，
+/// 导出前合并成一行并替换掉箭头
+fn sanitize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("-->", "->")
+}
+
+/// YouTube 要求第一个章节从 00:00 开始，视频超过一小时的时候所有时间都用 h:mm:ss
+fn youtube_chapters(chapters: &[VideoChapter]) -> String {
+    let with_hours = chapters.iter().any(|v| v.start_timestamp >= 3_600_000);
+    chapters
+        .iter()
+        .map(|chapter| {
+            format!(
+                "{} {}\n",
+                format_timestamp(chapter.start_timestamp, with_hours),
+                sanitize_title(&chapter.title)
+            )
+        })
+        .collect()
+}
+
+fn webvtt_chapters(chapters: &[VideoChapter]) -> String {
+    let mut result = "WEBVTT\n\n".to_string();
+    for (i, chapter) in chapters.iter().enumerate() {
+        result.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            AudioData::format_timestamp(chapter.start_timestamp.max(0) as u32, b'.'),
+            AudioData::format_timestamp(chapter.end_timestamp.max(0) as u32, b'.'),
+            sanitize_title(&chapter.title)
+        ));
+    }
+    result
+}
+
+pub fn export_chapters(chapters: &[VideoChapter], format: &ChapterExportFormat) -> String {
+    match format {
+        ChapterExportFormat::Youtube => youtube_chapters(chapters),
+        ChapterExportFormat::WebVtt => webvtt_chapters(chapters),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{export_chapters, ChapterExportFormat};
+    use content_base_task::video::chapter::VideoChapter;
+
+    #[test]
+    fn test_export_youtube() {
+        let chapters = vec![
+            VideoChapter {
+                title: "Intro".to_string(),
+                start_timestamp: 0,
+                end_timestamp: 65_000,
+            },
+            VideoChapter {
+                title: "Setup".to_string(),
+                start_timestamp: 65_000,
+                end_timestamp: 600_000,
+            },
+            VideoChapter {
+                title: "Review".to_string(),
+                start_timestamp: 600_000,
+                end_timestamp: 700_000,
+            },
+        ];
+        let result = export_chapters(&chapters, &ChapterExportFormat::Youtube);
+        assert_eq!(result, "00:00 Intro\n01:05 Setup\n10:00 Review\n");
+
+        let chapters = vec![
+            VideoChapter {
+                title: "Intro".to_string(),
+                start_timestamp: 0,
+                end_timestamp: 3_723_000,
+            },
+            VideoChapter {
+                title: "Review".to_string(),
+                start_timestamp: 3_723_000,
+                end_timestamp: 4_000_000,
+            },
+        ];
+        let result = export_chapters(&chapters, &ChapterExportFormat::Youtube);
+        assert_eq!(result, "0:00:00 Intro\n1:02:03 Review\n");
+    }
+
+    #[test]
+    fn test_export_webvtt() {
+        let chapters = vec![
+            VideoChapter {
+                title: "Intro".to_string(),
+                start_timestamp: 0,
+                end_timestamp: 65_500,
+            },
+            VideoChapter {
+                title: "Before --> After\n\nReview".to_string(),
+                start_timestamp: 65_500,
+                end_timestamp: 3_723_000,
+            },
+        ];
+        let result = export_chapters(&chapters, &ChapterExportFormat::WebVtt);
+        assert_eq!(
+            result,
+            "WEBVTT\n\n1\n00:00:00.000 --> 00:01:05.500\nIntro\n\n2\n00:01:05.500 --> 01:02:03.000\nBefore -> After Review\n\n"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use super::types::FilePathWithAssetObjectData;
+use content_base::{
+    duplicate::{NearDuplicateCluster, NearDuplicatePayload},
+    ContentBase,
+};
+use content_library::Library;
+use content_metadata::ContentType;
+use prisma_lib::{asset_object, file_path};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatesRequestPayload {
+    /// 0 到 1 之间，越大越严格，不传的时候使用 content base 的默认值
+    #[specta(optional)]
+    pub threshold: Option<f32>,
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateAssetData {
+    pub file_path: FilePathWithAssetObjectData,
+    /// 和 cluster 里其他素材的最高相似度
+    pub similarity: f32,
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePairData {
+    pub asset_object_hashes: (String, String),
+    pub similarity: f32,
+    pub embedding_similarity: f32,
+    pub hash_similarity: Option<f32>,
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateClusterData {
+    pub content_type: ContentType,
+    pub similarity: f32,
+    /// 推荐保留的素材，分辨率最高的，一样的话码率高的，再一样的话文件大的
+    pub best_asset_object_hash: String,
+    pub items: Vec<DuplicateAssetData>,
+    pub pairs: Vec<DuplicatePairData>,
+}
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeDuplicatesPayload {
+    pub asset_object_hashes: Vec<String>,
+    /// 不传的时候保留 asset_object_hashes 里质量最好的素材
+    #[specta(optional)]
+    pub keep_asset_object_hash: Option<String>,
+    /// 和列出重复素材时使用的阈值一样，用来检查 asset_object_hashes 是不是同一个 cluster
+    #[specta(optional)]
+    pub threshold: Option<f32>,
+}
+
+fn quality_key(asset_object_data: &asset_object::Data) -> (i64, i32, i32) {
+    let resolution =
+        asset_object_data.width.unwrap_or(0) as i64 * asset_object_data.height.unwrap_or(0) as i64;
+    (
+        resolution,
+        asset_object_data.bit_rate.unwrap_or(0),
+        asset_object_data.size,
+    )
+}
+
+fn best_asset_object<'a>(
+    asset_objects: impl Iterator<Item = &'a asset_object::Data>,
+) -> Option<&'a asset_object::Data> {
+    asset_objects.max_by_key(|v| quality_key(v))
+}
+
+fn cluster_data(
+    cluster: NearDuplicateCluster,
+    asset_objects: &HashMap<String, asset_object::Data>,
+) -> Option<DuplicateClusterData> {
+    let mut items = cluster
+        .file_identifiers
+        .iter()
+        .filter_map(|hash| {
+            let mut asset_object_data = asset_objects.get(hash)?.clone();
+            let mut file_path_data = asset_object_data.file_paths.take()?.into_iter().next()?;
+            file_path_data.asset_object = Some(Some(Box::new(asset_object_data)));
+            let similarity = cluster
+                .pairs
+                .iter()
+                .filter(|v| &v.file_identifiers.0 == hash || &v.file_identifiers.1 == hash)
+                .map(|v| v.similarity)
+                .fold(0.0f32, f32::max);
+            Some(DuplicateAssetData {
+                file_path: file_path_data.into(),
+                similarity,
+            })
+        })
+        .collect::<Vec<_>>();
+    // 素材在计算的过程中被删除了
+    if items.len() < 2 {
+        return None;
+    }
+    items.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    let best = best_asset_object(
+        cluster
+            .file_identifiers
+            .iter()
+            .filter_map(|hash| asset_objects.get(hash)),
+    )?;
+
+    Some(DuplicateClusterData {
+        content_type: cluster.content_type,
+        similarity: cluster.similarity,
+        best_asset_object_hash: best.hash.clone(),
+        items,
+        pairs: cluster
+            .pairs
+            .into_iter()
+            .map(|v| DuplicatePairData {
+                asset_object_hashes: v.file_identifiers,
+                similarity: v.similarity,
+                embedding_similarity: v.embedding_similarity,
+                hash_similarity: v.hash_similarity,
+            })
+            .collect(),
+    })
+}
+
+/// 整个素材库里相似或重复的图片和视频，检测结果由 content base 缓存，素材没有变化时不会重新计算
+/// 只有图片和视频有宽高，用 width 过滤掉其他类型，没有关联 file path 的素材会被定时任务删除，也不用比较
+async fn detect_duplicates(
+    library: &Library,
+    content_base: &ContentBase,
+    threshold: Option<f32>,
+) -> Result<(Vec<asset_object::Data>, Vec<NearDuplicateCluster>), rspc::Error> {
+    let asset_objects = library
+        .prisma_client()
+        .asset_object()
+        .find_many(vec![
+            asset_object::width::not(None),
+            asset_object::file_paths::some(vec![]),
+        ])
+        .with(
+            asset_object::file_paths::fetch(vec![])
+                .order_by(file_path::created_at::order(
+                    prisma_client_rust::Direction::Desc,
+                ))
+                .take(1),
+        )
+        .exec()
+        .await?;
+
+    let file_identifiers = asset_objects
+        .iter()
+        .map(|v| v.hash.clone())
+        .collect::<Vec<_>>();
+    let mut payload = NearDuplicatePayload::new(&file_identifiers);
+    if let Some(threshold) = threshold {
+        payload = payload.with_threshold(threshold);
+    }
+    let clusters = content_base.near_duplicates(payload).await.map_err(|e| {
+        tracing::error!("Failed to find near duplicates: {e}");
+        rspc::Error::new(
+            rspc::ErrorCode::InternalServerError,
+            format!("Failed to find near duplicates: {e}"),
+        )
+    })?;
+
+    Ok((asset_objects, clusters))
+}
+
+pub async fn list_duplicates(
+    library: &Library,
+    content_base: &ContentBase,
+    input: DuplicatesRequestPayload,
+) -> Result<Vec<DuplicateClusterData>, rspc::Error> {
+    let (asset_objects, clusters) =
+        detect_duplicates(library, content_base, input.threshold).await?;
+    let asset_objects = asset_objects
+        .into_iter()
+        .map(|v| (v.hash.clone(), v))
+        .collect::<HashMap<_, _>>();
+    Ok(clusters
+        .into_iter()
+        .filter_map(|cluster| cluster_data(cluster, &asset_objects))
+        .collect())
+}
+
+/// 合并重复的素材，只保留一个
+/// 其他素材的 file path 都指向保留的素材，文件夹里的条目不变，
+/// 其他素材不再关联任何 file path，会被 delete_unlinked_assets 定时任务删除，包括文件、索引和 artifacts
+/// 只能合并检测出来的同一个 cluster 里的素材，避免误传的 hash 把无关的文件指向保留的素材
+pub async fn merge_duplicates(
+    library: &Library,
+    content_base: &ContentBase,
+    input: MergeDuplicatesPayload,
+) -> Result<String, rspc::Error> {
+    let (_, clusters) = detect_duplicates(library, content_base, input.threshold).await?;
+    let in_same_cluster = clusters.iter().any(|cluster| {
+        input
+            .asset_object_hashes
+            .iter()
+            .chain(input.keep_asset_object_hash.iter())
+            .all(|hash| cluster.file_identifiers.contains(hash))
+    });
+    if !in_same_cluster {
+        return Err(rspc::Error::new(
+            rspc::ErrorCode::BadRequest,
+            String::from("asset objects are not in the same duplicate cluster"),
+        ));
+    }
+
+    let asset_objects = library
+        .prisma_client()
+        .asset_object()
+        .find_many(vec![asset_object::hash::in_vec(
+            input.asset_object_hashes.clone(),
+        )])
+        .exec()
+        .await?;
+
+    let keep = match &input.keep_asset_object_hash {
+        Some(hash) => asset_objects.iter().find(|v| &v.hash == hash),
+        None => best_asset_object(asset_objects.iter()),
+    }
+    .ok_or_else(|| {
+        rspc::Error::new(
+            rspc::ErrorCode::BadRequest,
+            String::from("asset object to keep not found"),
+        )
+    })?;
+
+    let keep_id = keep.id;
+    let merged_ids = asset_objects
+        .iter()
+        .filter(|v| v.id != keep_id)
+        .map(|v| v.id)
+        .collect::<Vec<_>>();
+    if merged_ids.is_empty() {
+        return Ok(keep.hash.clone());
+    }
+
+    library
+        .prisma_client()
+        .file_path()
+        .update_many(
+            vec![file_path::asset_object_id::in_vec(merged_ids)],
+            vec![file_path::asset_object_id::set(Some(keep_id))],
+        )
+        .exec()
+        .await?;
+
+    Ok(keep.hash.clone())
+}
@@ -0,0 +1,240 @@
+use content_handler::get_image_exif;
+use content_library::Library;
+use content_metadata::ContentMetadata;
+use prisma_lib::asset_object;
+
+/// 从 ContentMetadata 里提取出来、写入 AssetObject 单独字段的技术参数
+/// mediaData 是 JSON 字符串，没法在 sqlite 里过滤和排序
+#[derive(Debug, Default, PartialEq)]
+pub struct MediaIndex {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub aspect_ratio: Option<f64>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<i32>,
+    pub frame_rate: Option<f64>,
+    pub has_audio: Option<bool>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+}
+
+/// bitRate 在 sqlite 里是 Int，超出 i32 范围的值不写入，不能截断成错误的值
+fn bit_rate(bit_rate: usize) -> Option<i32> {
+    i32::try_from(bit_rate).ok()
+}
+
+impl MediaIndex {
+    pub fn from_metadata(metadata: &ContentMetadata) -> Self {
+        let size = |width: usize, height: usize| {
+            let aspect_ratio = if height > 0 {
+                Some(width as f64 / height as f64)
+            } else {
+                None
+            };
+            (Some(width as i32), Some(height as i32), aspect_ratio)
+        };
+        match metadata {
+            ContentMetadata::Video(metadata) => {
+                let (width, height, aspect_ratio) = size(metadata.width, metadata.height);
+                let frame_rate = if metadata.avg_frame_rate.denominator > 0 {
+                    Some(
+                        metadata.avg_frame_rate.numerator as f64
+                            / metadata.avg_frame_rate.denominator as f64,
+                    )
+                } else {
+                    None
+                };
+                Self {
+                    width,
+                    height,
+                    aspect_ratio,
+                    duration: Some(metadata.duration),
+                    bit_rate: bit_rate(metadata.bit_rate),
+                    frame_rate,
+                    has_audio: Some(metadata.audio.is_some()),
+                    ..Default::default()
+                }
+            }
+            ContentMetadata::Audio(metadata) => Self {
+                duration: Some(metadata.duration),
+                bit_rate: bit_rate(metadata.bit_rate),
+                has_audio: Some(true),
+                ..Default::default()
+            },
+            ContentMetadata::Image(metadata) => {
+                let (width, height, aspect_ratio) =
+                    size(metadata.width as usize, metadata.height as usize);
+                let exif = metadata.exif.clone().unwrap_or_default();
+                Self {
+                    width,
+                    height,
+                    aspect_ratio,
+                    camera_make: exif.make,
+                    camera_model: exif.model,
+                    ..Default::default()
+                }
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// 所有字段都会写入，素材类型变了的时候旧的值会被清掉
+    pub fn to_params(self) -> Vec<asset_object::SetParam> {
+        vec![
+            asset_object::width::set(self.width),
+            asset_object::height::set(self.height),
+            asset_object::aspect_ratio::set(self.aspect_ratio),
+            asset_object::duration::set(self.duration),
+            asset_object::bit_rate::set(self.bit_rate),
+            asset_object::frame_rate::set(self.frame_rate),
+            asset_object::has_audio::set(self.has_audio),
+            asset_object::camera_make::set(self.camera_make),
+            asset_object::camera_model::set(self.camera_model),
+        ]
+    }
+}
+
+/// 给加上这些字段之前导入的素材补上技术参数，打开素材库的时候在后台执行
+/// - 只处理 width 和 duration 都是空的图片和音视频，文档和网页没有这些参数，不需要每次都检查
+/// - 图片之前没有读取 EXIF，这里从文件里重新读取，并更新 mediaData
+pub async fn backfill_media_index(library: &Library) {
+    let asset_object_data_list = match library
+        .prisma_client()
+        .asset_object()
+        .find_many(vec![
+            asset_object::media_data::not(None),
+            asset_object::width::equals(None),
+            asset_object::duration::equals(None),
+            prisma_client_rust::operator::or(
+                ["image/", "video/", "audio/"]
+                    .into_iter()
+                    .map(|v| asset_object::mime_type::starts_with(v.to_string()))
+                    .collect(),
+            ),
+        ])
+        .exec()
+        .await
+    {
+        Ok(asset_object_data_list) => asset_object_data_list,
+        Err(e) => {
+            tracing::error!(
+                task = "backfill media index",
+                "Failed to fetch assets: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    let mut count = 0;
+    for asset_object_data in asset_object_data_list {
+        let Some(mut metadata) = asset_object_data
+            .media_data
+            .as_deref()
+            .and_then(|v| serde_json::from_str::<ContentMetadata>(v).ok())
+        else {
+            continue;
+        };
+
+        let mut params = vec![];
+        if let ContentMetadata::Image(image_metadata) = &mut metadata {
+            if image_metadata.exif.is_none() {
+                let file_path = library.file_full_path_on_disk(&asset_object_data.hash);
+                image_metadata.exif = get_image_exif(file_path);
+                if image_metadata.exif.is_some() {
+                    if let Ok(metadata_json) = serde_json::to_string(&metadata) {
+                        params.push(asset_object::media_data::set(Some(metadata_json)));
+                    }
+                }
+            }
+        }
+
+        let media_index = MediaIndex::from_metadata(&metadata);
+        if media_index == MediaIndex::default() {
+            continue;
+        }
+        params.extend(media_index.to_params());
+        if let Err(e) = library
+            .prisma_client()
+            .asset_object()
+            .update(asset_object::id::equals(asset_object_data.id), params)
+            .exec()
+            .await
+        {
+            tracing::error!(
+                task = "backfill media index",
+                "Failed to update asset {}: {}",
+                asset_object_data.hash,
+                e
+            );
+            continue;
+        }
+        count += 1;
+    }
+
+    tracing::info!(task = "backfill media index", "{} assets updated", count);
+}
+
+#[cfg(test)]
+mod test {
+    use super::MediaIndex;
+    use content_metadata::{
+        audio::AudioMetadata,
+        image::{ImageExif, ImageMetadata},
+        video::{VideoAvgFrameRate, VideoMetadata},
+        ContentMetadata,
+    };
+
+    #[test]
+    fn test_media_index_from_metadata() {
+        let video = ContentMetadata::Video(VideoMetadata {
+            width: 1080,
+            height: 1920,
+            duration: 150.5,
+            bit_rate: 8_000_000,
+            avg_frame_rate: VideoAvgFrameRate {
+                numerator: 30000,
+                denominator: 1001,
+            },
+            audio: Some(AudioMetadata {
+                bit_rate: 128_000,
+                duration: 150.5,
+            }),
+        });
+        let index = MediaIndex::from_metadata(&video);
+        assert_eq!(index.width, Some(1080));
+        assert_eq!(index.height, Some(1920));
+        assert!(index.aspect_ratio.unwrap() < 1.0);
+        assert_eq!(index.duration, Some(150.5));
+        assert!((index.frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(index.has_audio, Some(true));
+        assert_eq!(index.bit_rate, Some(8_000_000));
+
+        // 超出 Int 范围的码率不写入
+        let audio = ContentMetadata::Audio(AudioMetadata {
+            bit_rate: 3_000_000_000,
+            duration: 10.0,
+        });
+        assert_eq!(MediaIndex::from_metadata(&audio).bit_rate, None);
+
+        let image = ContentMetadata::Image(ImageMetadata {
+            width: 6000,
+            height: 4000,
+            color: "RGB".to_string(),
+            exif: Some(ImageExif {
+                make: Some("Canon".to_string()),
+                model: Some("Canon EOS R5".to_string()),
+                taken_at: None,
+            }),
+        });
+        let index = MediaIndex::from_metadata(&image);
+        assert_eq!(index.aspect_ratio, Some(1.5));
+        assert_eq!(index.camera_make, Some("Canon".to_string()));
+        assert_eq!(index.duration, None);
+
+        assert_eq!(
+            MediaIndex::from_metadata(&ContentMetadata::Unknown),
+            MediaIndex::default()
+        );
+    }
+}
@@ -1,17 +1,25 @@
 mod artifacts;
+mod ask;
+mod chapters;
 mod create;
 mod delete;
+mod duplicate;
 mod read;
 mod update;
 mod utils;
 mod web_page;
 
+pub(crate) mod media_index;
 pub(crate) mod process;
 pub(super) mod types;
 
 use self::{
+    ask::{ask, AssetAskRequestPayload, AssetAskResult},
     create::{create_asset_object, create_dir},
     delete::delete_file_path,
+    duplicate::{
+        list_duplicates, merge_duplicates, DuplicatesRequestPayload, MergeDuplicatesPayload,
+    },
     process::{build_content_index, export_video_segment, process_asset_metadata},
     read::{get_file_path, list_file_path},
     types::{FilePathRequestPayload, FilePathWithAssetObjectData},
@@ -22,6 +30,7 @@ use crate::{validators, CtxWithLibrary};
 use rspc::{Router, RouterBuilder};
 use serde::Deserialize;
 use specta::Type;
+use tokio::sync::mpsc;
 
 pub fn get_routes<TCtx>() -> RouterBuilder<TCtx>
 where
@@ -326,5 +335,42 @@ where
                 }
             })
         })
+        .query("duplicates", |t| {
+            t(|ctx: TCtx, input: DuplicatesRequestPayload| async move {
+                let library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+                list_duplicates(&library, &content_base, input).await
+            })
+        })
+        .mutation("merge_duplicates", |t| {
+            t(|ctx: TCtx, input: MergeDuplicatesPayload| async move {
+                let library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+                merge_duplicates(&library, &content_base, input).await
+            })
+        })
+        .subscription("ask", |t| {
+            t(|ctx, input: AssetAskRequestPayload| {
+                let content_base = ctx.content_base().expect("content base is valid");
+                let ai_handler = ctx.ai_handler().expect("ai handler is valid");
+
+                async_stream::stream! {
+                    let (tx, mut rx) = mpsc::channel(512);
+
+                    tokio::spawn(async move {
+                        let error_tx = tx.clone();
+                        if let Err(e) = ask(&content_base, &ai_handler, input, tx).await {
+                            tracing::error!("asset ask error: {}", e);
+                            let _ = error_tx.send(AssetAskResult::Error(e.to_string())).await;
+                            let _ = error_tx.send(AssetAskResult::Done).await;
+                        }
+                    });
+
+                    while let Some(event) = rx.recv().await {
+                        yield event;
+                    }
+                }
+            })
+        })
         .merge("artifacts.", artifacts::get_routes::<TCtx>())
 }
@@ -1,3 +1,4 @@
+use super::media_index::MediaIndex;
 use crate::CtxWithLibrary;
 use content_base::{delete::DeletePayload, upsert::UpsertPayload, ContentBase, TaskStatus};
 use content_base_task::{
@@ -241,15 +242,18 @@ pub async fn process_asset_metadata(
         _ => None,
     };
 
+    let mut params = vec![
+        prisma_lib::asset_object::media_data::set(metadata_json),
+        prisma_lib::asset_object::mime_type::set(Some(mime)),
+    ];
+    params.extend(MediaIndex::from_metadata(&metadata).to_params());
+
     let prisma_client = library.prisma_client();
     let prisma_handle = prisma_client
         .asset_object()
         .update(
             prisma_lib::asset_object::id::equals(asset_object_data.id),
-            vec![
-                prisma_lib::asset_object::media_data::set(metadata_json),
-                prisma_lib::asset_object::mime_type::set(Some(mime)),
-            ],
+            params,
         )
         .exec();
 
@@ -80,6 +80,26 @@ where
                     AIModelCategory::MultiModalEmbedding => {
                         settings.models.multi_modal_embedding = payload.model_id;
                     }
+                    // 二次排序是可选的，传空字符串表示关闭
+                    AIModelCategory::TextRerank => {
+                        settings.models.text_rerank = match payload.model_id.as_str() {
+                            "" => None,
+                            _ => Some(payload.model_id),
+                        };
+                    }
+                    // 物体检测也是可选的，传空字符串表示关闭，之后新加的素材不再创建检测任务
+                    AIModelCategory::ObjectDetection => {
+                        settings.models.object_detection = match payload.model_id.as_str() {
+                            "" => None,
+                            _ => Some(payload.model_id),
+                        };
+                    }
+                    AIModelCategory::Ocr => {
+                        settings.models.ocr = match payload.model_id.as_str() {
+                            "" => None,
+                            _ => Some(payload.model_id),
+                        };
+                    }
                     _ => {}
                 }
 
@@ -87,34 +107,91 @@ where
 
                 // manually trigger model update in ai_handler
                 // this require to manipulate raw Mutex ai_handler
-                let ai_handler = ctx.ai_handler_mutex();
-                let mut ai_handler = ai_handler.lock().unwrap();
-                if let Some(ai_handler) = &mut *ai_handler {
-                    if let Err(res) = match payload.category {
-                        AIModelCategory::TextEmbedding => {
-                            ai_handler.rebuild_text_embedding_model(&ctx)
-                        }
-                        AIModelCategory::MultiModalEmbedding => {
-                            ai_handler.rebuild_multi_modal_embedding_model(&ctx)
-                        }
-                        AIModelCategory::ImageCaption => {
-                            ai_handler.rebuild_image_caption_model(&ctx)
+                {
+                    let ai_handler = ctx.ai_handler_mutex();
+                    let mut ai_handler = ai_handler.lock().unwrap();
+                    if let Some(ai_handler) = &mut *ai_handler {
+                        if let Err(res) = match payload.category {
+                            AIModelCategory::TextEmbedding => {
+                                ai_handler.rebuild_text_embedding_model(&ctx)
+                            }
+                            AIModelCategory::MultiModalEmbedding => {
+                                ai_handler.rebuild_multi_modal_embedding_model(&ctx)
+                            }
+                            AIModelCategory::ImageCaption => {
+                                ai_handler.rebuild_image_caption_model(&ctx)
+                            }
+                            AIModelCategory::AudioTranscript => {
+                                ai_handler.rebuild_audio_transcript_model(&ctx)
+                            }
+                            AIModelCategory::LLM => ai_handler.rebuild_llm_model(&ctx),
+                            AIModelCategory::TextRerank => {
+                                ai_handler.rebuild_text_rerank_model(&ctx)
+                            }
+                            AIModelCategory::ObjectDetection => {
+                                ai_handler.rebuild_object_detection_model(&ctx)
+                            }
+                            AIModelCategory::Ocr => ai_handler.rebuild_ocr_model(&ctx),
+                        } {
+                            return Err(rspc::Error::new(
+                                rspc::ErrorCode::InternalServerError,
+                                format!("Failed to rebuild model: {}", res),
+                            ));
                         }
-                        AIModelCategory::AudioTranscript => {
-                            ai_handler.rebuild_audio_transcript_model(&ctx)
-                        }
-                        AIModelCategory::LLM => ai_handler.rebuild_llm_model(&ctx),
-                    } {
-                        return Err(rspc::Error::new(
-                            rspc::ErrorCode::InternalServerError,
-                            format!("Failed to rebuild model: {}", res),
-                        ));
                     }
                 }
 
+                // content base 里的模型是创建的时候传进去的，需要换成新的 ai handler 里的模型
+                ctx.reload_content_base().await?;
+
+                // 只有 embedding 模型会影响向量索引，同步索引并在后台重新处理受影响的素材
+                if matches!(
+                    payload.category,
+                    AIModelCategory::TextEmbedding | AIModelCategory::MultiModalEmbedding
+                ) {
+                    // 没有完成的任务还在任务池里，不需要再触发一次
+                    ctx.sync_vector_indexes(false);
+                }
+
                 Ok(())
             })
         })
+        .query("reindex_status", |t| {
+            #[derive(Serialize, Type)]
+            #[serde(rename_all = "camelCase")]
+            struct ReindexStatusResult {
+                total: usize,
+                scheduled: usize,
+                failed: usize,
+                running: bool,
+                /// 已经提交但是还没有处理完的素材数量
+                processing: usize,
+            }
+            t(|ctx, _: ()| async move {
+                let library = ctx.library()?;
+                let Some(status) = ctx.reindex_status() else {
+                    return Ok(None);
+                };
+                let processing = library
+                    .prisma_client()
+                    .asset_object()
+                    .count(vec![
+                        prisma_lib::asset_object::hash::in_vec(status.hashes.clone()),
+                        prisma_lib::asset_object::tasks::some(vec![
+                            prisma_lib::file_handler_task::exit_code::equals(None),
+                        ]),
+                    ])
+                    .exec()
+                    .await? as usize;
+                Ok(Some(ReindexStatusResult {
+                    total: status.total,
+                    scheduled: status.scheduled,
+                    failed: status.failed,
+                    running: status.running,
+                    processing,
+                }))
+            })
+        })
         .mutation("download_model", |t| {
             #[derive(Deserialize, Type, Debug)]
             #[serde(rename_all = "camelCase")]
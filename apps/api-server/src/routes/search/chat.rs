@@ -0,0 +1,187 @@
+use super::{
+    citation::{find_citations, CitationData},
+    rag::{retrieve_messages_chat_references, ChatReference, ChatRole, RetrievalResultData},
+};
+use crate::CtxWithLibrary;
+use content_library::Library;
+use prisma_client_rust::chrono::{DateTime, FixedOffset};
+use prisma_lib::{chat_message, chat_session};
+use rspc::{Router, RouterBuilder};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSessionData {
+    pub id: i32,
+    pub title: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl From<chat_session::Data> for ChatSessionData {
+    fn from(value: chat_session::Data) -> Self {
+        Self {
+            id: value.id,
+            title: value.title,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessageData {
+    pub id: i32,
+    pub role: ChatRole,
+    pub content: String,
+    /// 用户消息改写以后实际用于检索的 query
+    pub retrieval_query: Option<String>,
+    /// 回答引用的检索结果，已经删除的素材不会返回
+    pub references: Vec<RetrievalResultData>,
+    /// 回答里的 [n] 引用标记对应的检索结果
+    pub citations: Vec<CitationData>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+async fn get_chat_session(library: &Library, id: i32) -> Result<chat_session::Data, rspc::Error> {
+    library
+        .prisma_client()
+        .chat_session()
+        .find_unique(chat_session::id::equals(id))
+        .exec()
+        .await?
+        .ok_or_else(|| {
+            rspc::Error::new(
+                rspc::ErrorCode::NotFound,
+                String::from("chat session not found"),
+            )
+        })
+}
+
+async fn list_chat_messages(
+    library: &Library,
+    session_id: i32,
+) -> Result<Vec<ChatMessageData>, rspc::Error> {
+    let messages = library
+        .prisma_client()
+        .chat_message()
+        .find_many(vec![chat_message::session_id::equals(session_id)])
+        .order_by(chat_message::created_at::order(
+            prisma_client_rust::Direction::Asc,
+        ))
+        .order_by(chat_message::id::order(prisma_client_rust::Direction::Asc))
+        .exec()
+        .await?;
+
+    let (messages, references_list): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .filter_map(|message| {
+            let Some(role) = ChatRole::parse(&message.role) else {
+                tracing::warn!("invalid chat message role: {}", &message.role);
+                return None;
+            };
+            // 解析失败的时候当作没有引用，不影响消息本身的读取
+            let references = message
+                .references
+                .as_deref()
+                .and_then(|v| serde_json::from_str::<Vec<ChatReference>>(v).ok())
+                .unwrap_or_default();
+            Some(((message, role), references))
+        })
+        .unzip();
+    // 所有消息的引用一起查询文件
+    let retrieved_list = retrieve_messages_chat_references(library, &references_list).await?;
+
+    Ok(messages
+        .into_iter()
+        .zip(references_list)
+        .zip(retrieved_list)
+        .map(
+            |(((message, role), references), retrieved)| ChatMessageData {
+                id: message.id,
+                role,
+                citations: find_citations(&references, &message.content),
+                content: message.content,
+                retrieval_query: message.retrieval_query,
+                references: retrieved,
+                created_at: message.created_at,
+            },
+        )
+        .collect())
+}
+
+pub fn get_routes<TCtx>() -> RouterBuilder<TCtx>
+where
+    TCtx: CtxWithLibrary + Clone + Send + Sync + 'static,
+{
+    Router::<TCtx>::new()
+        .query("list", |t| {
+            t(|ctx: TCtx, _input: ()| async move {
+                let library = ctx.library()?;
+                let res = library
+                    .prisma_client()
+                    .chat_session()
+                    .find_many(vec![])
+                    .order_by(chat_session::updated_at::order(
+                        prisma_client_rust::Direction::Desc,
+                    ))
+                    .exec()
+                    .await?;
+                Ok(res
+                    .into_iter()
+                    .map(ChatSessionData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("messages", |t| {
+            t(|ctx: TCtx, session_id: i32| async move {
+                let library = ctx.library()?;
+                get_chat_session(&library, session_id).await?;
+                list_chat_messages(&library, session_id).await
+            })
+        })
+        .mutation("rename", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct ChatSessionRenamePayload {
+                id: i32,
+                title: String,
+            }
+            t(|ctx: TCtx, input: ChatSessionRenamePayload| async move {
+                let title = input.title.trim().to_string();
+                if title.is_empty() {
+                    return Err(rspc::Error::new(
+                        rspc::ErrorCode::BadRequest,
+                        String::from("chat session title is empty"),
+                    ));
+                }
+                let library = ctx.library()?;
+                get_chat_session(&library, input.id).await?;
+                let res = library
+                    .prisma_client()
+                    .chat_session()
+                    .update(
+                        chat_session::id::equals(input.id),
+                        vec![chat_session::title::set(title)],
+                    )
+                    .exec()
+                    .await?;
+                Ok(ChatSessionData::from(res))
+            })
+        })
+        .mutation("delete", |t| {
+            t(|ctx: TCtx, id: i32| async move {
+                let library = ctx.library()?;
+                // 消息会随着对话级联删除
+                library
+                    .prisma_client()
+                    .chat_session()
+                    .delete(chat_session::id::equals(id))
+                    .exec()
+                    .await?;
+                Ok(())
+            })
+        })
+}
@@ -0,0 +1,100 @@
+use super::rag::ChatReference;
+use content_base::query::payload::ContentIndexMetadata;
+use serde::Serialize;
+use specta::Type;
+
+/// 引用标记的最大长度，超过这个长度的 `[` 不再当作引用标记等待后续片段
+const MAX_CITATION_MARKER_LEN: usize = 16;
+
+/// 回答里的 `[n]` 引用标记对应的检索结果，index 从 1 开始，和 prompt 里的 Document n 一致
+#[derive(Serialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationData {
+    pub index: u32,
+    pub file_identifier: String,
+    pub metadata: ContentIndexMetadata,
+}
+
+/// 校验引用的序号，只有放进了 prompt 的文档才能被引用
+pub(super) fn citation_data(references: &[ChatReference], index: usize) -> Option<CitationData> {
+    if index == 0 {
+        return None;
+    }
+    references
+        .get(index - 1)
+        .filter(|reference| !reference.reference_content.is_empty())
+        .map(|reference| CitationData {
+            index: index as u32,
+            file_identifier: reference.file_identifier.clone(),
+            metadata: reference.metadata.clone(),
+        })
+}
+
+/// 解析完整回答里引用到的文档，同一个文档只返回一次
+pub(super) fn find_citations(references: &[ChatReference], content: &str) -> Vec<CitationData> {
+    let mut citations: Vec<CitationData> = vec![];
+    for index in CitationParser::default().feed(content) {
+        if citations.iter().any(|v| v.index as usize == index) {
+            continue;
+        }
+        if let Some(citation) = citation_data(references, index) {
+            citations.push(citation);
+        }
+    }
+    citations
+}
+
+/// 从 LLM 的流式输出里解析 `[1]`、`[1, 2]` 形式的引用标记
+/// 一个标记可能被拆分在多个片段里，没有结束的标记会留到下一个片段继续解析
+#[derive(Default)]
+pub(super) struct CitationParser {
+    pending: String,
+}
+
+impl CitationParser {
+    pub fn feed(&mut self, chunk: &str) -> Vec<usize> {
+        let text = std::mem::take(&mut self.pending) + chunk;
+        let mut indices = vec![];
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('[') {
+            let marker = &rest[start + 1..];
+            match marker.find(|c: char| !(c.is_ascii_digit() || c == ',' || c == ' ')) {
+                Some(end) => {
+                    if marker[end..].starts_with(']') {
+                        indices.extend(
+                            marker[..end]
+                                .split(',')
+                                .filter_map(|v| v.trim().parse::<usize>().ok()),
+                        );
+                    }
+                    rest = &marker[end..];
+                }
+                None => {
+                    if marker.len() < MAX_CITATION_MARKER_LEN {
+                        self.pending = rest[start..].to_string();
+                    }
+                    break;
+                }
+            }
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CitationParser;
+
+    #[test]
+    fn test_citation_parser() {
+        let mut parser = CitationParser::default();
+        assert_eq!(parser.feed("视频里出现了猫[1]和狗 [2, 3]。"), vec![1, 2, 3]);
+        // markdown 链接和普通的方括号不是引用
+        assert!(parser.feed("见 [这里](https://example.com) [a]").is_empty());
+        // 标记被拆分在多个片段里
+        assert!(parser.feed("猫在睡觉[").is_empty());
+        assert!(parser.feed("1").is_empty());
+        assert_eq!(parser.feed("2][4]"), vec![12, 4]);
+        assert_eq!(parser.feed("[[5]"), vec![5]);
+    }
+}
@@ -0,0 +1,78 @@
+use super::search::{
+    resolve_search_filters, search_results_to_page, SearchFilters, SearchResultPage,
+};
+use content_base::{
+    query::{ContentImageQuery, ContentImageQueryPayload},
+    ContentBase,
+};
+use content_library::Library;
+use serde::Deserialize;
+use specta::Type;
+use std::path::PathBuf;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(tag = "sourceType")]
+pub enum ImageSearchSource {
+    /// 本地图片文件，比如用户选择上传的图片
+    #[serde(rename_all = "camelCase")]
+    LocalFile { local_full_path: String },
+    /// 库里已有的素材，视频需要指定画面的时间戳（毫秒）
+    #[serde(rename_all = "camelCase")]
+    Asset {
+        asset_object_hash: String,
+        #[specta(optional)]
+        timestamp: Option<i32>,
+    },
+}
+
+#[derive(Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSearchRequestPayload {
+    pub source: ImageSearchSource,
+    #[specta(optional)]
+    pub filters: Option<SearchFilters>,
+    #[specta(optional)]
+    pub offset: Option<u32>,
+    #[specta(optional)]
+    pub limit: Option<u32>,
+}
+
+pub async fn search_by_image(
+    library: &Library,
+    content_base: &ContentBase,
+    input: ImageSearchRequestPayload,
+) -> Result<SearchResultPage, rspc::Error> {
+    let filter = resolve_search_filters(library, input.filters).await?;
+    let offset = input.offset.unwrap_or(0);
+    let image = match input.source {
+        ImageSearchSource::LocalFile { local_full_path } => {
+            ContentImageQuery::File(PathBuf::from(local_full_path))
+        }
+        ImageSearchSource::Asset {
+            asset_object_hash,
+            timestamp,
+        } => ContentImageQuery::Asset {
+            file_identifier: asset_object_hash,
+            timestamp: timestamp.map(|v| v as i64),
+        },
+    };
+    let query_payload = ContentImageQueryPayload {
+        image,
+        max_count: input.limit.map(|v| v as usize),
+        offset: offset as usize,
+        filter,
+    };
+
+    let search_results = content_base
+        .query_by_image(query_payload)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to search by image: {}", e);
+            rspc::Error::new(
+                rspc::ErrorCode::InternalServerError,
+                format!("failed to search by image: {}", e),
+            )
+        })?;
+
+    search_results_to_page(library, search_results, offset).await
+}
@@ -0,0 +1,43 @@
+use super::search::SearchHitData;
+use content_base::ContentBase;
+use serde::Deserialize;
+use specta::Type;
+
+#[derive(Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InAssetSearchRequestPayload {
+    pub asset_object_hash: String,
+    pub text: String,
+}
+
+/// 在一个素材里搜索，返回的片段按时间戳或者 chunk 的位置排序，用于在时间轴上标记命中位置
+pub async fn search_in_asset(
+    content_base: &ContentBase,
+    input: InAssetSearchRequestPayload,
+) -> Result<Vec<SearchHitData>, rspc::Error> {
+    let search_results = content_base
+        .query_in_asset(&input.asset_object_hash, &input.text)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search in asset: {e}");
+            rspc::Error::new(
+                rspc::ErrorCode::InternalServerError,
+                format!("Failed to search in asset: {e}"),
+            )
+        })?;
+
+    let hits = search_results
+        .into_iter()
+        .filter_map(|result| {
+            Some(SearchHitData {
+                metadata: result.metadata,
+                score: result.score,
+                hit_reason: result.hit_reason?,
+                reference_content: result.reference_content.unwrap_or_default(),
+                search_hint: result.search_hint,
+                explain: result.explain,
+            })
+        })
+        .collect();
+    Ok(hits)
+}
@@ -1,18 +1,29 @@
+mod chat;
+mod citation;
+mod image;
+mod in_asset;
+mod packing;
 mod rag;
 mod recommend;
+mod saved;
 mod search;
+mod suggestion;
 
-use glob::glob;
+use image::{search_by_image, ImageSearchRequestPayload};
+use in_asset::{search_in_asset, InAssetSearchRequestPayload};
 use rag::{rag, RAGRequestPayload};
 use recommend::{recommend_frames, RecommendRequestPayload};
 use rspc::{Router, RouterBuilder};
-use search::{search_all, SearchRequestPayload};
-use storage::Storage;
-use storage::{EntryMode, S3Storage};
+use search::{search_all, search_grouped, SearchRequestPayload};
+use serde::Serialize;
+use specta::Type;
+use suggestion::{
+    clear_search_history, record_search_history, search_suggestions,
+    SearchSuggestionsRequestPayload,
+};
 use tokio::sync::mpsc;
 
-use super::storage::location::get_hash_from_url;
-use crate::{library::get_library_settings, CtxWithLibrary};
+use crate::CtxWithLibrary;
 
 pub fn get_routes<TCtx>() -> RouterBuilder<TCtx>
 where
@@ -23,9 +34,37 @@ where
             t(|ctx: TCtx, input: SearchRequestPayload| async move {
                 let library = ctx.library()?;
                 let content_base = ctx.content_base()?;
+                // 翻页不重复记录
+                if input.offset.unwrap_or(0) == 0 {
+                    record_search_history(&library, &input.text).await;
+                }
                 search_all(&library, &content_base, input).await
             })
         })
+        .query("grouped", |t| {
+            t(|ctx: TCtx, input: SearchRequestPayload| async move {
+                let library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+                // 翻页不重复记录
+                if input.offset.unwrap_or(0) == 0 {
+                    record_search_history(&library, &input.text).await;
+                }
+                search_grouped(&library, &content_base, input).await
+            })
+        })
+        .query("by_image", |t| {
+            t(|ctx: TCtx, input: ImageSearchRequestPayload| async move {
+                let library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+                search_by_image(&library, &content_base, input).await
+            })
+        })
+        .query("in_asset", |t| {
+            t(|ctx: TCtx, input: InAssetSearchRequestPayload| async move {
+                let content_base = ctx.content_base()?;
+                search_in_asset(&content_base, input).await
+            })
+        })
         .query("recommend", |t| {
             t(|ctx: TCtx, input: RecommendRequestPayload| async move {
                 let library = ctx.library()?;
@@ -40,111 +79,43 @@ where
             })
         })
         .query("suggestions", |t| {
-            t(|ctx: TCtx, _input: ()| async move {
-                let library = ctx.library()?;
-                // let asset_object_data_list = library
-                //     .prisma_client()
-                //     .asset_object()
-                //     .find_many(vec![])
-                //     .exec()
-                //     .await?;
-                // let captions = asset_object_data_list
-                //     .into_iter()
-                //     .filter_map(|asset_object_data| {
-                //         // let video_handler = VideoHandler::new(
-                //         //     &asset_object_data.hash, &library
-                //         // ).ok()?;
-                //         // video_handler.get_artifacts_settings().ok()?;
-                //         Some("".to_string())
-                //     })
-                //     .collect::<Vec<String>>();
-                // Search local
-                let pattern = format!(
-                    "{}/artifacts/*/*/frame-caption-*/*.json",
-                    library.dir.to_string_lossy()
-                );
-                let entries = glob(&pattern).map_err(|e| {
+            t(
+                |ctx: TCtx, input: SearchSuggestionsRequestPayload| async move {
+                    let library = ctx.library()?;
+                    let content_base = ctx.content_base()?;
+                    search_suggestions(&library, &content_base, input).await
+                },
+            )
+        })
+        .query("tags", |t| {
+            /// 素材库里所有的标签，按素材数量从多到少排序，用于按标签筛选
+            #[derive(Serialize, Type)]
+            #[serde(rename_all = "camelCase")]
+            struct TagData {
+                tag: String,
+                asset_count: u32,
+            }
+            t(|ctx: TCtx, _: Option<serde_json::Value>| async move {
+                let content_base = ctx.content_base()?;
+                let tags = content_base.list_tags().await.map_err(|e| {
                     rspc::Error::new(
                         rspc::ErrorCode::InternalServerError,
-                        format!("glob failed: {}", e),
+                        format!("failed to list tags: {}", e),
                     )
                 })?;
-                let mut already_seen = std::collections::HashSet::new();
-                let mut results = entries
+                Ok(tags
                     .into_iter()
-                    .filter_map(|entry| {
-                        let json_path = entry.ok()?;
-                        dbg!(&json_path);
-                        dbg!(get_hash_from_url(json_path.as_os_str().to_str()?));
-                        if let Some(hash) = get_hash_from_url(json_path.as_os_str().to_str()?) {
-                            already_seen.insert(hash);
-                        }
-                        let json_str = std::fs::read_to_string(&json_path).ok()?;
-                        let json_val = serde_json::from_str::<serde_json::Value>(&json_str).ok()?;
-                        let caption = json_val.get("caption")?.as_str()?;
-                        Some(caption.to_owned())
+                    .map(|v| TagData {
+                        tag: v.tag,
+                        asset_count: v.asset_count,
                     })
-                    .collect::<Vec<String>>();
-                // Search S3
-                // glob not support now https://github.com/apache/opendal/issues/1251
-                if let Ok(s3_config) = get_library_settings(&library.dir).s3_config.ok_or(()) {
-                    if let Ok(storage) = S3Storage::new(&library.id, s3_config) {
-                        if let Ok(op) = storage.op() {
-                            match op.list_with("artifacts").recursive(true).await {
-                                Ok(entries) => {
-                                    let mut s3_results = Vec::new();
-                                    for entry in entries {
-                                        if !entry.path().ends_with(".json")
-                                            || !(entry.path().contains("frame-caption-")
-                                                && !entry
-                                                    .path()
-                                                    .contains("frame-caption-embedding-"))
-                                        {
-                                            continue;
-                                        }
-
-                                        if let Some(hash) = get_hash_from_url(entry.path()) {
-                                            if already_seen.contains(&hash) {
-                                                continue;
-                                            }
-                                        }
-
-                                        match entry.metadata().mode() {
-                                            EntryMode::FILE => match op.read(entry.path()).await {
-                                                Ok(data) => {
-                                                    if let Ok(data) =
-                                                        String::from_utf8(data.to_vec())
-                                                    {
-                                                        serde_json::from_str::<serde_json::Value>(
-                                                            &data,
-                                                        )
-                                                        .ok()
-                                                        .map(|value| {
-                                                            value.get("caption").map(|v| {
-                                                                v.as_str().map(|s| {
-                                                                    s3_results.push(s.to_owned());
-                                                                })
-                                                            });
-                                                        });
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    tracing::error!("failed to read file: {}", e);
-                                                }
-                                            },
-                                            _ => continue,
-                                        }
-                                    }
-                                    results.extend(s3_results);
-                                }
-                                Err(e) => {
-                                    tracing::error!("failed to list s3 entries: {}", e);
-                                }
-                            }
-                        }
-                    }
-                }
-                Ok(results)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .mutation("clear_history", |t| {
+            t(|ctx: TCtx, _: Option<serde_json::Value>| async move {
+                let library = ctx.library()?;
+                clear_search_history(&library).await
             })
         })
         .subscription("rag", |t| {
@@ -170,4 +141,6 @@ where
                 };
             })
         })
+        .merge("saved.", saved::get_routes::<TCtx>())
+        .merge("rag_sessions.", chat::get_routes::<TCtx>())
 }
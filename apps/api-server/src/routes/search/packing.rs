@@ -0,0 +1,375 @@
+use super::rag::ChatReference;
+use content_base::{
+    query::payload::{video::VideoSliceType, ContentIndexMetadata},
+    ContentBase,
+};
+
+/// 每个文档在 prompt 里除了内容以外的开销，也就是 `Document n:` 这些
+const DOCUMENT_OVERHEAD_TOKENS: usize = 8;
+/// 剩余的预算少于这个数时，不再截断文档放进 prompt，太短的片段没有意义
+const MIN_TRUNCATED_TOKENS: usize = 64;
+/// 扩展上下文时前后各加几个相邻的分段
+const CONTEXT_EXPAND_RADIUS: usize = 1;
+
+/// 片段所在的位置，同一个素材里同一种来源的片段才能比较是否重叠
+/// 返回左闭右开的区间，文档的 chunk index 也转换成左闭右开
+fn segment_span(metadata: &ContentIndexMetadata) -> Option<(&'static str, i64, i64)> {
+    match metadata {
+        ContentIndexMetadata::Video(metadata) => {
+            let source = match metadata.slice_type {
+                VideoSliceType::Visual => "video_visual",
+                VideoSliceType::Audio => "video_audio",
+            };
+            Some((source, metadata.start_timestamp, metadata.end_timestamp))
+        }
+        ContentIndexMetadata::Audio(metadata) => {
+            Some(("audio", metadata.start_timestamp, metadata.end_timestamp))
+        }
+        ContentIndexMetadata::RawText(metadata) => Some((
+            "raw_text",
+            metadata.start_index as i64,
+            metadata.end_index as i64 + 1,
+        )),
+        ContentIndexMetadata::WebPage(metadata) => Some((
+            "web_page",
+            metadata.start_index as i64,
+            metadata.end_index as i64 + 1,
+        )),
+        ContentIndexMetadata::Image(_) => None,
+    }
+}
+
+/// 同一个素材里同一种来源的两个片段是否有重叠
+fn segments_overlap(
+    file_identifier_a: &str,
+    metadata_a: &ContentIndexMetadata,
+    file_identifier_b: &str,
+    metadata_b: &ContentIndexMetadata,
+) -> bool {
+    if file_identifier_a != file_identifier_b {
+        return false;
+    }
+    match (segment_span(metadata_a), segment_span(metadata_b)) {
+        (Some((source_a, start_a, end_a)), Some((source_b, start_b, end_b))) => {
+            source_a == source_b && start_a < end_b && start_b < end_a
+        }
+        _ => false,
+    }
+}
+
+/// 去掉和分数更高的片段重叠的片段，以及内容完全相同的片段
+/// references 需要已经按分数从高到低排序
+pub(super) fn dedupe_references(references: Vec<ChatReference>) -> Vec<ChatReference> {
+    let mut res: Vec<ChatReference> = vec![];
+    for reference in references {
+        let duplicated = res.iter().any(|v| {
+            (v.file_identifier == reference.file_identifier
+                && v.reference_content == reference.reference_content)
+                || segments_overlap(
+                    &v.file_identifier,
+                    &v.metadata,
+                    &reference.file_identifier,
+                    &reference.metadata,
+                )
+        });
+        if !duplicated {
+            res.push(reference);
+        }
+    }
+    res
+}
+
+/// 文档放进 prompt 以后占用的 token 数
+fn document_tokens(content: &str, count_tokens: &impl Fn(&str) -> usize) -> usize {
+    if content.is_empty() {
+        0
+    } else {
+        count_tokens(content) + DOCUMENT_OVERHEAD_TOKENS
+    }
+}
+
+/// 截断文本，保证不超过 max_tokens 个 token
+fn truncate_to_tokens(
+    text: &str,
+    max_tokens: usize,
+    count_tokens: &impl Fn(&str) -> usize,
+) -> String {
+    let boundaries = text
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(text.len()))
+        .collect::<Vec<_>>();
+    // 二分查找最长的符合预算的前缀
+    let (mut lo, mut hi) = (0, boundaries.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi + 1) / 2;
+        if count_tokens(&text[..boundaries[mid]]) <= max_tokens {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    text[..boundaries[lo]].to_string()
+}
+
+/// 按分数从高到低把文档放进预算，放不下的跳过
+/// 剩余的预算足够时，放不下的文档截断以后放进去，返回放进去的文档和占用的 token 数
+pub(super) fn pack_references(
+    references: Vec<ChatReference>,
+    budget: usize,
+    count_tokens: &impl Fn(&str) -> usize,
+) -> (Vec<ChatReference>, usize) {
+    let mut used = 0;
+    let mut res = vec![];
+    for mut reference in references {
+        let tokens = document_tokens(&reference.reference_content, count_tokens);
+        if used + tokens <= budget {
+            used += tokens;
+            res.push(reference);
+            continue;
+        }
+        let remaining = budget - used;
+        if remaining >= MIN_TRUNCATED_TOKENS + DOCUMENT_OVERHEAD_TOKENS {
+            reference.reference_content = truncate_to_tokens(
+                &reference.reference_content,
+                remaining - DOCUMENT_OVERHEAD_TOKENS,
+                count_tokens,
+            );
+            used += document_tokens(&reference.reference_content, count_tokens);
+            res.push(reference);
+        }
+    }
+    (res, used)
+}
+
+/// 预算还有剩余的时候，按分数从高到低给文档加上前后相邻的内容
+/// 扩展以后会和其他文档（包括已经扩展过的范围）重叠的不扩展，避免同样的内容在 prompt 里出现两次
+pub(super) async fn expand_references(
+    content_base: &ContentBase,
+    mut references: Vec<ChatReference>,
+    mut remaining: usize,
+    count_tokens: &impl Fn(&str) -> usize,
+) -> Vec<ChatReference> {
+    // 每个文档在 prompt 里实际覆盖的范围，扩展以后会变大
+    let mut covered = references
+        .iter()
+        .map(|v| v.metadata.clone())
+        .collect::<Vec<_>>();
+    for idx in 0..references.len() {
+        if remaining < MIN_TRUNCATED_TOKENS {
+            break;
+        }
+        let reference = &references[idx];
+        if reference.reference_content.is_empty() {
+            continue;
+        }
+        let expanded = match content_base
+            .expand_reference(
+                &reference.file_identifier,
+                &reference.metadata,
+                CONTEXT_EXPAND_RADIUS,
+            )
+            .await
+        {
+            Ok(Some(expanded)) => expanded,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("failed to expand reference: {}", e);
+                continue;
+            }
+        };
+        let overlapped =
+            references
+                .iter()
+                .zip(covered.iter())
+                .enumerate()
+                .any(|(i, (v, metadata))| {
+                    i != idx
+                        && segments_overlap(
+                            &v.file_identifier,
+                            metadata,
+                            &reference.file_identifier,
+                            &expanded.metadata,
+                        )
+                });
+        if overlapped {
+            continue;
+        }
+        let tokens = document_tokens(&reference.reference_content, count_tokens);
+        let expanded_tokens = document_tokens(&expanded.content, count_tokens);
+        if expanded_tokens > tokens + remaining {
+            continue;
+        }
+        remaining = remaining + tokens - expanded_tokens;
+        // metadata 保持命中的位置不变，引用跳转到的还是命中的片段
+        references[idx].reference_content = expanded.content;
+        covered[idx] = expanded.metadata;
+    }
+    references
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dedupe_references, pack_references, truncate_to_tokens, ChatReference};
+    use content_base::query::payload::{
+        audio::{AudioIndexMetadata, AudioSliceType},
+        ContentIndexMetadata,
+    };
+
+    #[test]
+    fn test_dedupe_references() {
+        let references = dedupe_references(vec![
+            ChatReference {
+                file_identifier: "a".to_string(),
+                metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                    slice_type: AudioSliceType::Transcript,
+                    start_timestamp: 0,
+                    end_timestamp: 1000,
+                }),
+                score: 1.0,
+                reference_content: "x".to_string(),
+            },
+            ChatReference {
+                file_identifier: "a".to_string(),
+                metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                    slice_type: AudioSliceType::Transcript,
+                    start_timestamp: 500,
+                    end_timestamp: 1500,
+                }),
+                score: 1.0,
+                reference_content: "y".to_string(),
+            },
+            ChatReference {
+                file_identifier: "a".to_string(),
+                metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                    slice_type: AudioSliceType::Transcript,
+                    start_timestamp: 1000,
+                    end_timestamp: 2000,
+                }),
+                score: 1.0,
+                reference_content: "z".to_string(),
+            },
+            ChatReference {
+                file_identifier: "b".to_string(),
+                metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                    slice_type: AudioSliceType::Transcript,
+                    start_timestamp: 0,
+                    end_timestamp: 1000,
+                }),
+                score: 1.0,
+                reference_content: "x".to_string(),
+            },
+            ChatReference {
+                file_identifier: "b".to_string(),
+                metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                    slice_type: AudioSliceType::Transcript,
+                    start_timestamp: 3000,
+                    end_timestamp: 4000,
+                }),
+                score: 1.0,
+                reference_content: "x".to_string(),
+            },
+        ]);
+        let spans = references
+            .iter()
+            .map(|v| {
+                (
+                    v.file_identifier.as_str(),
+                    v.metadata.segment_range().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![("a", (0, 1000)), ("a", (1000, 2000)), ("b", (0, 1000))]
+        );
+    }
+
+    #[test]
+    fn test_pack_references() {
+        let count_tokens = |text: &str| text.chars().count();
+        let long = "l".repeat(200);
+        let (packed, used) = pack_references(
+            vec![
+                ChatReference {
+                    file_identifier: "a".to_string(),
+                    metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                        slice_type: AudioSliceType::Transcript,
+                        start_timestamp: 0,
+                        end_timestamp: 1000,
+                    }),
+                    score: 1.0,
+                    reference_content: "a".repeat(50),
+                },
+                ChatReference {
+                    file_identifier: "b".to_string(),
+                    metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                        slice_type: AudioSliceType::Transcript,
+                        start_timestamp: 0,
+                        end_timestamp: 1000,
+                    }),
+                    score: 1.0,
+                    reference_content: long.clone(),
+                },
+                ChatReference {
+                    file_identifier: "c".to_string(),
+                    metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                        slice_type: AudioSliceType::Transcript,
+                        start_timestamp: 0,
+                        end_timestamp: 1000,
+                    }),
+                    score: 1.0,
+                    reference_content: "c".repeat(20),
+                },
+            ],
+            150,
+            &count_tokens,
+        );
+        // b 放不下，截断到剩余的预算，c 已经没有预算了
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[1].reference_content, "l".repeat(150 - 58 - 8));
+        assert_eq!(used, 150);
+
+        // 剩余的预算太少，放不下的文档直接跳过，后面更短的文档还可以放进去
+        let (packed, used) = pack_references(
+            vec![
+                ChatReference {
+                    file_identifier: "a".to_string(),
+                    metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                        slice_type: AudioSliceType::Transcript,
+                        start_timestamp: 0,
+                        end_timestamp: 1000,
+                    }),
+                    score: 1.0,
+                    reference_content: long,
+                },
+                ChatReference {
+                    file_identifier: "c".to_string(),
+                    metadata: ContentIndexMetadata::Audio(AudioIndexMetadata {
+                        slice_type: AudioSliceType::Transcript,
+                        start_timestamp: 0,
+                        end_timestamp: 1000,
+                    }),
+                    score: 1.0,
+                    reference_content: "c".to_string(),
+                },
+            ],
+            60,
+            &count_tokens,
+        );
+        assert_eq!(packed.len(), 1);
+        assert_eq!(packed[0].file_identifier, "c");
+        assert_eq!(used, 9);
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let count_tokens = |text: &str| text.chars().count();
+        assert_eq!(truncate_to_tokens("你好世界", 2, &count_tokens), "你好");
+        assert_eq!(
+            truncate_to_tokens("你好世界", 10, &count_tokens),
+            "你好世界"
+        );
+        assert_eq!(truncate_to_tokens("你好世界", 0, &count_tokens), "");
+    }
+}
@@ -1,19 +1,72 @@
-use super::search::retrieve_assets_for_search;
-use crate::{ai::AIHandler, routes::assets::types::FilePathWithAssetObjectData};
+use super::{
+    citation::{citation_data, CitationData, CitationParser},
+    packing::{dedupe_references, expand_references, pack_references},
+    search::{
+        resolve_search_filters, retrieve_assets_for_search, ContentQueryResultTrait, SearchFilters,
+    },
+};
+use crate::{ai::AIHandler, routes::assets::types::FilePathWithAssetObjectData, validators};
 use ai::llm::{LLMInferenceParams, LLMMessage};
 use content_base::{
-    query::{payload::ContentIndexMetadata, ContentQueryPayload},
+    query::{payload::ContentIndexMetadata, ContentQueryFilter, ContentQueryPayload},
     ContentBase,
 };
 use content_library::Library;
+use prisma_lib::{chat_message, chat_session};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::HashSet;
 use tokio::sync::mpsc::Sender;
 
+/// 放进 prompt 的历史消息的 token 上限，超出的更早的消息会被丢弃
+const HISTORY_TOKEN_BUDGET: usize = 2048;
+/// 历史消息最多占用输入预算的几分之一，剩下的留给参考文档
+const HISTORY_BUDGET_DIVISOR: usize = 4;
+/// 每条消息在 chat template 里的额外开销，比如角色标记
+const MESSAGE_OVERHEAD_TOKENS: usize = 16;
+
+const RAG_SYSTEM_PROMPT: &str = r#"You are an assistant good at answer questions according to some pieces from different document.
+You should try to answer user question according to the provided document pieces.
+Keep your answer ground in the facts of the DOCUMENT.
+Try to response in markdown, with proper title, subtitles and bullet points.
+
+If the DOCUMENT doesn't contain the facts to answer the QUESTION, you have 2 options:
+- If you know the answer, just response without these information.
+- Else, return "I don't know" in the question's language.
+
+When you use facts from a document, cite it right after the sentence with the document number in square brackets, for example [1] or [1, 2].
+Only cite the documents listed in the DOCUMENTS of the current question.
+
+You should answer in the language of the QUESTION.
+"#;
+/// 新对话用第一个问题作为标题，超出的部分截断
+const SESSION_TITLE_MAX_CHARS: usize = 50;
+
 #[derive(Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct RAGRequestPayload {
     pub query: String,
+    /// 不传的时候创建新的对话，新对话的 id 通过 RAGResult::Session 返回
+    #[specta(optional)]
+    pub session_id: Option<i32>,
+    /// 不传的时候在整个库里检索
+    #[specta(optional)]
+    pub scope: Option<RAGScope>,
+}
+
+/// RAG 的检索范围，只有范围内的素材会被放进 prompt
+#[derive(Deserialize, Type, Debug)]
+#[serde(tag = "scopeType")]
+pub enum RAGScope {
+    /// 指定的素材，也就是 asset_object 的 hash
+    #[serde(rename_all = "camelCase")]
+    Assets { asset_object_hashes: Vec<String> },
+    /// 文件夹及其子文件夹，格式和 FilePath.materializedPath 一致，比如 `/a/b/`
+    #[serde(rename_all = "camelCase")]
+    Folder {
+        #[serde(deserialize_with = "validators::materialized_path_string")]
+        materialized_path: String,
+    },
 }
 
 #[derive(Serialize, Type)]
@@ -28,12 +81,299 @@ pub struct RetrievalResultData {
 #[derive(Serialize, Type)]
 #[serde(tag = "resultType", content = "data")]
 pub enum RAGResult {
+    Session(i32),
     Reference(RetrievalResultData),
     Response(String),
+    Citation(CitationData),
     Error(String),
     Done,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "user" => Some(ChatRole::User),
+            "assistant" => Some(ChatRole::Assistant),
+            _ => None,
+        }
+    }
+}
+
+/// 保存在 ChatMessage.references 里的检索结果，读取的时候再查询对应的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ChatReference {
+    pub file_identifier: String,
+    pub metadata: ContentIndexMetadata,
+    pub score: f32,
+    pub reference_content: String,
+}
+
+impl ContentQueryResultTrait for ChatReference {
+    fn file_identifier(&self) -> &str {
+        &self.file_identifier
+    }
+
+    fn metadata(&self) -> &ContentIndexMetadata {
+        &self.metadata
+    }
+
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+/// 查询引用对应的文件，素材已经被删除的引用会被去掉，返回剩下的引用和对应的结果
+async fn retrieve_chat_references(
+    library: &Library,
+    references: &[ChatReference],
+) -> Result<Vec<(ChatReference, RetrievalResultData)>, rspc::Error> {
+    retrieve_assets_for_search(library, references, |item, file_path| {
+        (
+            item.clone(),
+            RetrievalResultData {
+                file_path: file_path.clone().into(),
+                metadata: item.metadata.clone(),
+                score: item.score,
+                reference_content: item.reference_content.clone(),
+            },
+        )
+    })
+    .await
+}
+
+/// 带上所属消息序号的引用，多条消息的引用一起查询文件时使用
+#[derive(Debug)]
+struct MessageChatReference<'a> {
+    message_index: usize,
+    reference: &'a ChatReference,
+}
+
+impl ContentQueryResultTrait for MessageChatReference<'_> {
+    fn file_identifier(&self) -> &str {
+        &self.reference.file_identifier
+    }
+
+    fn metadata(&self) -> &ContentIndexMetadata {
+        &self.reference.metadata
+    }
+
+    fn score(&self) -> f32 {
+        self.reference.score
+    }
+}
+
+/// 多条消息的引用只查询一次文件，返回的结果和 references_list 一一对应
+pub(super) async fn retrieve_messages_chat_references(
+    library: &Library,
+    references_list: &[Vec<ChatReference>],
+) -> Result<Vec<Vec<RetrievalResultData>>, rspc::Error> {
+    let references = references_list
+        .iter()
+        .enumerate()
+        .flat_map(|(message_index, references)| {
+            references
+                .iter()
+                .map(move |reference| MessageChatReference {
+                    message_index,
+                    reference,
+                })
+        })
+        .collect::<Vec<_>>();
+    let results = retrieve_assets_for_search(library, &references, |item, file_path| {
+        (
+            item.message_index,
+            RetrievalResultData {
+                file_path: file_path.clone().into(),
+                metadata: item.reference.metadata.clone(),
+                score: item.reference.score,
+                reference_content: item.reference.reference_content.clone(),
+            },
+        )
+    })
+    .await?;
+
+    let mut grouped = references_list.iter().map(|_| vec![]).collect::<Vec<_>>();
+    for (message_index, result) in results {
+        grouped[message_index].push(result);
+    }
+    Ok(grouped)
+}
+
+fn rag_user_prompt(reference_content: &str, query: &str) -> String {
+    format!(
+        r#"DOCUMENTS:
+{}
+
+QUESTION:
+{}
+"#,
+        reference_content, query
+    )
+}
+
+/// 把检索范围转换成 content base 的过滤条件，范围内没有素材的时候不会检索到任何内容
+async fn resolve_scope(
+    library: &Library,
+    scope: Option<RAGScope>,
+) -> anyhow::Result<ContentQueryFilter> {
+    let filter = match scope {
+        None => ContentQueryFilter::default(),
+        Some(RAGScope::Assets {
+            asset_object_hashes,
+        }) => ContentQueryFilter {
+            file_identifiers: Some(asset_object_hashes),
+            ..Default::default()
+        },
+        Some(RAGScope::Folder { materialized_path }) => {
+            let filters = SearchFilters {
+                materialized_path: Some(materialized_path),
+                ..Default::default()
+            };
+            resolve_search_filters(library, Some(filters)).await?
+        }
+    };
+    Ok(filter)
+}
+
+/// 从最近的消息往前取，直到超出 token 预算，返回的消息保持原来的顺序
+/// 历史需要从用户的提问开始，所以开头的回答也会被丢掉
+fn history_within_budget<'a>(
+    history: &'a [(ChatRole, String)],
+    budget: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> &'a [(ChatRole, String)] {
+    let mut used = 0;
+    let mut start = history.len();
+    while start > 0 {
+        let tokens = count_tokens(&history[start - 1].1);
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        start -= 1;
+    }
+    while start < history.len() && history[start].0 != ChatRole::User {
+        start += 1;
+    }
+    &history[start..]
+}
+
+async fn load_history(
+    library: &Library,
+    session_id: i32,
+) -> anyhow::Result<Vec<(ChatRole, String)>> {
+    let messages = library
+        .prisma_client()
+        .chat_message()
+        .find_many(vec![chat_message::session_id::equals(session_id)])
+        .order_by(chat_message::created_at::order(
+            prisma_client_rust::Direction::Asc,
+        ))
+        .order_by(chat_message::id::order(prisma_client_rust::Direction::Asc))
+        .exec()
+        .await?;
+    Ok(messages
+        .into_iter()
+        .filter_map(|message| ChatRole::parse(&message.role).map(|role| (role, message.content)))
+        .collect())
+}
+
+/// 把追问改写成不依赖上下文的问题，用于检索
+async fn rewrite_query(
+    ai_handler: &AIHandler,
+    history: &[(ChatRole, String)],
+    query: &str,
+) -> anyhow::Result<String> {
+    let system_prompt = r#"You are an assistant that rewrites a follow up question into a standalone question.
+Given the CONVERSATION and the FOLLOW UP QUESTION, rewrite the follow up question so that it can be understood without the conversation, and can be used to search documents.
+Keep the language of the FOLLOW UP QUESTION.
+Only return the rewritten question, without any explanation.
+"#;
+    let conversation = history
+        .iter()
+        .map(|(role, content)| match role {
+            ChatRole::User => format!("User: {}", content),
+            ChatRole::Assistant => format!("Assistant: {}", content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let user_prompt = format!(
+        r#"CONVERSATION:
+{}
+
+FOLLOW UP QUESTION:
+{}
+"#,
+        conversation, query
+    );
+
+    let mut response = ai_handler
+        .llm
+        .0
+        .process_single((
+            vec![
+                LLMMessage::new_system(system_prompt),
+                LLMMessage::new_user(user_prompt.as_str()),
+            ],
+            LLMInferenceParams::default(),
+        ))
+        .await?;
+    let rewritten = response.to_string().await?;
+    let rewritten = rewritten.trim();
+    if rewritten.is_empty() {
+        anyhow::bail!("rewritten query is empty");
+    }
+    Ok(rewritten.to_string())
+}
+
+async fn get_or_create_session(
+    library: &Library,
+    session_id: Option<i32>,
+    query: &str,
+) -> anyhow::Result<i32> {
+    match session_id {
+        Some(session_id) => {
+            library
+                .prisma_client()
+                .chat_session()
+                .find_unique(chat_session::id::equals(session_id))
+                .exec()
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("chat session not found: {}", session_id))?;
+            Ok(session_id)
+        }
+        None => {
+            let title = query
+                .trim()
+                .chars()
+                .take(SESSION_TITLE_MAX_CHARS)
+                .collect::<String>();
+            let session = library
+                .prisma_client()
+                .chat_session()
+                .create(vec![chat_session::title::set(title)])
+                .exec()
+                .await?;
+            Ok(session.id)
+        }
+    }
+}
+
 pub async fn rag(
     library: &Library,
     content_base: &ContentBase,
@@ -41,79 +381,183 @@ pub async fn rag(
     input: RAGRequestPayload,
     tx: Sender<RAGResult>,
 ) -> anyhow::Result<()> {
+    let session_id = get_or_create_session(library, input.session_id, &input.query).await?;
+    tx.send(RAGResult::Session(session_id)).await?;
+
+    let context = ai_handler.llm_context;
+    // 输入可以使用的 token 数，需要给回答预留空间
+    let input_budget = context
+        .context_window
+        .saturating_sub(context.max_output_tokens);
+    let count_tokens = |text: &str| ai_handler.count_text_tokens(text);
+
+    let history = load_history(library, session_id).await?;
+    let history = history_within_budget(
+        &history,
+        (input_budget / HISTORY_BUDGET_DIVISOR).min(HISTORY_TOKEN_BUDGET),
+        count_tokens,
+    );
+
+    // 第一轮对话不需要改写，改写失败的时候直接用原来的问题检索
+    let retrieval_query = if history.is_empty() {
+        input.query.clone()
+    } else {
+        match rewrite_query(ai_handler, history, &input.query).await {
+            Ok(query) => {
+                tracing::debug!("rewrite query {} to {}", &input.query, &query);
+                query
+            }
+            Err(e) => {
+                tracing::warn!("failed to rewrite query: {}", e);
+                input.query.clone()
+            }
+        }
+    };
+
     let query_payload = ContentQueryPayload {
-        query: input.query.clone(),
+        query: retrieval_query.clone(),
         with_hit_reason: false,
         with_reference_content: true,
+        filter: resolve_scope(library, input.scope).await?,
         ..Default::default()
     };
-    let retrieval_results = content_base.query(query_payload).await?;
-    let results = retrieve_assets_for_search(library, &retrieval_results, |item, file_path| {
-        RetrievalResultData {
-            file_path: file_path.clone().into(),
+    let retrieval_results = content_base.query(query_payload).await?.results;
+    let references = retrieval_results
+        .iter()
+        .map(|item| ChatReference {
+            file_identifier: item.file_identifier.clone(),
             metadata: item.metadata.clone(),
             score: item.score,
             reference_content: item.reference_content.clone().unwrap_or_default(),
-        }
-    })
-    .await?;
+        })
+        .collect::<Vec<_>>();
+
+    // 参考文档可以使用的 token 数，系统提示词、历史消息和问题占用的部分要先扣掉
+    let prompt_tokens = count_tokens(RAG_SYSTEM_PROMPT)
+        + count_tokens(&rag_user_prompt("", &input.query))
+        + history
+            .iter()
+            .map(|(_, content)| count_tokens(content))
+            .sum::<usize>()
+        + MESSAGE_OVERHEAD_TOKENS * (history.len() + 2);
+    let reference_budget = input_budget.saturating_sub(prompt_tokens);
+    let references = dedupe_references(references);
+    let (references, used) = pack_references(references, reference_budget, &count_tokens);
+    let references = expand_references(
+        content_base,
+        references,
+        reference_budget.saturating_sub(used),
+        &count_tokens,
+    )
+    .await;
 
+    // 没有内容的文档和素材已经被删除的文档先去掉再编号
+    // 只返回放进了 prompt 的文档，这样 Document n 和第 n 个 Reference 是对应的
+    let references = references
+        .into_iter()
+        .filter(|v| !v.reference_content.is_empty())
+        .collect::<Vec<_>>();
+    let (references, results): (Vec<_>, Vec<_>) = retrieve_chat_references(library, &references)
+        .await?
+        .into_iter()
+        .unzip();
     for ref_item in results.into_iter() {
         tx.send(RAGResult::Reference(ref_item)).await?;
     }
 
-    let references_content = retrieval_results
-        .iter()
-        .map(|item| item.reference_content.clone())
-        .collect::<Vec<_>>();
-
     let mut reference_content = String::new();
-    references_content
-        .iter()
-        .enumerate()
-        // 过滤掉没有内容的文档
-        .filter_map(|(idx, v)| v.as_ref().map(|content| (idx, content)))
-        .for_each(|(idx, v)| {
-            reference_content.push_str(&format!("Document {}:\n{}\n\n", idx + 1, v));
-        });
+    references.iter().enumerate().for_each(|(idx, v)| {
+        reference_content.push_str(&format!(
+            "Document {}:\n{}\n\n",
+            idx + 1,
+            v.reference_content
+        ));
+    });
+    let user_prompt = rag_user_prompt(&reference_content, &input.query);
 
-    let system_prompt = r#"You are an assistant good at answer questions according to some pieces from different document.
-You should try to answer user question according to the provided document pieces.
-Keep your answer ground in the facts of the DOCUMENT.
-Try to response in markdown, with proper title, subtitles and bullet points.
+    let mut messages = vec![LLMMessage::new_system(RAG_SYSTEM_PROMPT)];
+    history.iter().for_each(|(role, content)| match role {
+        ChatRole::User => messages.push(LLMMessage::new_user(content)),
+        ChatRole::Assistant => messages.push(LLMMessage::new_assistant(content)),
+    });
+    messages.push(LLMMessage::new_user(user_prompt.as_str()));
 
-If the DOCUMENT doesn't contain the facts to answer the QUESTION, you have 2 options:
-- If you know the answer, just response without these information.
-- Else, return "I don't know" in the question's language.
+    let mut answer = String::new();
+    let generated = stream_answer(ai_handler, messages, &references, &tx, &mut answer).await;
 
-You should answer in the language of the QUESTION.
-"#;
-    let user_prompt = format!(
-        r#"DOCUMENTS:
-{}
+    // 问题和回答一起保存，生成中途出错的时候保存已经生成的部分
+    // 一点回答都没有的时候都不保存，避免对话里留下没有回答的问题
+    if !answer.is_empty() {
+        let client = library.prisma_client();
+        client
+            ._batch(vec![
+                client.chat_message().create(
+                    chat_session::id::equals(session_id),
+                    ChatRole::User.as_str().to_string(),
+                    input.query.clone(),
+                    vec![chat_message::retrieval_query::set(Some(retrieval_query))],
+                ),
+                client.chat_message().create(
+                    chat_session::id::equals(session_id),
+                    ChatRole::Assistant.as_str().to_string(),
+                    answer,
+                    vec![chat_message::references::set(Some(serde_json::to_string(
+                        &references,
+                    )?))],
+                ),
+            ])
+            .await?;
+        client
+            .chat_session()
+            .update(
+                chat_session::id::equals(session_id),
+                vec![chat_session::updated_at::set(chrono::Utc::now().into())],
+            )
+            .exec()
+            .await?;
+    }
+    generated?;
 
-QUESTION:
-{}
-"#,
-        reference_content, &input.query
-    );
+    tx.send(RAGResult::Done).await?;
+
+    Ok(())
+}
 
+/// 流式生成回答，生成的内容追加到 answer 里，出错返回的时候 answer 里是已经生成的部分
+async fn stream_answer(
+    ai_handler: &AIHandler,
+    messages: Vec<LLMMessage>,
+    references: &[ChatReference],
+    tx: &Sender<RAGResult>,
+    answer: &mut String,
+) -> anyhow::Result<()> {
     let llm = ai_handler.llm.clone();
     let mut response = llm
         .0
         .process_single((
-            vec![
-                LLMMessage::new_system(system_prompt),
-                LLMMessage::new_user(user_prompt.as_str()),
-            ],
-            LLMInferenceParams::default(),
+            messages,
+            LLMInferenceParams::default().with_max_tokens(ai_handler.llm_context.max_output_tokens),
         ))
         .await?;
 
+    let mut citation_parser = CitationParser::default();
+    let mut cited_indices = HashSet::new();
     while let Some(content) = response.next().await {
         match content {
             Ok(Some(data)) => {
+                answer.push_str(&data);
+                let indices = citation_parser.feed(&data);
                 tx.send(RAGResult::Response(data)).await?;
+                // 同一个文档只发送一次引用，不存在的序号忽略
+                for index in indices {
+                    if !cited_indices.insert(index) {
+                        continue;
+                    }
+                    match citation_data(references, index) {
+                        Some(citation) => tx.send(RAGResult::Citation(citation)).await?,
+                        None => tracing::warn!("invalid citation index: {}", index),
+                    }
+                }
             }
             Ok(None) => {
                 break;
@@ -123,8 +567,34 @@ QUESTION:
             }
         }
     }
+    Ok(())
+}
 
-    tx.send(RAGResult::Done).await?;
+#[cfg(test)]
+mod test {
+    use super::{history_within_budget, ChatRole};
 
-    Ok(())
+    #[test]
+    fn test_history_within_budget() {
+        let history = vec![
+            (ChatRole::User, "aaaa".to_string()),
+            (ChatRole::Assistant, "bbbbbb".to_string()),
+            (ChatRole::User, "cc".to_string()),
+            (ChatRole::Assistant, "ddd".to_string()),
+        ];
+        let count_tokens = |text: &str| text.len();
+
+        assert_eq!(history_within_budget(&history, 100, count_tokens).len(), 4);
+        // 只放得下最后两条
+        assert_eq!(
+            history_within_budget(&history, 10, count_tokens),
+            &history[2..]
+        );
+        // 放得下最后三条，但是开头是回答，也要丢掉
+        assert_eq!(
+            history_within_budget(&history, 11, count_tokens),
+            &history[2..]
+        );
+        assert!(history_within_budget(&history, 2, count_tokens).is_empty());
+    }
 }
@@ -1,5 +1,7 @@
-use super::search::SearchResultData;
-use content_base::ContentBase;
+use super::search::{
+    retrieve_assets_for_search, search_result_data_from_query_result, SearchResultData,
+};
+use content_base::{query::RecommendVideoFramePayload, ContentBase};
 use content_library::Library;
 use serde::Deserialize;
 use specta::Type;
@@ -12,24 +14,31 @@ pub struct RecommendRequestPayload {
 }
 
 pub async fn recommend_frames(
-    _library: &Library,
-    _content_base: &ContentBase,
-    _asset_object_hash: &str,
-    _timestamp: i32,
+    library: &Library,
+    content_base: &ContentBase,
+    asset_object_hash: &str,
+    timestamp: i32,
 ) -> Result<Vec<SearchResultData>, rspc::Error> {
-    // let payload = RecommendVideoFramePayload::new(asset_object_hash, timestamp as i64);
-    // let search_results = content_base
-    //     .recommend_video_frame(payload)
-    //     .await
-    //     .map_err(|e| {
-    //         tracing::error!("Failed to recommend frames: {e}");
-    //         rspc::Error::new(
-    //             rspc::ErrorCode::InternalServerError,
-    //             format!("Failed to recommend frames: {e}"),
-    //         )
-    //     })?;
+    let payload = RecommendVideoFramePayload::new(asset_object_hash, timestamp as i64);
+    let search_results = content_base
+        .recommend_video_frame(payload)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to recommend frames: {e}");
+            rspc::Error::new(
+                rspc::ErrorCode::InternalServerError,
+                format!("Failed to recommend frames: {e}"),
+            )
+        })?;
 
-    // let result = retrieve_assets_for_search(library, search_results).await?;
-    // Ok(result)
-    todo!()
+    let result = retrieve_assets_for_search(
+        library,
+        &search_results,
+        search_result_data_from_query_result,
+    )
+    .await?
+    .into_iter()
+    .filter_map(|x| x)
+    .collect();
+    Ok(result)
 }
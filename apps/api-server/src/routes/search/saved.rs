@@ -0,0 +1,274 @@
+use super::{
+    image::{search_by_image, ImageSearchRequestPayload, ImageSearchSource},
+    search::{search_all, SearchFilters, SearchRequestPayload, SearchResultPage},
+};
+use crate::{validators, CtxWithLibrary};
+use content_base::ContentBase;
+use content_library::Library;
+use prisma_client_rust::chrono::{DateTime, FixedOffset};
+use prisma_lib::saved_search;
+use rspc::{Router, RouterBuilder};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchData {
+    pub id: i32,
+    pub name: String,
+    pub query: String,
+    pub filters: Option<SearchFilters>,
+    pub image_asset_object_hash: Option<String>,
+    /// 智能文件夹所在的目录，None 表示没有固定在目录树里
+    pub pinned_materialized_path: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl From<saved_search::Data> for SavedSearchData {
+    fn from(value: saved_search::Data) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            query: value.query,
+            // 解析失败的时候当作没有过滤条件，不影响其他字段的读取
+            filters: value
+                .filters
+                .and_then(|v| serde_json::from_str::<SearchFilters>(&v).ok()),
+            image_asset_object_hash: value.image_asset_object_hash,
+            pinned_materialized_path: value.pinned_materialized_path,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// 创建和更新都用这个结构，更新时所有字段整体覆盖
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchPayload {
+    pub name: String,
+    pub query: String,
+    #[specta(optional)]
+    pub filters: Option<SearchFilters>,
+    /// 有示例图片时按图片搜索，query 不再使用
+    #[specta(optional)]
+    pub image_asset_object_hash: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "validators::optional_materialized_path_string"
+    )]
+    #[specta(optional)]
+    pub pinned_materialized_path: Option<String>,
+}
+
+impl SavedSearchPayload {
+    fn validate(&self) -> Result<(), rspc::Error> {
+        if self.name.trim().is_empty() {
+            return Err(rspc::Error::new(
+                rspc::ErrorCode::BadRequest,
+                String::from("saved search name is empty"),
+            ));
+        }
+        if self.query.trim().is_empty() && self.image_asset_object_hash.is_none() {
+            return Err(rspc::Error::new(
+                rspc::ErrorCode::BadRequest,
+                String::from("saved search requires a query or an image example"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn to_params(&self) -> Result<Vec<saved_search::SetParam>, rspc::Error> {
+        let filters = match &self.filters {
+            Some(filters) => Some(serde_json::to_string(filters).map_err(|e| {
+                rspc::Error::new(
+                    rspc::ErrorCode::BadRequest,
+                    format!("failed to serialize filters: {}", e),
+                )
+            })?),
+            None => None,
+        };
+        Ok(vec![
+            saved_search::query::set(self.query.trim().to_string()),
+            saved_search::filters::set(filters),
+            saved_search::image_asset_object_hash::set(self.image_asset_object_hash.clone()),
+            saved_search::pinned_materialized_path::set(self.pinned_materialized_path.clone()),
+        ])
+    }
+}
+
+async fn get_saved_search(library: &Library, id: i32) -> Result<saved_search::Data, rspc::Error> {
+    library
+        .prisma_client()
+        .saved_search()
+        .find_unique(saved_search::id::equals(id))
+        .exec()
+        .await?
+        .ok_or_else(|| {
+            rspc::Error::new(
+                rspc::ErrorCode::NotFound,
+                String::from("saved search not found"),
+            )
+        })
+}
+
+/// 执行保存的搜索，返回实时的搜索结果，智能文件夹打开时调用
+pub async fn evaluate_saved_search(
+    library: &Library,
+    content_base: &ContentBase,
+    id: i32,
+    offset: Option<u32>,
+    limit: Option<u32>,
+) -> Result<SearchResultPage, rspc::Error> {
+    let saved_search = SavedSearchData::from(get_saved_search(library, id).await?);
+    match saved_search.image_asset_object_hash {
+        Some(asset_object_hash) => {
+            let input = ImageSearchRequestPayload {
+                source: ImageSearchSource::Asset {
+                    asset_object_hash,
+                    timestamp: None,
+                },
+                filters: saved_search.filters,
+                offset,
+                limit,
+            };
+            search_by_image(library, content_base, input).await
+        }
+        None => {
+            let input = SearchRequestPayload {
+                text: saved_search.query,
+                filters: saved_search.filters,
+                offset,
+                limit,
+                explain: None,
+                expand: None,
+                sort: None,
+            };
+            search_all(library, content_base, input).await
+        }
+    }
+}
+
+pub fn get_routes<TCtx>() -> RouterBuilder<TCtx>
+where
+    TCtx: CtxWithLibrary + Clone + Send + Sync + 'static,
+{
+    Router::<TCtx>::new()
+        .query("list", |t| {
+            t(|ctx: TCtx, _input: ()| async move {
+                let library = ctx.library()?;
+                let res = library
+                    .prisma_client()
+                    .saved_search()
+                    .find_many(vec![])
+                    .order_by(saved_search::created_at::order(
+                        prisma_client_rust::Direction::Desc,
+                    ))
+                    .exec()
+                    .await?;
+                Ok(res
+                    .into_iter()
+                    .map(SavedSearchData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("get", |t| {
+            t(|ctx: TCtx, id: i32| async move {
+                let library = ctx.library()?;
+                let res = get_saved_search(&library, id).await?;
+                Ok(SavedSearchData::from(res))
+            })
+        })
+        .query("smart_folders", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct SmartFoldersQueryPayload {
+                #[serde(deserialize_with = "validators::materialized_path_string")]
+                materialized_path: String,
+            }
+            // 和 assets.list 查询同一个目录，目录树里智能文件夹和普通文件夹放在一起显示
+            t(|ctx: TCtx, input: SmartFoldersQueryPayload| async move {
+                let library = ctx.library()?;
+                let res = library
+                    .prisma_client()
+                    .saved_search()
+                    .find_many(vec![saved_search::pinned_materialized_path::equals(Some(
+                        input.materialized_path,
+                    ))])
+                    .order_by(saved_search::name::order(
+                        prisma_client_rust::Direction::Asc,
+                    ))
+                    .exec()
+                    .await?;
+                Ok(res
+                    .into_iter()
+                    .map(SavedSearchData::from)
+                    .collect::<Vec<_>>())
+            })
+        })
+        .query("evaluate", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct SavedSearchEvaluatePayload {
+                id: i32,
+                #[specta(optional)]
+                offset: Option<u32>,
+                #[specta(optional)]
+                limit: Option<u32>,
+            }
+            t(|ctx: TCtx, input: SavedSearchEvaluatePayload| async move {
+                let library = ctx.library()?;
+                let content_base = ctx.content_base()?;
+                evaluate_saved_search(&library, &content_base, input.id, input.offset, input.limit)
+                    .await
+            })
+        })
+        .mutation("create", |t| {
+            t(|ctx: TCtx, input: SavedSearchPayload| async move {
+                input.validate()?;
+                let library = ctx.library()?;
+                let res = library
+                    .prisma_client()
+                    .saved_search()
+                    .create(input.name.trim().to_string(), input.to_params()?)
+                    .exec()
+                    .await?;
+                Ok(SavedSearchData::from(res))
+            })
+        })
+        .mutation("update", |t| {
+            #[derive(Deserialize, Type, Debug)]
+            #[serde(rename_all = "camelCase")]
+            struct SavedSearchUpdatePayload {
+                id: i32,
+                data: SavedSearchPayload,
+            }
+            t(|ctx: TCtx, input: SavedSearchUpdatePayload| async move {
+                input.data.validate()?;
+                let library = ctx.library()?;
+                get_saved_search(&library, input.id).await?;
+                let mut params = input.data.to_params()?;
+                params.push(saved_search::name::set(input.data.name.trim().to_string()));
+                let res = library
+                    .prisma_client()
+                    .saved_search()
+                    .update(saved_search::id::equals(input.id), params)
+                    .exec()
+                    .await?;
+                Ok(SavedSearchData::from(res))
+            })
+        })
+        .mutation("delete", |t| {
+            t(|ctx: TCtx, id: i32| async move {
+                let library = ctx.library()?;
+                library
+                    .prisma_client()
+                    .saved_search()
+                    .delete(saved_search::id::equals(id))
+                    .exec()
+                    .await?;
+                Ok(())
+            })
+        })
+}
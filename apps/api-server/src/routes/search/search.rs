@@ -1,19 +1,288 @@
-use crate::routes::assets::types::FilePathWithAssetObjectData;
+use crate::{library::get_library_settings, routes::assets::types::FilePathWithAssetObjectData};
 use content_base::{
     query::{
-        payload::{ContentIndexMetadata, ContentQueryHitReason, ContentQueryResult},
-        ContentQueryPayload,
+        parser::{MediaOrientation, ParsedQuery},
+        payload::{
+            ContentIndexMetadata, ContentQueryExplain, ContentQueryGroupedResult,
+            ContentQueryHitReason, ContentQueryPage, ContentQueryResult,
+        },
+        AssetCreatedAtLoader, AssetSortValuesLoader, ContentQueryFilter, ContentQueryPayload,
+        ContentQueryRankOptions, ContentQuerySort,
     },
     ContentBase,
 };
 use content_library::Library;
+use content_metadata::ContentType;
+use futures::FutureExt;
+use prisma_client_rust::chrono::{DateTime, FixedOffset, NaiveDate};
+use prisma_lib::{asset_object, file_path};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::{collections::HashMap, sync::Arc};
+
+/// 宽高比和 1 相差不超过这个值的画面算正方形
+const SQUARE_ASPECT_RATIO_TOLERANCE: f64 = 0.01;
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRangeFilter {
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberRangeFilter<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFilters {
+    pub content_types: Option<Vec<ContentType>>,
+    /// 只搜索这个文件夹及其子文件夹，格式和 FilePath.materializedPath 一致，比如 `/a/b/`
+    pub materialized_path: Option<String>,
+    pub created_at: Option<DateRangeFilter>,
+    pub updated_at: Option<DateRangeFilter>,
+    /// 文件大小，单位 byte
+    pub size: Option<NumberRangeFilter<i32>>,
+    /// 音视频时长，单位秒，没有时长的内容（图片、文档等）会被排除
+    pub duration: Option<NumberRangeFilter<f64>>,
+    /// 最低分辨率，按短边的像素数计算，比如 4K 是 2160
+    pub resolution: Option<u32>,
+    pub orientation: Option<MediaOrientation>,
+    /// 码率，单位 bit/s
+    pub bit_rate: Option<NumberRangeFilter<i32>>,
+    pub frame_rate: Option<NumberRangeFilter<f64>>,
+    /// 视频是否有音轨
+    pub has_audio: Option<bool>,
+    /// 照片的相机厂商或者型号，部分匹配
+    pub camera: Option<String>,
+    /// 画面里必须同时有这些物体，COCO 类别名，比如 dog、car
+    pub objects: Option<Vec<String>>,
+    /// 素材必须同时有这些标签
+    pub tags: Option<Vec<String>>,
+}
 
 #[derive(Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchRequestPayload {
     pub text: String,
+    #[specta(optional)]
+    pub filters: Option<SearchFilters>,
+    #[specta(optional)]
+    pub offset: Option<u32>,
+    #[specta(optional)]
+    pub limit: Option<u32>,
+    /// 返回每个结果的分数明细，用于调试排序
+    #[specta(optional)]
+    pub explain: Option<bool>,
+    /// 用 LLM 扩展查询（同义词、中英文翻译、画面描述），第一次搜索会比较慢，结果会缓存
+    #[specta(optional)]
+    pub expand: Option<bool>,
+    /// 不设置时按相关度排序
+    #[specta(optional)]
+    pub sort: Option<SearchSort>,
+}
+
+#[derive(Deserialize, Type, Debug, Clone, Copy)]
+pub enum SearchSortKey {
+    CreatedAt,
+    Size,
+    Duration,
+    /// 按像素数（宽 * 高）
+    Resolution,
+    BitRate,
+    FrameRate,
+}
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSort {
+    pub key: SearchSortKey,
+    /// 默认从大到小
+    #[specta(optional)]
+    pub descending: Option<bool>,
+}
+
+/// 把 SearchFilters 转换成 content base 的过滤条件
+/// 文件夹、时间、大小、时长这些条件在 prisma 里查询，得到符合条件的 file_identifier 列表
+pub(super) async fn resolve_search_filters(
+    library: &Library,
+    filters: Option<SearchFilters>,
+) -> Result<ContentQueryFilter, rspc::Error> {
+    let Some(filters) = filters else {
+        return Ok(ContentQueryFilter::default());
+    };
+
+    let mut where_params = vec![];
+    if let Some(materialized_path) = filters.materialized_path {
+        where_params.push(asset_object::file_paths::some(vec![
+            file_path::materialized_path::starts_with(materialized_path),
+        ]));
+    }
+    if let Some(created_at) = filters.created_at {
+        if let Some(from) = created_at.from {
+            where_params.push(asset_object::created_at::gte(from));
+        }
+        if let Some(to) = created_at.to {
+            where_params.push(asset_object::created_at::lte(to));
+        }
+    }
+    if let Some(updated_at) = filters.updated_at {
+        if let Some(from) = updated_at.from {
+            where_params.push(asset_object::updated_at::gte(from));
+        }
+        if let Some(to) = updated_at.to {
+            where_params.push(asset_object::updated_at::lte(to));
+        }
+    }
+    if let Some(size) = &filters.size {
+        if let Some(min) = size.min {
+            where_params.push(asset_object::size::gte(min));
+        }
+        if let Some(max) = size.max {
+            where_params.push(asset_object::size::lte(max));
+        }
+    }
+
+    // 技术参数在处理 metadata 的时候从 mediaData 里提取出来了，见 assets::media_index
+    if let Some(duration) = &filters.duration {
+        // 没有时长的内容（图片、文档等）排除掉
+        where_params.push(asset_object::duration::not(None));
+        if let Some(min) = duration.min {
+            where_params.push(asset_object::duration::gte(min));
+        }
+        if let Some(max) = duration.max {
+            where_params.push(asset_object::duration::lte(max));
+        }
+    }
+    if let Some(resolution) = filters.resolution {
+        // 短边不小于 resolution，也就是宽和高都不小于 resolution
+        where_params.push(asset_object::width::gte(resolution as i32));
+        where_params.push(asset_object::height::gte(resolution as i32));
+    }
+    if let Some(orientation) = filters.orientation {
+        // 宽高比是浮点数，1080x1079 这种差一两个像素的也算正方形
+        where_params.push(match orientation {
+            MediaOrientation::Landscape => {
+                asset_object::aspect_ratio::gt(1.0 + SQUARE_ASPECT_RATIO_TOLERANCE)
+            }
+            MediaOrientation::Portrait => {
+                asset_object::aspect_ratio::lt(1.0 - SQUARE_ASPECT_RATIO_TOLERANCE)
+            }
+            MediaOrientation::Square => prisma_client_rust::operator::and(vec![
+                asset_object::aspect_ratio::gte(1.0 - SQUARE_ASPECT_RATIO_TOLERANCE),
+                asset_object::aspect_ratio::lte(1.0 + SQUARE_ASPECT_RATIO_TOLERANCE),
+            ]),
+        });
+    }
+    if let Some(bit_rate) = &filters.bit_rate {
+        if let Some(min) = bit_rate.min {
+            where_params.push(asset_object::bit_rate::gte(min));
+        }
+        if let Some(max) = bit_rate.max {
+            where_params.push(asset_object::bit_rate::lte(max));
+        }
+    }
+    if let Some(frame_rate) = &filters.frame_rate {
+        if let Some(min) = frame_rate.min {
+            where_params.push(asset_object::frame_rate::gte(min));
+        }
+        if let Some(max) = frame_rate.max {
+            where_params.push(asset_object::frame_rate::lte(max));
+        }
+    }
+    if let Some(has_audio) = filters.has_audio {
+        where_params.push(asset_object::has_audio::equals(Some(has_audio)));
+    }
+    if let Some(camera) = filters.camera.as_ref().filter(|v| !v.trim().is_empty()) {
+        let camera = camera.trim().to_string();
+        where_params.push(prisma_client_rust::operator::or(vec![
+            asset_object::camera_make::contains(camera.clone()),
+            asset_object::camera_model::contains(camera),
+        ]));
+    }
+
+    let file_identifiers = if where_params.is_empty() {
+        None
+    } else {
+        let hashes = library
+            .prisma_client()
+            .asset_object()
+            .find_many(where_params)
+            .exec()
+            .await?
+            .into_iter()
+            .map(|asset_object_data| asset_object_data.hash)
+            .collect::<Vec<_>>();
+        Some(hashes)
+    };
+
+    Ok(ContentQueryFilter {
+        // 类型、物体和标签在 SurrealDB 里直接过滤，不需要先列出素材
+        content_types: filters.content_types,
+        file_identifiers,
+        objects: filters.objects.unwrap_or_default(),
+        tags: filters.tags.unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// 把搜索语法里的 in:、before:、after:、duration:、res: 这些条件合并到 SearchFilters 里
+/// 界面上已经设置了的条件优先
+fn merge_parsed_filters(
+    filters: Option<SearchFilters>,
+    parsed: &ParsedQuery,
+) -> Option<SearchFilters> {
+    let has_parsed_filters = parsed.materialized_path.is_some()
+        || parsed.before.is_some()
+        || parsed.after.is_some()
+        || parsed.duration.is_some()
+        || parsed.resolution.is_some()
+        || parsed.orientation.is_some()
+        || parsed.has_audio.is_some()
+        || parsed.camera.is_some();
+    if !has_parsed_filters {
+        return filters;
+    }
+
+    // 日期按 UTC 的 0 点计算
+    let to_datetime = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .map(|v| v.and_utc().fixed_offset())
+    };
+
+    let mut filters = filters.unwrap_or_default();
+    if filters.materialized_path.is_none() {
+        filters.materialized_path = parsed.materialized_path.clone();
+    }
+    if filters.created_at.is_none() && (parsed.before.is_some() || parsed.after.is_some()) {
+        filters.created_at = Some(DateRangeFilter {
+            from: parsed.after.and_then(to_datetime),
+            to: parsed.before.and_then(to_datetime),
+        });
+    }
+    if filters.duration.is_none() {
+        filters.duration = parsed.duration.as_ref().map(|v| NumberRangeFilter {
+            min: v.min,
+            max: v.max,
+        });
+    }
+    if filters.resolution.is_none() {
+        filters.resolution = parsed.resolution;
+    }
+    if filters.orientation.is_none() {
+        filters.orientation = parsed.orientation;
+    }
+    if filters.has_audio.is_none() {
+        filters.has_audio = parsed.has_audio;
+    }
+    if filters.camera.is_none() {
+        filters.camera = parsed.camera.clone();
+    }
+    Some(filters)
 }
 
 #[derive(Serialize, Type)]
@@ -25,19 +294,169 @@ pub struct SearchResultData {
     pub hit_reason: ContentQueryHitReason,
     pub reference_content: String,
     pub search_hint: String,
+    pub explain: Option<ContentQueryExplain>,
 }
 
-pub async fn search_all(
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultPage {
+    pub items: Vec<SearchResultData>,
+    /// 命中结果总数的估计值
+    pub total: u32,
+    pub offset: u32,
+}
+
+/// 一个素材里命中的片段
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHitData {
+    pub metadata: ContentIndexMetadata,
+    pub score: f32,
+    pub hit_reason: ContentQueryHitReason,
+    pub reference_content: String,
+    pub search_hint: String,
+    pub explain: Option<ContentQueryExplain>,
+}
+
+/// 按素材分组的搜索结果，hits 按照在素材里的位置排序
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchGroupedResultData {
+    pub file_path: FilePathWithAssetObjectData,
+    pub score: f32,
+    pub hits: Vec<SearchHitData>,
+}
+
+#[derive(Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchGroupedResultPage {
+    pub items: Vec<SearchGroupedResultData>,
+    /// 命中素材总数的估计值
+    pub total: u32,
+    pub offset: u32,
+}
+
+/// 解析搜索语法，合并过滤条件，生成 content base 的搜索参数
+async fn build_query_payload(
     library: &Library,
-    content_base: &ContentBase,
     input: SearchRequestPayload,
-) -> Result<Vec<SearchResultData>, rspc::Error> {
-    let query_payload = ContentQueryPayload {
-        query: input.text.clone(),
+) -> Result<ContentQueryPayload, rspc::Error> {
+    let parsed = ParsedQuery::parse(&input.text);
+    let filters = merge_parsed_filters(input.filters, &parsed);
+    let mut filter = resolve_search_filters(library, filters).await?;
+    parsed.apply_to_filter(&mut filter);
+    let rank = build_rank_options(library, input.explain.unwrap_or(false));
+    let sort = match input.sort {
+        Some(sort) => Some(build_sort(library, sort)),
+        None => None,
+    };
+    // 只有物体或者标签条件的时候用物体的类别名和标签搜索，比如 object:dog|car 搜索 "dog car"
+    let mut query = parsed.text();
+    if query.trim().is_empty() {
+        query = filter
+            .objects
+            .iter()
+            .chain(filter.tags.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    Ok(ContentQueryPayload {
+        query,
+        max_count: input.limit.map(|v| v as usize),
+        offset: input.offset.unwrap_or(0) as usize,
         with_hit_reason: true,
         with_reference_content: true,
-        ..Default::default()
+        filter,
+        mode: parsed.mode,
+        rank,
+        expand_query: input.expand.unwrap_or(false),
+        sort,
+    })
+}
+
+/// 排序用的属性在搜索以后从 sqlite 里读取，只查询命中的素材，没有这个属性的素材排在最后
+fn build_sort(library: &Library, sort: SearchSort) -> ContentQuerySort {
+    let prisma_client = library.prisma_client();
+    let key = sort.key;
+    let values: AssetSortValuesLoader = Arc::new(move |hashes| {
+        let prisma_client = prisma_client.clone();
+        async move {
+            let asset_objects = prisma_client
+                .asset_object()
+                .find_many(vec![asset_object::hash::in_vec(hashes)])
+                .exec()
+                .await?;
+            Ok::<_, anyhow::Error>(
+                asset_objects
+                    .into_iter()
+                    .filter_map(|v| {
+                        let value = match key {
+                            SearchSortKey::CreatedAt => {
+                                Some(v.created_at.timestamp_millis() as f64)
+                            }
+                            SearchSortKey::Size => Some(v.size as f64),
+                            SearchSortKey::Duration => v.duration,
+                            SearchSortKey::Resolution => match (v.width, v.height) {
+                                (Some(width), Some(height)) => Some(width as f64 * height as f64),
+                                _ => None,
+                            },
+                            SearchSortKey::BitRate => v.bit_rate.map(|v| v as f64),
+                            SearchSortKey::FrameRate => v.frame_rate,
+                        };
+                        value.map(|value| (v.hash, value))
+                    })
+                    .collect::<HashMap<_, _>>(),
+            )
+        }
+        .boxed()
+    });
+    ContentQuerySort {
+        values,
+        descending: sort.descending.unwrap_or(true),
+    }
+}
+
+/// 排序权重从 library 的设置里读取，设置了 recency 权重时在搜索以后查询命中素材的创建时间
+fn build_rank_options(library: &Library, explain: bool) -> ContentQueryRankOptions {
+    let weights = get_library_settings(&library.dir).rank_weights;
+    let asset_created_at = if weights.recency > 0.0 {
+        let prisma_client = library.prisma_client();
+        let load: AssetCreatedAtLoader = Arc::new(move |hashes| {
+            let prisma_client = prisma_client.clone();
+            async move {
+                let asset_objects = prisma_client
+                    .asset_object()
+                    .find_many(vec![asset_object::hash::in_vec(hashes)])
+                    .exec()
+                    .await?;
+                Ok::<_, anyhow::Error>(
+                    asset_objects
+                        .into_iter()
+                        .map(|v| (v.hash, v.created_at.timestamp_millis()))
+                        .collect::<HashMap<_, _>>(),
+                )
+            }
+            .boxed()
+        });
+        Some(load)
+    } else {
+        None
     };
+    ContentQueryRankOptions {
+        weights,
+        asset_created_at,
+        explain,
+    }
+}
+
+pub async fn search_all(
+    library: &Library,
+    content_base: &ContentBase,
+    input: SearchRequestPayload,
+) -> Result<SearchResultPage, rspc::Error> {
+    let offset = input.offset.unwrap_or(0);
+    let query_payload = build_query_payload(library, input).await?;
     let res = content_base.query(query_payload).await;
     // tracing::debug!("search result: {:?}", res);
 
@@ -52,25 +471,100 @@ pub async fn search_all(
         }
     };
 
-    let result = retrieve_assets_for_search(library, &search_results, |item, file_path| {
-        let hit_reason = match &item.hit_reason {
-            Some(hit_reason) => hit_reason.clone(),
-            None => return None,
-        };
-        Some(SearchResultData {
+    search_results_to_page(library, search_results, offset).await
+}
+
+pub(super) async fn search_results_to_page(
+    library: &Library,
+    search_results: ContentQueryPage,
+    offset: u32,
+) -> Result<SearchResultPage, rspc::Error> {
+    let result = retrieve_assets_for_search(
+        library,
+        &search_results.results,
+        search_result_data_from_query_result,
+    )
+    .await?
+    .into_iter()
+    .filter_map(|x| x)
+    .collect();
+    Ok(SearchResultPage {
+        items: result,
+        total: search_results.total as u32,
+        offset,
+    })
+}
+
+/// 一个素材一条结果，同一个素材的所有命中片段放在 hits 里
+pub async fn search_grouped(
+    library: &Library,
+    content_base: &ContentBase,
+    input: SearchRequestPayload,
+) -> Result<SearchGroupedResultPage, rspc::Error> {
+    let offset = input.offset.unwrap_or(0);
+    let query_payload = build_query_payload(library, input).await?;
+    let search_results = content_base
+        .query_grouped(query_payload)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to search grouped: {}", e);
+            rspc::Error::new(
+                rspc::ErrorCode::InternalServerError,
+                format!("failed to search grouped: {}", e),
+            )
+        })?;
+
+    let items = retrieve_assets_for_search(library, &search_results.results, |item, file_path| {
+        let hits = item
+            .hits
+            .iter()
+            .filter_map(|hit| {
+                Some(SearchHitData {
+                    metadata: hit.metadata.clone(),
+                    score: hit.score,
+                    hit_reason: hit.hit_reason.clone()?,
+                    reference_content: hit.reference_content.clone().unwrap_or_default(),
+                    search_hint: hit.search_hint.clone(),
+                    explain: hit.explain.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        SearchGroupedResultData {
             file_path: file_path.clone().into(),
-            metadata: item.metadata.clone(),
             score: item.score,
-            hit_reason,
-            reference_content: item.reference_content.clone().unwrap_or_default(),
-            search_hint: item.search_hint.clone(),
-        })
+            hits,
+        }
     })
     .await?
     .into_iter()
-    .filter_map(|x| x)
+    .filter(|item| !item.hits.is_empty())
     .collect();
-    Ok(result)
+
+    Ok(SearchGroupedResultPage {
+        items,
+        total: search_results.total as u32,
+        offset,
+    })
+}
+
+/// 没有 hit_reason 的结果不返回给前端
+pub(super) fn search_result_data_from_query_result(
+    item: &ContentQueryResult,
+    file_path: &file_path::Data,
+) -> Option<SearchResultData> {
+    let hit_reason = match &item.hit_reason {
+        Some(hit_reason) => hit_reason.clone(),
+        None => return None,
+    };
+    Some(SearchResultData {
+        file_path: file_path.clone().into(),
+        metadata: item.metadata.clone(),
+        score: item.score,
+        hit_reason,
+        reference_content: item.reference_content.clone().unwrap_or_default(),
+        search_hint: item.search_hint.clone(),
+        explain: item.explain.clone(),
+    })
 }
 
 /// 以下是 search 和 rag 共用的辅助函数，实现一个 trait 用于统一处理不同类型的搜索结果，目前只有一种类型
@@ -93,6 +587,24 @@ impl ContentQueryResultTrait for ContentQueryResult {
     }
 }
 
+impl ContentQueryResultTrait for ContentQueryGroupedResult {
+    fn file_identifier(&self) -> &str {
+        &self.file_identifier
+    }
+    /// 分组结果至少有一个片段，返回分数最高的片段
+    fn metadata(&self) -> &ContentIndexMetadata {
+        &self
+            .hits
+            .iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+            .expect("grouped result has at least one hit")
+            .metadata
+    }
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
 pub async fn retrieve_assets_for_search<TOriginal, TTarget, TFnConvert>(
     library: &Library,
     search_results: &[TOriginal],
@@ -0,0 +1,186 @@
+use content_base::ContentBase;
+use content_library::Library;
+use prisma_lib::search_history;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashSet;
+
+const DEFAULT_SUGGESTION_COUNT: usize = 10;
+/// 历史记录按前缀匹配以后最多取这么多条参与排序
+const MAX_HISTORY_CANDIDATES: i64 = 200;
+/// 历史记录的热度半衰期，30 天前搜索过的记录权重减半
+const HISTORY_HALF_LIFE_DAYS: f64 = 30.0;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSuggestionsRequestPayload {
+    /// 用户当前输入的完整内容
+    pub text: String,
+    #[specta(optional)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Type, Debug, Clone, PartialEq)]
+pub enum SearchSuggestionSource {
+    /// 搜索历史
+    History,
+    /// 索引里的词，用来补全最后一个词
+    Term,
+}
+
+#[derive(Serialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSuggestion {
+    /// 补全以后的完整搜索内容
+    pub text: String,
+    pub source: SearchSuggestionSource,
+    /// 历史记录是搜索次数，词是包含这个词的素材数量
+    pub count: u32,
+}
+
+/// 记录一次搜索，出错不影响搜索本身
+pub async fn record_search_history(library: &Library, text: &str) {
+    let query = text.trim().to_string();
+    if query.is_empty() {
+        return;
+    }
+    let res = library
+        .prisma_client()
+        .search_history()
+        .upsert(
+            search_history::query::equals(query.clone()),
+            search_history::create(query, vec![]),
+            vec![
+                search_history::count::increment(1),
+                search_history::last_searched_at::set(chrono::Utc::now().into()),
+            ],
+        )
+        .exec()
+        .await;
+    if let Err(e) = res {
+        tracing::error!("failed to record search history: {}", e);
+    }
+}
+
+pub async fn clear_search_history(library: &Library) -> Result<(), rspc::Error> {
+    library
+        .prisma_client()
+        .search_history()
+        .delete_many(vec![])
+        .exec()
+        .await?;
+    Ok(())
+}
+
+/// 历史记录的热度，搜索次数按最后一次搜索的时间衰减
+fn history_score(data: &search_history::Data, now: chrono::DateTime<chrono::Utc>) -> f64 {
+    let days = (now - data.last_searched_at.with_timezone(&chrono::Utc))
+        .num_seconds()
+        .max(0) as f64
+        / 86400.0;
+    data.count as f64 * 0.5_f64.powf(days / HISTORY_HALF_LIFE_DAYS)
+}
+
+/// 输入框里最后一个需要补全的词，以及它前面的内容
+/// 搜索语法（带冒号的条件、引号、排除）不补全
+fn split_last_word(text: &str) -> Option<(&str, &str)> {
+    if text.ends_with(char::is_whitespace) {
+        return None;
+    }
+    let (head, last) = match text.rfind(char::is_whitespace) {
+        Some(index) => text.split_at(index + 1),
+        None => ("", text),
+    };
+    if last.is_empty() || last.contains(':') || last.contains('"') || last.starts_with('-') {
+        return None;
+    }
+    Some((head, last))
+}
+
+/// 搜索补全，先匹配搜索历史，再用索引里的词补全最后一个词
+/// 输入为空时返回最常用的搜索历史
+pub async fn search_suggestions(
+    library: &Library,
+    content_base: &ContentBase,
+    input: SearchSuggestionsRequestPayload,
+) -> Result<Vec<SearchSuggestion>, rspc::Error> {
+    let limit = input
+        .limit
+        .map(|v| v as usize)
+        .unwrap_or(DEFAULT_SUGGESTION_COUNT);
+    let text = input.text.trim_start();
+
+    let where_params = if text.is_empty() {
+        vec![]
+    } else {
+        vec![search_history::query::starts_with(text.to_string())]
+    };
+    let history = library
+        .prisma_client()
+        .search_history()
+        .find_many(where_params)
+        .order_by(search_history::last_searched_at::order(
+            prisma_client_rust::Direction::Desc,
+        ))
+        .take(MAX_HISTORY_CANDIDATES)
+        .exec()
+        .await?;
+    let now = chrono::Utc::now();
+    let mut history = history
+        .into_iter()
+        .map(|v| (history_score(&v, now), v))
+        .collect::<Vec<_>>();
+    history.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut seen = HashSet::new();
+    let mut suggestions = history
+        .into_iter()
+        .filter(|(_, v)| v.query != text && seen.insert(v.query.clone()))
+        .take(limit)
+        .map(|(_, v)| SearchSuggestion {
+            text: v.query,
+            source: SearchSuggestionSource::History,
+            count: v.count.max(0) as u32,
+        })
+        .collect::<Vec<_>>();
+
+    if suggestions.len() < limit {
+        if let Some((head, last)) = split_last_word(text) {
+            let terms = content_base.suggest_terms(last, limit).await.map_err(|e| {
+                rspc::Error::new(
+                    rspc::ErrorCode::InternalServerError,
+                    format!("failed to suggest terms: {}", e),
+                )
+            })?;
+            let term_suggestions = terms
+                .into_iter()
+                .map(|v| SearchSuggestion {
+                    text: format!("{}{}", head, v.term),
+                    source: SearchSuggestionSource::Term,
+                    count: v.asset_count,
+                })
+                .filter(|v| v.text != text && seen.insert(v.text.clone()))
+                .take(limit - suggestions.len())
+                .collect::<Vec<_>>();
+            suggestions.extend(term_suggestions);
+        }
+    }
+
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_last_word;
+
+    #[test]
+    fn test_split_last_word() {
+        assert_eq!(split_last_word("视频剪"), Some(("", "视频剪")));
+        assert_eq!(split_last_word("red car"), Some(("red ", "car")));
+        assert_eq!(split_last_word("red "), None);
+        assert_eq!(split_last_word("red type:vid"), None);
+        assert_eq!(split_last_word("red -car"), None);
+        assert_eq!(split_last_word("\"red car"), Some(("\"red ", "car")));
+        assert_eq!(split_last_word("\"red car\""), None);
+    }
+}
@@ -18,6 +18,15 @@ pub enum VideoTaskTypeSpecta {
     TransChunk,
     TransChunkSum,
     TransChunkSumEmbed,
+    TransSum,
+    PerceptualHash,
+    FrameObjectDetection,
+    FrameOcr,
+    FrameOcrEmbed,
+    Chapter,
+    ChapterEmbed,
+    Tags,
+    VisualTags,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
@@ -29,6 +38,8 @@ pub enum AudioTaskTypeSpecta {
     TransChunk,
     TransChunkSum,
     TransChunkSumEmbed,
+    TransSum,
+    Tags,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
@@ -38,6 +49,11 @@ pub enum ImageTaskTypeSpecta {
     Embedding,
     Description,
     DescEmbed,
+    PerceptualHash,
+    ObjectDetection,
+    Ocr,
+    OcrEmbed,
+    Tags,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
@@ -46,6 +62,8 @@ pub enum RawTextTaskTypeSpecta {
     Chunk,
     ChunkSum,
     ChunkSumEmbed,
+    Sum,
+    Tags,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
@@ -55,6 +73,8 @@ pub enum WebPageTaskTypeSpecta {
     Chunk,
     ChunkSum,
     ChunkSumEmbed,
+    Sum,
+    Tags,
 }
 
 #[derive(Clone, Debug, Type, Serialize, Deserialize)]
@@ -97,6 +117,31 @@ impl From<ContentTaskType> for ContentTaskTypeSpecta {
                 VideoTaskType::TransChunkSumEmbed(_) => {
                     ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::TransChunkSumEmbed)
                 }
+                VideoTaskType::TransSum(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::TransSum)
+                }
+                VideoTaskType::PerceptualHash(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::PerceptualHash)
+                }
+                VideoTaskType::FrameObjectDetection(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::FrameObjectDetection)
+                }
+                VideoTaskType::FrameOcr(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::FrameOcr)
+                }
+                VideoTaskType::FrameOcrEmbed(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::FrameOcrEmbed)
+                }
+                VideoTaskType::Chapter(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::Chapter)
+                }
+                VideoTaskType::ChapterEmbed(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::ChapterEmbed)
+                }
+                VideoTaskType::Tags(_) => ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::Tags),
+                VideoTaskType::VisualTags(_) => {
+                    ContentTaskTypeSpecta::Video(VideoTaskTypeSpecta::VisualTags)
+                }
             },
             ContentTaskType::Audio(t) => match t {
                 AudioTaskType::Thumbnail(_) => {
@@ -117,6 +162,10 @@ impl From<ContentTaskType> for ContentTaskTypeSpecta {
                 AudioTaskType::TransChunkSumEmbed(_) => {
                     ContentTaskTypeSpecta::Audio(AudioTaskTypeSpecta::TransChunkSumEmbed)
                 }
+                AudioTaskType::TransSum(_) => {
+                    ContentTaskTypeSpecta::Audio(AudioTaskTypeSpecta::TransSum)
+                }
+                AudioTaskType::Tags(_) => ContentTaskTypeSpecta::Audio(AudioTaskTypeSpecta::Tags),
             },
             ContentTaskType::Image(t) => match t {
                 ImageTaskType::Thumbnail(_) => {
@@ -131,6 +180,17 @@ impl From<ContentTaskType> for ContentTaskTypeSpecta {
                 ImageTaskType::DescEmbed(_) => {
                     ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::DescEmbed)
                 }
+                ImageTaskType::PerceptualHash(_) => {
+                    ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::PerceptualHash)
+                }
+                ImageTaskType::ObjectDetection(_) => {
+                    ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::ObjectDetection)
+                }
+                ImageTaskType::Ocr(_) => ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::Ocr),
+                ImageTaskType::OcrEmbed(_) => {
+                    ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::OcrEmbed)
+                }
+                ImageTaskType::Tags(_) => ContentTaskTypeSpecta::Image(ImageTaskTypeSpecta::Tags),
             },
             ContentTaskType::RawText(t) => match t {
                 RawTextTaskType::Chunk(_) => {
@@ -142,6 +202,12 @@ impl From<ContentTaskType> for ContentTaskTypeSpecta {
                 RawTextTaskType::ChunkSumEmbed(_) => {
                     ContentTaskTypeSpecta::RawText(RawTextTaskTypeSpecta::ChunkSumEmbed)
                 }
+                RawTextTaskType::Sum(_) => {
+                    ContentTaskTypeSpecta::RawText(RawTextTaskTypeSpecta::Sum)
+                }
+                RawTextTaskType::Tags(_) => {
+                    ContentTaskTypeSpecta::RawText(RawTextTaskTypeSpecta::Tags)
+                }
             },
             ContentTaskType::WebPage(t) => match t {
                 WebPageTaskType::Transform(_) => {
@@ -156,6 +222,12 @@ impl From<ContentTaskType> for ContentTaskTypeSpecta {
                 WebPageTaskType::ChunkSumEmbed(_) => {
                     ContentTaskTypeSpecta::WebPage(WebPageTaskTypeSpecta::ChunkSumEmbed)
                 }
+                WebPageTaskType::Sum(_) => {
+                    ContentTaskTypeSpecta::WebPage(WebPageTaskTypeSpecta::Sum)
+                }
+                WebPageTaskType::Tags(_) => {
+                    ContentTaskTypeSpecta::WebPage(WebPageTaskTypeSpecta::Tags)
+                }
             },
         }
     }
@@ -1,4 +1,5 @@
 use serde::de::{self, Deserializer, Unexpected, Visitor};
+use serde::Deserialize;
 use std::fmt;
 
 struct MaterializedPathString;
@@ -34,6 +35,19 @@ where
     deserializer.deserialize_str(MaterializedPathString)
 }
 
+/// 可以为空的 materialized path，需要配合 `#[serde(default)]` 使用
+pub fn optional_materialized_path_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "materialized_path_string")] String);
+    let value = Option::<Wrapper>::deserialize(deserializer)?;
+    Ok(value.map(|Wrapper(v)| v))
+}
+
 struct PathNameString;
 
 impl<'de> Visitor<'de> for PathNameString {
@@ -27,9 +27,7 @@ function withFindSimilarExplorerItem(BaseComponent: BaseContextMenuItem) {
                 filePath: 'filePaths' in item ? item.filePaths[0] : void 0,
               })
             },
-            // FIXME disable find similar items for now, because search.recommend is not implemented for now
-            // true,
-            false,
+            true,
           ] as const
         })
         .otherwise(() => {
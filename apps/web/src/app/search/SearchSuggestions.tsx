@@ -3,7 +3,7 @@ import Icon from '@gendam/ui/icons'
 import { useMemo, useState } from 'react'
 
 export default function SearchSuggestions({ onSelectText }: { onSelectText: (text: string) => void }) {
-  const suggestionsQuery = rspc.useQuery(['search.suggestions'])
+  const suggestionsQuery = rspc.useQuery(['search.suggestions', { text: '' }])
   const [suggestSeed, setSuggestSeed] = useState(0)
   const pickedSuggestions = useMemo(() => {
     // shuffle pick 5 suggestions
@@ -26,8 +26,8 @@ export default function SearchSuggestions({ onSelectText }: { onSelectText: (tex
     <>
       <div className="text-ink/50 mb-2 text-xs">
         {pickedSuggestions.map((suggestion, index) => (
-          <div key={index} className="py-1 text-center hover:underline" onClick={() => onSelectText(suggestion)}>
-            &quot;{suggestion}&quot;
+          <div key={index} className="py-1 text-center hover:underline" onClick={() => onSelectText(suggestion.text)}>
+            &quot;{suggestion.text}&quot;
           </div>
         ))}
       </div>
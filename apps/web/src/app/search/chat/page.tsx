@@ -2,6 +2,7 @@
 
 import Viewport from '@/components/Viewport'
 import { ExtractExplorerItem } from '@/Explorer/types'
+import { CitationData } from '@/lib/bindings'
 import { rspc } from '@/lib/rspc'
 import Icon from '@gendam/ui/icons'
 import { Button } from '@gendam/ui/v2/button'
@@ -22,6 +23,7 @@ export default function TestChatPage() {
   const [text, setText] = useState('')
   const [response, setResponse] = useState('')
   const [referenceList, setReferenceList] = useState<ExtractExplorerItem<'RetrievalResult'>[]>([])
+  const [citations, setCitations] = useState<CitationData[]>([])
   const [errorMessage, setErrorMessage] = useState<string | undefined>(void 0)
   const [responseState, setResponseState] = useState<ResponseState>(ResponseState.INIT)
 
@@ -35,6 +37,7 @@ export default function TestChatPage() {
       setResponse('')
       setErrorMessage(void 0)
       setReferenceList([])
+      setCitations([])
     }
   }, [responseState])
 
@@ -44,7 +47,9 @@ export default function TestChatPage() {
       setResponseState(ResponseState.FETCHING_REFERENCE)
     },
     onData: (result) => {
-      if (result.resultType === 'Reference') {
+      if (result.resultType === 'Session') {
+        // 这个页面每次都是新的对话，不需要记录 session id
+      } else if (result.resultType === 'Reference') {
         setReferenceList((v) => [
           ...v,
           {
@@ -60,6 +65,8 @@ export default function TestChatPage() {
       } else if (result.resultType === 'Response') {
         setResponseState(ResponseState.GENERATING)
         setResponse((v) => (v += result.data))
+      } else if (result.resultType === 'Citation') {
+        setCitations((v) => [...v, result.data])
       } else if (result.resultType === 'Error') {
         setResponseState(ResponseState.ERROR)
         setErrorMessage(result.data)
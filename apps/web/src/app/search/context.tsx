@@ -92,8 +92,13 @@ export function SearchPageContextProvider({ children }: PropsWithChildren<{}>) {
       }
       setIsLoading(true)
       try {
-        const res = await client.query([payload.api, payload])
-        setData(res)
+        if (payload.api === 'search.all') {
+          const res = await client.query(['search.all', payload])
+          setData(res.items)
+        } else {
+          const res = await client.query(['search.recommend', payload])
+          setData(res)
+        }
         setIsSuccess(true)
       } catch (e) {
         setData([])
@@ -8,7 +8,17 @@ export function ModelList() {
   return (
     <div>
       {settings &&
-        (['MultiModalEmbedding', 'TextEmbedding', 'ImageCaption', 'AudioTranscript'] as const).map((category) => (
+        (
+          [
+            'MultiModalEmbedding',
+            'TextEmbedding',
+            'ImageCaption',
+            'AudioTranscript',
+            'TextRerank',
+            'ObjectDetection',
+            'Ocr',
+          ] as const
+        ).map((category) => (
           <div key={category} className="mt-4">
             <div className="mb-2 text-lg font-bold">{category}</div>
             <div>
@@ -7,7 +7,9 @@ pub mod clip;
 pub mod llava_phi3_mini;
 pub mod llm;
 pub mod moondream;
+pub mod ocr;
 pub mod text_embedding;
+pub mod text_rerank;
 pub mod utils;
 pub mod whisper;
 pub mod yolo;
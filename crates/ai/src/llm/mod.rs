@@ -73,6 +73,26 @@ impl Default for LLMInferenceParams {
     }
 }
 
+impl LLMInferenceParams {
+    /// 生成的最大 token 数
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// 采样温度，0 表示每次都选概率最大的 token
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 固定随机数种子，同样的输入可以得到同样的输出
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
 pub(crate) trait LLMModel {
     fn get_completion(
         &self,
@@ -0,0 +1,492 @@
+use crate::{ort::load_onnx_model, Model};
+use anyhow::{anyhow, bail};
+use image::{imageops::FilterType, RgbImage};
+use ndarray::{Array4, Axis, Ix2, Ix3};
+use ort::Session;
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+impl Model for OCR {
+    type Item = PathBuf;
+    type Output = Vec<OCRTextBlock>;
+
+    fn batch_size_limit(&self) -> usize {
+        1
+    }
+
+    async fn process(
+        &mut self,
+        items: Vec<Self::Item>,
+    ) -> anyhow::Result<Vec<anyhow::Result<Self::Output>>> {
+        if items.len() > self.batch_size_limit() {
+            bail!("too many items");
+        }
+
+        let mut results = vec![];
+
+        for item in items {
+            let res = self.recognize(&item).await;
+            results.push(res);
+        }
+
+        Ok(results)
+    }
+}
+
+/// PaddleOCR 导出的 ONNX 模型，先用 DB 检测模型找到文字区域，再用 CRNN 识别模型逐个识别
+/// 比如 PP-OCRv4 的 ch_PP-OCRv4_det_infer.onnx 和 ch_PP-OCRv4_rec_infer.onnx
+pub struct OCR {
+    det_model: Session,
+    rec_model: Session,
+    /// 识别模型输出的第 0 类是 CTC 的 blank，第 i 类对应 characters[i - 1]
+    characters: Vec<String>,
+    det_input_name: String,
+    det_output_name: String,
+    rec_input_name: String,
+    rec_output_name: String,
+    /// 检测模型输出的概率图二值化的阈值
+    det_threshold: f32,
+    /// 文字区域内平均概率的阈值，低于这个值的区域忽略
+    box_threshold: f32,
+    /// 识别结果平均置信度的阈值
+    rec_threshold: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct OCRTextBlock {
+    text: String,
+    confidence: f32,
+    /// (xmin, ymin, xmax, ymax)，相对于图片宽高的比例
+    bounding_box: (f32, f32, f32, f32),
+}
+
+impl OCRTextBlock {
+    pub fn get_text(&self) -> &str {
+        self.text.as_str()
+    }
+
+    pub fn get_confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    /// (xmin, ymin, xmax, ymax)，相对于图片宽高的比例
+    pub fn get_bounding_box(&self) -> (f32, f32, f32, f32) {
+        self.bounding_box
+    }
+}
+
+/// 检测模型输入的最长边
+const DET_LIMIT_SIDE_LEN: u32 = 960;
+/// 识别模型输入的高度和最小宽度
+const REC_IMAGE_HEIGHT: u32 = 48;
+const REC_IMAGE_MIN_WIDTH: u32 = 320;
+/// 检测到的文字区域最短边的像素数，在检测模型输入的尺寸上计算
+const MIN_BOX_SIZE: usize = 3;
+const UNCLIP_RATIO: f32 = 1.5;
+
+/// 检测模型输出的概率图上的一个文字区域，坐标是概率图上的像素
+#[derive(Debug, Clone, PartialEq)]
+struct DetectedBox {
+    x_min: usize,
+    y_min: usize,
+    x_max: usize,
+    y_max: usize,
+}
+
+impl OCR {
+    /// dict_path 是识别模型的字典文件，每行一个字符
+    /// 不传的时候从识别模型的 metadata 里读取，RapidOCR 转换的模型会把字典写在 `character` 里
+    pub fn new(
+        det_model_path: impl AsRef<Path>,
+        rec_model_path: impl AsRef<Path>,
+        dict_path: Option<impl AsRef<Path>>,
+    ) -> anyhow::Result<Self> {
+        let det_model = load_onnx_model(det_model_path.as_ref(), None)?;
+        let rec_model = load_onnx_model(rec_model_path.as_ref(), None)?;
+
+        let dict = match dict_path {
+            Some(dict_path) => std::fs::read_to_string(dict_path.as_ref())?,
+            None => rec_model
+                .metadata()?
+                .custom("character")?
+                .ok_or(anyhow!("character dict not found in model metadata"))?,
+        };
+        let mut characters = dict
+            .lines()
+            .map(|v| v.trim_end_matches('\r').to_string())
+            .collect::<Vec<_>>();
+        // PaddleOCR 的 use_space_char，空格在字典的最后
+        characters.push(" ".to_string());
+
+        let input_name = |session: &Session| {
+            session
+                .inputs
+                .first()
+                .map(|v| v.name.clone())
+                .ok_or(anyhow!("model input not found"))
+        };
+        let output_name = |session: &Session| {
+            session
+                .outputs
+                .first()
+                .map(|v| v.name.clone())
+                .ok_or(anyhow!("model output not found"))
+        };
+
+        Ok(Self {
+            det_input_name: input_name(&det_model)?,
+            det_output_name: output_name(&det_model)?,
+            rec_input_name: input_name(&rec_model)?,
+            rec_output_name: output_name(&rec_model)?,
+            det_model,
+            rec_model,
+            characters,
+            det_threshold: 0.3,
+            box_threshold: 0.6,
+            rec_threshold: 0.5,
+        })
+    }
+
+    /// 按从上到下、从左到右的顺序返回识别到的文字
+    pub async fn recognize(
+        &self,
+        image_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Vec<OCRTextBlock>> {
+        let original_image = image::open(image_path)?.to_rgb8();
+        let (width, height) = original_image.dimensions();
+        if width == 0 || height == 0 {
+            return Ok(vec![]);
+        }
+
+        let boxes = self.detect(&original_image)?;
+
+        let mut results = vec![];
+        for (x_min, y_min, x_max, y_max) in boxes {
+            let crop_width = (x_max - x_min).max(1);
+            let crop_height = (y_max - y_min).max(1);
+            let crop =
+                image::imageops::crop_imm(&original_image, x_min, y_min, crop_width, crop_height)
+                    .to_image();
+            // 竖排的文字转成横排再识别
+            let crop = if crop_height as f32 / crop_width as f32 >= 1.5 {
+                image::imageops::rotate270(&crop)
+            } else {
+                crop
+            };
+
+            let (text, confidence) = self.recognize_line(&crop)?;
+            if text.trim().is_empty() || confidence < self.rec_threshold {
+                continue;
+            }
+
+            results.push(OCRTextBlock {
+                text,
+                confidence,
+                bounding_box: (
+                    x_min as f32 / width as f32,
+                    y_min as f32 / height as f32,
+                    x_max as f32 / width as f32,
+                    y_max as f32 / height as f32,
+                ),
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// 返回原图上的文字区域 (xmin, ymin, xmax, ymax)，是水平的矩形
+    fn detect(&self, image: &RgbImage) -> anyhow::Result<Vec<(u32, u32, u32, u32)>> {
+        let (width, height) = image.dimensions();
+        let scale = (DET_LIMIT_SIDE_LEN as f32 / width.max(height) as f32).min(1.0);
+        // Sizes have to be divisible by 32.
+        let resize = |v: u32| (((v as f32 * scale / 32.0).round() as u32) * 32).max(32);
+        let (resized_width, resized_height) = (resize(width), resize(height));
+        let img =
+            image::imageops::resize(image, resized_width, resized_height, FilterType::Triangle);
+
+        // PaddleOCR 用 opencv 读取图片，通道顺序是 BGR
+        let mean = [0.485, 0.456, 0.406];
+        let std = [0.229, 0.224, 0.225];
+        let mut array = Array4::zeros((1, 3, resized_height as usize, resized_width as usize));
+        for (x, y, p) in img.enumerate_pixels() {
+            for c in 0..3 {
+                array[[0, c, y as usize, x as usize]] =
+                    (p[2 - c] as f32 / 255.0 - mean[c]) / std[c];
+            }
+        }
+
+        let outputs = self
+            .det_model
+            .run(ort::inputs![self.det_input_name.as_str() => array.view()]?)?;
+        let output = outputs
+            .get(self.det_output_name.as_str())
+            .ok_or(anyhow!("output not found"))?
+            .try_extract_tensor::<f32>()?
+            .view()
+            .to_owned();
+
+        // output 的形状是 (1, 1, height, width)
+        let probability = output
+            .index_axis_move(Axis(0), 0)
+            .index_axis_move(Axis(0), 0)
+            .into_dimensionality::<Ix2>()?;
+        let (map_height, map_width) = probability.dim();
+        let probability = probability.iter().copied().collect::<Vec<_>>();
+
+        let mut boxes = find_boxes(
+            &probability,
+            map_width,
+            map_height,
+            self.det_threshold,
+            self.box_threshold,
+        );
+        sort_boxes(&mut boxes);
+
+        // 检测模型输出的区域比文字本身小，需要向外扩展，再换算回原图的坐标
+        let scale_x = width as f32 / map_width as f32;
+        let scale_y = height as f32 / map_height as f32;
+        Ok(boxes
+            .into_iter()
+            .map(|v| {
+                let box_width = (v.x_max - v.x_min + 1) as f32;
+                let box_height = (v.y_max - v.y_min + 1) as f32;
+                let distance =
+                    box_width * box_height * UNCLIP_RATIO / (2.0 * (box_width + box_height));
+                let x_min = ((v.x_min as f32 - distance) * scale_x).max(0.0) as u32;
+                let y_min = ((v.y_min as f32 - distance) * scale_y).max(0.0) as u32;
+                let x_max = (((v.x_max + 1) as f32 + distance) * scale_x).min(width as f32) as u32;
+                let y_max = (((v.y_max + 1) as f32 + distance) * scale_y).min(height as f32) as u32;
+                (x_min, y_min, x_max, y_max)
+            })
+            .filter(|(x_min, y_min, x_max, y_max)| x_max > x_min && y_max > y_min)
+            .collect())
+    }
+
+    /// 识别一行文字，返回文字和平均置信度
+    fn recognize_line(&self, image: &RgbImage) -> anyhow::Result<(String, f32)> {
+        let (width, height) = image.dimensions();
+        let resized_width =
+            ((REC_IMAGE_HEIGHT as f32 * width as f32 / height as f32).ceil() as u32).max(1);
+        let img =
+            image::imageops::resize(image, resized_width, REC_IMAGE_HEIGHT, FilterType::Triangle);
+
+        // 宽度不够的时候右边补 0
+        let input_width = resized_width.max(REC_IMAGE_MIN_WIDTH);
+        let mut array = Array4::zeros((1, 3, REC_IMAGE_HEIGHT as usize, input_width as usize));
+        for (x, y, p) in img.enumerate_pixels() {
+            for c in 0..3 {
+                array[[0, c, y as usize, x as usize]] = (p[2 - c] as f32 / 255.0 - 0.5) / 0.5;
+            }
+        }
+
+        let outputs = self
+            .rec_model
+            .run(ort::inputs![self.rec_input_name.as_str() => array.view()]?)?;
+        let output = outputs
+            .get(self.rec_output_name.as_str())
+            .ok_or(anyhow!("output not found"))?
+            .try_extract_tensor::<f32>()?
+            .view()
+            .to_owned();
+
+        // output 的形状是 (1, 时间步, 类别数)，已经做过 softmax
+        let output = output
+            .into_dimensionality::<Ix3>()?
+            .index_axis_move(Axis(0), 0);
+        let predictions = output
+            .outer_iter()
+            .map(|step| {
+                step.iter()
+                    .copied()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap_or((0, 0.0))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(ctc_greedy_decode(&predictions, &self.characters))
+    }
+}
+
+/// 概率图二值化以后按 4 连通找出所有区域
+fn find_boxes(
+    probability: &[f32],
+    width: usize,
+    height: usize,
+    threshold: f32,
+    box_threshold: f32,
+) -> Vec<DetectedBox> {
+    let mut visited = vec![false; probability.len()];
+    let mut boxes = vec![];
+
+    for start in 0..probability.len() {
+        if visited[start] || probability[start] <= threshold {
+            continue;
+        }
+        visited[start] = true;
+
+        let mut queue = VecDeque::from([start]);
+        let (mut x_min, mut y_min) = (usize::MAX, usize::MAX);
+        let (mut x_max, mut y_max) = (0, 0);
+        let (mut score_sum, mut count) = (0.0, 0);
+
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            x_min = x_min.min(x);
+            y_min = y_min.min(y);
+            x_max = x_max.max(x);
+            y_max = y_max.max(y);
+            score_sum += probability[index];
+            count += 1;
+
+            let mut neighbors = vec![];
+            if x > 0 {
+                neighbors.push(index - 1);
+            }
+            if x + 1 < width {
+                neighbors.push(index + 1);
+            }
+            if y > 0 {
+                neighbors.push(index - width);
+            }
+            if y + 1 < height {
+                neighbors.push(index + width);
+            }
+            for neighbor in neighbors {
+                if !visited[neighbor] && probability[neighbor] > threshold {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        if x_max - x_min + 1 < MIN_BOX_SIZE || y_max - y_min + 1 < MIN_BOX_SIZE {
+            continue;
+        }
+        if score_sum / (count as f32) < box_threshold {
+            continue;
+        }
+
+        boxes.push(DetectedBox {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        });
+    }
+
+    boxes
+}
+
+/// 从上到下排序，同一行（纵向距离小于 10 个像素）的从左到右排序
+fn sort_boxes(boxes: &mut [DetectedBox]) {
+    boxes.sort_by_key(|v| (v.y_min, v.x_min));
+    for i in 1..boxes.len() {
+        let mut j = i;
+        while j > 0
+            && boxes[j].y_min.abs_diff(boxes[j - 1].y_min) < 10
+            && boxes[j].x_min < boxes[j - 1].x_min
+        {
+            boxes.swap(j, j - 1);
+            j -= 1;
+        }
+    }
+}
+
+/// CTC 贪心解码，去掉 blank（第 0 类）并合并连续重复的字符
+fn ctc_greedy_decode(predictions: &[(usize, f32)], characters: &[String]) -> (String, f32) {
+    let mut text = String::new();
+    let mut scores = vec![];
+    let mut last_index = 0;
+
+    for &(index, score) in predictions {
+        if index != 0 && index != last_index {
+            if let Some(character) = characters.get(index - 1) {
+                text.push_str(character);
+                scores.push(score);
+            }
+        }
+        last_index = index;
+    }
+
+    let confidence = if scores.is_empty() {
+        0.0
+    } else {
+        scores.iter().sum::<f32>() / scores.len() as f32
+    };
+
+    (text, confidence)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ctc_greedy_decode, find_boxes, sort_boxes};
+
+    #[test]
+    fn test_ctc_greedy_decode() {
+        let characters = ["a", "b", "c", " "]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        // a a blank a b b blank c
+        let predictions = [
+            (1, 0.9),
+            (1, 0.8),
+            (0, 0.9),
+            (1, 0.7),
+            (2, 0.6),
+            (2, 0.9),
+            (0, 0.9),
+            (3, 0.8),
+        ];
+        let (text, confidence) = ctc_greedy_decode(&predictions, &characters);
+        assert_eq!(text, "aabc");
+        assert!((confidence - 0.75).abs() < 1e-6);
+
+        let (text, confidence) = ctc_greedy_decode(&[(0, 0.9), (0, 0.9)], &characters);
+        assert_eq!(text, "");
+        assert_eq!(confidence, 0.0);
+    }
+
+    #[test]
+    fn test_find_boxes() {
+        let (width, height) = (20, 10);
+        let mut probability = vec![0.0; width * height];
+        // 第二行右边一个区域，第一行左边一个区域，还有一个太小的区域
+        for y in 5..8 {
+            for x in 12..18 {
+                probability[y * width + x] = 0.9;
+            }
+        }
+        for y in 1..4 {
+            for x in 2..8 {
+                probability[y * width + x] = 0.8;
+            }
+        }
+        probability[9 * width + 1] = 0.9;
+
+        let mut boxes = find_boxes(&probability, width, height, 0.3, 0.6);
+        sort_boxes(&mut boxes);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(
+            (
+                boxes[0].x_min,
+                boxes[0].y_min,
+                boxes[0].x_max,
+                boxes[0].y_max
+            ),
+            (2, 1, 7, 3)
+        );
+        assert_eq!(
+            (
+                boxes[1].x_min,
+                boxes[1].y_min,
+                boxes[1].x_max,
+                boxes[1].y_max
+            ),
+            (12, 5, 17, 7)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    ort::load_onnx_model,
+    traits::{TextRerankInput, TextRerankOutput},
+    Model,
+};
+use anyhow::anyhow;
+use ndarray::Axis;
+use ort::Session;
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+/// Cross-encoder 模型，把 query 和 document 拼在一起输入，输出一个相关性分数
+/// 比如 bge-reranker-base，输入是 input_ids、attention_mask（和可选的 token_type_ids），输出是 logits
+pub struct OrtTextRerank {
+    model: Session,
+    tokenizer: Tokenizer,
+    /// 有些模型（比如 XLM-RoBERTa）没有 token_type_ids 输入
+    with_token_type_ids: bool,
+}
+
+impl OrtTextRerank {
+    pub async fn new(
+        model_path: impl AsRef<Path>,
+        tokenizer_config_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let model = load_onnx_model(model_path, None)?;
+
+        let tokenizer = match Tokenizer::from_file(tokenizer_config_path) {
+            Ok(mut tokenizer) => {
+                let truncation = tokenizers::utils::truncation::TruncationParams {
+                    max_length: 512,
+                    ..Default::default()
+                };
+                tokenizer.with_truncation(Some(truncation)).ok();
+                // 单条推理不需要 padding
+                tokenizer.with_padding(None);
+
+                Some(tokenizer)
+            }
+            _ => None,
+        }
+        .ok_or(anyhow::anyhow!("can not load tokenizer"))?;
+
+        let with_token_type_ids = model
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        Ok(Self {
+            model,
+            tokenizer,
+            with_token_type_ids,
+        })
+    }
+
+    pub async fn get_rerank_score(&self, query: &str, document: &str) -> anyhow::Result<f32> {
+        let encoding = self
+            .tokenizer
+            .encode((query, document), true)
+            .map_err(|err| anyhow!(err))?;
+
+        let ids = ndarray::arr1(encoding.get_ids())
+            .mapv(|x| x as i64)
+            .insert_axis(Axis(0));
+        let attention_mask = ndarray::arr1(encoding.get_attention_mask())
+            .mapv(|x| x as i64)
+            .insert_axis(Axis(0));
+
+        let outputs = if self.with_token_type_ids {
+            let token_type_ids = ndarray::arr1(encoding.get_type_ids())
+                .mapv(|x| x as i64)
+                .insert_axis(Axis(0));
+            self.model.run(ort::inputs![
+                "input_ids" => ids.view(),
+                "attention_mask" => attention_mask.view(),
+                "token_type_ids" => token_type_ids.view()
+            ]?)?
+        } else {
+            self.model.run(
+                ort::inputs!["input_ids" => ids.view(), "attention_mask" => attention_mask.view()]?,
+            )?
+        };
+
+        let logits = outputs
+            .get("logits")
+            .ok_or(anyhow!("output not found"))?
+            .try_extract_tensor::<f32>()?;
+        let logit = logits
+            .iter()
+            .next()
+            .copied()
+            .ok_or(anyhow!("empty logits"))?;
+
+        // sigmoid，转换到 0 到 1
+        Ok(1.0 / (1.0 + (-logit).exp()))
+    }
+}
+
+impl Model for OrtTextRerank {
+    type Item = TextRerankInput;
+    type Output = TextRerankOutput;
+
+    fn batch_size_limit(&self) -> usize {
+        1
+    }
+
+    async fn process(
+        &mut self,
+        items: Vec<TextRerankInput>,
+    ) -> anyhow::Result<Vec<anyhow::Result<TextRerankOutput>>> {
+        let mut results = vec![];
+
+        for (query, document) in items {
+            let res = self.get_rerank_score(&query, &document).await;
+            results.push(res);
+        }
+
+        Ok(results)
+    }
+}
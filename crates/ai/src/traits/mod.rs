@@ -3,7 +3,10 @@ mod image_caption;
 mod image_embedding;
 mod llm;
 mod multi_modal_embedding;
+mod object_detection;
+mod ocr;
 mod text_embedding;
+mod text_rerank;
 
 use crate::{loader, HandlerPayload};
 pub use audio_transcript::*;
@@ -12,9 +15,12 @@ pub use image_caption::*;
 pub use image_embedding::*;
 pub use llm::*;
 pub use multi_modal_embedding::*;
+pub use object_detection::*;
+pub use ocr::*;
 use std::fmt::Debug;
 use std::{collections::HashMap, sync::Arc, time::Duration};
 pub use text_embedding::*;
+pub use text_rerank::*;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
 pub trait Model {
@@ -0,0 +1,7 @@
+use super::AIModel;
+use crate::yolo::YOLODetectionResult;
+use std::path::PathBuf;
+
+pub type ObjectDetectionInput = PathBuf;
+pub type ObjectDetectionOutput = Vec<YOLODetectionResult>;
+pub type ObjectDetectionModel = AIModel<ObjectDetectionInput, ObjectDetectionOutput>;
@@ -0,0 +1,7 @@
+use super::AIModel;
+use crate::ocr::OCRTextBlock;
+use std::path::PathBuf;
+
+pub type OcrInput = PathBuf;
+pub type OcrOutput = Vec<OCRTextBlock>;
+pub type OcrModel = AIModel<OcrInput, OcrOutput>;
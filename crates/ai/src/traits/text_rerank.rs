@@ -0,0 +1,7 @@
+use super::AIModel;
+
+/// (query, document)
+pub type TextRerankInput = (String, String);
+/// document 和 query 的相关性分数，范围是 0 到 1，越大越相关
+pub type TextRerankOutput = f32;
+pub type TextRerankModel = AIModel<TextRerankInput, TextRerankOutput>;
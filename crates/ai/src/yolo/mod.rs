@@ -42,8 +42,10 @@ pub struct YOLO {
     nms_threshold: f32,
 }
 
+#[derive(Debug, Clone)]
 pub struct YOLODetectionResult {
     class_name: String,
+    /// 坐标是相对于图片宽高的比例，范围是 0 到 1，和模型输入的尺寸无关
     bounding_box: Bbox<Vec<KeyPoint>>,
 }
 
@@ -55,6 +57,16 @@ impl YOLODetectionResult {
     pub fn get_confidence(&self) -> f32 {
         self.bounding_box.confidence
     }
+
+    /// (xmin, ymin, xmax, ymax)，相对于图片宽高的比例
+    pub fn get_bounding_box(&self) -> (f32, f32, f32, f32) {
+        (
+            self.bounding_box.xmin,
+            self.bounding_box.ymin,
+            self.bounding_box.xmax,
+            self.bounding_box.ymax,
+        )
+    }
 }
 
 impl YOLO {
@@ -66,7 +78,12 @@ impl YOLO {
         });
 
         let model_path = download.download_if_not_exists(&model_uri).await?;
-        let model = load_onnx_model(model_path, None)?;
+        Self::from_model_path(model_path)
+    }
+
+    /// 使用已经下载好的模型文件，模型由 model_list.json 配置和下载
+    pub fn from_model_path(model_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let model = load_onnx_model(model_path.as_ref(), None)?;
 
         Ok(Self {
             model,
@@ -162,6 +179,8 @@ impl YOLO {
 
         let mut results = vec![];
 
+        // 模型输出的坐标是缩放以后的图片上的像素，换算成比例，方便在原图或者缩略图上画框
+        let (width, height) = (width as f32, height as f32);
         bboxes
             .iter()
             .enumerate()
@@ -169,7 +188,14 @@ impl YOLO {
                 boxes_list.iter().for_each(|bbox| {
                     results.push(YOLODetectionResult {
                         class_name: coco_classes::NAMES[class_index].to_string(),
-                        bounding_box: bbox.clone(),
+                        bounding_box: Bbox {
+                            xmin: (bbox.xmin / width).clamp(0.0, 1.0),
+                            ymin: (bbox.ymin / height).clamp(0.0, 1.0),
+                            xmax: (bbox.xmax / width).clamp(0.0, 1.0),
+                            ymax: (bbox.ymax / height).clamp(0.0, 1.0),
+                            confidence: bbox.confidence,
+                            data: bbox.data.clone(),
+                        },
                     })
                 })
             });
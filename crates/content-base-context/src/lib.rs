@@ -1,7 +1,9 @@
 pub mod artifacts;
+pub mod tagging;
 
 use ai::{
-    AudioTranscriptModel, ImageCaptionModel, LLMModel, MultiModalEmbeddingModel, TextEmbeddingModel,
+    AudioTranscriptModel, ImageCaptionModel, LLMModel, MultiModalEmbeddingModel,
+    ObjectDetectionModel, OcrModel, TextEmbeddingModel, TextRerankModel,
 };
 use anyhow::bail;
 use std::{
@@ -9,6 +11,7 @@ use std::{
     sync::Arc,
 };
 use storage_macro::Storage;
+use tagging::TaggingConfig;
 
 #[derive(Clone, Storage)]
 pub struct ContentBaseCtx {
@@ -20,6 +23,10 @@ pub struct ContentBaseCtx {
     image_caption: Option<(Arc<ImageCaptionModel>, String)>,
     llm: Option<(Arc<LLMModel>, String)>,
     text_tokenizer: Option<(Arc<ai::tokenizers::Tokenizer>, String)>,
+    text_rerank: Option<(Arc<TextRerankModel>, String)>,
+    object_detection: Option<(Arc<ObjectDetectionModel>, String)>,
+    ocr: Option<(Arc<OcrModel>, String)>,
+    tagging: TaggingConfig,
 }
 
 impl ContentBaseCtx {
@@ -33,6 +40,10 @@ impl ContentBaseCtx {
             image_caption: None,
             llm: None,
             text_tokenizer: None,
+            text_rerank: None,
+            object_detection: None,
+            ocr: None,
+            tagging: TaggingConfig::default(),
         }
     }
 
@@ -92,6 +103,34 @@ impl ContentBaseCtx {
         self
     }
 
+    /// 搜索结果的二次排序，可选
+    pub fn with_text_rerank(mut self, text_rerank: Arc<TextRerankModel>, model_id: &str) -> Self {
+        self.text_rerank = Some((text_rerank, model_id.to_string()));
+        self
+    }
+
+    /// 图片和视频帧的物体检测，可选，没有设置的时候不会创建物体检测的任务
+    pub fn with_object_detection(
+        mut self,
+        object_detection: Arc<ObjectDetectionModel>,
+        model_id: &str,
+    ) -> Self {
+        self.object_detection = Some((object_detection, model_id.to_string()));
+        self
+    }
+
+    /// 图片和视频帧的文字识别，可选，没有设置的时候不会创建 OCR 的任务
+    pub fn with_ocr(mut self, ocr: Arc<OcrModel>, model_id: &str) -> Self {
+        self.ocr = Some((ocr, model_id.to_string()));
+        self
+    }
+
+    /// 打标签的提示词和标签数量上限，没有设置的时候用默认配置
+    pub fn with_tagging(mut self, tagging: TaggingConfig) -> Self {
+        self.tagging = tagging;
+        self
+    }
+
     pub fn multi_modal_embedding(&self) -> anyhow::Result<(&MultiModalEmbeddingModel, &str)> {
         match self.multi_modal_embedding.as_ref() {
             Some(v) => Ok((&v.0, &v.1)),
@@ -147,6 +186,37 @@ impl ContentBaseCtx {
         }
     }
 
+    pub fn text_rerank(&self) -> anyhow::Result<(&TextRerankModel, &str)> {
+        match self.text_rerank.as_ref() {
+            Some(v) => Ok((&v.0, &v.1)),
+            _ => {
+                bail!("text_rerank is not enabled")
+            }
+        }
+    }
+
+    pub fn object_detection(&self) -> anyhow::Result<(&ObjectDetectionModel, &str)> {
+        match self.object_detection.as_ref() {
+            Some(v) => Ok((&v.0, &v.1)),
+            _ => {
+                bail!("object_detection is not enabled")
+            }
+        }
+    }
+
+    pub fn ocr(&self) -> anyhow::Result<(&OcrModel, &str)> {
+        match self.ocr.as_ref() {
+            Some(v) => Ok((&v.0, &v.1)),
+            _ => {
+                bail!("ocr is not enabled")
+            }
+        }
+    }
+
+    pub fn tagging(&self) -> &TaggingConfig {
+        &self.tagging
+    }
+
     /// Generate text embedding and save it to `path`.
     /// Empty string will be ignored and no error will be raised.
     pub async fn save_text_embedding(
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_TAGGING_PROMPT: &str = r#"You are an assistant skilled in tagging files for search.
+You will be given descriptions, transcripts and summaries of a file, such as a video, an audio, an image or a document.
+You should give a few short keyword tags about the file, for example the scene, the topic, the type of the content and the people in it.
+
+Guidelines:
+- Each tag should be a word or a short phrase, such as "outdoor", "product demo", "female speaker".
+- Put the most important tags first.
+- Do not make up anything that is not mentioned in the input.
+- Tags should be in the same language with input."#;
+
+pub const DEFAULT_MAX_TAGS: usize = 10;
+
+/// 素材打标签的配置，来自素材库的设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaggingConfig {
+    pub prompt: String,
+    pub max_tags: usize,
+}
+
+impl Default for TaggingConfig {
+    fn default() -> Self {
+        Self {
+            prompt: DEFAULT_TAGGING_PROMPT.to_string(),
+            max_tags: DEFAULT_MAX_TAGS,
+        }
+    }
+}
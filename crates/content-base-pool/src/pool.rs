@@ -15,9 +15,17 @@ use tokio::sync::{
 use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct TaskPool {
     tx: mpsc::Sender<TaskPayload>,
+    /// 执行任务时使用的 ctx，切换模型的时候通过 update_ctx 替换，不需要重新创建 TaskPool
+    ctx: Arc<RwLock<ContentBaseCtx>>,
+}
+
+impl std::fmt::Debug for TaskPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskPool").field("tx", &self.tx).finish()
+    }
 }
 
 #[derive(Clone)]
@@ -266,17 +274,23 @@ impl TaskPool {
         });
 
         // loop for task execution
-        let cb = content_base.clone();
+        let ctx = Arc::new(RwLock::new(content_base.clone()));
+        let cb = ctx.clone();
         tokio::spawn(async move {
             cpu_task_ctx.loop_for_task_execution(&cb).await;
         });
 
-        let cb = content_base.clone();
+        let cb = ctx.clone();
         tokio::spawn(async move {
             io_task_ctx.loop_for_task_execution(&cb).await;
         });
 
-        Ok(Self { tx })
+        Ok(Self { tx, ctx })
+    }
+
+    /// 替换执行任务时使用的 ctx，已经开始执行的任务继续使用原来的 ctx
+    pub async fn update_ctx(&self, content_base: &ContentBaseCtx) {
+        *self.ctx.write().await = content_base.clone();
     }
 
     pub async fn add_task(
@@ -339,7 +353,7 @@ impl TaskPoolContext {
     ///
     /// 如果是 run 方法里面直接用 tracing::info_span!，需要创建一个 span 然后立即 enter，
     /// 接下来用 `async {}.instrument(span)` 和 `span.in_scope(|| {})` 来执行 async 和 sync 的代码，有点麻烦
-    pub async fn loop_for_task_execution(&self, content_base: &ContentBaseCtx) {
+    pub async fn loop_for_task_execution(&self, content_base: &RwLock<ContentBaseCtx>) {
         let mut count: usize = 0;
         let mut task_interval = tokio::time::interval(std::time::Duration::from_secs(1));
         let mut status_interval = tokio::time::interval(std::time::Duration::from_secs(60));
@@ -350,7 +364,8 @@ impl TaskPoolContext {
                     let Some((task_id, priority, current_task)) = self.pop_next_task(count + 1).await else {
                         continue
                     };
-                    self.async_exec_task(content_base, task_id, priority, current_task).await;
+                    let content_base = content_base.read().await.clone();
+                    self.async_exec_task(&content_base, task_id, priority, current_task).await;
                     count += 1;
                 }
                 // 状态打印
@@ -1,17 +1,21 @@
 pub mod trans_chunk;
 pub mod trans_chunk_sum;
 pub mod trans_chunk_sum_embed;
+pub mod trans_sum;
 pub mod transcript;
+pub mod tags;
 pub mod thumbnail;
 pub mod waveform;
 
 use content_base_derive::ContentTask;
 use storage_macro::Storage;
 use strum_macros::{EnumIter, EnumString};
+use tags::AudioTagsTask;
 use thumbnail::AudioThumbnailTask;
 use trans_chunk::AudioTransChunkTask;
 use trans_chunk_sum::AudioTransChunkSumTask;
 use trans_chunk_sum_embed::AudioTransChunkSumEmbedTask;
+use trans_sum::AudioTransSumTask;
 use transcript::AudioTranscriptTask;
 use waveform::AudioWaveformTask;
 use crate::task::ContentTaskType;
@@ -25,6 +29,8 @@ pub enum AudioTaskType {
     TransChunk(AudioTransChunkTask),
     TransChunkSum(AudioTransChunkSumTask),
     TransChunkSumEmbed(AudioTransChunkSumEmbedTask),
+    TransSum(AudioTransSumTask),
+    Tags(AudioTagsTask),
 }
 
 impl Into<ContentTaskType> for AudioTaskType {
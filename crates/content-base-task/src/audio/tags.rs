@@ -0,0 +1,69 @@
+use super::{
+    trans_chunk::{AudioTransChunkTask, AudioTranscriptChunkTrait},
+    trans_chunk_sum::{AudioTransChunkSumTask, AudioTransChunkSumTrait},
+    AudioTaskType,
+};
+use crate::{tags::TagsTrait, ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct AudioTagsTask;
+
+#[async_trait]
+impl TagsTrait for AudioTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        let chunks = AudioTransChunkTask
+            .chunk_content(file_identifier, ctx)
+            .await?;
+        let mut lines = vec![];
+        for chunk in chunks.iter() {
+            let summarization = AudioTransChunkSumTask
+                .sum_content(
+                    file_identifier,
+                    ctx,
+                    chunk.start_timestamp,
+                    chunk.end_timestamp,
+                )
+                .await?;
+            lines.push(format!("Transcript: {}", summarization.trim()));
+        }
+        Ok(lines)
+    }
+}
+
+#[async_trait]
+impl ContentTask for AudioTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![AudioTransChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for AudioTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Audio(AudioTaskType::Tags(self.clone()))
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use super::{
+    trans_chunk::{AudioTransChunkTask, AudioTranscriptChunkTrait},
+    trans_chunk_sum::{AudioTransChunkSumTask, AudioTransChunkSumTrait},
+    AudioTaskType,
+};
+use crate::{
+    record::{TaskRunOutput, TaskRunRecord},
+    summary::reduce_summaries,
+    ContentTask, ContentTaskType,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::{json, Value};
+use storage_macro::Storage;
+
+/// 把每个转录分段的总结合并成整个音视频的总结
+#[async_trait]
+pub trait AudioTransSumTrait: Into<ContentTaskType> + Clone + Storage {
+    fn chunk_task(&self) -> impl AudioTranscriptChunkTrait;
+    fn chunk_sum_task(&self) -> impl AudioTransChunkSumTrait;
+
+    async fn trans_sum_output(
+        &self,
+        task_run_record: &TaskRunRecord,
+    ) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn run_trans_sum(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let chunks = self
+            .chunk_task()
+            .chunk_content(&file_info.file_identifier, ctx)
+            .await?;
+        let chunk_sum_task = self.chunk_sum_task();
+        let mut summaries = vec![];
+        for chunk in chunks.iter() {
+            summaries.push(
+                chunk_sum_task
+                    .sum_content(
+                        &file_info.file_identifier,
+                        ctx,
+                        chunk.start_timestamp,
+                        chunk.end_timestamp,
+                    )
+                    .await?,
+            );
+        }
+
+        let summarization = reduce_summaries(ctx, summaries).await?;
+
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+        self.write(
+            output_path,
+            json!({
+                "summarization": summarization
+            })
+            .to_string()
+            .into(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    fn trans_sum_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        json!({
+            "model": ctx.llm().expect("llm is set").1
+        })
+    }
+
+    async fn trans_sum_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<String> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        let json_string: Value = serde_json::from_str(&content_str)?;
+        let summarization = json_string["summarization"]
+            .as_str()
+            .ok_or(anyhow::anyhow!(
+                "no summarization found in transcript summarization file"
+            ))?;
+        Ok(summarization.to_string())
+    }
+}
+
+#[derive(Clone, Storage, Debug, Default)]
+pub struct AudioTransSumTask;
+
+impl AudioTransSumTrait for AudioTransSumTask {
+    fn chunk_task(&self) -> impl AudioTranscriptChunkTrait {
+        AudioTransChunkTask
+    }
+
+    fn chunk_sum_task(&self) -> impl AudioTransChunkSumTrait {
+        AudioTransChunkSumTask
+    }
+}
+
+#[async_trait]
+impl ContentTask for AudioTransSumTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.trans_sum_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_trans_sum(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.trans_sum_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![AudioTransChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for AudioTransSumTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Audio(AudioTaskType::TransSum(self.clone()))
+    }
+}
@@ -1,14 +1,24 @@
 pub mod desc_embed;
 pub mod description;
 pub mod embedding;
+pub mod object_detection;
+pub mod ocr;
+pub mod ocr_embed;
+pub mod perceptual_hash;
+pub mod tags;
 pub mod thumbnail;
 
 use content_base_derive::ContentTask;
 use desc_embed::ImageDescEmbedTask;
 use description::ImageDescriptionTask;
 use embedding::ImageEmbeddingTask;
+use object_detection::ImageObjectDetectionTask;
+use ocr::ImageOcrTask;
+use ocr_embed::ImageOcrEmbedTask;
+use perceptual_hash::ImagePerceptualHashTask;
 use storage_macro::Storage;
 use strum::{EnumIter, EnumString};
+use tags::ImageTagsTask;
 use thumbnail::ImageThumbnailTask;
 
 use crate::ContentTaskType;
@@ -20,6 +30,11 @@ pub enum ImageTaskType {
     Embedding(ImageEmbeddingTask),
     Description(ImageDescriptionTask),
     DescEmbed(ImageDescEmbedTask),
+    PerceptualHash(ImagePerceptualHashTask),
+    ObjectDetection(ImageObjectDetectionTask),
+    Ocr(ImageOcrTask),
+    OcrEmbed(ImageOcrEmbedTask),
+    Tags(ImageTagsTask),
 }
 
 impl Into<ContentTaskType> for ImageTaskType {
@@ -0,0 +1,119 @@
+use super::ImageTaskType;
+use crate::{ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+/// 坐标是相对于图片宽高的比例，范围是 0 到 1，前端可以直接按缩略图的尺寸画框
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x_min: f32,
+    pub y_min: f32,
+    pub x_max: f32,
+    pub y_max: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DetectedObject {
+    /// COCO 的类别名，比如 person, dog, car
+    pub label: String,
+    pub confidence: f32,
+    pub bbox: BoundingBox,
+}
+
+impl From<&ai::yolo::YOLODetectionResult> for DetectedObject {
+    fn from(result: &ai::yolo::YOLODetectionResult) -> Self {
+        let (x_min, y_min, x_max, y_max) = result.get_bounding_box();
+        Self {
+            label: result.get_class_name().to_string(),
+            confidence: result.get_confidence(),
+            bbox: BoundingBox {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            },
+        }
+    }
+}
+
+/// 用 ctx 里的物体检测模型检测一张图片，结果按置信度从高到低排序
+pub(crate) async fn detect_objects(
+    image_path: PathBuf,
+    ctx: &ContentBaseCtx,
+) -> anyhow::Result<Vec<DetectedObject>> {
+    let (model, _) = ctx.object_detection()?;
+    let results = model.process_single(image_path).await?;
+    let mut objects = results.iter().map(DetectedObject::from).collect::<Vec<_>>();
+    objects.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok(objects)
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct ImageObjectDetectionTask;
+
+#[async_trait]
+impl ContentTask for ImageObjectDetectionTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let objects = detect_objects(file_info.file_full_path_on_disk.clone(), ctx).await?;
+
+        self.write(output_path, serde_json::to_string(&objects)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        // 模型是可选的，未配置时返回 Null，这样不会和任何已有的运行记录匹配
+        match ctx.object_detection() {
+            Ok((_, model_id)) => json!({
+                "model": model_id,
+            }),
+            Err(_) => Value::Null,
+        }
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![] as Vec<ContentTaskType>
+    }
+}
+
+impl Into<ContentTaskType> for ImageObjectDetectionTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Image(ImageTaskType::ObjectDetection(self.clone()))
+    }
+}
+
+impl ImageObjectDetectionTask {
+    pub async fn objects_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<DetectedObject>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
@@ -0,0 +1,126 @@
+use super::{object_detection::BoundingBox, ImageTaskType};
+use crate::{ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OcrTextBlock {
+    pub text: String,
+    pub confidence: f32,
+    pub bbox: BoundingBox,
+}
+
+impl From<&ai::ocr::OCRTextBlock> for OcrTextBlock {
+    fn from(result: &ai::ocr::OCRTextBlock) -> Self {
+        let (x_min, y_min, x_max, y_max) = result.get_bounding_box();
+        Self {
+            text: result.get_text().to_string(),
+            confidence: result.get_confidence(),
+            bbox: BoundingBox {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            },
+        }
+    }
+}
+
+/// 用 ctx 里的 OCR 模型识别一张图片，结果按从上到下、从左到右排序
+pub(crate) async fn recognize_text(
+    image_path: PathBuf,
+    ctx: &ContentBaseCtx,
+) -> anyhow::Result<Vec<OcrTextBlock>> {
+    let (model, _) = ctx.ocr()?;
+    let results = model.process_single(image_path).await?;
+    Ok(results.iter().map(OcrTextBlock::from).collect())
+}
+
+/// 把识别到的文字拼成一段，每个文字区域一行，用于索引和 embedding
+pub fn blocks_text(blocks: &[OcrTextBlock]) -> String {
+    blocks
+        .iter()
+        .map(|v| v.text.trim())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct ImageOcrTask;
+
+#[async_trait]
+impl ContentTask for ImageOcrTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let blocks = recognize_text(file_info.file_full_path_on_disk.clone(), ctx).await?;
+
+        self.write(output_path, serde_json::to_string(&blocks)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        // 模型是可选的，未配置时返回 Null，这样不会和任何已有的运行记录匹配
+        match ctx.ocr() {
+            Ok((_, model_id)) => json!({
+                "model": model_id,
+            }),
+            Err(_) => Value::Null,
+        }
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![] as Vec<ContentTaskType>
+    }
+}
+
+impl Into<ContentTaskType> for ImageOcrTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Image(ImageTaskType::Ocr(self.clone()))
+    }
+}
+
+impl ImageOcrTask {
+    pub async fn ocr_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<OcrTextBlock>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+
+    pub async fn ocr_text_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<String> {
+        let blocks = self.ocr_content(file_identifier, ctx).await?;
+        Ok(blocks_text(&blocks))
+    }
+}
@@ -0,0 +1,71 @@
+use super::{ocr::ImageOcrTask, ImageTaskType};
+use crate::{ContentTask, ContentTaskType, FileInfo, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct ImageOcrEmbedTask;
+
+#[async_trait]
+impl ContentTask for ImageOcrEmbedTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let text = ImageOcrTask
+            .ocr_text_content(&file_info.file_identifier, ctx)
+            .await?;
+
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        // 没有识别到文字的时候不会生成文件
+        ctx.save_text_embedding(&text, &output_path).await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        json!({
+            "model": ctx.text_embedding().expect("text embedding is set").1
+        })
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![ImageOcrTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for ImageOcrEmbedTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Image(ImageTaskType::OcrEmbed(self.clone()))
+    }
+}
+
+impl ImageOcrEmbedTask {
+    pub async fn ocr_embed_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<f32>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
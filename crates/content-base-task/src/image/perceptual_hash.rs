@@ -0,0 +1,123 @@
+use super::ImageTaskType;
+use crate::{ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use image::{imageops::FilterType, DynamicImage, ImageReader};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use storage_macro::Storage;
+
+/// 计算图片的 dHash (difference hash)
+/// 把图片缩小到 9 * 8 的灰度图，每一行比较相邻两个像素的亮度，得到 64 位的 hash
+/// 对缩放、压缩和轻微调色不敏感，可以用来判断两张图片是否是同一张图的不同版本
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let gray = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = gray.get_pixel(x, y).0[0];
+            let right = gray.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+    hash
+}
+
+pub fn dhash_from_file(path: impl AsRef<Path>) -> anyhow::Result<u64> {
+    let image = ImageReader::open(path.as_ref())?
+        .with_guessed_format()?
+        .decode()?;
+    Ok(dhash(&image))
+}
+
+/// 两个 hash 的相似度，1 表示完全一样，0 表示每一位都不同
+pub fn hash_similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / 64.0
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct ImagePerceptualHashTask;
+
+#[async_trait]
+impl ContentTask for ImagePerceptualHashTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let hash = dhash_from_file(&file_info.file_full_path_on_disk)?;
+
+        self.write(output_path, serde_json::to_string(&hash)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, _: &ContentBaseCtx) -> Value {
+        json!({
+            "method": "dhash",
+        })
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![] as Vec<ContentTaskType>
+    }
+}
+
+impl Into<ContentTaskType> for ImagePerceptualHashTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Image(ImageTaskType::PerceptualHash(self.clone()))
+    }
+}
+
+impl ImagePerceptualHashTask {
+    pub async fn hash_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<u64> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dhash, hash_similarity};
+    use image::{DynamicImage, ImageBuffer, Rgb};
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            let v = (x * 200 / width + y * 55 / height) as u8;
+            Rgb([v, v / 2, 255 - v])
+        }))
+    }
+
+    #[test]
+    fn test_dhash() {
+        let image = gradient(640, 480);
+        // 缩放以后 hash 基本不变
+        let resized = image.resize_exact(320, 240, image::imageops::FilterType::Lanczos3);
+        assert!(hash_similarity(dhash(&image), dhash(&resized)) > 0.9);
+        // 水平翻转以后亮度的变化方向相反
+        let flipped = image.fliph();
+        assert!(hash_similarity(dhash(&image), dhash(&flipped)) < 0.5);
+        assert_eq!(hash_similarity(0, u64::MAX), 0.0);
+    }
+}
@@ -0,0 +1,53 @@
+use super::{description::ImageDescriptionTask, ImageTaskType};
+use crate::{tags::TagsTrait, ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct ImageTagsTask;
+
+#[async_trait]
+impl TagsTrait for ImageTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        let caption = ImageDescriptionTask
+            .description_content(file_identifier, ctx)
+            .await?;
+        Ok(vec![format!("Visual: {}", caption.trim())])
+    }
+}
+
+#[async_trait]
+impl ContentTask for ImageTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![ImageDescriptionTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for ImageTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Image(ImageTaskType::Tags(self.clone()))
+    }
+}
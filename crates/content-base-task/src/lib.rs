@@ -1,10 +1,12 @@
 mod record;
+mod summary;
 mod task;
 mod traits;
 
 pub mod audio;
 pub mod image;
 pub mod raw_text;
+pub mod tags;
 pub mod video;
 pub mod web_page;
 
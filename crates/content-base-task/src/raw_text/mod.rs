@@ -1,6 +1,8 @@
 pub mod chunk;
 pub mod chunk_sum;
 pub mod chunk_sum_embed;
+pub mod sum;
+pub mod tags;
 
 use crate::ContentTaskType;
 use chunk::RawTextChunkTask;
@@ -8,7 +10,9 @@ use chunk_sum::RawTextChunkSumTask;
 use chunk_sum_embed::RawTextChunkSumEmbedTask;
 use content_base_derive::ContentTask;
 use storage_macro::Storage;
+use sum::RawTextSumTask;
 use strum::{EnumIter, EnumString};
+use tags::RawTextTagsTask;
 
 #[derive(Clone, Debug, EnumIter, EnumString, strum_macros::Display, ContentTask, Storage)]
 #[strum(serialize_all = "kebab-case")]
@@ -16,6 +20,8 @@ pub enum RawTextTaskType {
     Chunk(RawTextChunkTask),
     ChunkSum(RawTextChunkSumTask),
     ChunkSumEmbed(RawTextChunkSumEmbedTask),
+    Sum(RawTextSumTask),
+    Tags(RawTextTagsTask),
 }
 
 impl Into<ContentTaskType> for RawTextTaskType {
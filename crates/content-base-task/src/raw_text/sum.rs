@@ -0,0 +1,134 @@
+use super::{
+    chunk::{DocumentChunkTrait, RawTextChunkTask},
+    chunk_sum::{DocumentChunkSumTrait, RawTextChunkSumTask},
+    RawTextTaskType,
+};
+use crate::{
+    summary::reduce_summaries, ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+/// 把文档每个 chunk 的总结合并成整个文档的总结
+#[async_trait]
+pub trait DocumentSumTrait: Into<ContentTaskType> + Clone + Storage {
+    fn chunk_task(&self) -> impl DocumentChunkTrait;
+    fn chunk_sum_task(&self) -> impl DocumentChunkSumTrait;
+
+    async fn sum_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn run_document_sum(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let chunks = self
+            .chunk_task()
+            .chunk_content(&file_info.file_identifier, ctx)
+            .await?;
+        let chunk_sum_task = self.chunk_sum_task();
+        let mut summaries = vec![];
+        for i in 0..chunks.len() {
+            summaries.push(
+                chunk_sum_task
+                    .sum_content(&file_info.file_identifier, ctx, i)
+                    .await?,
+            );
+        }
+
+        let summarization = reduce_summaries(ctx, summaries).await?;
+
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+        self.write(
+            output_path,
+            json!({
+                "summarization": summarization
+            })
+            .to_string()
+            .into(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    fn document_sum_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        json!({
+            "model": ctx.llm().expect("llm is set").1
+        })
+    }
+
+    async fn document_sum_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<String> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+
+        let content_str = self.read_to_string(output_path)?;
+        let json_string: Value = serde_json::from_str(&content_str)?;
+        let summarization = json_string["summarization"]
+            .as_str()
+            .ok_or(anyhow::anyhow!(
+                "no summarization found in document summarization file"
+            ))?;
+        Ok(summarization.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct RawTextSumTask;
+
+impl DocumentSumTrait for RawTextSumTask {
+    fn chunk_task(&self) -> impl DocumentChunkTrait {
+        RawTextChunkTask
+    }
+
+    fn chunk_sum_task(&self) -> impl DocumentChunkSumTrait {
+        RawTextChunkSumTask
+    }
+}
+
+#[async_trait]
+impl ContentTask for RawTextSumTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.sum_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_document_sum(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.document_sum_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![RawTextChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for RawTextSumTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::RawText(RawTextTaskType::Sum(self.clone()))
+    }
+}
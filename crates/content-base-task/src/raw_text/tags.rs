@@ -0,0 +1,72 @@
+use super::{
+    chunk::DocumentChunkTrait,
+    chunk_sum::{DocumentChunkSumTrait, RawTextChunkSumTask},
+    RawTextTaskType,
+};
+use crate::{tags::TagsTrait, ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+/// 文档每个 chunk 的总结，raw text 和 web page 共用
+pub(crate) async fn document_tag_sources(
+    chunk_sum_task: &impl DocumentChunkSumTrait,
+    file_identifier: &str,
+    ctx: &ContentBaseCtx,
+) -> anyhow::Result<Vec<String>> {
+    let chunks = chunk_sum_task
+        .chunk_task()
+        .chunk_content(file_identifier, ctx)
+        .await?;
+    let mut lines = vec![];
+    for i in 0..chunks.len() {
+        let summarization = chunk_sum_task.sum_content(file_identifier, ctx, i).await?;
+        lines.push(format!("Paragraph {}: {}", i + 1, summarization.trim()));
+    }
+    Ok(lines)
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct RawTextTagsTask;
+
+#[async_trait]
+impl TagsTrait for RawTextTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        document_tag_sources(&RawTextChunkSumTask, file_identifier, ctx).await
+    }
+}
+
+#[async_trait]
+impl ContentTask for RawTextTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![RawTextChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for RawTextTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::RawText(RawTextTaskType::Tags(self.clone()))
+    }
+}
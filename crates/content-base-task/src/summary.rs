@@ -0,0 +1,136 @@
+use ai::{
+    llm::{LLMInferenceParams, LLMMessage},
+    tokenizers::Tokenizer,
+};
+use content_base_context::ContentBaseCtx;
+
+/// 每次合并的分段总结的 token 上限，需要比最小的 LLM 上下文窗口小，给提示词和输出留出空间
+const SUMMARY_BATCH_TOKENS: usize = 1024;
+
+const ASSET_SUMMARY_SYSTEM_PROMPT: &str = r#"You are an assistant skilled in summarization.
+You will be given a list of short summaries of consecutive parts of the same content, in order.
+You should combine them into one coherent summary of the whole content.
+
+Guidelines:
+- Focus on essential information: Prioritize the core messages and the overall structure.
+- Maintain clarity and conciseness: Craft your summary using accessible language.
+- Do not list the parts one by one, describe the content as a whole.
+
+Additional Rules:
+- Content: just response with the summary only, do not start with hint or prompt, do not contain anything else.
+- Word count: aim for a summary with no more than 150 words.
+- Language: summary should be in the same language with input"#;
+
+/// 用 tokenizer 计算文本的 token 数，编码失败的时候按字符数估算
+pub(crate) fn count_text_tokens(tokenizer: &Tokenizer, text: &str) -> usize {
+    tokenizer
+        .encode(text, false)
+        .map(|encoding| encoding.len())
+        .unwrap_or_else(|_| text.chars().count())
+}
+
+/// 按 token 数把分段总结分批，每一批至少有两个总结，保证每一轮合并以后数量都会减少
+pub(crate) fn batch_summaries(
+    summaries: &[String],
+    max_tokens: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Vec<Vec<String>> {
+    let mut batches: Vec<Vec<String>> = vec![];
+    let mut batch: Vec<String> = vec![];
+    let mut batch_tokens = 0;
+    for summary in summaries {
+        let tokens = count_tokens(summary);
+        if batch.len() >= 2 && batch_tokens + tokens > max_tokens {
+            batches.push(std::mem::take(&mut batch));
+            batch_tokens = 0;
+        }
+        batch.push(summary.clone());
+        batch_tokens += tokens;
+    }
+    if batch.len() == 1 && !batches.is_empty() {
+        // 最后剩下的一个并到上一批里，避免它原样进入下一轮
+        batches
+            .last_mut()
+            .expect("last batch exists")
+            .append(&mut batch);
+    } else if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+async fn reduce_batch(ctx: &ContentBaseCtx, batch: &[String]) -> anyhow::Result<String> {
+    let llm = ctx.llm()?.0;
+    let user_prompt = batch
+        .iter()
+        .enumerate()
+        .map(|(idx, summary)| format!("Part {}:\n{}", idx + 1, summary))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut response = llm
+        .process_single((
+            vec![
+                LLMMessage::new_system(ASSET_SUMMARY_SYSTEM_PROMPT),
+                LLMMessage::new_user(&user_prompt),
+            ],
+            LLMInferenceParams::default(),
+        ))
+        .await?;
+
+    Ok(response.to_string().await?.trim().to_string())
+}
+
+/// 把分段总结逐层合并成一个总结
+/// 每一轮按 token 数分批，每一批用 LLM 合并成一个总结，直到只剩一个
+pub(crate) async fn reduce_summaries(
+    ctx: &ContentBaseCtx,
+    mut summaries: Vec<String>,
+) -> anyhow::Result<String> {
+    if summaries.is_empty() {
+        anyhow::bail!("no chunk summarization to reduce");
+    }
+    let (tokenizer, _) = ctx.text_tokenizer()?;
+    let count_tokens = |text: &str| count_text_tokens(tokenizer, text);
+
+    while summaries.len() > 1 {
+        let batches = batch_summaries(&summaries, SUMMARY_BATCH_TOKENS, count_tokens);
+        let mut reduced = vec![];
+        for batch in batches.iter() {
+            reduced.push(reduce_batch(ctx, batch).await?);
+        }
+        tracing::debug!("reduce {} summaries to {}", summaries.len(), reduced.len());
+        summaries = reduced;
+    }
+
+    Ok(summaries.remove(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::batch_summaries;
+
+    #[test]
+    fn test_batch_summaries() {
+        let summaries = ["aaaa", "bbbb", "cccc", "dd", "eeeeeeee"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        let count_tokens = |text: &str| text.len();
+
+        let batches = batch_summaries(&summaries, 10, count_tokens);
+        assert_eq!(
+            batches,
+            vec![vec!["aaaa", "bbbb"], vec!["cccc", "dd", "eeeeeeee"],]
+        );
+
+        // 单个总结超出上限的时候，也至少两个一批
+        let batches = batch_summaries(&summaries, 1, count_tokens);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].len(), 3);
+
+        let batches = batch_summaries(&summaries, 100, count_tokens);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 5);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{summary::count_text_tokens, ContentTaskType, FileInfo, TaskRunOutput, TaskRunRecord};
+use ai::llm::{LLMInferenceParams, LLMMessage};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+/// 发给 LLM 的素材内容的 token 上限，超出的时候均匀地抽取一部分
+const TAGS_INPUT_TOKENS: usize = 2048;
+
+/// 固定采样温度和种子，同样的内容和设置重新运行的时候得到同样的标签
+const TAGS_TEMPERATURE: f64 = 0.0;
+const TAGS_SEED: u64 = 42;
+
+/// 单个标签的最大字符数，更长的一般是 LLM 没有按要求输出的句子
+const MAX_TAG_CHARS: usize = 64;
+
+fn tags_format_prompt(max_tags: usize) -> String {
+    format!(
+        r#"Response Format:
+- Respond with a JSON array of no more than {} strings, for example ["outdoor", "product demo", "female speaker"].
+- Do not respond with anything else."#,
+        max_tags
+    )
+}
+
+/// 按顺序均匀地抽取内容，直到总 token 数不超过上限，保证开头到结尾都有内容被选中
+pub(crate) fn pack_sources(
+    sources: &[String],
+    max_tokens: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Vec<String> {
+    let tokens = sources.iter().map(|v| count_tokens(v)).collect::<Vec<_>>();
+    let mut step = 1;
+    while step < sources.len() && tokens.iter().step_by(step).sum::<usize>() > max_tokens {
+        step += 1;
+    }
+    sources.iter().step_by(step).cloned().collect()
+}
+
+/// 从 LLM 的返回中取出 JSON 数组，返回里可能带有 markdown 代码块或者其他文字
+pub(crate) fn parse_tags(response: &str) -> anyhow::Result<Vec<String>> {
+    let (Some(start), Some(end)) = (response.find('['), response.rfind(']')) else {
+        anyhow::bail!("no json array found in tags response: {}", response);
+    };
+    if start > end {
+        anyhow::bail!("no json array found in tags response: {}", response);
+    }
+    let values: Vec<Value> = serde_json::from_str(&response[start..=end])?;
+    Ok(values
+        .into_iter()
+        .filter_map(|v| v.as_str().map(|v| v.to_string()))
+        .collect())
+}
+
+/// 统一标签的格式，去掉空的、过长的和重复的标签，最多保留 `max_tags` 个
+pub(crate) fn normalize_tags(tags: Vec<String>, max_tags: usize) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for tag in tags {
+        let tag = tag
+            .trim()
+            .trim_start_matches('#')
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        if tag.is_empty() || tag.chars().count() > MAX_TAG_CHARS || result.contains(&tag) {
+            continue;
+        }
+        result.push(tag);
+    }
+    result.truncate(max_tags);
+    result
+}
+
+/// 用 LLM 根据素材的画面描述、转录和分段总结生成关键词标签
+#[async_trait]
+pub trait TagsTrait: Into<ContentTaskType> + Clone + Storage {
+    /// 用来生成标签的内容，按在素材中的先后顺序排列
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>>;
+
+    async fn tags_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn run_tags(
+        &self,
+        file_info: &FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let sources = self.tag_sources(&file_info.file_identifier, ctx).await?;
+        if sources.is_empty() {
+            anyhow::bail!("no content to generate tags");
+        }
+
+        let (tokenizer, _) = ctx.text_tokenizer()?;
+        let sources = pack_sources(&sources, TAGS_INPUT_TOKENS, |text| {
+            count_text_tokens(tokenizer, text)
+        });
+
+        let config = ctx.tagging();
+        let system_prompt = format!(
+            "{}\n\n{}",
+            config.prompt.trim(),
+            tags_format_prompt(config.max_tags)
+        );
+        let llm = ctx.llm()?.0;
+        let mut response = llm
+            .process_single((
+                vec![
+                    LLMMessage::new_system(&system_prompt),
+                    LLMMessage::new_user(&sources.join("\n")),
+                ],
+                LLMInferenceParams::default()
+                    .with_temperature(TAGS_TEMPERATURE)
+                    .with_seed(TAGS_SEED),
+            ))
+            .await?;
+        let response = response.to_string().await?;
+        let tags = normalize_tags(parse_tags(&response)?, config.max_tags);
+
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+        self.write(
+            output_path,
+            json!({
+                "tags": tags
+            })
+            .to_string()
+            .into(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// 提示词和标签数量也是参数，修改设置以后会重新生成标签
+    fn tags_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        let config = ctx.tagging();
+        json!({
+            "model": ctx.llm().expect("llm is set").1,
+            "prompt": config.prompt,
+            "max_tags": config.max_tags,
+        })
+    }
+
+    async fn tags_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+
+        let content_str = self.read_to_string(output_path)?;
+        let json_string: Value = serde_json::from_str(&content_str)?;
+        let tags = json_string["tags"]
+            .as_array()
+            .ok_or(anyhow::anyhow!("no tags found in tags file"))?
+            .iter()
+            .filter_map(|v| v.as_str().map(|v| v.to_string()))
+            .collect();
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{normalize_tags, pack_sources, parse_tags};
+
+    #[test]
+    fn test_pack_sources() {
+        let sources = ["aaaa", "bbbb", "cccc", "dddd", "eeee"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        let count_tokens = |text: &str| text.len();
+
+        assert_eq!(pack_sources(&sources, 100, count_tokens).len(), 5);
+        assert_eq!(
+            pack_sources(&sources, 12, count_tokens),
+            vec!["aaaa", "cccc", "eeee"]
+        );
+        assert_eq!(
+            pack_sources(&sources, 8, count_tokens),
+            vec!["aaaa", "dddd"]
+        );
+        // 一条都放不下的时候保留第一条
+        assert_eq!(pack_sources(&sources, 1, count_tokens), vec!["aaaa"]);
+    }
+
+    #[test]
+    fn test_parse_and_normalize_tags() {
+        let response = r##"```json
+["Outdoor", " product   demo ", "#Female Speaker", "outdoor", "", 1]
+```"##;
+        let tags = parse_tags(response).unwrap();
+        assert_eq!(tags.len(), 5);
+        assert_eq!(
+            normalize_tags(tags.clone(), 10),
+            vec!["outdoor", "product demo", "female speaker"]
+        );
+        assert_eq!(normalize_tags(tags, 2), vec!["outdoor", "product demo"]);
+
+        assert!(parse_tags("no tags").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use super::{
+    frame::{VideoFrameTask, VIDEO_FRAME_SUMMARY_BATCH_SIZE},
+    frame_description::VideoFrameDescriptionTask,
+    trans_chunk::VideoTransChunkTask,
+    trans_chunk_sum::VideoTransChunkSumTask,
+    VideoTaskType,
+};
+use crate::{
+    audio::{trans_chunk::AudioTranscriptChunkTrait, trans_chunk_sum::AudioTransChunkSumTrait},
+    summary::{batch_summaries, count_text_tokens},
+    ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use ai::llm::{LLMInferenceParams, LLMMessage};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+/// 每次让 LLM 分章节的时间线的 token 上限，长视频会分成几段时间线分别处理
+const CHAPTER_WINDOW_TOKENS: usize = 2048;
+
+/// 章节的最短时长，YouTube 要求每个章节至少 10 秒
+const MIN_CHAPTER_DURATION_MS: i64 = 10_000;
+
+const CHAPTER_SYSTEM_PROMPT: &str = r#"You are an assistant skilled in splitting videos into chapters.
+You will be given a timeline of a video, each line starts with a time range like [01:05 - 01:30], followed by a transcript summary or a visual description of the clip.
+You should split the timeline into chapters according to the changes of topics and scenes, and give each chapter a short title.
+
+Guidelines:
+- A chapter should cover a complete topic or scene, do not create a chapter for every line.
+- Title: no more than 8 words, describe what the chapter is about.
+- Start: the start time of the chapter, which must be one of the start times in the timeline, in the format mm:ss or h:mm:ss.
+
+Additional Rules:
+- Response with a JSON array only, do not contain anything else, for example:
+[{"title": "Introduction", "start": "00:00"}, {"title": "Unboxing the camera", "start": "01:05"}]
+- Language: titles should be in the same language with the timeline"#;
+
+/// 视频的一个章节，时间都是毫秒
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoChapter {
+    pub title: String,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+/// LLM 返回的章节，只有开始时间，结束时间由下一个章节决定
+#[derive(Debug, Deserialize)]
+struct ChapterCandidate {
+    title: String,
+    start: Value,
+}
+
+/// 毫秒转换成 mm:ss，超过一小时或者 `with_hours` 的时候是 h:mm:ss
+///
+/// 给 LLM 的时间线、问答的上下文和导出的章节列表都用这个格式
+pub fn format_timestamp(timestamp: i64, with_hours: bool) -> String {
+    let seconds = timestamp.max(0) / 1000;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if with_hours || hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+/// 解析 LLM 返回的开始时间，支持 mm:ss、h:mm:ss 和秒数，返回毫秒
+fn parse_timestamp(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(seconds) => seconds.as_f64().map(|v| (v * 1000.0) as i64),
+        Value::String(text) => {
+            let mut seconds = 0;
+            for part in text.trim().split(':') {
+                seconds = seconds * 60 + part.trim().parse::<i64>().ok()?;
+            }
+            Some(seconds * 1000)
+        }
+        _ => None,
+    }
+}
+
+/// LLM 有时会在 JSON 前后加上说明或者 markdown 代码块，只取第一个 [ 到最后一个 ] 之间的内容
+fn parse_chapter_candidates(response: &str) -> anyhow::Result<Vec<(String, i64)>> {
+    let start = response.find('[');
+    let end = response.rfind(']');
+    let candidates: Vec<ChapterCandidate> = match (start, end) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&response[start..=end])?,
+        _ => anyhow::bail!("no json array found in response: {}", response),
+    };
+    Ok(candidates
+        .into_iter()
+        .filter_map(|v| Some((v.title.trim().to_string(), parse_timestamp(&v.start)?)))
+        .filter(|(title, _)| !title.is_empty())
+        .collect())
+}
+
+/// 把 LLM 返回的章节整理成首尾相接的章节
+/// - 按开始时间排序，第一个章节从 0 开始，最后一个章节到视频结束
+/// - 和上一个章节标题一样（比如分段处理时间线的时候跨段的章节）或者离上一个章节太近的合并到上一个章节
+fn normalize_chapters(mut candidates: Vec<(String, i64)>, duration: i64) -> Vec<VideoChapter> {
+    candidates.sort_by_key(|(_, start)| *start);
+    let mut chapters: Vec<VideoChapter> = vec![];
+    for (title, start) in candidates {
+        let start = start.clamp(0, duration);
+        match chapters.last() {
+            Some(last)
+                if last.title == title
+                    || start - last.start_timestamp < MIN_CHAPTER_DURATION_MS => {}
+            _ => chapters.push(VideoChapter {
+                title,
+                start_timestamp: if chapters.is_empty() { 0 } else { start },
+                end_timestamp: duration,
+            }),
+        }
+    }
+    if chapters.len() > 1 {
+        let last = chapters.last().expect("last chapter exists");
+        if duration - last.start_timestamp < MIN_CHAPTER_DURATION_MS {
+            chapters.pop();
+        }
+    }
+    let ends = chapters
+        .iter()
+        .skip(1)
+        .map(|v| v.start_timestamp)
+        .chain([duration])
+        .collect::<Vec<_>>();
+    for (chapter, end_timestamp) in chapters.iter_mut().zip(ends) {
+        chapter.end_timestamp = end_timestamp;
+    }
+    chapters
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoChapterTask;
+
+impl VideoChapterTask {
+    /// 转录分段的总结和画面描述按时间排成一条时间线，返回每一行和视频的时长
+    async fn timeline(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<(Vec<String>, i64)> {
+        let mut lines: Vec<(i64, String)> = vec![];
+        let mut duration = 0;
+
+        let chunks = VideoTransChunkTask
+            .chunk_content(file_identifier, ctx)
+            .await?;
+        for chunk in chunks.iter() {
+            let summarization = VideoTransChunkSumTask
+                .sum_content(
+                    file_identifier,
+                    ctx,
+                    chunk.start_timestamp,
+                    chunk.end_timestamp,
+                )
+                .await?;
+            lines.push((
+                chunk.start_timestamp,
+                format!(
+                    "[{} - {}] Transcript: {}",
+                    format_timestamp(chunk.start_timestamp, false),
+                    format_timestamp(chunk.end_timestamp, false),
+                    summarization.trim()
+                ),
+            ));
+            duration = duration.max(chunk.end_timestamp);
+        }
+
+        let frames = VideoFrameTask.frame_content(file_identifier, ctx).await?;
+        for frames_chunk in frames.chunks(VIDEO_FRAME_SUMMARY_BATCH_SIZE) {
+            let start_timestamp = frames_chunk.first().expect("first frame exists").timestamp;
+            let end_timestamp = frames_chunk.last().expect("last frame exists").timestamp;
+            let caption = VideoFrameDescriptionTask
+                .frame_description_content(file_identifier, ctx, start_timestamp, end_timestamp)
+                .await?;
+            lines.push((
+                start_timestamp,
+                format!(
+                    "[{} - {}] Visual: {}",
+                    format_timestamp(start_timestamp, false),
+                    format_timestamp(end_timestamp, false),
+                    caption.trim()
+                ),
+            ));
+            duration = duration.max(end_timestamp);
+        }
+
+        // sort_by_key 是稳定排序，同一时间开始的转录排在画面前面
+        lines.sort_by_key(|(start_timestamp, _)| *start_timestamp);
+        Ok((lines.into_iter().map(|(_, line)| line).collect(), duration))
+    }
+}
+
+#[async_trait]
+impl ContentTask for VideoChapterTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let (lines, duration) = self.timeline(&file_info.file_identifier, ctx).await?;
+        if lines.is_empty() {
+            anyhow::bail!("no transcript or frame description to split chapters");
+        }
+
+        let llm = ctx.llm()?.0;
+        let (tokenizer, _) = ctx.text_tokenizer()?;
+        let windows = batch_summaries(&lines, CHAPTER_WINDOW_TOKENS, |text| {
+            count_text_tokens(tokenizer, text)
+        });
+        let mut candidates = vec![];
+        for window in windows.iter() {
+            let mut response = llm
+                .process_single((
+                    vec![
+                        LLMMessage::new_system(CHAPTER_SYSTEM_PROMPT),
+                        LLMMessage::new_user(&window.join("\n")),
+                    ],
+                    LLMInferenceParams::default(),
+                ))
+                .await?;
+            let response = response.to_string().await?;
+            candidates.extend(parse_chapter_candidates(&response)?);
+        }
+
+        let chapters = normalize_chapters(candidates, duration);
+        if chapters.is_empty() {
+            anyhow::bail!("no chapters generated for video");
+        }
+
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+        self.write(output_path, serde_json::to_string(&chapters)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        json!({
+            "model": ctx.llm().expect("llm is set").1
+        })
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![
+            VideoTransChunkSumTask.into(),
+            VideoFrameDescriptionTask.into(),
+        ]
+    }
+}
+
+impl Into<ContentTaskType> for VideoChapterTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::Chapter(self.clone()))
+    }
+}
+
+impl VideoChapterTask {
+    /// 按开始时间排序的章节
+    pub async fn chapters_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<VideoChapter>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_timestamp, normalize_chapters, parse_chapter_candidates, VideoChapter};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0, false), "00:00");
+        assert_eq!(format_timestamp(65_500, false), "01:05");
+        assert_eq!(format_timestamp(3_723_000, false), "1:02:03");
+        assert_eq!(format_timestamp(65_500, true), "0:01:05");
+    }
+
+    #[test]
+    fn test_parse_chapter_candidates() {
+        let response = r#"```json
+[{"title": "Intro", "start": "00:05"}, {"title": "Details", "start": "1:02:03"}, {"title": "End", "start": 90}, {"title": "", "start": "00:10"}, {"title": "Bad", "start": "soon"}]
+```"#;
+        assert_eq!(
+            parse_chapter_candidates(response).unwrap(),
+            vec![
+                ("Intro".to_string(), 5_000),
+                ("Details".to_string(), 3_723_000),
+                ("End".to_string(), 90_000),
+            ]
+        );
+        assert!(parse_chapter_candidates("no chapters").is_err());
+    }
+
+    #[test]
+    fn test_normalize_chapters() {
+        let candidates = vec![
+            ("Setup".to_string(), 60_000),
+            ("Intro".to_string(), 5_000),
+            // 离上一个章节太近
+            ("Too close".to_string(), 65_000),
+            // 分段处理时间线的时候跨段的章节
+            ("Setup".to_string(), 100_000),
+            ("Review".to_string(), 150_000),
+            // 超出视频时长，而且最后一个章节太短
+            ("Outro".to_string(), 500_000),
+        ];
+        assert_eq!(
+            normalize_chapters(candidates, 200_000),
+            vec![
+                VideoChapter {
+                    title: "Intro".to_string(),
+                    start_timestamp: 0,
+                    end_timestamp: 60_000,
+                },
+                VideoChapter {
+                    title: "Setup".to_string(),
+                    start_timestamp: 60_000,
+                    end_timestamp: 150_000,
+                },
+                VideoChapter {
+                    title: "Review".to_string(),
+                    start_timestamp: 150_000,
+                    end_timestamp: 200_000,
+                },
+            ]
+        );
+        assert_eq!(normalize_chapters(vec![], 200_000), vec![]);
+    }
+}
@@ -0,0 +1,103 @@
+use super::{frame::VideoFrameTask, VideoTaskType};
+use crate::{
+    image::object_detection::{detect_objects, DetectedObject},
+    ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameObjects {
+    pub timestamp: i64,
+    pub objects: Vec<DetectedObject>,
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoFrameObjectDetectionTask;
+
+#[async_trait]
+impl ContentTask for VideoFrameObjectDetectionTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let frame_infos = VideoFrameTask
+            .frame_content(&file_info.file_identifier, ctx)
+            .await?;
+        // 所有帧的检测结果存在一个文件里，没有检测到物体的帧也保留，方便按时间戳对齐
+        let mut frames = vec![];
+        for frame_info in frame_infos {
+            let image_absolute_path = self
+                .get_absolute_path(frame_info.image_file.clone())
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to get absolute path for frame image file {:?}: {:?}",
+                        frame_info.image_file.clone(),
+                        e
+                    )
+                })?;
+            frames.push(FrameObjects {
+                timestamp: frame_info.timestamp,
+                objects: detect_objects(image_absolute_path, ctx).await?,
+            });
+        }
+
+        self.write(output_path, serde_json::to_string(&frames)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        // 模型是可选的，未配置时返回 Null，这样不会和任何已有的运行记录匹配
+        match ctx.object_detection() {
+            Ok((_, model_id)) => json!({
+                "model": model_id,
+            }),
+            Err(_) => Value::Null,
+        }
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoFrameTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoFrameObjectDetectionTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::FrameObjectDetection(self.clone()))
+    }
+}
+
+impl VideoFrameObjectDetectionTask {
+    /// 按时间戳排序的每一帧的检测结果
+    pub async fn frame_objects_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<FrameObjects>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
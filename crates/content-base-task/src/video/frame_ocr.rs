@@ -0,0 +1,219 @@
+use super::{frame::VideoFrameTask, VideoTaskType};
+use crate::{
+    image::ocr::{blocks_text, recognize_text, OcrTextBlock},
+    ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameText {
+    pub timestamp: i64,
+    pub blocks: Vec<OcrTextBlock>,
+}
+
+/// 连续几帧上一样的文字，比如字幕和幻灯片
+#[derive(Clone, Debug, PartialEq)]
+pub struct OcrSegment {
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub text: String,
+}
+
+/// 把相邻帧上识别到的一样的文字合并成一段，没有文字的帧会打断前后的合并
+pub fn ocr_segments(frames: &[FrameText]) -> Vec<OcrSegment> {
+    let mut segments: Vec<OcrSegment> = vec![];
+    let mut last_text = String::new();
+    for frame in frames {
+        let text = blocks_text(&frame.blocks);
+        if !text.is_empty() && text == last_text {
+            if let Some(segment) = segments.last_mut() {
+                segment.end_timestamp = frame.timestamp;
+            }
+        } else if !text.is_empty() {
+            segments.push(OcrSegment {
+                start_timestamp: frame.timestamp,
+                end_timestamp: frame.timestamp,
+                text: text.clone(),
+            });
+        }
+        last_text = text;
+    }
+    segments
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoFrameOcrTask;
+
+#[async_trait]
+impl ContentTask for VideoFrameOcrTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let frame_infos = VideoFrameTask
+            .frame_content(&file_info.file_identifier, ctx)
+            .await?;
+        // 所有帧的识别结果存在一个文件里，没有文字的帧也保留，方便按时间戳对齐
+        let mut frames = vec![];
+        for frame_info in frame_infos {
+            let image_absolute_path = self
+                .get_absolute_path(frame_info.image_file.clone())
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to get absolute path for frame image file {:?}: {:?}",
+                        frame_info.image_file.clone(),
+                        e
+                    )
+                })?;
+            frames.push(FrameText {
+                timestamp: frame_info.timestamp,
+                blocks: recognize_text(image_absolute_path, ctx).await?,
+            });
+        }
+
+        self.write(output_path, serde_json::to_string(&frames)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        // 模型是可选的，未配置时返回 Null，这样不会和任何已有的运行记录匹配
+        match ctx.ocr() {
+            Ok((_, model_id)) => json!({
+                "model": model_id,
+            }),
+            Err(_) => Value::Null,
+        }
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoFrameTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoFrameOcrTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::FrameOcr(self.clone()))
+    }
+}
+
+impl VideoFrameOcrTask {
+    /// 按时间戳排序的每一帧的识别结果
+    pub async fn frame_text_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<FrameText>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+
+    pub async fn ocr_segments_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<OcrSegment>> {
+        let frames = self.frame_text_content(file_identifier, ctx).await?;
+        Ok(ocr_segments(&frames))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ocr_segments, FrameText, OcrSegment};
+    use crate::image::{object_detection::BoundingBox, ocr::OcrTextBlock};
+
+    #[test]
+    fn test_ocr_segments() {
+        let bbox = BoundingBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 1.0,
+            y_max: 1.0,
+        };
+        let first_page = OcrTextBlock {
+            text: "第一页".to_string(),
+            confidence: 0.9,
+            bbox: bbox.clone(),
+        };
+        let title = OcrTextBlock {
+            text: "标题".to_string(),
+            confidence: 0.9,
+            bbox: bbox.clone(),
+        };
+        let second_page = OcrTextBlock {
+            text: "第二页".to_string(),
+            confidence: 0.9,
+            bbox,
+        };
+        let frames = vec![
+            FrameText {
+                timestamp: 0,
+                blocks: vec![first_page.clone(), title.clone()],
+            },
+            FrameText {
+                timestamp: 1000,
+                blocks: vec![first_page.clone(), title.clone()],
+            },
+            FrameText {
+                timestamp: 2000,
+                blocks: vec![],
+            },
+            FrameText {
+                timestamp: 3000,
+                blocks: vec![first_page, title],
+            },
+            FrameText {
+                timestamp: 4000,
+                blocks: vec![second_page.clone()],
+            },
+            FrameText {
+                timestamp: 5000,
+                blocks: vec![second_page],
+            },
+        ];
+        assert_eq!(
+            ocr_segments(&frames),
+            vec![
+                OcrSegment {
+                    start_timestamp: 0,
+                    end_timestamp: 1000,
+                    text: "第一页\n标题".to_string(),
+                },
+                OcrSegment {
+                    start_timestamp: 3000,
+                    end_timestamp: 3000,
+                    text: "第一页\n标题".to_string(),
+                },
+                OcrSegment {
+                    start_timestamp: 4000,
+                    end_timestamp: 5000,
+                    text: "第二页".to_string(),
+                },
+            ]
+        );
+    }
+}
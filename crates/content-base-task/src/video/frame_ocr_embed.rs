@@ -0,0 +1,80 @@
+use super::{frame_ocr::VideoFrameOcrTask, VideoTaskType};
+use crate::{ContentTask, ContentTaskType, FileInfo, TaskRunOutput, TaskRunRecord};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoFrameOcrEmbedTask;
+
+#[async_trait]
+impl ContentTask for VideoFrameOcrEmbedTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::Folder(PathBuf::from(format!(
+            "{}-{}",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let segments = VideoFrameOcrTask
+            .ocr_segments_content(&file_info.file_identifier, ctx)
+            .await?;
+        for segment in segments {
+            let output_path = output_path.join(format!(
+                "{}-{}.json",
+                segment.start_timestamp, segment.end_timestamp
+            ));
+            ctx.save_text_embedding(&segment.text, &output_path).await?;
+        }
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        json!({
+            "model": ctx.text_embedding().expect("text embedding is set").1
+        })
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoFrameOcrTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoFrameOcrEmbedTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::FrameOcrEmbed(self.clone()))
+    }
+}
+
+impl VideoFrameOcrEmbedTask {
+    pub async fn frame_ocr_embed_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+        start_timestamp: i64,
+        end_timestamp: i64,
+    ) -> anyhow::Result<Vec<f32>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type
+            .task_output_path(file_identifier, ctx)
+            .await?
+            .join(format!("{}-{}.json", start_timestamp, end_timestamp));
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
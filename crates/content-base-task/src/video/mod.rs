@@ -1,27 +1,43 @@
 pub mod audio;
+pub mod chapter;
+pub mod chapter_embed;
 pub mod frame;
 pub mod frame_desc_embed;
 pub mod frame_description;
 pub mod frame_embedding;
+pub mod frame_object_detection;
+pub mod frame_ocr;
+pub mod frame_ocr_embed;
+pub mod perceptual_hash;
+pub mod tags;
 pub mod thumbnail;
 pub mod trans_chunk;
 pub mod trans_chunk_sum;
 pub mod trans_chunk_sum_embed;
+pub mod trans_sum;
 pub mod transcript;
 
 use crate::task::ContentTaskType;
 use audio::VideoAudioTask;
+use chapter::VideoChapterTask;
+use chapter_embed::VideoChapterEmbedTask;
 use content_base_derive::ContentTask;
 use frame::VideoFrameTask;
 use frame_desc_embed::VideoFrameDescEmbedTask;
 use frame_description::VideoFrameDescriptionTask;
 use frame_embedding::VideoFrameEmbeddingTask;
+use frame_object_detection::VideoFrameObjectDetectionTask;
+use frame_ocr::VideoFrameOcrTask;
+use frame_ocr_embed::VideoFrameOcrEmbedTask;
+use perceptual_hash::VideoPerceptualHashTask;
 use storage_macro::Storage;
 use strum_macros::{EnumIter, EnumString};
+use tags::{VideoTagsTask, VideoVisualTagsTask};
 use thumbnail::VideoThumbnailTask;
 use trans_chunk::VideoTransChunkTask;
 use trans_chunk_sum::VideoTransChunkSumTask;
 use trans_chunk_sum_embed::VideoTransChunkSumEmbedTask;
+use trans_sum::VideoTransSumTask;
 use transcript::VideoTranscriptTask;
 
 #[derive(Clone, Debug, EnumIter, EnumString, strum_macros::Display, ContentTask, Storage)]
@@ -37,6 +53,15 @@ pub enum VideoTaskType {
     TransChunk(VideoTransChunkTask),
     TransChunkSum(VideoTransChunkSumTask),
     TransChunkSumEmbed(VideoTransChunkSumEmbedTask),
+    TransSum(VideoTransSumTask),
+    PerceptualHash(VideoPerceptualHashTask),
+    FrameObjectDetection(VideoFrameObjectDetectionTask),
+    FrameOcr(VideoFrameOcrTask),
+    FrameOcrEmbed(VideoFrameOcrEmbedTask),
+    Chapter(VideoChapterTask),
+    ChapterEmbed(VideoChapterEmbedTask),
+    Tags(VideoTagsTask),
+    VisualTags(VideoVisualTagsTask),
 }
 
 impl Into<ContentTaskType> for VideoTaskType {
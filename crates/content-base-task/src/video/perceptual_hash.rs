@@ -0,0 +1,99 @@
+use super::{frame::VideoFrameTask, VideoTaskType};
+use crate::{
+    image::perceptual_hash::dhash_from_file, ContentTask, ContentTaskType, TaskRunOutput,
+    TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FramePerceptualHash {
+    pub timestamp: i64,
+    pub hash: u64,
+}
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoPerceptualHashTask;
+
+#[async_trait]
+impl ContentTask for VideoPerceptualHashTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        let task_type: ContentTaskType = self.clone().into();
+        Ok(TaskRunOutput::File(PathBuf::from(format!(
+            "{}-{}.json",
+            task_type.to_string(),
+            task_run_record.id()
+        ))))
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        let output_path = task_run_record
+            .output_path(&file_info.file_identifier, ctx)
+            .await?;
+
+        let frame_infos = VideoFrameTask
+            .frame_content(&file_info.file_identifier, ctx)
+            .await?;
+        // 所有帧的 hash 存在一个文件里，每个 hash 只有 8 个字节
+        let mut hashes = vec![];
+        for frame_info in frame_infos {
+            let image_absolute_path = self
+                .get_absolute_path(frame_info.image_file.clone())
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to get absolute path for frame image file {:?}: {:?}",
+                        frame_info.image_file.clone(),
+                        e
+                    )
+                })?;
+            hashes.push(FramePerceptualHash {
+                timestamp: frame_info.timestamp,
+                hash: dhash_from_file(&image_absolute_path)?,
+            });
+        }
+
+        self.write(output_path, serde_json::to_string(&hashes)?.into())
+            .await?;
+
+        Ok(())
+    }
+
+    fn task_parameters(&self, _: &ContentBaseCtx) -> Value {
+        json!({
+            "method": "dhash",
+        })
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoFrameTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoPerceptualHashTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::PerceptualHash(self.clone()))
+    }
+}
+
+impl VideoPerceptualHashTask {
+    /// 按时间戳排序的每一帧的 hash
+    pub async fn hash_content(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<FramePerceptualHash>> {
+        let task_type: ContentTaskType = self.clone().into();
+        let output_path = task_type.task_output_path(file_identifier, ctx).await?;
+        let content_str = self.read_to_string(output_path)?;
+        Ok(serde_json::from_str(&content_str)?)
+    }
+}
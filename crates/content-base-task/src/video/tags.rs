@@ -0,0 +1,147 @@
+use super::{
+    frame::{VideoFrameTask, VIDEO_FRAME_SUMMARY_BATCH_SIZE},
+    frame_description::VideoFrameDescriptionTask,
+    trans_chunk::VideoTransChunkTask,
+    trans_chunk_sum::VideoTransChunkSumTask,
+    VideoTaskType,
+};
+use crate::{
+    audio::{trans_chunk::AudioTranscriptChunkTrait, trans_chunk_sum::AudioTransChunkSumTrait},
+    tags::TagsTrait,
+    ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+/// 画面描述，带上开始时间用来和转录排序
+async fn frame_descriptions(
+    file_identifier: &str,
+    ctx: &ContentBaseCtx,
+) -> anyhow::Result<Vec<(i64, String)>> {
+    let mut lines = vec![];
+    let frames = VideoFrameTask.frame_content(file_identifier, ctx).await?;
+    for frames_chunk in frames.chunks(VIDEO_FRAME_SUMMARY_BATCH_SIZE) {
+        let start_timestamp = frames_chunk.first().expect("first frame exists").timestamp;
+        let end_timestamp = frames_chunk.last().expect("last frame exists").timestamp;
+        let caption = VideoFrameDescriptionTask
+            .frame_description_content(file_identifier, ctx, start_timestamp, end_timestamp)
+            .await?;
+        lines.push((start_timestamp, format!("Visual: {}", caption.trim())));
+    }
+    Ok(lines)
+}
+
+/// 有音频的视频，用画面描述和转录的分段总结生成标签
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoTagsTask;
+
+#[async_trait]
+impl TagsTrait for VideoTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut lines = frame_descriptions(file_identifier, ctx).await?;
+        let chunks = VideoTransChunkTask
+            .chunk_content(file_identifier, ctx)
+            .await?;
+        for chunk in chunks.iter() {
+            let summarization = VideoTransChunkSumTask
+                .sum_content(
+                    file_identifier,
+                    ctx,
+                    chunk.start_timestamp,
+                    chunk.end_timestamp,
+                )
+                .await?;
+            lines.push((
+                chunk.start_timestamp,
+                format!("Transcript: {}", summarization.trim()),
+            ));
+        }
+        lines.sort_by_key(|(start_timestamp, _)| *start_timestamp);
+        Ok(lines.into_iter().map(|(_, line)| line).collect())
+    }
+}
+
+#[async_trait]
+impl ContentTask for VideoTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![
+            VideoTransChunkSumTask.into(),
+            VideoFrameDescriptionTask.into(),
+        ]
+    }
+}
+
+impl Into<ContentTaskType> for VideoTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::Tags(self.clone()))
+    }
+}
+
+/// 没有音频的视频，只用画面描述生成标签
+#[derive(Clone, Debug, Default, Storage)]
+pub struct VideoVisualTagsTask;
+
+#[async_trait]
+impl TagsTrait for VideoVisualTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        let lines = frame_descriptions(file_identifier, ctx).await?;
+        Ok(lines.into_iter().map(|(_, line)| line).collect())
+    }
+}
+
+#[async_trait]
+impl ContentTask for VideoVisualTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoFrameDescriptionTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoVisualTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::VisualTags(self.clone()))
+    }
+}
@@ -0,0 +1,57 @@
+use super::{
+    trans_chunk::VideoTransChunkTask, trans_chunk_sum::VideoTransChunkSumTask, VideoTaskType,
+};
+use crate::{
+    audio::{
+        trans_chunk::AudioTranscriptChunkTrait, trans_chunk_sum::AudioTransChunkSumTrait,
+        trans_sum::AudioTransSumTrait,
+    },
+    record::{TaskRunOutput, TaskRunRecord},
+    ContentTask, ContentTaskType,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use storage_macro::Storage;
+
+#[derive(Clone, Storage, Debug, Default)]
+pub struct VideoTransSumTask;
+
+impl AudioTransSumTrait for VideoTransSumTask {
+    fn chunk_task(&self) -> impl AudioTranscriptChunkTrait {
+        VideoTransChunkTask
+    }
+
+    fn chunk_sum_task(&self) -> impl AudioTransChunkSumTrait {
+        VideoTransChunkSumTask
+    }
+}
+
+#[async_trait]
+impl ContentTask for VideoTransSumTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.trans_sum_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_trans_sum(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> serde_json::Value {
+        self.trans_sum_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![VideoTransChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for VideoTransSumTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::Video(VideoTaskType::TransSum(self.clone()))
+    }
+}
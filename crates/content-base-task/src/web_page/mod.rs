@@ -2,6 +2,8 @@ pub mod transform;
 pub mod chunk;
 pub mod chunk_sum;
 pub mod chunk_sum_embed;
+pub mod sum;
+pub mod tags;
 
 use crate::ContentTaskType;
 use transform::WebPageTransformTask;
@@ -10,7 +12,9 @@ use chunk_sum::WebPageChunkSumTask;
 use chunk_sum_embed::WebPageChunkSumEmbedTask;
 use content_base_derive::ContentTask;
 use storage_macro::Storage;
+use sum::WebPageSumTask;
 use strum::{EnumIter, EnumString};
+use tags::WebPageTagsTask;
 
 #[derive(Clone, Debug, EnumIter, EnumString, strum_macros::Display, ContentTask, Storage)]
 #[strum(serialize_all = "kebab-case")]
@@ -19,6 +23,8 @@ pub enum WebPageTaskType {
     Chunk(WebPageChunkTask),
     ChunkSum(WebPageChunkSumTask),
     ChunkSumEmbed(WebPageChunkSumEmbedTask),
+    Sum(WebPageSumTask),
+    Tags(WebPageTagsTask),
 }
 
 impl Into<ContentTaskType> for WebPageTaskType {
@@ -0,0 +1,55 @@
+use crate::{
+    raw_text::{
+        chunk::DocumentChunkTrait, chunk_sum::DocumentChunkSumTrait, sum::DocumentSumTrait,
+    },
+    ContentTask, ContentTaskType, TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+use super::{chunk::WebPageChunkTask, chunk_sum::WebPageChunkSumTask, WebPageTaskType};
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct WebPageSumTask;
+
+impl DocumentSumTrait for WebPageSumTask {
+    fn chunk_task(&self) -> impl DocumentChunkTrait {
+        WebPageChunkTask
+    }
+
+    fn chunk_sum_task(&self) -> impl DocumentChunkSumTrait {
+        WebPageChunkSumTask
+    }
+}
+
+#[async_trait]
+impl ContentTask for WebPageSumTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.sum_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_document_sum(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.document_sum_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![WebPageChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for WebPageSumTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::WebPage(WebPageTaskType::Sum(self.clone()))
+    }
+}
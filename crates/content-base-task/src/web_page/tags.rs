@@ -0,0 +1,53 @@
+use super::{chunk_sum::WebPageChunkSumTask, WebPageTaskType};
+use crate::{
+    raw_text::tags::document_tag_sources, tags::TagsTrait, ContentTask, ContentTaskType,
+    TaskRunOutput, TaskRunRecord,
+};
+use async_trait::async_trait;
+use content_base_context::ContentBaseCtx;
+use serde_json::Value;
+use storage_macro::Storage;
+
+#[derive(Clone, Debug, Default, Storage)]
+pub struct WebPageTagsTask;
+
+#[async_trait]
+impl TagsTrait for WebPageTagsTask {
+    async fn tag_sources(
+        &self,
+        file_identifier: &str,
+        ctx: &ContentBaseCtx,
+    ) -> anyhow::Result<Vec<String>> {
+        document_tag_sources(&WebPageChunkSumTask, file_identifier, ctx).await
+    }
+}
+
+#[async_trait]
+impl ContentTask for WebPageTagsTask {
+    async fn task_output(&self, task_run_record: &TaskRunRecord) -> anyhow::Result<TaskRunOutput> {
+        self.tags_output(task_run_record).await
+    }
+
+    async fn inner_run(
+        &self,
+        file_info: &crate::FileInfo,
+        ctx: &ContentBaseCtx,
+        task_run_record: &mut TaskRunRecord,
+    ) -> anyhow::Result<()> {
+        self.run_tags(file_info, ctx, task_run_record).await
+    }
+
+    fn task_parameters(&self, ctx: &ContentBaseCtx) -> Value {
+        self.tags_parameters(ctx)
+    }
+
+    fn task_dependencies(&self) -> Vec<ContentTaskType> {
+        vec![WebPageChunkSumTask.into()]
+    }
+}
+
+impl Into<ContentTaskType> for WebPageTagsTask {
+    fn into(self) -> ContentTaskType {
+        ContentTaskType::WebPage(WebPageTaskType::Tags(self.clone()))
+    }
+}
@@ -184,3 +184,9 @@ pub const STOP_WORDS: [&str; 182] = [
     "it's",
     "here's",
 ];
+
+/// 中文停用词，分词以后再过滤
+pub const CJK_STOP_WORDS: [&str; 24] = [
+    "的", "了", "是", "在", "和", "与", "及", "或", "也", "都", "就", "而", "被", "把", "吗", "呢",
+    "吧", "啊", "着", "过", "这", "那", "这个", "那个",
+];
@@ -1,19 +1,38 @@
-use crate::db::DB;
+use crate::db::{EmbeddingSchema, ReindexScope, DB};
+use crate::duplicate::NearDuplicateCache;
+use crate::query::{QueryEmbeddingCache, QueryExpansionCache, SearchResultCache};
 use crate::ContentBase;
 use content_base_context::ContentBaseCtx;
 use content_base_pool::{TaskPool, TaskPriority};
 use content_base_task::{
-    audio::{trans_chunk_sum_embed::AudioTransChunkSumEmbedTask, waveform::AudioWaveformTask},
-    image::{desc_embed::ImageDescEmbedTask, embedding::ImageEmbeddingTask},
-    raw_text::chunk_sum_embed::RawTextChunkSumEmbedTask,
+    audio::{
+        tags::AudioTagsTask, trans_chunk_sum_embed::AudioTransChunkSumEmbedTask,
+        trans_sum::AudioTransSumTask, waveform::AudioWaveformTask,
+    },
+    image::{
+        desc_embed::ImageDescEmbedTask, embedding::ImageEmbeddingTask,
+        object_detection::ImageObjectDetectionTask, ocr_embed::ImageOcrEmbedTask,
+        perceptual_hash::ImagePerceptualHashTask, tags::ImageTagsTask,
+    },
+    raw_text::{
+        chunk_sum_embed::RawTextChunkSumEmbedTask, sum::RawTextSumTask, tags::RawTextTagsTask,
+    },
     video::{
         // frame::VideoFrameTask,
         // frame_description::VideoFrameDescriptionTask,
+        chapter_embed::VideoChapterEmbedTask,
         frame_desc_embed::VideoFrameDescEmbedTask,
         frame_embedding::VideoFrameEmbeddingTask,
+        frame_object_detection::VideoFrameObjectDetectionTask,
+        frame_ocr_embed::VideoFrameOcrEmbedTask,
+        perceptual_hash::VideoPerceptualHashTask,
+        tags::{VideoTagsTask, VideoVisualTagsTask},
         trans_chunk_sum_embed::VideoTransChunkSumEmbedTask,
+        trans_sum::VideoTransSumTask,
+    },
+    web_page::{
+        chunk_sum_embed::WebPageChunkSumEmbedTask, sum::WebPageSumTask, tags::WebPageTagsTask,
     },
-    web_page::chunk_sum_embed::WebPageChunkSumEmbedTask,
     ContentTaskType,
 };
 use content_metadata::ContentMetadata;
@@ -29,6 +48,10 @@ impl ContentBase {
             ctx: ctx.clone(),
             task_pool,
             surrealdb_client: db,
+            query_expansion_cache: QueryExpansionCache::new(),
+            query_embedding_cache: QueryEmbeddingCache::new(),
+            search_result_cache: SearchResultCache::new(),
+            near_duplicate_cache: NearDuplicateCache::new(),
         })
     }
 
@@ -36,8 +59,46 @@ impl ContentBase {
         &self.ctx
     }
 
+    /// 用新的 ctx（比如切换了模型）创建 ContentBase，复用已有的 TaskPool 和数据库连接
+    pub async fn with_ctx(&self, ctx: &ContentBaseCtx) -> Self {
+        self.task_pool.update_ctx(ctx).await;
+        // 模型变了以后搜索结果也会变
+        self.search_result_cache.clear();
+        self.query_embedding_cache.clear();
+        self.near_duplicate_cache.clear();
+        Self {
+            ctx: ctx.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// 根据当前的 embedding 模型同步向量索引，维度需要调用方根据模型配置提供
+    /// 返回需要重新处理的素材，模型变化以后对应的索引数据已经被清空
+    pub async fn sync_vector_indexes(
+        &self,
+        text_dim: usize,
+        multi_modal_dim: usize,
+    ) -> anyhow::Result<ReindexScope> {
+        let schema = EmbeddingSchema {
+            text_model: self.ctx.text_embedding()?.1.to_string(),
+            text_dim,
+            multi_modal_model: self.ctx.multi_modal_embedding()?.1.to_string(),
+            multi_modal_dim,
+        };
+        let scope = self
+            .surrealdb_client
+            .read()
+            .await
+            .sync_vector_indexes(&schema)
+            .await?;
+        self.search_result_cache.clear();
+        Ok(scope)
+    }
+
     /// 列出每种类型的内容处理需要执行的所有任务，因为有任务依赖关系，只需要列出最顶层的任务
+    /// 可选模型对应的任务（比如物体检测和 OCR）只有在 ctx 里设置了模型的时候才会列出
     pub fn get_content_processing_tasks(
+        ctx: &ContentBaseCtx,
         metadata: &ContentMetadata,
     ) -> Vec<(ContentTaskType, TaskPriority)> {
         let mut tasks = vec![];
@@ -48,28 +109,56 @@ impl ContentBase {
             ContentMetadata::Video(metadata) => {
                 if metadata.audio.is_some() {
                     tasks.push((VideoTransChunkSumEmbedTask.into(), TaskPriority::Low));
+                    tasks.push((VideoTransSumTask.into(), TaskPriority::Low));
+                    // 章节需要转录分段的总结，没有音频的视频不分章节
+                    tasks.push((VideoChapterEmbedTask.into(), TaskPriority::Low));
+                    tasks.push((VideoTagsTask.into(), TaskPriority::Low));
+                } else {
+                    // 任务依赖是固定的，没有音频的视频用只依赖画面描述的标签任务
+                    tasks.push((VideoVisualTagsTask.into(), TaskPriority::Low));
                 }
                 // tasks.push((VideoFrameTask.into(), TaskPriority::Low));
                 tasks.push((VideoFrameEmbeddingTask.into(), TaskPriority::Low));
                 tasks.push((VideoFrameDescEmbedTask.into(), TaskPriority::Low));
+                tasks.push((VideoPerceptualHashTask.into(), TaskPriority::Low));
+                if ctx.object_detection().is_ok() {
+                    tasks.push((VideoFrameObjectDetectionTask.into(), TaskPriority::Low));
+                }
+                if ctx.ocr().is_ok() {
+                    tasks.push((VideoFrameOcrEmbedTask.into(), TaskPriority::Low));
+                }
             }
             ContentMetadata::Audio(_metadata) => {
                 tasks.extend([
                     (AudioWaveformTask.into(), TaskPriority::Normal),
                     (AudioTransChunkSumEmbedTask.into(), TaskPriority::Normal),
+                    (AudioTransSumTask.into(), TaskPriority::Normal),
+                    (AudioTagsTask.into(), TaskPriority::Normal),
                 ]);
             }
             ContentMetadata::Image(_) => {
                 tasks.extend([
                     (ImageEmbeddingTask.into(), TaskPriority::Normal),
                     (ImageDescEmbedTask.into(), TaskPriority::Normal),
+                    (ImagePerceptualHashTask.into(), TaskPriority::Normal),
+                    (ImageTagsTask.into(), TaskPriority::Normal),
                 ]);
+                if ctx.object_detection().is_ok() {
+                    tasks.push((ImageObjectDetectionTask.into(), TaskPriority::Normal));
+                }
+                if ctx.ocr().is_ok() {
+                    tasks.push((ImageOcrEmbedTask.into(), TaskPriority::Normal));
+                }
             }
             ContentMetadata::RawText(_) => {
                 tasks.push((RawTextChunkSumEmbedTask.into(), TaskPriority::Normal));
+                tasks.push((RawTextSumTask.into(), TaskPriority::Normal));
+                tasks.push((RawTextTagsTask.into(), TaskPriority::Normal));
             }
             ContentMetadata::WebPage(_) => {
                 tasks.push((WebPageChunkSumEmbedTask.into(), TaskPriority::Normal));
+                tasks.push((WebPageSumTask.into(), TaskPriority::Normal));
+                tasks.push((WebPageTagsTask.into(), TaskPriority::Normal));
             }
             _ => {
                 tracing::warn!("unsupported metadata, do not have any tasks");
@@ -19,7 +19,12 @@ impl DB {
             tracing::error!("Failed to initialize surrealdb: {}", e);
             e
         })?;
-        Ok(Self { client })
+        let db = Self { client };
+        // 迁移失败不影响打开 library，只是旧数据按词搜索不到
+        if let Err(e) = db.migrate_text_segmentation().await {
+            tracing::warn!("Failed to migrate text segmentation: {}", e);
+        }
+        Ok(db)
     }
 
     async fn init_db(path: impl AsRef<Path>) -> anyhow::Result<Surreal<Db>> {
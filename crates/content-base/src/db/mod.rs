@@ -10,6 +10,10 @@ pub mod search;
 pub mod shared;
 pub mod utils;
 
+pub use op::index::{EmbeddingSchema, ReindexScope};
+pub use op::tag::TagCount;
+pub use op::term::TermSuggestion;
+
 #[derive(Clone, Debug)]
 pub struct DB {
     #[cfg(feature = "embedded-db")]
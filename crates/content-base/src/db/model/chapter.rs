@@ -0,0 +1,59 @@
+use super::{id::ID, text::TextModel, ModelCreate};
+use async_trait::async_trait;
+use educe::Educe;
+use serde::Serialize;
+
+/// 视频的一个章节，章节标题存在 text 表里
+#[derive(Serialize, Educe, Clone)]
+#[educe(Debug)]
+pub struct ChapterFrameModel {
+    pub id: Option<ID>,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+const CHAPTER_FRAME_CREATE_STATEMENT: &'static str = r#"
+(CREATE ONLY chapter_frame CONTENT {{
+    start_timestamp: $start_timestamp,
+    end_timestamp: $end_timestamp
+}}).id
+"#;
+
+#[async_trait]
+impl<T> ModelCreate<T, (Self, Vec<TextModel>)> for ChapterFrameModel
+where
+    T: surrealdb::Connection,
+{
+    async fn create_only(
+        client: &surrealdb::Surreal<T>,
+        (chapter_frame, chapter_texts): &(Self, Vec<TextModel>),
+    ) -> anyhow::Result<surrealdb::sql::Thing> {
+        let text_records = TextModel::create_batch(client, chapter_texts).await?;
+        if text_records.is_empty() {
+            anyhow::bail!("Failed to insert chapter frame texts, texts is empty");
+        }
+        let mut resp = client
+            .query(CHAPTER_FRAME_CREATE_STATEMENT)
+            .bind(chapter_frame.clone())
+            .await?;
+        if let Err(errors_map) = crate::check_db_error_from_resp!(resp) {
+            anyhow::bail!("Failed to insert chapter frame, errors: {:?}", errors_map);
+        };
+        let Some(chapter_frame_record) = resp.take::<Option<surrealdb::sql::Thing>>(0)? else {
+            anyhow::bail!("Failed to insert chapter frame, no id returned");
+        };
+        tracing::debug!(id=%chapter_frame_record, "Chapter frame created in surrealdb");
+        client
+            .query("RELATE $relation_in -> contains -> $relation_outs;")
+            .bind(("relation_in", chapter_frame_record.clone()))
+            .bind(("relation_outs", text_records.clone()))
+            .await?;
+        Ok(chapter_frame_record)
+    }
+}
+
+impl ChapterFrameModel {
+    pub fn table() -> &'static str {
+        "chapter_frame"
+    }
+}
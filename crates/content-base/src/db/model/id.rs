@@ -9,6 +9,8 @@ pub enum TB {
     Item,
     ImageFrame,
     AudioFrame,
+    OcrFrame,
+    ChapterFrame,
     Audio,
     Video,
     Page,
@@ -28,6 +30,8 @@ impl TB {
         mapping.insert("item", TB::Item);
         mapping.insert("image_frame", TB::ImageFrame);
         mapping.insert("audio_frame", TB::AudioFrame);
+        mapping.insert("ocr_frame", TB::OcrFrame);
+        mapping.insert("chapter_frame", TB::ChapterFrame);
         mapping.insert("audio", TB::Audio);
         mapping.insert("video", TB::Video);
         mapping.insert("page", TB::Page);
@@ -1,5 +1,6 @@
-use super::{ModelCreate, ModelDelete};
+use super::{ocr::OcrFrameModel, text::TextModel, ModelCreate, ModelDelete};
 use crate::db::model::id::ID;
+use crate::segment::segment;
 use async_trait::async_trait;
 use educe::Educe;
 use serde::Serialize;
@@ -15,13 +16,17 @@ pub struct ImageModel {
     pub caption: String,
     #[educe(Debug(ignore))]
     pub caption_embedding: Vec<f32>,
+
+    /// 检测到的物体类别，去重以后的 COCO 类别名，没有配置物体检测模型的时候是空的
+    pub objects: Vec<String>,
 }
 
 const CREATE_STATEMENT: &'static str = r#"
 (CREATE ONLY image CONTENT {
     embedding: $embedding,
     caption: $caption,
-    caption_embedding: $caption_embedding
+    caption_embedding: $caption_embedding,
+    objects: $objects
 }).id
 "#;
 
@@ -34,7 +39,12 @@ where
         client: &surrealdb::Surreal<T>,
         image: &Self,
     ) -> anyhow::Result<surrealdb::sql::Thing> {
-        let mut resp = client.query(CREATE_STATEMENT).bind(image.clone()).await?;
+        // 中文需要先分词，全文索引才能按词命中
+        let image = ImageModel {
+            caption: segment(&image.caption),
+            ..image.clone()
+        };
+        let mut resp = client.query(CREATE_STATEMENT).bind(image).await?;
         if let Err(errors_map) = crate::check_db_error_from_resp!(resp) {
             anyhow::bail!("Failed to insert image, errors: {:?}", errors_map);
         };
@@ -46,14 +56,39 @@ where
     }
 }
 
+/// 单独的图片和图片上识别到的文字，视频帧和页面里的图片不做 OCR
+#[async_trait]
+impl<T> ModelCreate<T, (Self, Vec<(OcrFrameModel, Vec<TextModel>)>)> for ImageModel
+where
+    T: surrealdb::Connection,
+{
+    async fn create_only(
+        client: &surrealdb::Surreal<T>,
+        (image, ocr_frames): &(Self, Vec<(OcrFrameModel, Vec<TextModel>)>),
+    ) -> anyhow::Result<surrealdb::sql::Thing> {
+        let ocr_frame_records = OcrFrameModel::create_batch(client, ocr_frames).await?;
+        let image_record = ImageModel::create_only(client, image).await?;
+        if !ocr_frame_records.is_empty() {
+            client
+                .query("RELATE $relation_in -> contains -> $relation_outs;")
+                .bind(("relation_in", image_record.clone()))
+                .bind(("relation_outs", ocr_frame_records))
+                .await?;
+        }
+        Ok(image_record)
+    }
+}
+
 const IMAGE_DELETE_STATEMENT: &'static str = r#"
 LET $v = (
     SELECT
+        ->contains->ocr_frame AS ocr_frames,
+        ->contains->ocr_frame->contains->text AS texts,
         ->with->payload AS payload,
         id
     FROM ONLY $record
 );
-let $ids = array::flatten([$v.payload, $v.id]);
+let $ids = array::flatten([$v.texts, $v.ocr_frames, $v.payload, $v.id]);
 DELETE $ids;
 "#;
 
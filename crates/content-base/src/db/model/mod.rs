@@ -1,7 +1,9 @@
 pub mod audio;
+pub mod chapter;
 pub mod document;
 pub mod id;
 pub mod image;
+pub mod ocr;
 pub mod page;
 pub mod payload;
 pub mod text;
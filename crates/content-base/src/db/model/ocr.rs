@@ -0,0 +1,60 @@
+use super::{id::ID, text::TextModel, ModelCreate};
+use async_trait::async_trait;
+use educe::Educe;
+use serde::Serialize;
+
+/// 图片或者视频上连续几帧识别到的同一段文字，文字存在 text 表里
+/// 单独的图片只有一个 ocr_frame，时间戳都是 0
+#[derive(Serialize, Educe, Clone)]
+#[educe(Debug)]
+pub struct OcrFrameModel {
+    pub id: Option<ID>,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+const OCR_FRAME_CREATE_STATEMENT: &'static str = r#"
+(CREATE ONLY ocr_frame CONTENT {{
+    start_timestamp: $start_timestamp,
+    end_timestamp: $end_timestamp
+}}).id
+"#;
+
+#[async_trait]
+impl<T> ModelCreate<T, (Self, Vec<TextModel>)> for OcrFrameModel
+where
+    T: surrealdb::Connection,
+{
+    async fn create_only(
+        client: &surrealdb::Surreal<T>,
+        (ocr_frame, ocr_texts): &(Self, Vec<TextModel>),
+    ) -> anyhow::Result<surrealdb::sql::Thing> {
+        let text_records = TextModel::create_batch(client, ocr_texts).await?;
+        if text_records.is_empty() {
+            anyhow::bail!("Failed to insert ocr frame texts, texts is empty");
+        }
+        let mut resp = client
+            .query(OCR_FRAME_CREATE_STATEMENT)
+            .bind(ocr_frame.clone())
+            .await?;
+        if let Err(errors_map) = crate::check_db_error_from_resp!(resp) {
+            anyhow::bail!("Failed to insert ocr frame, errors: {:?}", errors_map);
+        };
+        let Some(ocr_frame_record) = resp.take::<Option<surrealdb::sql::Thing>>(0)? else {
+            anyhow::bail!("Failed to insert ocr frame, no id returned");
+        };
+        tracing::debug!(id=%ocr_frame_record, "OCR frame created in surrealdb");
+        client
+            .query("RELATE $relation_in -> contains -> $relation_outs;")
+            .bind(("relation_in", ocr_frame_record.clone()))
+            .bind(("relation_outs", text_records.clone()))
+            .await?;
+        Ok(ocr_frame_record)
+    }
+}
+
+impl OcrFrameModel {
+    pub fn table() -> &'static str {
+        "ocr_frame"
+    }
+}
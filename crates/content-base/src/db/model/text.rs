@@ -1,5 +1,6 @@
 use super::id::ID;
 use super::ModelCreate;
+use crate::segment::segment;
 use async_trait::async_trait;
 use educe::Educe;
 use serde::Serialize;
@@ -33,7 +34,12 @@ where
         client: &surrealdb::Surreal<T>,
         text: &Self,
     ) -> anyhow::Result<surrealdb::sql::Thing> {
-        let mut resp = client.query(CREATE_STATEMENT).bind(text.clone()).await?;
+        // 中文需要先分词，全文索引才能按词命中
+        let text = TextModel {
+            content: segment(&text.content),
+            ..text.clone()
+        };
+        let mut resp = client.query(CREATE_STATEMENT).bind(text).await?;
         if let Err(errors_map) = crate::check_db_error_from_resp!(resp) {
             anyhow::bail!("Failed to insert text, errors: {:?}", errors_map);
         };
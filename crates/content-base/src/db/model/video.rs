@@ -1,5 +1,6 @@
 use super::{
-    audio::AudioFrameModel, id::ID, image::ImageModel, text::TextModel, ModelCreate, ModelDelete,
+    audio::AudioFrameModel, chapter::ChapterFrameModel, id::ID, image::ImageModel,
+    ocr::OcrFrameModel, text::TextModel, ModelCreate, ModelDelete,
 };
 use async_trait::async_trait;
 use educe::Educe;
@@ -81,6 +82,8 @@ impl<T>
             Self,
             Vec<(ImageFrameModel, Vec<ImageModel>)>,
             Vec<(AudioFrameModel, Vec<TextModel>)>,
+            Vec<(OcrFrameModel, Vec<TextModel>)>,
+            Vec<(ChapterFrameModel, Vec<TextModel>)>,
         ),
     > for VideoModel
 where
@@ -88,14 +91,18 @@ where
 {
     async fn create_only(
         client: &surrealdb::Surreal<T>,
-        (_video, image_frames, audio_frames): &(
+        (_video, image_frames, audio_frames, ocr_frames, chapter_frames): &(
             Self,
             Vec<(ImageFrameModel, Vec<ImageModel>)>,
             Vec<(AudioFrameModel, Vec<TextModel>)>,
+            Vec<(OcrFrameModel, Vec<TextModel>)>,
+            Vec<(ChapterFrameModel, Vec<TextModel>)>,
         ),
     ) -> anyhow::Result<surrealdb::sql::Thing> {
         let image_frame_records = ImageFrameModel::create_batch(client, image_frames).await?;
         let audio_frame_records = AudioFrameModel::create_batch(client, audio_frames).await?;
+        let ocr_frame_records = OcrFrameModel::create_batch(client, ocr_frames).await?;
+        let chapter_frame_records = ChapterFrameModel::create_batch(client, chapter_frames).await?;
         let mut resp = client.query(VIDEO_CREATE_STATEMENT).await?;
         if let Err(errors_map) = crate::check_db_error_from_resp!(resp) {
             anyhow::bail!("Failed to insert video, errors: {:?}", errors_map);
@@ -108,6 +115,8 @@ where
             .into_iter()
             .chain(image_frame_records.into_iter())
             .chain(audio_frame_records.into_iter())
+            .chain(ocr_frame_records.into_iter())
+            .chain(chapter_frame_records.into_iter())
             .collect::<Vec<_>>();
         client
             .query("RELATE $relation_in -> contains -> $relation_outs;")
@@ -125,11 +134,15 @@ LET $v = (
         ->contains->image_frame->contains->image AS images,
         ->contains->audio_frame AS audio_frames,
         ->contains->audio_frame->contains->text AS texts,
+        ->contains->ocr_frame AS ocr_frames,
+        ->contains->ocr_frame->contains->text AS ocr_texts,
+        ->contains->chapter_frame AS chapter_frames,
+        ->contains->chapter_frame->contains->text AS chapter_texts,
         ->with->payload AS payload,
         id
     FROM ONLY $record
 );
-let $ids = array::flatten([$v.images, $v.texts, $v.image_frames, $v.audio_frames, $v.payload, $v.id]);
+let $ids = array::flatten([$v.images, $v.texts, $v.ocr_texts, $v.chapter_texts, $v.image_frames, $v.audio_frames, $v.ocr_frames, $v.chapter_frames, $v.payload, $v.id]);
 DELETE $ids;
 "#;
 
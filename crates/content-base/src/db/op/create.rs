@@ -3,9 +3,11 @@ use crate::{
     db::{
         model::{
             audio::{AudioFrameModel, AudioModel},
+            chapter::ChapterFrameModel,
             document::DocumentModel,
             id::ID,
             image::ImageModel,
+            ocr::OcrFrameModel,
             page::PageModel,
             payload::PayloadModel,
             text::TextModel,
@@ -17,6 +19,18 @@ use crate::{
     },
 };
 
+/// 网页和文档每一页的文本和图片描述
+fn page_texts(
+    pages: &[(PageModel, Vec<TextModel>, Vec<ImageModel>)],
+) -> impl Iterator<Item = &str> {
+    pages.iter().flat_map(|(_, texts, images)| {
+        texts
+            .iter()
+            .map(|v| v.content.as_str())
+            .chain(images.iter().map(|v| v.caption.as_str()))
+    })
+}
+
 /// insert api
 impl DB {
     /// 在创建之前清空已有的索引
@@ -28,14 +42,34 @@ impl DB {
         Ok(())
     }
 
+    /// 更新搜索补全的词表，词表不影响搜索，出错只记录日志
+    async fn _insert_terms_after_create<'a>(
+        &self,
+        file_identifier: &str,
+        texts: impl IntoIterator<Item = &'a str>,
+    ) {
+        if let Err(e) = self.insert_terms(file_identifier, texts).await {
+            tracing::warn!("insert_terms error: {:?}", e);
+        }
+    }
+
     #[tracing::instrument(skip_all, fields(file_identifier))]
     pub async fn insert_image(
         &self,
         file_identifier: String,
-        image: ImageModel,
+        (image, ocr_frames): (ImageModel, Vec<(OcrFrameModel, Vec<TextModel>)>),
     ) -> anyhow::Result<ID> {
         self._purge_index_before_create(&file_identifier).await?;
-        let record = ImageModel::create_only(&self.client, &image).await?;
+        let data = (image, ocr_frames);
+        let record = ImageModel::create_only(&self.client, &data).await?;
+        let ocr_texts = data.1.iter().flat_map(|(_, texts)| texts);
+        self._insert_terms_after_create(
+            &file_identifier,
+            [data.0.caption.as_str()]
+                .into_iter()
+                .chain(ocr_texts.map(|v| v.content.as_str())),
+        )
+        .await;
         PayloadModel::create_for_model(&self.client, &record, &file_identifier.into()).await?;
         Ok(ID::from(record))
     }
@@ -47,7 +81,11 @@ impl DB {
         (audio_model, audio_frames): (AudioModel, Vec<(AudioFrameModel, Vec<TextModel>)>),
     ) -> anyhow::Result<ID> {
         self._purge_index_before_create(&file_identifier).await?;
-        let record = AudioModel::create_only(&self.client, &(audio_model, audio_frames)).await?;
+        let data = (audio_model, audio_frames);
+        let record = AudioModel::create_only(&self.client, &data).await?;
+        let texts = data.1.iter().flat_map(|(_, texts)| texts);
+        self._insert_terms_after_create(&file_identifier, texts.map(|v| v.content.as_str()))
+            .await;
         PayloadModel::create_for_model(&self.client, &record, &file_identifier.into()).await?;
         Ok(ID::from(record))
     }
@@ -56,15 +94,37 @@ impl DB {
     pub async fn insert_video(
         &self,
         file_identifier: String,
-        (video, image_frames, audio_frames): (
+        (video, image_frames, audio_frames, ocr_frames, chapter_frames): (
             VideoModel,
             Vec<(ImageFrameModel, Vec<ImageModel>)>,
             Vec<(AudioFrameModel, Vec<TextModel>)>,
+            Vec<(OcrFrameModel, Vec<TextModel>)>,
+            Vec<(ChapterFrameModel, Vec<TextModel>)>,
         ),
     ) -> anyhow::Result<ID> {
         self._purge_index_before_create(&file_identifier).await?;
-        let record =
-            VideoModel::create_only(&self.client, &(video, image_frames, audio_frames)).await?;
+        let data = (
+            video,
+            image_frames,
+            audio_frames,
+            ocr_frames,
+            chapter_frames,
+        );
+        let record = VideoModel::create_only(&self.client, &data).await?;
+        let captions = data.1.iter().flat_map(|(_, images)| images);
+        let texts = data
+            .2
+            .iter()
+            .chain(data.3.iter())
+            .chain(data.4.iter())
+            .flat_map(|(_, texts)| texts);
+        self._insert_terms_after_create(
+            &file_identifier,
+            captions
+                .map(|v| v.caption.as_str())
+                .chain(texts.map(|v| v.content.as_str())),
+        )
+        .await;
         PayloadModel::create_for_model(&self.client, &record, &file_identifier.into()).await?;
         Ok(ID::from(record))
     }
@@ -79,7 +139,10 @@ impl DB {
         ),
     ) -> anyhow::Result<ID> {
         self._purge_index_before_create(&file_identifier).await?;
-        let record = WebPageModel::create_only(&self.client, &(web_page, pages)).await?;
+        let data = (web_page, pages);
+        let record = WebPageModel::create_only(&self.client, &data).await?;
+        self._insert_terms_after_create(&file_identifier, page_texts(&data.1))
+            .await;
         PayloadModel::create_for_model(&self.client, &record, &file_identifier.into()).await?;
         Ok(ID::from(record))
     }
@@ -94,7 +157,10 @@ impl DB {
         ),
     ) -> anyhow::Result<ID> {
         self._purge_index_before_create(&file_identifier).await?;
-        let record = DocumentModel::create_only(&self.client, &(document, pages)).await?;
+        let data = (document, pages);
+        let record = DocumentModel::create_only(&self.client, &data).await?;
+        self._insert_terms_after_create(&file_identifier, page_texts(&data.1))
+            .await;
         PayloadModel::create_for_model(&self.client, &record, &file_identifier.into()).await?;
         Ok(ID::from(record))
     }
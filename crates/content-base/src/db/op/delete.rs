@@ -68,15 +68,17 @@ impl DB {
                     "document" => {
                         DocumentModel::delete_cascade(&self.client, &record).await?;
                     }
-                    "web" => {
+                    "web_page" => {
                         WebPageModel::delete_cascade(&self.client, &record).await?;
                     }
                     _ => {
                         tracing::warn!("unexpected content type: {}", record.tb.as_str());
-                        return Ok(());
                     }
                 };
             }
+            // 不管素材是什么类型，补全词和标签都要清理掉
+            self.delete_terms(file_identifier).await?;
+            self.delete_tags(file_identifier).await?;
             Ok(())
         }
         .instrument(tracing::Span::current())
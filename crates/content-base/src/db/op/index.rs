@@ -0,0 +1,257 @@
+use crate::check_db_error_from_resp;
+use crate::db::{
+    sql::{VectorIndexEmbedding, VECTOR_INDEXES},
+    DB,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const EMBEDDING_SCHEMA_RECORD: &str = "meta:embedding_schema";
+
+/// 包含 image 记录的素材，image 表的 embedding 是 multi modal 模型生成的
+/// 音频素材只有 text，multi modal 模型变化的时候不需要重新处理
+const ASSETS_WITH_IMAGES_QUERY: &str = r#"
+SELECT VALUE file_identifier FROM payload
+WHERE file_identifier != NONE AND record::tb(<-with[0].in) IN ['image', 'video', 'document', 'web_page'];
+"#;
+
+/// 同步向量索引以后需要重新处理的素材
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReindexScope {
+    /// 索引没有变化
+    None,
+    /// text embedding 模型变了，所有素材都有文本向量，文本向量全部被清空
+    All,
+    /// 只有 multi modal embedding 模型变了，只清空了这些包含图片的素材的图片向量
+    Assets(Vec<String>),
+}
+
+/// 生成向量索引时使用的 embedding 模型和维度，保存在 surrealdb 里
+/// 模型或者维度变化以后，已有的向量和新的 query 向量不在同一个空间里，需要重建索引
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingSchema {
+    pub text_model: String,
+    pub text_dim: usize,
+    pub multi_modal_model: String,
+    pub multi_modal_dim: usize,
+}
+
+/// `INFO FOR TABLE` 的结果，只需要 indexes，key 是索引名字，value 是 DEFINE INDEX 语句
+#[derive(Debug, Deserialize)]
+struct TableInfo {
+    indexes: HashMap<String, String>,
+}
+
+fn hnsw_index_prefix(table: &str, column: &str) -> String {
+    format!("idx_{}_{}_hnsw_d", table, column)
+}
+
+impl EmbeddingSchema {
+    fn dim(&self, embedding: VectorIndexEmbedding) -> usize {
+        match embedding {
+            VectorIndexEmbedding::Text => self.text_dim,
+            VectorIndexEmbedding::MultiModal => self.multi_modal_dim,
+        }
+    }
+
+    fn model(&self, embedding: VectorIndexEmbedding) -> &str {
+        match embedding {
+            VectorIndexEmbedding::Text => &self.text_model,
+            VectorIndexEmbedding::MultiModal => &self.multi_modal_model,
+        }
+    }
+
+    /// 和之前的 schema 比较，模型或者维度变化了的 embedding
+    fn changed_embeddings(&self, previous: &EmbeddingSchema) -> Vec<VectorIndexEmbedding> {
+        [VectorIndexEmbedding::Text, VectorIndexEmbedding::MultiModal]
+            .into_iter()
+            .filter(|v| self.model(*v) != previous.model(*v) || self.dim(*v) != previous.dim(*v))
+            .collect()
+    }
+
+    /// 需要的向量索引 (table, index name, DEFINE INDEX 语句, embedding)
+    fn vector_indexes(&self) -> Vec<(&'static str, String, String, VectorIndexEmbedding)> {
+        VECTOR_INDEXES
+            .iter()
+            .map(|(table, column, dist, embedding)| {
+                let dim = self.dim(*embedding);
+                let name = format!("{}{}", hnsw_index_prefix(table, column), dim);
+                let statement = format!(
+                    "DEFINE INDEX IF NOT EXISTS {name} ON {table} FIELDS {column} HNSW DIMENSION {dim} DIST {dist};"
+                );
+                (*table, name, statement, *embedding)
+            })
+            .collect()
+    }
+}
+
+/// 清空 embedding 模型变化了的向量字段，文本内容、BM25 索引、补全词和标签都保留，重新处理素材的时候再生成向量
+/// 旧的 library 里向量字段的类型是 array，需要先改成 option<array> 才能设置为 NONE
+fn clear_vector_columns_statements(changed_embeddings: &[VectorIndexEmbedding]) -> Vec<String> {
+    VECTOR_INDEXES
+        .iter()
+        .filter(|(.., embedding)| changed_embeddings.contains(embedding))
+        .flat_map(|(table, column, ..)| {
+            [
+                format!("DEFINE FIELD OVERWRITE {column} ON TABLE {table} TYPE option<array>;"),
+                format!("UPDATE {table} SET {column} = NONE WHERE {column} != NONE;"),
+            ]
+        })
+        .collect()
+}
+
+impl DB {
+    /// 根据 embedding 模型同步向量索引
+    /// - 模型和之前记录的一致，只补上缺少的索引
+    /// - text embedding 模型变化了（或者没有记录但是已有的文本索引维度不一致），清空文本向量，所有素材都要重新处理
+    /// - 只有 multi modal embedding 模型变化了，清空图片向量，只重新处理包含图片的素材
+    ///
+    /// 受影响的 HNSW 索引会先删掉，清空向量以后再按新的维度创建，返回需要重新处理的素材
+    #[tracing::instrument(skip(self))]
+    pub async fn sync_vector_indexes(
+        &self,
+        schema: &EmbeddingSchema,
+    ) -> anyhow::Result<ReindexScope> {
+        let current_schema = self.embedding_schema().await?;
+        let vector_indexes = schema.vector_indexes();
+
+        let mut stale_indexes = vec![];
+        for table in VECTOR_INDEXES
+            .iter()
+            .map(|(table, ..)| *table)
+            .collect::<std::collections::BTreeSet<_>>()
+        {
+            let mut resp = self
+                .client
+                .query(format!("INFO FOR TABLE {};", table))
+                .await?;
+            let Some(table_info) = resp.take::<Option<TableInfo>>(0)? else {
+                continue;
+            };
+            for name in table_info.indexes.into_keys() {
+                let embedding = VECTOR_INDEXES.iter().find_map(|(t, column, _, embedding)| {
+                    (*t == table && name.starts_with(&hnsw_index_prefix(t, column)))
+                        .then_some(*embedding)
+                });
+                if let Some(embedding) = embedding {
+                    if !vector_indexes.iter().any(|(_, n, ..)| *n == name) {
+                        stale_indexes.push((table, name, embedding));
+                    }
+                }
+            }
+        }
+
+        let changed_embeddings = match &current_schema {
+            Some(current_schema) => schema.changed_embeddings(current_schema),
+            None => stale_indexes.iter().map(|(.., v)| *v).collect(),
+        };
+
+        let scope = if changed_embeddings.contains(&VectorIndexEmbedding::Text) {
+            tracing::warn!(
+                from = ?current_schema,
+                "text embedding model changed, clear text vectors of all assets"
+            );
+            ReindexScope::All
+        } else if changed_embeddings.contains(&VectorIndexEmbedding::MultiModal) {
+            tracing::warn!(
+                from = ?current_schema,
+                "multi modal embedding model changed, clear image vectors"
+            );
+            let mut resp = self.client.query(ASSETS_WITH_IMAGES_QUERY).await?;
+            check_db_error_from_resp!(resp).map_err(|errors_map| {
+                anyhow::anyhow!("assets with images lookup error: {:?}", errors_map)
+            })?;
+            ReindexScope::Assets(resp.take::<Vec<String>>(0)?)
+        } else {
+            ReindexScope::None
+        };
+
+        // 维度没变但是模型变了的索引也要删掉，不然清空向量的时候还要逐条更新旧的索引
+        let mut statements = stale_indexes
+            .iter()
+            .map(|(table, name, _)| (*table, name.as_str()))
+            .chain(
+                vector_indexes
+                    .iter()
+                    .filter(|(.., embedding)| changed_embeddings.contains(embedding))
+                    .map(|(table, name, ..)| (*table, name.as_str())),
+            )
+            .map(|(table, name)| format!("REMOVE INDEX IF EXISTS {} ON {};", name, table))
+            .collect::<Vec<_>>();
+        statements.extend(clear_vector_columns_statements(&changed_embeddings));
+        statements.extend(vector_indexes.into_iter().map(|(_, _, s, _)| s));
+        let mut resp = self
+            .client
+            .query(statements.join("\n"))
+            .query(format!(
+                "UPSERT {} CONTENT $schema;",
+                EMBEDDING_SCHEMA_RECORD
+            ))
+            .bind(("schema", schema.clone()))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("define vector index error: {:?}", errors_map))?;
+
+        Ok(scope)
+    }
+
+    async fn embedding_schema(&self) -> anyhow::Result<Option<EmbeddingSchema>> {
+        let mut resp = self
+            .client
+            .query(format!("SELECT * FROM ONLY {};", EMBEDDING_SCHEMA_RECORD))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("embedding schema error: {:?}", errors_map))?;
+        Ok(resp.take::<Option<EmbeddingSchema>>(0)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EmbeddingSchema;
+    use crate::db::sql::VectorIndexEmbedding;
+
+    #[test]
+    fn test_vector_indexes() {
+        let schema = EmbeddingSchema {
+            text_model: "puff-base-v1".to_string(),
+            text_dim: 1024,
+            multi_modal_model: "clip-multilingual-v1".to_string(),
+            multi_modal_dim: 512,
+        };
+        let names = schema
+            .vector_indexes()
+            .into_iter()
+            .map(|(_, name, ..)| name)
+            .collect::<Vec<_>>();
+        // 和之前写死在 CREATE_TABLE 里的索引名字保持一致，已有的 library 不需要重建
+        assert_eq!(
+            names,
+            vec![
+                "idx_text_embedding_hnsw_d1024",
+                "idx_image_embedding_hnsw_d512",
+                "idx_image_caption_embedding_hnsw_d1024",
+            ]
+        );
+
+        let multi_modal_changed = EmbeddingSchema {
+            multi_modal_model: "clip-v2".to_string(),
+            ..schema.clone()
+        };
+        assert_eq!(
+            multi_modal_changed.changed_embeddings(&schema),
+            vec![VectorIndexEmbedding::MultiModal]
+        );
+        assert!(schema.changed_embeddings(&schema).is_empty());
+
+        let schema = EmbeddingSchema {
+            text_dim: 768,
+            ..schema
+        };
+        let (_, _, statement, _) = &schema.vector_indexes()[0];
+        assert_eq!(
+            statement,
+            "DEFINE INDEX IF NOT EXISTS idx_text_embedding_hnsw_d768 ON text FIELDS embedding HNSW DIMENSION 768 DIST EUCLIDEAN;"
+        );
+    }
+}
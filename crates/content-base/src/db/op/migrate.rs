@@ -0,0 +1,134 @@
+use crate::check_db_error_from_resp;
+use crate::db::{
+    model::{image::ImageModel, text::TextModel},
+    DB,
+};
+use crate::segment::{desegment, segment};
+use serde::{Deserialize, Serialize};
+
+const TEXT_SEGMENTATION_RECORD: &str = "meta:text_segmentation";
+/// 分词方式变化以后加 1，打开 library 的时候已有的文本会按新的方式重新分词
+const TEXT_SEGMENTATION_VERSION: u32 = 1;
+/// 每批重新分词的记录数量，文本很多的时候不要一次全部读到内存里
+const TEXT_SEGMENTATION_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TextSegmentation {
+    version: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SegmentEntity {
+    id: surrealdb::sql::Thing,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SegmentUpdate {
+    id: surrealdb::sql::Thing,
+    value: String,
+}
+
+impl DB {
+    /// 分词之前写入的 text.content 和 image.caption 没有分隔符，BM25 按词命中不了
+    /// 版本号和记录的不一致时，把这些文本重新分词后写回，全文索引会跟着更新
+    #[tracing::instrument(skip(self))]
+    pub(crate) async fn migrate_text_segmentation(&self) -> anyhow::Result<()> {
+        let mut resp = self
+            .client
+            .query(format!("SELECT * FROM ONLY {};", TEXT_SEGMENTATION_RECORD))
+            .await?;
+        check_db_error_from_resp!(resp).map_err(|errors_map| {
+            anyhow::anyhow!("text segmentation lookup error: {:?}", errors_map)
+        })?;
+        let current = resp.take::<Option<TextSegmentation>>(0)?;
+        if current.is_some_and(|v| v.version == TEXT_SEGMENTATION_VERSION) {
+            return Ok(());
+        }
+
+        for (table, column) in [
+            (TextModel::table(), "content"),
+            (ImageModel::table(), "caption"),
+        ] {
+            let count = self
+                .segment_column(table, column, TEXT_SEGMENTATION_BATCH_SIZE)
+                .await?;
+            tracing::info!("{} {} records segmented", count, table);
+        }
+
+        // 所有批次都成功以后才记录版本号，中途出错下次打开 library 的时候会重新处理
+        let mut resp = self
+            .client
+            .query(format!(
+                "UPSERT {} CONTENT $segmentation;",
+                TEXT_SEGMENTATION_RECORD
+            ))
+            .bind((
+                "segmentation",
+                TextSegmentation {
+                    version: TEXT_SEGMENTATION_VERSION,
+                },
+            ))
+            .await?;
+        check_db_error_from_resp!(resp).map_err(|errors_map| {
+            anyhow::anyhow!("text segmentation update error: {:?}", errors_map)
+        })?;
+        Ok(())
+    }
+
+    /// 按 id 排序分批读取并重新分词，返回更新的记录数量
+    /// 更新不会改变 WHERE 条件的结果，所以用 START 翻页不会漏掉记录
+    pub(super) async fn segment_column(
+        &self,
+        table: &str,
+        column: &str,
+        batch_size: usize,
+    ) -> anyhow::Result<usize> {
+        let mut start = 0;
+        let mut count = 0;
+        loop {
+            let mut resp = self
+                .client
+                .query(format!(
+                    "SELECT id, {column} AS value FROM {table} WHERE type::is::string({column}) ORDER BY id LIMIT $limit START $start;"
+                ))
+                .bind(("limit", batch_size))
+                .bind(("start", start))
+                .await?;
+            check_db_error_from_resp!(resp).map_err(|errors_map| {
+                anyhow::anyhow!("{} lookup error: {:?}", table, errors_map)
+            })?;
+            let entities = resp.take::<Vec<SegmentEntity>>(0)?;
+            let fetched = entities.len();
+            // 已经分过词的文本先还原再分词，结果不变的不需要更新
+            let updates = entities
+                .into_iter()
+                .filter_map(|entity| {
+                    let value = segment(&desegment(&entity.value));
+                    (value != entity.value).then_some(SegmentUpdate {
+                        id: entity.id,
+                        value,
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !updates.is_empty() {
+                count += updates.len();
+                let mut resp = self
+                    .client
+                    .query(format!(
+                        "FOR $item IN $updates {{ UPDATE $item.id SET {column} = $item.value; }};"
+                    ))
+                    .bind(("updates", updates))
+                    .await?;
+                check_db_error_from_resp!(resp).map_err(|errors_map| {
+                    anyhow::anyhow!("{} segment error: {:?}", table, errors_map)
+                })?;
+            }
+            if fetched < batch_size {
+                break;
+            }
+            start += batch_size;
+        }
+        Ok(count)
+    }
+}
@@ -1,3 +1,7 @@
 mod create;
 mod delete;
+pub mod index;
+mod migrate;
+pub mod tag;
+pub mod term;
 mod test;
@@ -0,0 +1,164 @@
+//! 素材的关键词标签
+//!
+//! 标签是 LLM 根据素材内容生成的，见 content_base_task::tags，写入索引的时候保存到 `asset_tag` 表，
+//! 一个素材一条记录，id 是 file_identifier。重新处理素材的时候整条记录会被替换，所以重复运行不会产生重复的标签。
+
+use crate::check_db_error_from_resp;
+use crate::db::DB;
+use crate::query::ContentQueryFilter;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const SET_TAGS_STATEMENT: &'static str = r#"
+UPSERT type::thing('asset_tag', $file_identifier) CONTENT {
+    file_identifier: $file_identifier,
+    tags: $tags
+};
+"#;
+
+const DELETE_TAGS_STATEMENT: &'static str = r#"
+DELETE type::thing('asset_tag', $file_identifier);
+"#;
+
+const ASSET_TAGS_STATEMENT: &'static str = r#"
+SELECT VALUE tags FROM ONLY type::thing('asset_tag', $file_identifier);
+"#;
+
+const TAGGED_FILE_IDENTIFIERS_STATEMENT: &'static str = r#"
+SELECT VALUE file_identifier FROM asset_tag WHERE tags CONTAINSALL $tags;
+"#;
+
+const ALL_TAGS_STATEMENT: &'static str = r#"
+SELECT VALUE tags FROM asset_tag;
+"#;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    /// 有这个标签的素材数量
+    pub asset_count: u32,
+}
+
+/// 按素材数量从多到少排序，数量一样的按标签排序
+pub(crate) fn count_tags(assets_tags: Vec<Vec<String>>) -> Vec<TagCount> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for tags in assets_tags {
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut result = counts
+        .into_iter()
+        .map(|(tag, asset_count)| TagCount { tag, asset_count })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        b.asset_count
+            .cmp(&a.asset_count)
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    result
+}
+
+impl DB {
+    /// 替换素材的标签，标签为空的时候删除记录
+    pub(crate) async fn set_tags(
+        &self,
+        file_identifier: &str,
+        tags: Vec<String>,
+    ) -> anyhow::Result<()> {
+        if tags.is_empty() {
+            return self.delete_tags(file_identifier).await;
+        }
+        let mut resp = self
+            .client
+            .query(SET_TAGS_STATEMENT)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .bind(("tags", tags))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("set tags error: {:?}", errors_map))?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_tags(&self, file_identifier: &str) -> anyhow::Result<()> {
+        let mut resp = self
+            .client
+            .query(DELETE_TAGS_STATEMENT)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("delete tags error: {:?}", errors_map))?;
+        Ok(())
+    }
+
+    pub async fn asset_tags(&self, file_identifier: &str) -> anyhow::Result<Vec<String>> {
+        let mut resp = self
+            .client
+            .query(ASSET_TAGS_STATEMENT)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("get asset tags error: {:?}", errors_map))?;
+        Ok(resp.take::<Option<Vec<String>>>(0)?.unwrap_or_default())
+    }
+
+    /// 素材库里所有的标签
+    pub async fn list_tags(&self) -> anyhow::Result<Vec<TagCount>> {
+        let mut resp = self.client.query(ALL_TAGS_STATEMENT).await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("list tags error: {:?}", errors_map))?;
+        Ok(count_tags(resp.take::<Vec<Vec<String>>>(0)?))
+    }
+
+    /// 把 filter 里的标签转换成 file_identifiers，和已有的 file_identifiers 取交集
+    pub(crate) async fn resolve_tags_filter(
+        &self,
+        filter: &ContentQueryFilter,
+    ) -> anyhow::Result<ContentQueryFilter> {
+        let mut filter = filter.clone();
+        if filter.tags.is_empty() {
+            return Ok(filter);
+        }
+        let tags = filter
+            .tags
+            .iter()
+            .map(|v| v.trim().to_lowercase())
+            .collect::<Vec<_>>();
+        let mut resp = self
+            .client
+            .query(TAGGED_FILE_IDENTIFIERS_STATEMENT)
+            .bind(("tags", tags))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("filter tags error: {:?}", errors_map))?;
+        let tagged = resp.take::<Vec<String>>(0)?;
+        filter.file_identifiers = Some(match filter.file_identifiers.take() {
+            Some(existing) => existing
+                .into_iter()
+                .filter(|v| tagged.contains(v))
+                .collect(),
+            None => tagged,
+        });
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::count_tags;
+
+    #[test]
+    fn test_count_tags() {
+        let tags = count_tags(vec![
+            vec!["outdoor".to_string(), "product demo".to_string()],
+            vec!["product demo".to_string()],
+            vec!["beach".to_string()],
+        ]);
+        assert_eq!(
+            tags.iter()
+                .map(|v| (v.tag.as_str(), v.asset_count))
+                .collect::<Vec<_>>(),
+            vec![("product demo", 2), ("beach", 1), ("outdoor", 1)]
+        );
+    }
+}
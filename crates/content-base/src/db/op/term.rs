@@ -0,0 +1,148 @@
+//! 搜索补全用的词表
+//!
+//! BM25 索引里的词没法直接查询，所以写入 text 和 image 的时候另外维护一份：
+//! - `term` 表，一个词一条记录，asset_count 是包含这个词的素材数量，term 字段有索引，补全时按前缀范围查询
+//! - `asset_term` 表，记录每个素材包含哪些词，删除素材的时候用来减少 asset_count
+//!
+//! 加上词表之前写入的数据需要重新索引才会出现在补全里。
+
+use crate::check_db_error_from_resp;
+use crate::db::DB;
+use crate::segment::cut_words;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+/// 单字和太长的词基本没有补全的意义
+const MIN_TERM_CHARS: usize = 2;
+const MAX_TERM_CHARS: usize = 32;
+
+const INSERT_TERMS_STATEMENT: &'static str = r#"
+FOR $term IN $terms {
+    UPSERT type::thing('term', $term) SET term = $term, asset_count = (asset_count OR 0) + 1;
+};
+UPSERT type::thing('asset_term', $file_identifier) SET terms = $terms;
+"#;
+
+const DELETE_TERMS_STATEMENT: &'static str = r#"
+LET $terms = (SELECT VALUE terms FROM ONLY type::thing('asset_term', $file_identifier)) OR [];
+FOR $term IN $terms {
+    UPDATE type::thing('term', $term) SET asset_count -= 1;
+    DELETE type::thing('term', $term) WHERE asset_count <= 0;
+};
+DELETE type::thing('asset_term', $file_identifier);
+"#;
+
+/// 用前缀的范围查询，term 字段上有索引，不需要扫描整个表
+const SUGGEST_TERMS_STATEMENT: &'static str = r#"
+SELECT term, asset_count FROM term
+WHERE term >= $prefix AND term < $prefix_end
+ORDER BY asset_count DESC, term ASC
+LIMIT $limit;
+"#;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TermSuggestion {
+    pub term: String,
+    /// 包含这个词的素材数量
+    pub asset_count: u32,
+}
+
+/// 从文本里提取补全用的词，和全文索引一样先分词，再统一转成小写，结果去重并排序
+pub(crate) fn extract_terms<'a>(texts: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut terms = BTreeSet::new();
+    for text in texts {
+        for word in cut_words(text) {
+            let len = word.chars().count();
+            if len < MIN_TERM_CHARS || len > MAX_TERM_CHARS {
+                continue;
+            }
+            if !word.chars().all(char::is_alphanumeric) || word.chars().all(|c| c.is_numeric()) {
+                continue;
+            }
+            terms.insert(word.to_lowercase());
+        }
+    }
+    terms.into_iter().collect()
+}
+
+impl DB {
+    /// 记录素材包含的词，需要在 delete_terms 之后调用，否则 asset_count 会重复计算
+    pub(crate) async fn insert_terms<'a>(
+        &self,
+        file_identifier: &str,
+        texts: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<()> {
+        let terms = extract_terms(texts);
+        let mut resp = self
+            .client
+            .query(INSERT_TERMS_STATEMENT)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .bind(("terms", terms))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("insert terms error: {:?}", errors_map))?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_terms(&self, file_identifier: &str) -> anyhow::Result<()> {
+        let mut resp = self
+            .client
+            .query(DELETE_TERMS_STATEMENT)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("delete terms error: {:?}", errors_map))?;
+        Ok(())
+    }
+
+    /// 按前缀查询词表，包含这个词的素材越多越靠前
+    pub async fn suggest_terms(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<TermSuggestion>> {
+        let prefix = prefix.trim().to_lowercase();
+        if prefix.is_empty() {
+            return Ok(vec![]);
+        }
+        // 所有以 prefix 开头的字符串都小于 prefix + char::MAX
+        let prefix_end = format!("{}{}", prefix, char::MAX);
+        let mut resp = self
+            .client
+            .query(SUGGEST_TERMS_STATEMENT)
+            .bind(("prefix", prefix))
+            .bind(("prefix_end", prefix_end))
+            .bind(("limit", limit))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("suggest terms error: {:?}", errors_map))?;
+        Ok(resp.take::<Vec<TermSuggestion>>(0)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::extract_terms;
+
+    #[test]
+    fn test_extract_terms() {
+        let terms = extract_terms([
+            "我们今天讨论一下视频剪辑的流程",
+            "Video editing, 2024 VIDEO a",
+        ]);
+        assert!(terms.contains(&"视频".to_string()));
+        assert!(terms.contains(&"剪辑".to_string()));
+        assert!(terms.contains(&"video".to_string()));
+        assert!(terms.contains(&"editing".to_string()));
+        // 单字、纯数字和标点不算
+        assert!(!terms.contains(&"的".to_string()));
+        assert!(!terms.contains(&"2024".to_string()));
+        assert!(!terms.contains(&"a".to_string()));
+        assert!(!terms.iter().any(|t| t.contains(',')));
+        // 去重并排序
+        let mut sorted = terms.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(terms, sorted);
+    }
+}
@@ -2,11 +2,15 @@
 mod tests {
     use crate::check_db_error_from_resp;
     use crate::db::model::id::{ID, TB};
+    use crate::db::model::image::ImageModel;
     use crate::db::model::text::TextModel;
     use crate::db::shared::test::{
-        fake_audio_model, fake_document, fake_file_identifier, fake_image_model, fake_page_model,
-        fake_video_model, fake_web_page_model, gen_vector, setup,
+        fake_audio_model, fake_chapter_frame_model, fake_document, fake_file_identifier,
+        fake_image_model, fake_ocr_frame_model, fake_page_model, fake_text_model, fake_video_model,
+        fake_video_model_with_ocr, fake_web_page_model, gen_vector, setup,
     };
+    use crate::db::{EmbeddingSchema, ReindexScope};
+    use crate::query::ContentQueryFilter;
     use itertools::Itertools;
     use test_log::test;
 
@@ -42,7 +46,7 @@ mod tests {
         let _guard = get_test_lock().await.lock().await;
         let db = setup(None).await;
         let _ = db
-            .insert_image(fake_file_identifier(), fake_image_model())
+            .insert_image(fake_file_identifier(), (fake_image_model(), vec![]))
             .await;
     }
 
@@ -68,6 +72,21 @@ mod tests {
         assert_eq!(id.tb(), &TB::Video);
     }
 
+    #[test(tokio::test)]
+    async fn test_insert_video_with_ocr() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        let id = db
+            .insert_video(file_identifier.clone(), fake_video_model_with_ocr())
+            .await
+            .unwrap();
+        assert_eq!(id.tb(), &TB::Video);
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .expect("delete video");
+    }
+
     #[test(tokio::test)]
     async fn test_insert_page() {
         let _guard = get_test_lock().await.lock().await;
@@ -104,7 +123,7 @@ mod tests {
         let db = setup(None).await;
         let file_identifier = fake_file_identifier();
         let _image = db
-            .insert_image(file_identifier.clone(), fake_image_model())
+            .insert_image(file_identifier.clone(), (fake_image_model(), vec![]))
             .await
             .unwrap();
         db.delete_by_file_identifier(&file_identifier)
@@ -148,6 +167,281 @@ mod tests {
         // assert!(video_res.is_empty());
     }
 
+    #[test(tokio::test)]
+    async fn test_delete_web_page() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        db.insert_web_page(file_identifier.clone(), fake_web_page_model())
+            .await
+            .unwrap();
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .expect("delete web page");
+        let mut resp = db
+            .client
+            .query("SELECT VALUE id FROM payload WHERE file_identifier = $file_identifier")
+            .bind(("file_identifier", file_identifier.clone()))
+            .await
+            .unwrap();
+        let ids: Vec<surrealdb::sql::Thing> = resp.take(0).unwrap();
+        assert!(ids.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn test_migrate_text_segmentation() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let id: surrealdb::sql::Thing =
+            (&db._insert_text(None, fake_text_model()).await.unwrap()).into();
+        // 模拟分词之前写入的数据
+        db.client
+            .query("DELETE meta:text_segmentation;")
+            .query("UPDATE $id SET content = '旧数据里的海边灯塔';")
+            .bind(("id", id.clone()))
+            .await
+            .unwrap();
+
+        db.migrate_text_segmentation().await.unwrap();
+        let mut resp = db
+            .client
+            .query("SELECT VALUE content FROM ONLY $id")
+            .bind(("id", id.clone()))
+            .await
+            .unwrap();
+        let content: Option<String> = resp.take(0).unwrap();
+        let content = content.unwrap();
+        assert!(content.contains(crate::segment::SEGMENT_SEPARATOR));
+        assert_eq!(crate::segment::desegment(&content), "旧数据里的海边灯塔");
+
+        db.client
+            .query("DELETE $id")
+            .bind(("id", id))
+            .await
+            .unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn test_segment_column_in_batches() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let mut ids = vec![];
+        for _ in 0..5 {
+            let id: surrealdb::sql::Thing =
+                (&db._insert_text(None, fake_text_model()).await.unwrap()).into();
+            db.client
+                .query("UPDATE $id SET content = '分批处理的旧数据';")
+                .bind(("id", id.clone()))
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        // 每批 2 条，需要翻页才能处理完所有记录
+        let count = db.segment_column("text", "content", 2).await.unwrap();
+        assert!(count >= ids.len());
+        let mut resp = db
+            .client
+            .query("SELECT VALUE content FROM $ids")
+            .bind(("ids", ids.clone()))
+            .await
+            .unwrap();
+        let contents: Vec<String> = resp.take(0).unwrap();
+        assert_eq!(contents.len(), ids.len());
+        assert!(contents
+            .iter()
+            .all(|v| v.contains(crate::segment::SEGMENT_SEPARATOR)));
+        // 再处理一次没有需要更新的记录
+        assert_eq!(db.segment_column("text", "content", 2).await.unwrap(), 0);
+
+        db.client
+            .query("DELETE $ids")
+            .bind(("ids", ids))
+            .await
+            .unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn test_suggest_terms() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        let image = ImageModel {
+            caption: "Zyxwvut sunrise over 海边的灯塔".to_string(),
+            ..fake_image_model()
+        };
+        db.insert_image(file_identifier.clone(), (image, vec![]))
+            .await
+            .unwrap();
+
+        let terms = db.suggest_terms("zyx", 10).await.unwrap();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].term, "zyxwvut");
+        assert_eq!(terms[0].asset_count, 1);
+        let terms = db.suggest_terms("灯", 10).await.unwrap();
+        assert!(terms.iter().any(|v| v.term == "灯塔"));
+
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+        let terms = db.suggest_terms("zyx", 10).await.unwrap();
+        assert!(terms.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn test_insert_image_with_ocr() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        let (ocr_frame, _) = fake_ocr_frame_model();
+        let ocr_text = TextModel {
+            content: "Qwertyxu 季度报告".to_string(),
+            ..fake_text_model()
+        };
+        db.insert_image(
+            file_identifier.clone(),
+            (fake_image_model(), vec![(ocr_frame, vec![ocr_text])]),
+        )
+        .await
+        .unwrap();
+
+        let terms = db.suggest_terms("qwerty", 10).await.unwrap();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].term, "qwertyxu");
+
+        // 删除图片的时候 OCR 的文字也一起删除
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+        let mut resp = db
+            .client
+            .query("SELECT VALUE id FROM text WHERE string::contains(content, 'Qwertyxu')")
+            .await
+            .unwrap();
+        let ids: Vec<surrealdb::sql::Thing> = resp.take(0).unwrap();
+        assert!(ids.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn test_insert_video_with_chapters() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        let (chapter_frame, _) = fake_chapter_frame_model();
+        let chapter_title = TextModel {
+            content: "Plumbobchapter 开箱".to_string(),
+            ..fake_text_model()
+        };
+        let (video, image_frames, audio_frames, ocr_frames, _) = fake_video_model();
+        db.insert_video(
+            file_identifier.clone(),
+            (
+                video,
+                image_frames,
+                audio_frames,
+                ocr_frames,
+                vec![(chapter_frame, vec![chapter_title])],
+            ),
+        )
+        .await
+        .unwrap();
+
+        let terms = db.suggest_terms("plumbob", 10).await.unwrap();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].term, "plumbobchapter");
+
+        // 删除视频的时候章节标题也一起删除
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+        let mut resp = db
+            .client
+            .query("SELECT VALUE id FROM text WHERE string::contains(content, 'Plumbobchapter')")
+            .await
+            .unwrap();
+        let ids: Vec<surrealdb::sql::Thing> = resp.take(0).unwrap();
+        assert!(ids.is_empty());
+    }
+
+    #[test(tokio::test)]
+    async fn test_asset_tags() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        db.insert_image(file_identifier.clone(), (fake_image_model(), vec![]))
+            .await
+            .unwrap();
+
+        let tags = vec!["quokka tag".to_string(), "outdoor".to_string()];
+        db.set_tags(&file_identifier, tags.clone()).await.unwrap();
+        // 重复保存的时候替换原来的标签
+        db.set_tags(&file_identifier, tags.clone()).await.unwrap();
+        assert_eq!(db.asset_tags(&file_identifier).await.unwrap(), tags);
+        let all_tags = db.list_tags().await.unwrap();
+        let quokka = all_tags.iter().find(|v| v.tag == "quokka tag").unwrap();
+        assert_eq!(quokka.asset_count, 1);
+
+        let filter = ContentQueryFilter {
+            tags: vec!["Quokka Tag".to_string()],
+            ..Default::default()
+        };
+        let resolved = db.resolve_tags_filter(&filter).await.unwrap();
+        assert_eq!(
+            resolved.file_identifiers,
+            Some(vec![file_identifier.clone()])
+        );
+        // 和已有的 file_identifiers 取交集
+        let filter = ContentQueryFilter {
+            file_identifiers: Some(vec!["other".to_string()]),
+            ..filter
+        };
+        let resolved = db.resolve_tags_filter(&filter).await.unwrap();
+        assert_eq!(resolved.file_identifiers, Some(vec![]));
+
+        // 删除素材的时候标签也一起删除
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+        assert!(db.asset_tags(&file_identifier).await.unwrap().is_empty());
+        let all_tags = db.list_tags().await.unwrap();
+        assert!(!all_tags.iter().any(|v| v.tag == "quokka tag"));
+    }
+
+    #[test(tokio::test)]
+    async fn test_resolve_excluded_filter() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let excluded_file_identifier = fake_file_identifier();
+        let image = ImageModel {
+            caption: "a rough Xylodraft of the poster".to_string(),
+            ..fake_image_model()
+        };
+        db.insert_image(excluded_file_identifier.clone(), (image, vec![]))
+            .await
+            .unwrap();
+        let file_identifier = fake_file_identifier();
+        db.insert_image(file_identifier.clone(), (fake_image_model(), vec![]))
+            .await
+            .unwrap();
+
+        let filter = ContentQueryFilter {
+            excluded: vec!["xylodraft".to_string()],
+            ..Default::default()
+        };
+        let resolved = db.resolve_excluded_filter(&filter).await.unwrap();
+        assert_eq!(
+            resolved.excluded_file_identifiers,
+            vec![excluded_file_identifier.clone()]
+        );
+
+        db.delete_by_file_identifier(&excluded_file_identifier)
+            .await
+            .unwrap();
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+    }
+
     #[test(tokio::test)]
     async fn test_upsert() {
         let _guard = get_test_lock().await.lock().await;
@@ -176,4 +470,60 @@ mod tests {
         let result = resp.take::<Vec<surrealdb::sql::Thing>>(0).unwrap();
         assert_eq!(result.len(), 1);
     }
+
+    #[test(tokio::test)]
+    async fn test_sync_vector_indexes_keeps_content() {
+        let _guard = get_test_lock().await.lock().await;
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        let image = ImageModel {
+            caption: "Vbnmlkj lighthouse".to_string(),
+            ..fake_image_model()
+        };
+        db.insert_image(file_identifier.clone(), (image, vec![]))
+            .await
+            .unwrap();
+        db.set_tags(&file_identifier, vec!["vbnmlkj tag".to_string()])
+            .await
+            .unwrap();
+
+        // 换一个维度相同的 text embedding 模型
+        let scope = db
+            .sync_vector_indexes(&EmbeddingSchema {
+                text_model: "other-text-embedding".to_string(),
+                text_dim: 1024,
+                multi_modal_model: "test-multi-modal-embedding".to_string(),
+                multi_modal_dim: 512,
+            })
+            .await
+            .unwrap();
+        assert_eq!(scope, ReindexScope::All);
+
+        // 只清空文本向量，图片向量、描述、补全词和标签都保留
+        let mut resp = db
+            .client
+            .query("SELECT caption, caption_embedding, array::len(embedding) AS dim FROM image WHERE caption = 'Vbnmlkj lighthouse';")
+            .await
+            .unwrap();
+        #[derive(serde::Deserialize)]
+        struct Row {
+            caption_embedding: Option<Vec<f32>>,
+            dim: usize,
+        }
+        let rows: Vec<Row> = resp.take(0).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].caption_embedding.is_none());
+        assert_eq!(rows[0].dim, 512);
+        assert_eq!(db.suggest_terms("vbnml", 10).await.unwrap().len(), 1);
+        assert_eq!(
+            db.asset_tags(&file_identifier).await.unwrap(),
+            vec!["vbnmlkj tag".to_string()]
+        );
+
+        // 恢复测试用的模型
+        setup(None).await;
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+    }
 }
@@ -1,8 +1,11 @@
 use itertools::Itertools;
 
-use super::model::id::ID;
-use crate::concat_arrays;
-use crate::query::model::{FullTextSearchResult, SearchType, VectorSearchResult, VectorSearchType};
+use super::model::id::{ID, TB};
+use crate::query::{
+    model::{FullTextSearchResult, SearchType, VectorSearchResult, VectorSearchType},
+    payload::{ContentQueryExplain, ContentQuerySignalExplain},
+    RankWeights,
+};
 use std::collections::{HashMap, HashSet};
 pub struct Rank;
 
@@ -14,6 +17,8 @@ pub struct RankResult {
     /// 真正的得分等辅助信息，格式是 distance:xxx, score:xxx，取决于搜索类型
     pub search_hint: String,
     pub search_type: SearchType,
+    /// 分数明细，只有 rank 融合以后的结果才有
+    pub explain: Option<ContentQueryExplain>,
 }
 
 #[allow(dead_code)]
@@ -54,6 +59,7 @@ impl Rank {
             b_score
                 .partial_cmp(&a_score)
                 .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.id_with_table().cmp(&b.id.id_with_table()))
         });
 
         let rank_results = res
@@ -74,6 +80,7 @@ impl Rank {
                     score,
                     search_hint,
                     search_type: SearchType::FullText,
+                    explain: None,
                 }
             })
             .collect();
@@ -92,6 +99,7 @@ impl Rank {
             a.distance
                 .partial_cmp(&b.distance)
                 .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.id_with_table().cmp(&b.id.id_with_table()))
         });
         Ok(res
             .drain(..drain)
@@ -106,46 +114,147 @@ impl Rank {
                     score,
                     search_hint,
                     search_type: SearchType::Vector(x.vector_type),
+                    explain: None,
                 }
             })
             .collect())
     }
 
+    /// 每个查询（原始查询和扩展查询）的全文和向量召回分别排序，所有排序一起用加权 rrf 融合
+    /// 文本向量和图像向量的权重不一样时分成两路排序，默认权重下的结果和不加权时一样
+    /// 来源的权重按召回的信号乘在每一路的 rrf 分数上，见 `source_weight`
+    /// transcript_ids 是上一层对象是 audio_frame 的 text 记录
     pub fn rank(
-        (full_text_data, vector_data): (Vec<FullTextSearchResult>, Vec<VectorSearchResult>),
+        queries: Vec<(Vec<FullTextSearchResult>, Vec<VectorSearchResult>)>,
+        weights: &RankWeights,
+        transcript_ids: &HashSet<String>,
         remove_duplicate: bool,
         drain: Option<usize>,
     ) -> anyhow::Result<Vec<RankResult>> {
-        let full_text_rank = Rank::full_text_rank(full_text_data, ScoreType::Average, None)?;
-        let vector_rank = Rank::vector_rank(vector_data, None)?;
-        // tracing::debug!("full_text_rank: {:?}", full_text_rank);
-        // tracing::debug!("vector_rank: {:?}", vector_rank);
-        let concat_arrays = concat_arrays!(full_text_rank.clone(), vector_rank.clone()).into_vec();
-        let mut rank_result: Vec<RankResult> = Rank::rrf(vec![full_text_rank, vector_rank], None)
+        let mut full_text_tokens = HashMap::new();
+        let mut rankings = vec![];
+        for (full_text_data, vector_data) in queries {
+            for x in full_text_data.iter() {
+                full_text_tokens
+                    .entry(x.id.id_with_table())
+                    .or_insert_with(|| x.score.clone());
+            }
+            rankings.push((
+                Rank::full_text_rank(full_text_data, ScoreType::Average, None)?,
+                weights.full_text,
+            ));
+            if weights.text_vector == weights.vision_vector {
+                // 两种向量的权重一样时，和之前一样放在一起按 distance 排序
+                rankings.push((Rank::vector_rank(vector_data, None)?, weights.text_vector));
+            } else {
+                let (text_vector_data, vision_vector_data): (Vec<_>, Vec<_>) = vector_data
+                    .into_iter()
+                    .partition(|x| x.vector_type == VectorSearchType::Text);
+                rankings.push((
+                    Rank::vector_rank(text_vector_data, None)?,
+                    weights.text_vector,
+                ));
+                rankings.push((
+                    Rank::vector_rank(vision_vector_data, None)?,
+                    weights.vision_vector,
+                ));
+            }
+        }
+
+        // 多个查询都召回的记录，explain 里显示排名最靠前的那一次
+        let mut full_text_explains = HashMap::new();
+        let mut text_vector_explains = HashMap::new();
+        let mut vision_vector_explains = HashMap::new();
+        for (ranking, weight) in rankings.iter() {
+            for (rank, x) in ranking.iter().enumerate() {
+                let explain = ContentQuerySignalExplain {
+                    rank: rank as u32,
+                    value: x.score,
+                    weight: *weight,
+                };
+                let explains = match x.search_type {
+                    SearchType::FullText => &mut full_text_explains,
+                    SearchType::Vector(VectorSearchType::Text) => &mut text_vector_explains,
+                    SearchType::Vector(VectorSearchType::Vision) => &mut vision_vector_explains,
+                };
+                explains
+                    .entry(x.id.id_with_table())
+                    .and_modify(|existing: &mut ContentQuerySignalExplain| {
+                        if explain.rank < existing.rank {
+                            *existing = explain.clone();
+                        }
+                    })
+                    .or_insert(explain);
+            }
+        }
+
+        let source_weight = |x: &RankResult| Rank::source_weight(x, weights, transcript_ids);
+        let mut hits: HashMap<String, Vec<&RankResult>> = HashMap::new();
+        for (ranking, _) in rankings.iter() {
+            for x in ranking.iter() {
+                hits.entry(x.id.id_with_table()).or_default().push(x);
+            }
+        }
+        let scores = Rank::weighted_rrf(
+            rankings
+                .iter()
+                .map(|(v, weight)| (v.iter().collect_vec(), *weight)),
+            None,
+            |x| source_weight(*x),
+        )
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        let fused_ranking = Rank::weighted_rrf(
+            rankings
+                .iter()
+                .map(|(v, weight)| (v.iter().collect_vec(), *weight)),
+            None,
+            |_| 1.0,
+        );
+        let mut rank_result: Vec<RankResult> = fused_ranking
             .into_iter()
-            .filter_map(|(id, score)| {
-                let items = concat_arrays
+            .filter_map(|(id, fused_score)| {
+                let items = hits.get(&id)?;
+                let item = (*items.first()?).clone();
+                let search_hint = items
                     .iter()
-                    .filter(|y| y.id.id_with_table() == id)
-                    .collect_vec();
-                if items.len() > 0 {
-                    let item = items[0].clone();
-                    let search_hint = items
-                        .iter()
-                        .map(|x| x.search_hint.clone())
-                        .collect::<Vec<String>>()
-                        .join(",");
-                    Some(RankResult {
-                        id: item.id,
-                        score,
-                        search_hint,
-                        search_type: item.search_type,
-                    })
-                } else {
-                    None
-                }
+                    .map(|x| x.search_hint.clone())
+                    .collect::<Vec<String>>()
+                    .join(",");
+                // 只有图像向量召回的记录不乘来源的权重
+                let source_weight = items
+                    .iter()
+                    .find(|x| x.search_type != SearchType::Vector(VectorSearchType::Vision))
+                    .map(|x| source_weight(*x))
+                    .unwrap_or(1.0);
+                let score = scores.get(&id).copied().unwrap_or(fused_score);
+                let explain = ContentQueryExplain {
+                    full_text: full_text_explains.get(&id).cloned(),
+                    full_text_tokens: full_text_tokens.get(&id).cloned().unwrap_or_default(),
+                    text_vector: text_vector_explains.get(&id).cloned(),
+                    vision_vector: vision_vector_explains.get(&id).cloned(),
+                    fused_score,
+                    source_weight,
+                    recency_boost: 0.0,
+                    rerank_score: None,
+                    final_score: score,
+                };
+                Some(RankResult {
+                    id: item.id,
+                    score,
+                    search_hint,
+                    search_type: item.search_type,
+                    explain: Some(explain),
+                })
             })
             .collect();
+        // 来源的权重会改变顺序，需要重新排序
+        rank_result.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.id_with_table().cmp(&b.id.id_with_table()))
+        });
         // tracing::debug!("rank_result: {:?}", rank_result);
         if remove_duplicate {
             let mut seen = HashSet::new();
@@ -170,16 +279,51 @@ impl Rankable for RankResult {
     }
 }
 
+impl<T: Rankable> Rankable for &T {
+    fn id(&self) -> String {
+        (*self).id()
+    }
+}
+
 impl Rank {
+    #[cfg(test)]
     fn rrf<T: Rankable>(rankings: Vec<Vec<T>>, k: Option<usize>) -> Vec<(String, f32)> {
+        Rank::weighted_rrf(rankings.into_iter().map(|v| (v, 1.0)), k, |_| 1.0)
+    }
+
+    /// 来源的权重，按召回这条记录的信号区分
+    /// - image 表通过描述的全文或者文本向量召回时是 caption，通过图像向量召回时不加权
+    /// - text 表只有音频和视频的转录是 transcript，OCR、章节标题、文档和网页的文本不加权
+    fn source_weight(
+        item: &RankResult,
+        weights: &RankWeights,
+        transcript_ids: &HashSet<String>,
+    ) -> f32 {
+        match (item.id.tb(), &item.search_type) {
+            (TB::Image, SearchType::Vector(VectorSearchType::Vision)) => 1.0,
+            (TB::Image, _) => weights.caption,
+            (TB::Text, _) if transcript_ids.contains(&item.id.id_with_table()) => {
+                weights.transcript
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// 每一路召回的 rrf 分数乘以对应的权重再相加
+    /// item_weight 是单条记录在这一路召回里额外的权重
+    fn weighted_rrf<T: Rankable>(
+        rankings: impl IntoIterator<Item = (Vec<T>, f32)>,
+        k: Option<usize>,
+        item_weight: impl Fn(&T) -> f32,
+    ) -> Vec<(String, f32)> {
         let mut rrf_scores: HashMap<String, f32> = HashMap::new();
         let k = k.unwrap_or(60);
 
-        for ranking in rankings {
+        for (ranking, weight) in rankings {
             for (rank, item) in ranking.into_iter().enumerate() {
                 let doc_id = item.id();
                 let score = rrf_scores.entry(doc_id.clone()).or_insert(0.0);
-                *score += 1.0 / (k as f32 + rank as f32 + 1.0);
+                *score += weight * item_weight(&item) / (k as f32 + rank as f32 + 1.0);
             }
         }
 
@@ -188,7 +332,12 @@ impl Rank {
             .map(|(id, &score)| (id.clone(), score))
             .collect();
 
-        fused_ranking.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // rrf_scores 是 HashMap，遍历顺序不固定，分数相同时需要按照 id 排序，否则分页的结果会不稳定
+        fused_ranking.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
 
         fused_ranking
     }
@@ -201,6 +350,8 @@ mod test {
         rank::{Rank, Rankable, ScoreType},
     };
     use crate::query::model::{FullTextSearchResult, VectorSearchResult, VectorSearchType};
+    use crate::query::RankWeights;
+    use std::collections::HashSet;
 
     #[test]
     fn test_vector_rank() {
@@ -315,4 +466,156 @@ mod test {
                 .collect::<Vec<String>>()
         );
     }
+
+    #[test]
+    fn test_rrf_stable_order() {
+        // doc1 和 doc2 的分数完全一样，多次执行的顺序应该一致
+        let rankings = || {
+            vec![
+                vec!["doc1".to_string(), "doc2".to_string()],
+                vec!["doc2".to_string(), "doc1".to_string()],
+            ]
+        };
+        let expected = Rank::rrf(rankings(), None);
+        assert_eq!(expected[0].0, "doc1");
+        for _ in 0..10 {
+            assert_eq!(expected, Rank::rrf(rankings(), None));
+        }
+    }
+
+    #[test]
+    fn test_weighted_rank() {
+        let data = || {
+            (
+                vec![FullTextSearchResult {
+                    id: ID::new("1".to_string(), "text"),
+                    score: vec![("a".to_string(), 0.5)],
+                }],
+                vec![VectorSearchResult {
+                    id: ID::new("2".to_string(), "image"),
+                    distance: 0.1,
+                    vector_type: VectorSearchType::Vision,
+                }],
+            )
+        };
+        let no_transcripts = HashSet::new();
+
+        // 默认权重下和不加权的 rrf 一样，分数相同时按 id 排序
+        let res = Rank::rank(
+            vec![data()],
+            &RankWeights::default(),
+            &no_transcripts,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res[0].score, res[1].score);
+        assert_eq!(res[0].id.id_with_table(), "image:2");
+
+        // 降低图像向量的权重
+        let weights = RankWeights {
+            vision_vector: 0.8,
+            ..Default::default()
+        };
+        let res = Rank::rank(vec![data()], &weights, &no_transcripts, false, None).unwrap();
+        assert_eq!(res[0].id.id_with_table(), "text:1");
+        assert_eq!(res[1].id.id_with_table(), "image:2");
+        let explain = res[1].explain.clone().unwrap();
+        assert_eq!(explain.vision_vector.unwrap().weight, 0.8);
+        assert!(explain.full_text.is_none());
+        assert_eq!(explain.final_score, res[1].score);
+
+        // 只有图像向量召回的 image 不乘画面描述的权重，顺序不变
+        let weights = RankWeights {
+            caption: 2.0,
+            ..Default::default()
+        };
+        let res = Rank::rank(vec![data()], &weights, &no_transcripts, false, None).unwrap();
+        assert_eq!(res[0].score, res[1].score);
+        assert_eq!(res[0].explain.clone().unwrap().source_weight, 1.0);
+
+        // 不是转录的 text 不乘转录的权重，是转录的时候顺序反过来
+        let weights = RankWeights {
+            transcript: 2.0,
+            ..Default::default()
+        };
+        let res = Rank::rank(vec![data()], &weights, &no_transcripts, false, None).unwrap();
+        assert_eq!(res[0].id.id_with_table(), "image:2");
+        let transcripts = HashSet::from(["text:1".to_string()]);
+        let res = Rank::rank(vec![data()], &weights, &transcripts, false, None).unwrap();
+        assert_eq!(res[0].id.id_with_table(), "text:1");
+        let explain = res[0].explain.clone().unwrap();
+        assert_eq!(explain.source_weight, 2.0);
+        assert_eq!(explain.final_score, explain.fused_score * 2.0);
+
+        // 通过描述召回的 image 乘画面描述的权重，图像向量的那一路不乘
+        let data = (
+            vec![FullTextSearchResult {
+                id: ID::new("3".to_string(), "image"),
+                score: vec![("a".to_string(), 0.5)],
+            }],
+            vec![
+                VectorSearchResult {
+                    id: ID::new("2".to_string(), "image"),
+                    distance: 0.1,
+                    vector_type: VectorSearchType::Vision,
+                },
+                VectorSearchResult {
+                    id: ID::new("3".to_string(), "image"),
+                    distance: 0.2,
+                    vector_type: VectorSearchType::Vision,
+                },
+            ],
+        );
+        let weights = RankWeights {
+            caption: 2.0,
+            ..Default::default()
+        };
+        let res = Rank::rank(vec![data], &weights, &no_transcripts, false, None).unwrap();
+        assert_eq!(res[0].id.id_with_table(), "image:3");
+        let explain = res[0].explain.clone().unwrap();
+        assert_eq!(explain.source_weight, 2.0);
+        assert_eq!(explain.final_score, 2.0 / 61.0 + 1.0 / 62.0);
+    }
+
+    #[test]
+    fn test_rank_queries() {
+        // 原始查询里 text:2 的分数更高，扩展查询只召回了 text:1
+        let queries = vec![
+            (
+                vec![
+                    FullTextSearchResult {
+                        id: ID::new("1".to_string(), "text"),
+                        score: vec![("a".to_string(), 0.5)],
+                    },
+                    FullTextSearchResult {
+                        id: ID::new("2".to_string(), "text"),
+                        score: vec![("a".to_string(), 0.9)],
+                    },
+                ],
+                vec![],
+            ),
+            (
+                vec![FullTextSearchResult {
+                    id: ID::new("1".to_string(), "text"),
+                    score: vec![("b".to_string(), 0.1)],
+                }],
+                vec![],
+            ),
+        ];
+        // 分数不同的查询之间只比较排名，两个查询都召回的 text:1 排在前面
+        let res = Rank::rank(
+            queries,
+            &RankWeights::default(),
+            &HashSet::new(),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].id.id_with_table(), "text:1");
+        let explain = res[0].explain.clone().unwrap();
+        assert_eq!(explain.full_text.unwrap().rank, 0);
+        assert_eq!(explain.full_text_tokens, vec![("a".to_string(), 0.5)]);
+    }
 }
@@ -0,0 +1,336 @@
+use crate::{
+    check_db_error_from_resp,
+    db::{
+        model::{
+            audio::{AudioFrameModel, AudioModel},
+            chapter::ChapterFrameModel,
+            document::DocumentModel,
+            image::ImageModel,
+            ocr::OcrFrameModel,
+            page::PageModel,
+            text::TextModel,
+            video::VideoModel,
+            web_page::WebPageModel,
+        },
+        DB,
+    },
+    query::{model::VectorSearchType, ContentQueryFilter, ContentQuerySource},
+    segment::SEGMENT_SEPARATOR,
+};
+use content_metadata::ContentType;
+
+/// text 和 image 是最底层的对象，需要向上回溯两层 contains 才能找到对应的 asset（video、audio、document 等）
+/// 单独的图片没有上层对象，asset 就是它自己
+const ASSET_RECORD_EXPR: &str = "(<-contains[0].in<-contains[0].in ?? id)";
+const FILE_IDENTIFIER_EXPR: &str =
+    "(<-contains[0].in<-contains[0].in->with[0].out.file_identifier ?? ->with[0].out.file_identifier)";
+
+pub(super) const FILTER_TABLES_VAR: &str = "filter_tables";
+pub(super) const FILTER_FILE_IDENTIFIERS_VAR: &str = "filter_file_identifiers";
+pub(super) const FILTER_PHRASES_VAR: &str = "filter_phrases";
+pub(super) const FILTER_EXCLUDED_VAR: &str = "filter_excluded";
+pub(super) const FILTER_EXCLUDED_FILE_IDENTIFIERS_VAR: &str = "filter_excluded_file_identifiers";
+pub(super) const FILTER_OBJECTS_VAR: &str = "filter_objects";
+
+fn content_type_table(content_type: &ContentType) -> &'static str {
+    match content_type {
+        ContentType::Video => VideoModel::table(),
+        ContentType::Audio => AudioModel::table(),
+        ContentType::Image => ImageModel::table(),
+        ContentType::RawText => DocumentModel::table(),
+        ContentType::WebPage => WebPageModel::table(),
+        _ => "",
+    }
+}
+
+/// 短语和排除词匹配的文本字段
+fn text_column(table: &str) -> Option<&'static str> {
+    if table == TextModel::table() {
+        Some("content")
+    } else if table == ImageModel::table() {
+        Some("caption")
+    } else {
+        None
+    }
+}
+
+/// 去掉分词的分隔符并转换成小写的文本，用来匹配短语和排除词
+fn normalized_text(column: &str) -> String {
+    format!(
+        "string::lowercase(string::replace({}, '{}', ''))",
+        column, SEGMENT_SEPARATOR
+    )
+}
+
+impl ContentQuerySource {
+    /// 这个来源在 text 表里的记录的上一层对象，不是 text 表的来源返回空
+    fn text_parent_tables(&self) -> Vec<&'static str> {
+        match self {
+            ContentQuerySource::Transcript => {
+                vec![AudioFrameModel::table(), ChapterFrameModel::table()]
+            }
+            ContentQuerySource::Text => vec![PageModel::table()],
+            ContentQuerySource::Ocr => vec![OcrFrameModel::table()],
+            ContentQuerySource::Caption | ContentQuerySource::Vision => vec![],
+        }
+    }
+}
+
+impl ContentQueryFilter {
+    /// 根据 sources 判断是否需要搜索这个表
+    /// vector_type 为 None 表示全文搜索
+    pub(super) fn allows_column(
+        &self,
+        table: &str,
+        vector_type: Option<&VectorSearchType>,
+    ) -> bool {
+        // 只有 image 表有物体检测的结果
+        if !self.objects.is_empty() && table != ImageModel::table() {
+            return false;
+        }
+        let Some(sources) = &self.sources else {
+            return true;
+        };
+        if table == TextModel::table() {
+            return sources
+                .iter()
+                .any(|source| !source.text_parent_tables().is_empty());
+        }
+        let source = match vector_type {
+            Some(VectorSearchType::Vision) => ContentQuerySource::Vision,
+            _ => ContentQuerySource::Caption,
+        };
+        sources.contains(&source)
+    }
+
+    /// 生成附加在 WHERE 后面的条件，以 AND 开头，没有过滤条件时返回空字符串
+    /// 条件里用到的变量需要通过 `bind_values` 绑定
+    pub(super) fn to_where_clause(&self, table: &str) -> String {
+        let mut clauses = vec![];
+        // text 表里有转录、文档和网页的文本、OCR 的文字，按上一层对象的表筛选
+        if let (true, Some(sources)) = (table == TextModel::table(), &self.sources) {
+            let parent_tables = sources
+                .iter()
+                .flat_map(|source| source.text_parent_tables())
+                .map(|table| format!("'{}'", table))
+                .collect::<Vec<_>>();
+            clauses.push(format!(
+                "record::tb(<-contains[0].in) IN [{}]",
+                parent_tables.join(", ")
+            ));
+        }
+        if self.content_types.is_some() {
+            clauses.push(format!(
+                "record::tb({}) IN ${}",
+                ASSET_RECORD_EXPR, FILTER_TABLES_VAR
+            ));
+        }
+        if self.file_identifiers.is_some() {
+            clauses.push(format!(
+                "{} IN ${}",
+                FILE_IDENTIFIER_EXPR, FILTER_FILE_IDENTIFIERS_VAR
+            ));
+        }
+        if !self.excluded_file_identifiers.is_empty() {
+            clauses.push(format!(
+                "{} NOT IN ${}",
+                FILE_IDENTIFIER_EXPR, FILTER_EXCLUDED_FILE_IDENTIFIERS_VAR
+            ));
+        }
+        if !self.objects.is_empty() {
+            if table == ImageModel::table() {
+                clauses.push(format!("objects CONTAINSALL ${}", FILTER_OBJECTS_VAR));
+            } else {
+                clauses.push("false".to_string());
+            }
+        }
+        if let Some(column) = text_column(table) {
+            // 数据库里的文本是分过词的，比较之前去掉分隔符
+            let text = normalized_text(column);
+            for i in 0..self.phrases.len() {
+                clauses.push(format!(
+                    "string::contains({}, ${}[{}])",
+                    text, FILTER_PHRASES_VAR, i
+                ));
+            }
+        }
+        clauses
+            .into_iter()
+            .map(|clause| format!(" AND {}", clause))
+            .collect::<String>()
+    }
+
+    pub(super) fn bind_values(&self) -> [(&'static str, Vec<String>); 5] {
+        let tables = self
+            .content_types
+            .as_ref()
+            .map(|v| {
+                v.iter()
+                    .map(|t| content_type_table(t).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let file_identifiers = self.file_identifiers.clone().unwrap_or_default();
+        let lowercase = |v: &Vec<String>| v.iter().map(|s| s.to_lowercase()).collect::<Vec<_>>();
+        [
+            (FILTER_TABLES_VAR, tables),
+            (FILTER_FILE_IDENTIFIERS_VAR, file_identifiers),
+            (FILTER_PHRASES_VAR, lowercase(&self.phrases)),
+            (
+                FILTER_EXCLUDED_FILE_IDENTIFIERS_VAR,
+                self.excluded_file_identifiers.clone(),
+            ),
+            (FILTER_OBJECTS_VAR, lowercase(&self.objects)),
+        ]
+    }
+}
+
+impl DB {
+    /// 把 filter 里的排除词转换成 excluded_file_identifiers
+    /// 排除词要按素材判断，一个片段里有这个词，同一个素材的其他片段也不能出现在结果里
+    pub(crate) async fn resolve_excluded_filter(
+        &self,
+        filter: &ContentQueryFilter,
+    ) -> anyhow::Result<ContentQueryFilter> {
+        let mut filter = filter.clone();
+        if filter.excluded.is_empty() {
+            return Ok(filter);
+        }
+        let statements = [TextModel::table(), ImageModel::table()]
+            .into_iter()
+            .filter_map(|table| {
+                let text = normalized_text(text_column(table)?);
+                let conditions = (0..filter.excluded.len())
+                    .map(|i| {
+                        format!(
+                            "string::contains({}, ${}[{}])",
+                            text, FILTER_EXCLUDED_VAR, i
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                Some(format!(
+                    "SELECT VALUE {} FROM {} WHERE {};",
+                    FILE_IDENTIFIER_EXPR,
+                    table,
+                    conditions.join(" OR ")
+                ))
+            })
+            .collect::<Vec<_>>();
+        let excluded = filter
+            .excluded
+            .iter()
+            .map(|v| v.to_lowercase())
+            .collect::<Vec<_>>();
+        let mut resp = self
+            .client
+            .query(statements.join("\n"))
+            .bind((FILTER_EXCLUDED_VAR, excluded))
+            .await?;
+        check_db_error_from_resp!(resp)
+            .map_err(|errors_map| anyhow::anyhow!("filter excluded error: {:?}", errors_map))?;
+        for i in 0..statements.len() {
+            let file_identifiers = resp.take::<Vec<Option<String>>>(i)?;
+            for file_identifier in file_identifiers.into_iter().flatten() {
+                if !filter.excluded_file_identifiers.contains(&file_identifier) {
+                    filter.excluded_file_identifiers.push(file_identifier);
+                }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::query::{model::VectorSearchType, ContentQueryFilter, ContentQuerySource};
+    use content_metadata::ContentType;
+
+    #[test]
+    fn test_where_clause() {
+        assert_eq!(ContentQueryFilter::default().to_where_clause("text"), "");
+
+        let filter = ContentQueryFilter {
+            content_types: Some(vec![ContentType::Video, ContentType::RawText]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.to_where_clause("text"),
+            " AND record::tb((<-contains[0].in<-contains[0].in ?? id)) IN $filter_tables"
+        );
+        let [(_, tables), (_, file_identifiers), (_, phrases), (_, excluded), (_, objects)] =
+            filter.bind_values();
+        assert_eq!(tables, vec!["video", "document"]);
+        assert!(file_identifiers.is_empty());
+        assert!(phrases.is_empty());
+        assert!(excluded.is_empty());
+        assert!(objects.is_empty());
+    }
+
+    #[test]
+    fn test_phrase_clause() {
+        // 排除词不在片段上过滤，而是先转换成 excluded_file_identifiers 按素材排除
+        let filter = ContentQueryFilter {
+            phrases: vec!["Product Launch".to_string()],
+            excluded: vec!["draft".to_string()],
+            excluded_file_identifiers: vec!["abc".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.to_where_clause("image"),
+            " AND (<-contains[0].in<-contains[0].in->with[0].out.file_identifier ?? ->with[0].out.file_identifier) NOT IN $filter_excluded_file_identifiers AND string::contains(string::lowercase(string::replace(caption, '\u{2008}', '')), $filter_phrases[0])"
+        );
+        let [_, _, (_, phrases), (_, excluded), _] = filter.bind_values();
+        assert_eq!(phrases, vec!["product launch"]);
+        assert_eq!(excluded, vec!["abc"]);
+    }
+
+    #[test]
+    fn test_sources() {
+        let filter = ContentQueryFilter {
+            sources: Some(vec![ContentQuerySource::Transcript]),
+            ..Default::default()
+        };
+        assert!(filter.allows_column("text", None));
+        assert!(!filter.allows_column("image", None));
+        assert!(!filter.allows_column("image", Some(&VectorSearchType::Vision)));
+        assert_eq!(
+            filter.to_where_clause("text"),
+            " AND record::tb(<-contains[0].in) IN ['audio_frame', 'chapter_frame']"
+        );
+
+        // 文档和网页的文本不属于转录
+        let filter = ContentQueryFilter {
+            sources: Some(vec![ContentQuerySource::Text, ContentQuerySource::Ocr]),
+            ..Default::default()
+        };
+        assert!(filter.allows_column("text", None));
+        assert_eq!(
+            filter.to_where_clause("text"),
+            " AND record::tb(<-contains[0].in) IN ['page', 'ocr_frame']"
+        );
+
+        let filter = ContentQueryFilter {
+            sources: Some(vec![ContentQuerySource::Vision]),
+            ..Default::default()
+        };
+        assert!(!filter.allows_column("image", Some(&VectorSearchType::Text)));
+        assert!(filter.allows_column("image", Some(&VectorSearchType::Vision)));
+    }
+
+    #[test]
+    fn test_objects() {
+        let filter = ContentQueryFilter {
+            objects: vec!["Dog".to_string(), "car".to_string()],
+            ..Default::default()
+        };
+        assert!(!filter.allows_column("text", None));
+        assert!(filter.allows_column("image", Some(&VectorSearchType::Vision)));
+        assert_eq!(
+            filter.to_where_clause("image"),
+            " AND objects CONTAINSALL $filter_objects"
+        );
+        assert_eq!(filter.to_where_clause("text"), " AND false");
+        let [.., (_, objects)] = filter.bind_values();
+        assert_eq!(objects, vec!["dog", "car"]);
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
         model::{image::ImageModel, text::TextModel},
         DB,
     },
-    query::model::FullTextSearchResult,
+    query::{model::FullTextSearchResult, ContentQueryFilter},
+    segment::desegment,
 };
 use futures::future::join_all;
 use serde::Deserialize;
@@ -14,7 +15,14 @@ use std::convert::Into;
 use surrealdb::sql::Thing;
 
 pub const MAX_FULLTEXT_TOKEN: usize = 100;
-pub const FULL_TEXT_QUERY_LIMIT: usize = 100;
+/// 每个字段最多取多少条全文搜索结果，这些结果会全部参与 rank 和分页，决定了能翻多少页
+/// 排序结果会按 query 缓存，所以候选多一些也只在第一页搜索一次
+pub const FULL_TEXT_QUERY_LIMIT: usize = 500;
+
+/// limit 为 None 时不限制条数，只在一个素材里搜索的时候使用
+fn limit_clause(limit: Option<usize>) -> String {
+    limit.map(|v| format!(" LIMIT {}", v)).unwrap_or_default()
+}
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct FullTextSearchEntity {
@@ -56,13 +64,19 @@ impl From<FullTextWithHighlightSearchEntity> for FullTextSearchResult {
     fn from(value: FullTextWithHighlightSearchEntity) -> Self {
         FullTextSearchResult {
             id: value.id.clone().into(),
-            score: vec![(value.highlight, value.score)],
+            // 索引里的文本是分过词的，高亮结果需要还原成原文
+            score: vec![(desegment(&value.highlight), value.score)],
         }
     }
 }
 
 // 使用 $query var 就不需要在两边加引号了，sueeral 会自动处理类型，加了引号就搜索不出来了
-fn full_text_query_statement(table: &str, column: &str) -> String {
+fn full_text_query_statement(
+    table: &str,
+    column: &str,
+    filter: &ContentQueryFilter,
+    limit: Option<usize>,
+) -> String {
     format!(
         r#"
 SELECT
@@ -70,19 +84,20 @@ SELECT
     search::score(0) as score,
     search::highlight('{mark_left}', '{mark_right}', 0) AS highlight
 FROM {table}
-WHERE {column} @0@ $query
-LIMIT {limit};"#,
+WHERE {column} @0@ $query{filter}{limit};"#,
         table = table,
         column = column,
+        filter = filter.to_where_clause(table),
         mark_left = HIGHLIGHT_MARK.0,
         mark_right = HIGHLIGHT_MARK.1,
-        limit = FULL_TEXT_QUERY_LIMIT
+        limit = limit_clause(limit)
     )
 }
 
 /// 组装 (table, column) 的元组数组，给后面使用
 /// 全文搜索 Image 的 prompt 和 Text 的 data，然后再回溯关联的对象
-fn full_text_search_columns() -> Vec<(&'static str, &'static str)> {
+/// 不在 filter 的 sources 里的字段会被跳过
+fn full_text_search_columns(filter: &ContentQueryFilter) -> Vec<(&'static str, &'static str)> {
     let params = vec![
         (ImageModel::table(), ImageModel::full_text_columns()),
         (TextModel::table(), TextModel::full_text_columns()),
@@ -95,6 +110,7 @@ fn full_text_search_columns() -> Vec<(&'static str, &'static str)> {
             .collect::<Vec<(&str, &str)>>()
     })
     .flatten()
+    .filter(|(table, _)| filter.allows_column(table, None))
     .collect::<Vec<(&str, &str)>>();
     params
 }
@@ -104,11 +120,14 @@ impl DB {
         &self,
         data: Vec<String>,
         with_highlight: bool,
+        filter: &ContentQueryFilter,
+        limit: Option<usize>,
     ) -> anyhow::Result<Vec<FullTextSearchResult>> {
         Ok(if with_highlight {
-            self.full_text_search_with_highlight(data).await?
+            self.full_text_search_with_highlight(data, filter, limit)
+                .await?
         } else {
-            self._full_text_search(data).await?
+            self._full_text_search(data, filter, limit).await?
         })
     }
 
@@ -118,11 +137,13 @@ impl DB {
     /// - 缺点是高亮结果是分散的
     /// SELECT id, search::score(0) AS score_0, search::score(1) AS score_1, search::score(2) AS score_2
     /// FROM {table}
-    /// WHERE {column} @0@ '$word_0' OR {column} @1@ '$word_1' OR {column} @2@ '$word_2'
+    /// WHERE ({column} @0@ '$word_0' OR {column} @1@ '$word_1' OR {column} @2@ '$word_2') {filter}
     /// LIMIT {limit};
     async fn _full_text_search(
         &self,
         data: Vec<String>,
+        filter: &ContentQueryFilter,
+        limit: Option<usize>,
     ) -> anyhow::Result<Vec<FullTextSearchResult>> {
         if data.is_empty() {
             return Ok(vec![]);
@@ -134,7 +155,7 @@ impl DB {
         };
 
         // 组装 (table, column) 的元组数组，给后面使用
-        let columns = full_text_search_columns();
+        let columns = full_text_search_columns(filter);
         let futures = columns.into_iter().map(|(table, column)| {
             let (search_scores, where_clauses): (Vec<String>, Vec<String>) = data
                 .iter()
@@ -147,16 +168,21 @@ impl DB {
                 .unzip();
 
             let sql = format!(
-                "SELECT id, {select} FROM {table} WHERE {where_clauses} LIMIT {limit};",
+                "SELECT id, {select} FROM {table} WHERE ({where_clauses}){filter}{limit};",
                 select = search_scores.join(", "),
                 table = table,
                 where_clauses = where_clauses.join(" OR "),
-                limit = FULL_TEXT_QUERY_LIMIT
+                filter = filter.to_where_clause(table),
+                limit = limit_clause(limit)
             );
 
             let data: Vec<String> = data.into_iter().map(|d| d.to_string()).collect();
             async move {
-                let text: Vec<FullTextSearchEntity> = self.client.query(sql).await?.take(0)?;
+                let mut query = self.client.query(sql);
+                for (name, value) in filter.bind_values() {
+                    query = query.bind((name, value));
+                }
+                let text: Vec<FullTextSearchEntity> = query.await?.take(0)?;
                 Ok::<_, anyhow::Error>(
                     text.iter()
                         .map(|t| t.convert_to_result(&data))
@@ -182,6 +208,8 @@ impl DB {
     pub async fn full_text_search_with_highlight(
         &self,
         data: Vec<String>,
+        filter: &ContentQueryFilter,
+        limit: Option<usize>,
     ) -> anyhow::Result<Vec<FullTextSearchResult>> {
         if data.is_empty() {
             return Ok(vec![]);
@@ -189,16 +217,16 @@ impl DB {
 
         let query = data.join(" ");
         // 组装 (table, column) 的元组数组，给后面使用
-        let columns = full_text_search_columns();
+        let columns = full_text_search_columns(filter);
         let futures = columns.into_iter().map(|(table, column)| {
-            let query_statement = full_text_query_statement(table, column);
+            let query_statement = full_text_query_statement(table, column, filter, limit);
             let query = query.clone();
             async move {
-                let mut resp = self
-                    .client
-                    .query(query_statement)
-                    .bind(("query", query))
-                    .await?;
+                let mut query = self.client.query(query_statement).bind(("query", query));
+                for (name, value) in filter.bind_values() {
+                    query = query.bind((name, value));
+                }
+                let mut resp = query.await?;
                 check_db_error_from_resp!(resp).map_err(|errors_map| {
                     tracing::error!("full_text_search_with_highlight errors: {errors_map:?}");
                     anyhow::anyhow!("full_text_search_with_highlight errors: {errors_map:?}")
@@ -0,0 +1,136 @@
+use crate::query::payload::{ContentQueryGroupedResult, ContentQueryHit, ContentQueryResult};
+use std::collections::HashMap;
+
+/// 除了分数最高的片段，其他片段按这个比例加到素材的分数上
+/// 命中多个片段的素材会排在只命中一个片段、分数差不多的素材前面，但不会超过明显更相关的素材
+const EXTRA_HIT_SCORE_WEIGHT: f32 = 0.1;
+
+/// 把同一个素材的结果合并成一条
+/// - 素材分数 = 最高的片段分数 + 其他片段分数之和 * EXTRA_HIT_SCORE_WEIGHT
+/// - 片段按照在素材里的位置排序，方便前端按时间线展示
+/// - 素材按分数从高到低排序，分数相同时按 file_identifier 排序，保证翻页时顺序稳定
+pub(crate) fn group_results_by_asset(
+    query_results: Vec<ContentQueryResult>,
+) -> Vec<ContentQueryGroupedResult> {
+    let mut groups: HashMap<String, Vec<ContentQueryHit>> = HashMap::new();
+    for result in query_results {
+        groups
+            .entry(result.file_identifier.clone())
+            .or_default()
+            .push(result.into());
+    }
+
+    let mut grouped_results = groups
+        .into_iter()
+        .map(|(file_identifier, mut hits)| {
+            let max_score = hits.iter().map(|hit| hit.score).fold(0.0, f32::max);
+            let total_score = hits.iter().map(|hit| hit.score).sum::<f32>();
+            let score = max_score + (total_score - max_score) * EXTRA_HIT_SCORE_WEIGHT;
+            hits.sort_by(|a, b| {
+                a.metadata
+                    .segment_range()
+                    .cmp(&b.metadata.segment_range())
+                    .then_with(|| {
+                        b.score
+                            .partial_cmp(&a.score)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+            });
+            ContentQueryGroupedResult {
+                file_identifier,
+                score,
+                hits,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    grouped_results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.file_identifier.cmp(&b.file_identifier))
+    });
+
+    grouped_results
+}
+
+#[cfg(test)]
+mod test {
+    use super::group_results_by_asset;
+    use crate::query::payload::{
+        video::{VideoIndexMetadata, VideoSliceType},
+        ContentIndexMetadata, ContentQueryResult,
+    };
+
+    #[test]
+    fn test_group_results_by_asset() {
+        let results = vec![
+            ContentQueryResult {
+                file_identifier: "a".to_string(),
+                score: 0.5,
+                metadata: ContentIndexMetadata::Video(VideoIndexMetadata {
+                    slice_type: VideoSliceType::Visual,
+                    start_timestamp: 220_000,
+                    end_timestamp: 221_000,
+                }),
+                hit_reason: None,
+                reference_content: None,
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "b".to_string(),
+                score: 0.45,
+                metadata: ContentIndexMetadata::Video(VideoIndexMetadata {
+                    slice_type: VideoSliceType::Visual,
+                    start_timestamp: 0,
+                    end_timestamp: 1_000,
+                }),
+                hit_reason: None,
+                reference_content: None,
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "a".to_string(),
+                score: 0.3,
+                metadata: ContentIndexMetadata::Video(VideoIndexMetadata {
+                    slice_type: VideoSliceType::Visual,
+                    start_timestamp: 12_000,
+                    end_timestamp: 13_000,
+                }),
+                hit_reason: None,
+                reference_content: None,
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "a".to_string(),
+                score: 0.2,
+                metadata: ContentIndexMetadata::Video(VideoIndexMetadata {
+                    slice_type: VideoSliceType::Visual,
+                    start_timestamp: 425_000,
+                    end_timestamp: 426_000,
+                }),
+                hit_reason: None,
+                reference_content: None,
+                search_hint: "".to_string(),
+                explain: None,
+            },
+        ];
+        let grouped = group_results_by_asset(results);
+        assert_eq!(grouped.len(), 2);
+
+        assert_eq!(grouped[0].file_identifier, "a");
+        assert!((grouped[0].score - 0.55).abs() < 1e-6);
+        let starts = grouped[0]
+            .hits
+            .iter()
+            .filter_map(|hit| hit.metadata.segment_range().map(|(start, _)| start))
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![12_000, 220_000, 425_000]);
+
+        assert_eq!(grouped[1].file_identifier, "b");
+        assert_eq!(grouped[1].hits.len(), 1);
+    }
+}
@@ -0,0 +1,255 @@
+use super::vector_search::{vector_distance, vector_search_columns, VectorSearchEntity};
+use crate::{
+    check_db_error_from_resp,
+    db::DB,
+    query::{
+        model::{TextSearchModel, VectorSearchResult, VectorSearchType},
+        payload::ContentQueryResult,
+        ContentQueryFilter, ContentQueryMode, ContentQueryRankOptions,
+    },
+};
+use futures::future::join_all;
+
+/// 素材内搜索时每个向量字段至少保留最接近的多少条
+const IN_ASSET_VECTOR_MIN_LIMIT: usize = 10;
+/// 素材内的片段越多，保留的向量结果也越多，按片段数的比例计算
+const IN_ASSET_VECTOR_RATIO: usize = 10;
+
+/// 向量搜索总能返回结果，全部保留的话每个片段都会被标记成命中
+/// 按素材里的记录数取最接近的一部分，长视频不会只剩下固定的几条
+fn in_asset_vector_limit(total: usize) -> usize {
+    (total / IN_ASSET_VECTOR_RATIO).max(IN_ASSET_VECTOR_MIN_LIMIT)
+}
+
+/// 素材内的记录不多，不走 HNSW 索引，直接计算距离
+/// 索引是在整个库里取最近邻，再按素材过滤的话大部分结果都会被过滤掉
+/// payload <-with- video/audio/document/web_page ->contains-> frame/page ->contains-> text/image
+fn in_asset_vector_query_statement(table: &str, column: &str) -> String {
+    format!(
+        r#"
+LET $segments = array::flatten(
+    (SELECT VALUE <-with[0].in->contains->? FROM payload WHERE file_identifier = $file_identifier)
+);
+LET $records = array::flatten((SELECT VALUE ->contains->{table} FROM $segments));
+SELECT
+    id,
+    {distance} AS distance
+FROM $records
+WHERE {column} != NONE
+ORDER BY distance;
+"#,
+        table = table,
+        column = column,
+        distance = vector_distance(table, column),
+    )
+}
+
+impl DB {
+    /// 🔍 只在一个素材里做向量搜索
+    pub async fn vector_search_in_asset(
+        &self,
+        file_identifier: &str,
+        text_embedding: Vec<f32>,
+        vision_embedding: Vec<f32>,
+    ) -> anyhow::Result<Vec<VectorSearchResult>> {
+        if text_embedding.is_empty() || vision_embedding.is_empty() {
+            anyhow::bail!("data is empty in vector search");
+        }
+
+        let futures = vector_search_columns(&ContentQueryFilter::default())
+            .into_iter()
+            .map(|(table, column, vector_type)| {
+                let vector_value = match vector_type {
+                    VectorSearchType::Text => text_embedding.clone(),
+                    VectorSearchType::Vision => vision_embedding.clone(),
+                };
+                let query_statement = in_asset_vector_query_statement(table, column);
+                async move {
+                    let mut res = self
+                        .client
+                        .query(query_statement)
+                        .bind(("file_identifier", file_identifier.to_string()))
+                        .bind(("vector_value", vector_value))
+                        .await?;
+                    check_db_error_from_resp!(res).map_err(|errors_map| {
+                        tracing::error!("vector_search_in_asset errors: {errors_map:?}");
+                        anyhow::anyhow!("vector_search_in_asset errors: {errors_map:?}")
+                    })?;
+                    // 前两条语句是 LET
+                    let res: Vec<VectorSearchEntity> = res.take(2)?;
+                    let limit = in_asset_vector_limit(res.len());
+                    Ok::<_, anyhow::Error>(
+                        res.iter()
+                            .take(limit)
+                            .map(|d| VectorSearchResult {
+                                id: d.id.clone().into(),
+                                distance: d.distance,
+                                vector_type: vector_type.clone(),
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                }
+            });
+
+        Ok(join_all(futures)
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 只在一个素材里搜索，返回全部命中的片段，按时间戳或者 chunk 的位置排序
+    pub(crate) async fn search_in_asset(
+        &self,
+        text: TextSearchModel,
+        file_identifier: &str,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        // 全文搜索有 BM25 索引，直接用 file_identifiers 过滤，素材内的命中全部返回
+        let filter = ContentQueryFilter {
+            file_identifiers: Some(vec![file_identifier.to_string()]),
+            ..Default::default()
+        };
+        let full_text_results = match text.mode {
+            ContentQueryMode::Vector => vec![],
+            _ => {
+                self.full_text_search(text.tokens.0, true, &filter, None)
+                    .await?
+            }
+        };
+        let vector_results = match text.mode {
+            ContentQueryMode::FullText => vec![],
+            _ => {
+                self.vector_search_in_asset(
+                    file_identifier,
+                    text.text_embedding,
+                    text.vision_embedding,
+                )
+                .await?
+            }
+        };
+        tracing::debug!(
+            "{} found in full text search, {} found in vector search",
+            full_text_results.len(),
+            vector_results.len()
+        );
+
+        let mut query_results = self
+            .rank_and_lookup(
+                vec![(full_text_results, vector_results)],
+                &ContentQueryRankOptions::default(),
+                None,
+            )
+            .await?;
+        // 按片段位置排序，方便在时间轴上标记和依次跳转
+        query_results.sort_by(|a, b| {
+            a.metadata
+                .segment_range()
+                .cmp(&b.metadata.segment_range())
+                .then_with(|| {
+                    b.score
+                        .partial_cmp(&a.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+        });
+
+        Ok(query_results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{in_asset_vector_limit, in_asset_vector_query_statement};
+    use crate::{
+        db::{
+            model::{
+                audio::{AudioFrameModel, AudioModel},
+                text::TextModel,
+            },
+            shared::test::{fake_file_identifier, fake_text_model, setup},
+        },
+        query::{
+            model::{TextSearchModel, TextToken},
+            ContentQueryMode,
+        },
+    };
+    use test_log::test;
+
+    #[test]
+    fn test_in_asset_vector_query_statement() {
+        let statement = in_asset_vector_query_statement("image", "embedding");
+        assert!(statement.contains("1 - vector::similarity::cosine(embedding, $vector_value)"));
+        assert!(statement.contains("->contains->image"));
+        let statement = in_asset_vector_query_statement("text", "embedding");
+        assert!(statement.contains("vector::distance::euclidean(embedding, $vector_value)"));
+        assert!(statement.contains("->contains->text"));
+        assert!(!statement.contains("LIMIT"));
+    }
+
+    #[test]
+    fn test_in_asset_vector_limit() {
+        assert_eq!(in_asset_vector_limit(3), 10);
+        assert_eq!(in_asset_vector_limit(150), 15);
+    }
+
+    #[test(tokio::test)]
+    async fn test_search_in_asset() {
+        let db = setup(None).await;
+        let file_identifier = fake_file_identifier();
+        // 超过全局搜索每个字段的条数上限，素材内搜索仍然全部返回
+        let audio_frames = (0..150)
+            .map(|i| {
+                (
+                    AudioFrameModel {
+                        id: None,
+                        start_timestamp: i * 10_000,
+                        end_timestamp: i * 10_000 + 5_000,
+                    },
+                    vec![TextModel {
+                        content: format!("frame {} mentions Quillwort", i),
+                        ..fake_text_model()
+                    }],
+                )
+            })
+            .collect::<Vec<_>>();
+        db.insert_audio(
+            file_identifier.clone(),
+            (AudioModel { id: None }, audio_frames),
+        )
+        .await
+        .unwrap();
+
+        let text_embedding = fake_text_model().embedding;
+        let results = db
+            .search_in_asset(
+                TextSearchModel {
+                    data: "quillwort".to_string(),
+                    tokens: TextToken(vec!["quillwort".to_string()]),
+                    text_embedding: text_embedding.clone(),
+                    vision_embedding: vec![0.0; 512],
+                    mode: ContentQueryMode::FullText,
+                    variants: vec![],
+                },
+                &file_identifier,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 150);
+        let ranges = results
+            .iter()
+            .map(|v| v.metadata.segment_range().unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, (0..150).map(|i| i * 10_000).collect::<Vec<_>>());
+
+        let vector_results = db
+            .vector_search_in_asset(&file_identifier, text_embedding, vec![0.0; 512])
+            .await
+            .unwrap();
+        assert_eq!(vector_results.len(), 15);
+
+        db.delete_by_file_identifier(&file_identifier)
+            .await
+            .unwrap();
+    }
+}
@@ -1,26 +1,46 @@
+mod filter;
 mod full_text_search;
+mod group;
+mod in_asset;
 mod test;
 mod vector_search;
+use futures::future::try_join_all;
+pub(crate) use group::group_results_by_asset;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Into;
+pub(crate) use vector_search::vector_candidates;
 
 use super::rank::RankResult;
 use crate::{
-    db::{model::id::ID, rank::Rank, DB},
+    check_db_error_from_resp,
+    db::{
+        model::{
+            audio::AudioFrameModel,
+            id::{ID, TB},
+        },
+        rank::Rank,
+        DB,
+    },
     query::{
-        model::{SearchModel, SearchType, VectorSearchType},
+        model::{
+            FullTextSearchResult, SearchModel, SearchType, TextSearchVariant, VectorSearchResult,
+            VectorSearchType,
+        },
         payload::{
             audio::{AudioIndexMetadata, AudioSliceType},
             image::ImageIndexMetadata,
             raw_text::{RawTextChunkType, RawTextIndexMetadata},
             video::{VideoIndexMetadata, VideoSliceType},
             web_page::{WebPageChunkType, WebPageIndexMetadata},
-            ContentIndexMetadata, ContentQueryHitReason, ContentQueryResult,
+            ContentIndexMetadata, ContentQueryHitReason, ContentQueryPage, ContentQueryResult,
         },
+        ContentQueryFilter, ContentQueryMode, ContentQueryRankOptions,
     },
+    segment::desegment,
     // utils::extract_highlighted_content,
 };
+use full_text_search::FULL_TEXT_QUERY_LIMIT;
 
 async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
     surrealdb_client: &surrealdb::Surreal<T>,
@@ -49,7 +69,7 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
 
     let mut query_results: Vec<ContentQueryResult> = Vec::new();
     // text 和 image 可能对应到同样的视频片段，那么，视频的分数是不是应该增加？
-    // 也就是 query_results 里面是会有重复的 file_identifier 的
+    // 也就是 query_results 里面是会有重复的 file_identifier 的，需要按素材展示的时候用 group_results_by_asset
     // https://github.com/bmrlab/gendam/issues/105#issuecomment-2509669785
     // TODO: 需要合并一下重复的 frame
 
@@ -63,9 +83,10 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
             Some(v) => v.clone(),
             None => "".to_string(),
         };
-        let reference_text = record.reference_text.clone();
+        // text.content 和 image.caption 是分过词的，需要还原成原文
+        let reference_text = desegment(&record.reference_text);
         let metadata = match (record.asset_id.tb.as_str(), &record.segment) {
-            ("image", None) => {
+            ("image", None) | ("image", Some(SegmentLookup::OcrFrame(_))) => {
                 let metadata = ImageIndexMetadata { data: 0 };
                 ContentIndexMetadata::Image(metadata)
             }
@@ -85,6 +106,24 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
                 };
                 ContentIndexMetadata::Video(metadata)
             }
+            // 画面上的文字也是画面的一部分
+            ("video", Some(SegmentLookup::OcrFrame(segment))) => {
+                let metadata = VideoIndexMetadata {
+                    slice_type: VideoSliceType::Visual,
+                    start_timestamp: segment.start_timestamp,
+                    end_timestamp: segment.end_timestamp,
+                };
+                ContentIndexMetadata::Video(metadata)
+            }
+            // 章节主要按转录划分，定位到章节开始的地方
+            ("video", Some(SegmentLookup::ChapterFrame(segment))) => {
+                let metadata = VideoIndexMetadata {
+                    slice_type: VideoSliceType::Audio,
+                    start_timestamp: segment.start_timestamp,
+                    end_timestamp: segment.end_timestamp,
+                };
+                ContentIndexMetadata::Video(metadata)
+            }
             ("video", Some(SegmentLookup::AudioFrame(segment))) => {
                 let metadata = VideoIndexMetadata {
                     slice_type: VideoSliceType::Audio,
@@ -117,7 +156,17 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
                 );
             }
         };
+        let is_ocr = matches!(&record.segment, Some(SegmentLookup::OcrFrame(_)));
+        let is_chapter = matches!(&record.segment, Some(SegmentLookup::ChapterFrame(_)));
         let hit_reasone = match rank_result.search_type {
+            SearchType::FullText if is_ocr => ContentQueryHitReason::OcrMatch(highlight),
+            SearchType::Vector(VectorSearchType::Text) if is_ocr => {
+                ContentQueryHitReason::OcrMatch(reference_text.clone())
+            }
+            SearchType::FullText if is_chapter => ContentQueryHitReason::ChapterMatch(highlight),
+            SearchType::Vector(VectorSearchType::Text) if is_chapter => {
+                ContentQueryHitReason::ChapterMatch(reference_text.clone())
+            }
             SearchType::FullText => match &metadata {
                 ContentIndexMetadata::Video(metadata) => match metadata.slice_type {
                     VideoSliceType::Visual => ContentQueryHitReason::CaptionMatch(highlight),
@@ -132,21 +181,21 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
             SearchType::Vector(VectorSearchType::Text) => match &metadata {
                 ContentIndexMetadata::Video(metadata) => match metadata.slice_type {
                     VideoSliceType::Visual => {
-                        ContentQueryHitReason::SemanticCaptionMatch(reference_text)
+                        ContentQueryHitReason::SemanticCaptionMatch(reference_text.clone())
                     }
                     VideoSliceType::Audio => {
-                        ContentQueryHitReason::SemanticTranscriptMatch(reference_text)
+                        ContentQueryHitReason::SemanticTranscriptMatch(reference_text.clone())
                     }
                 },
                 ContentIndexMetadata::Audio(metadata) => match metadata.slice_type {
                     AudioSliceType::Transcript => {
-                        ContentQueryHitReason::SemanticTranscriptMatch(reference_text)
+                        ContentQueryHitReason::SemanticTranscriptMatch(reference_text.clone())
                     }
                 },
                 ContentIndexMetadata::Image(_) => {
-                    ContentQueryHitReason::SemanticCaptionMatch(reference_text)
+                    ContentQueryHitReason::SemanticCaptionMatch(reference_text.clone())
                 }
-                _ => ContentQueryHitReason::SemanticTextMatch(reference_text),
+                _ => ContentQueryHitReason::SemanticTextMatch(reference_text.clone()),
             },
             SearchType::Vector(VectorSearchType::Vision) => ContentQueryHitReason::VisionMatch,
         };
@@ -155,14 +204,57 @@ async fn lookup_assets_by_image_text_ids<T: surrealdb::Connection>(
             score: rank_result.score,
             metadata,
             hit_reason: Some(hit_reasone),
-            reference_content: Some(record.reference_text),
+            reference_content: Some(reference_text),
             search_hint: rank_result.search_hint.clone(),
+            explain: rank_result.explain.clone(),
         });
     }
 
     Ok(query_results)
 }
 
+/// 新素材加分的半衰期，创建 30 天的素材加分是刚创建的一半
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// 按素材创建时间加分，score 乘以 1 + recency * 0.5 ^ (天数 / 半衰期)
+/// asset_created_at 的 key 是 file_identifier，没有创建时间的素材不加分
+pub(crate) fn apply_recency(
+    query_results: &mut [ContentQueryResult],
+    recency: f32,
+    asset_created_at: &HashMap<String, i64>,
+) {
+    if recency <= 0.0 {
+        return;
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    for result in query_results.iter_mut() {
+        let Some(created_at) = asset_created_at.get(&result.file_identifier) else {
+            continue;
+        };
+        let age_days = (now - created_at).max(0) as f32 / 86_400_000.0;
+        let boost = recency * 0.5_f32.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        result.score *= 1.0 + boost;
+        if let Some(explain) = result.explain.as_mut() {
+            explain.recency_boost = boost;
+            explain.final_score = result.score;
+        }
+    }
+}
+
+/// 按分数从高到低排序，分数相同时按照文件和片段位置排序，保证翻页时顺序稳定
+pub(crate) fn sort_by_score(query_results: &mut [ContentQueryResult]) {
+    query_results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.file_identifier.cmp(&b.file_identifier))
+            .then_with(|| a.metadata.segment_range().cmp(&b.metadata.segment_range()))
+    });
+}
+
 async fn merge_frames(query_results: &mut Vec<ContentQueryResult>) -> anyhow::Result<()> {
     type Meta = ContentIndexMetadata;
     query_results.sort_by(|a, b| {
@@ -282,7 +374,10 @@ fn merge_if_overlapping(a: &mut ContentQueryResult, b: &mut ContentQueryResult)
             _ => {}
         }
 
-        // 取较高的分数
+        // 取较高的分数，分数明细也用分数较高的那个
+        if b.score > a.score {
+            a.explain = b.explain.take();
+        }
         a.score = a.score.max(b.score);
 
         // 合并 search_hint
@@ -305,64 +400,255 @@ impl DB {
         &self,
         data: SearchModel,
         with_highlight: bool,
+        offset: usize,
         max_count: usize,
-    ) -> anyhow::Result<Vec<ContentQueryResult>> {
-        match data {
-            SearchModel::Text(text) => {
-                tracing::debug!("search tokens: {:?}", text.tokens.0);
+        filter: &ContentQueryFilter,
+    ) -> anyhow::Result<ContentQueryPage> {
+        let query_results = self
+            .search_results(
+                data,
+                with_highlight,
+                filter,
+                &ContentQueryRankOptions::default(),
+                vector_candidates(offset, max_count),
+            )
+            .await?;
 
-                let full_text_results =
-                    self.full_text_search(text.tokens.0, with_highlight).await?;
-                tracing::debug!("{} found in full text search", full_text_results.len());
+        let total = query_results.len();
+        let results = query_results
+            .into_iter()
+            .skip(offset)
+            .take(max_count)
+            .collect::<Vec<_>>();
 
-                // let hit_words = full_text_results.iter().map(|x| extract_highlighted_content(&x.score[0].0)).flatten().collect::<Vec<String>>();
-                // tracing::debug!("hit words {hit_words:?}");
+        Ok(ContentQueryPage { results, total })
+    }
+
+    /// 搜索、rank、回溯素材并合并相邻片段，返回按分数排序的全部结果
+    /// 需要在分页之前做二次排序或者分组时使用
+    /// vector_candidates 是向量搜索每个字段的最近邻数量，按需要分页到的位置计算
+    pub(crate) async fn search_results(
+        &self,
+        data: SearchModel,
+        with_highlight: bool,
+        filter: &ContentQueryFilter,
+        rank_options: &ContentQueryRankOptions,
+        vector_candidates: usize,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        let filter = &self.resolve_tags_filter(filter).await?;
+        let filter = &self.resolve_excluded_filter(filter).await?;
+        // 高亮模式下全文搜索是整句搜索的，只有一个分数，explain 需要单独按分词搜索一次
+        let explain_tokens = rank_options.explain && with_highlight;
+        let (queries, full_text_token_scores) = match data {
+            SearchModel::Text(text) => {
+                // 原始查询和扩展查询同时搜索，每个查询的结果单独排序，在 rank 里一起融合
+                let mode = text.mode;
+                let variants = std::iter::once(TextSearchVariant {
+                    data: text.data,
+                    tokens: text.tokens,
+                    text_embedding: text.text_embedding,
+                    vision_embedding: text.vision_embedding,
+                })
+                .chain(text.variants);
+                let recalls = try_join_all(variants.map(|variant| {
+                    self.recall_text_query(
+                        variant,
+                        mode,
+                        with_highlight,
+                        explain_tokens,
+                        filter,
+                        vector_candidates,
+                    )
+                }))
+                .await?;
 
+                let mut queries = vec![];
+                let mut full_text_token_scores: HashMap<ID, Vec<(String, f32)>> = HashMap::new();
+                for (full_text_results, vector_results, token_scores) in recalls {
+                    queries.push((full_text_results, vector_results));
+                    for (id, scores) in token_scores {
+                        full_text_token_scores.entry(id).or_default().extend(scores);
+                    }
+                }
+                (queries, explain_tokens.then_some(full_text_token_scores))
+            }
+            SearchModel::Image(image) => {
+                // 以图搜图没有全文搜索，只有图像向量
                 let vector_results = self
-                    .vector_search(text.text_embedding, text.vision_embedding)
+                    .image_vector_search(image.vision_embedding, filter, vector_candidates)
                     .await?;
-                tracing::debug!("{} found in vector search", vector_results.len());
+                tracing::debug!("{} found in image vector search", vector_results.len());
 
-                // 需要复制一下 full_text_results 和 vector_results，rank 方法会清空这两个 vec
-                let rank_result = Rank::rank(
-                    (full_text_results.clone(), vector_results.clone()),
-                    false,
-                    Some(max_count),
-                )?;
-                tracing::debug!("{} results after rank", rank_result.len());
+                (vec![(vec![], vector_results)], None)
+            }
+        };
 
-                let full_text_highlight_map = full_text_results
-                    .iter()
-                    .map(|r| match r.score.get(0) {
-                        Some((highlight, _score)) => (r.id.clone(), highlight.clone()),
-                        None => (r.id.clone(), "".to_string()),
-                    })
-                    .collect::<HashMap<_, _>>();
-                let rank_results_map = rank_result
-                    .into_iter()
-                    .map(|r| (r.id.clone(), r))
-                    .collect::<HashMap<_, _>>();
-                let mut query_results = lookup_assets_by_image_text_ids(
-                    &self.client,
-                    &rank_results_map,
-                    &full_text_highlight_map,
+        let mut query_results = self
+            .rank_and_lookup(queries, rank_options, full_text_token_scores)
+            .await?;
+
+        // 最后需要排序一下因为 query_results 是按照 id 的顺序返回的
+        sort_by_score(&mut query_results);
+
+        Ok(query_results)
+    }
+
+    /// 一个查询（原始查询或者扩展查询）的全文和向量召回
+    /// explain_tokens 为 true 时再按分词搜索一次，返回每个分词的分数
+    async fn recall_text_query(
+        &self,
+        variant: TextSearchVariant,
+        mode: ContentQueryMode,
+        with_highlight: bool,
+        explain_tokens: bool,
+        filter: &ContentQueryFilter,
+        vector_candidates: usize,
+    ) -> anyhow::Result<(
+        Vec<FullTextSearchResult>,
+        Vec<VectorSearchResult>,
+        HashMap<ID, Vec<(String, f32)>>,
+    )> {
+        tracing::debug!("search: {}, tokens: {:?}", variant.data, variant.tokens.0);
+
+        let mut token_scores = HashMap::new();
+        let full_text_results = match mode {
+            ContentQueryMode::Vector => vec![],
+            _ => {
+                if explain_tokens {
+                    token_scores = self
+                        .full_text_search(
+                            variant.tokens.0.clone(),
+                            false,
+                            filter,
+                            Some(FULL_TEXT_QUERY_LIMIT),
+                        )
+                        .await?
+                        .into_iter()
+                        .map(|r| (r.id, r.score))
+                        .collect();
+                }
+                self.full_text_search(
+                    variant.tokens.0,
+                    with_highlight,
+                    filter,
+                    Some(FULL_TEXT_QUERY_LIMIT),
                 )
-                .await?;
-                tracing::debug!("{} results after lookup", query_results.len());
+                .await?
+            }
+        };
+        tracing::debug!("{} found in full text search", full_text_results.len());
 
-                merge_frames(&mut query_results).await?;
+        let vector_results = match mode {
+            ContentQueryMode::FullText => vec![],
+            _ => {
+                self.vector_search(
+                    variant.text_embedding,
+                    variant.vision_embedding,
+                    filter,
+                    vector_candidates,
+                )
+                .await?
+            }
+        };
+        tracing::debug!("{} found in vector search", vector_results.len());
+
+        Ok((full_text_results, vector_results, token_scores))
+    }
 
-                // 最后需要排序一下因为 query_results 是按照 id 的顺序返回的
-                query_results.sort_by(|a, b| {
-                    b.score
-                        .partial_cmp(&a.score)
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                });
+    /// 召回的 text 记录里上一层对象是 audio_frame 的，也就是音频和视频的转录，rank 时乘转录的权重
+    async fn transcript_text_ids(
+        &self,
+        queries: &[(Vec<FullTextSearchResult>, Vec<VectorSearchResult>)],
+    ) -> anyhow::Result<HashSet<String>> {
+        let things = queries
+            .iter()
+            .flat_map(|(full_text_results, vector_results)| {
+                full_text_results
+                    .iter()
+                    .map(|r| &r.id)
+                    .chain(vector_results.iter().map(|r| &r.id))
+            })
+            .filter(|id| id.tb() == &TB::Text)
+            .map(surrealdb::sql::Thing::from)
+            .collect::<Vec<_>>();
+        if things.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let mut resp = self
+            .client
+            .query(format!(
+                "SELECT VALUE id FROM text WHERE id IN $ids AND record::tb(<-contains[0].in) = '{}';",
+                AudioFrameModel::table()
+            ))
+            .bind(("ids", things))
+            .await?;
+        check_db_error_from_resp!(resp).map_err(|errors_map| {
+            anyhow::anyhow!("transcript text lookup error: {:?}", errors_map)
+        })?;
+        Ok(resp
+            .take::<Vec<surrealdb::sql::Thing>>(0)?
+            .into_iter()
+            .map(|thing| ID::from(thing).id_with_table())
+            .collect())
+    }
 
-                Ok(query_results)
+    /// rank 以后回溯素材并合并相邻片段，返回的结果还没有排序
+    /// queries 是每个查询各自的全文和向量召回
+    async fn rank_and_lookup(
+        &self,
+        queries: Vec<(Vec<FullTextSearchResult>, Vec<VectorSearchResult>)>,
+        rank_options: &ContentQueryRankOptions,
+        full_text_token_scores: Option<HashMap<ID, Vec<(String, f32)>>>,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        // 多个查询都命中的记录，使用原始查询的高亮
+        let mut full_text_highlight_map = HashMap::new();
+        for (full_text_results, _) in queries.iter() {
+            for r in full_text_results {
+                let highlight = match r.score.get(0) {
+                    Some((highlight, _score)) => highlight.clone(),
+                    None => "".to_string(),
+                };
+                full_text_highlight_map
+                    .entry(r.id.clone())
+                    .or_insert(highlight);
             }
-            _ => unimplemented!(),
         }
+        let transcript_ids = if rank_options.weights.transcript != 1.0 {
+            self.transcript_text_ids(&queries).await?
+        } else {
+            HashSet::new()
+        };
+        // 这里不截断，所有候选都参与后面的合并，这样不同页之间的排序才是一致的
+        let mut rank_result =
+            Rank::rank(queries, &rank_options.weights, &transcript_ids, false, None)?;
+        if let Some(token_scores) = &full_text_token_scores {
+            for r in rank_result.iter_mut() {
+                if let Some(explain) = r.explain.as_mut() {
+                    explain.full_text_tokens = token_scores.get(&r.id).cloned().unwrap_or_default();
+                }
+            }
+        }
+        tracing::debug!("{} results after rank", rank_result.len());
+
+        let rank_results_map = rank_result
+            .into_iter()
+            .map(|r| (r.id.clone(), r))
+            .collect::<HashMap<_, _>>();
+        let mut query_results = lookup_assets_by_image_text_ids(
+            &self.client,
+            &rank_results_map,
+            &full_text_highlight_map,
+        )
+        .await?;
+        tracing::debug!("{} results after lookup", query_results.len());
+
+        if !rank_options.explain {
+            query_results.iter_mut().for_each(|r| r.explain = None);
+        }
+
+        merge_frames(&mut query_results).await?;
+
+        Ok(query_results)
     }
 }
 
@@ -381,6 +667,8 @@ struct PayloadLookupResult {
 enum SegmentLookup {
     AudioFrame(FrameLookup),
     ImageFrame(FrameLookup),
+    OcrFrame(FrameLookup),
+    ChapterFrame(FrameLookup),
     Page(PageLookup),
 }
 #[derive(Debug, Deserialize)]
@@ -1,21 +1,62 @@
+use super::filter::FILTER_FILE_IDENTIFIERS_VAR;
 use crate::{
     check_db_error_from_resp,
     db::{
         model::{image::ImageModel, text::TextModel},
+        sql::VECTOR_INDEXES,
         DB,
     },
-    query::model::{VectorSearchResult, VectorSearchType},
+    query::{
+        model::{VectorSearchResult, VectorSearchType},
+        ContentQueryFilter,
+    },
 };
 use futures::future::join_all;
 use serde::Deserialize;
 use std::convert::Into;
 
-const VECTOR_QUERY_LIMIT: usize = 100;
+/// 每个向量字段的最近邻数量（K）按分页需要的结果数取整到这个数的倍数，同一段里翻页的 K 一样
+const VECTOR_CANDIDATES_STEP: usize = 100;
+/// K 的上限，再往后翻页只剩全文搜索的结果
+const MAX_VECTOR_CANDIDATES: usize = 1000;
+/// EF 的下限，K 比较小的时候也保持原来的准确度
+const MIN_VECTOR_EF: usize = 100;
+// 以图搜图时只有图像向量一路召回，需要更多的候选
+const IMAGE_QUERY_MIN_CANDIDATES: usize = 100;
+/// 有过滤条件时取 K 的多少倍的最近邻，过滤以后再截断到 K
+const FILTERED_VECTOR_NEIGHBORS_RATIO: usize = 10;
+
+/// 分页到 offset + max_count 需要的向量搜索候选数量
+pub(crate) fn vector_candidates(offset: usize, max_count: usize) -> usize {
+    (offset
+        .saturating_add(max_count)
+        .div_ceil(VECTOR_CANDIDATES_STEP)
+        * VECTOR_CANDIDATES_STEP)
+        .clamp(VECTOR_CANDIDATES_STEP, MAX_VECTOR_CANDIDATES)
+}
+
+/// <|K,EF|> K 是最近邻的数量，EF 是候选列表的大小，越大越准确但是越慢，EF 不能小于 K
+fn vector_range(candidates: usize) -> String {
+    format!("<|{},{}|>", candidates, candidates.max(MIN_VECTOR_EF))
+}
+
+/// 找到视频里最接近 $timestamp 的 image_frame，返回它的图像向量
+const VIDEO_FRAME_EMBEDDING_SQL: &str = r#"
+LET $frames = array::flatten(
+    (SELECT VALUE <-with[0].in->contains->image_frame FROM payload WHERE file_identifier = $file_identifier)
+);
+SELECT
+    math::abs(start_timestamp - $timestamp) AS distance,
+    (->contains->image.embedding)[0] AS embedding
+FROM $frames
+ORDER BY distance
+LIMIT 1;
+"#;
 
-// TODO: vision 和 text 向量现在采用了不同的命中范围，这个要继续调整
-// <|K,EF|> K 是最近邻的数量，EF 是候选列表的大小，越大越准确但是越慢
-const VISION_VECTOR_RANGE: &str = "<|5,100|>";
-const TEXT_VECTOR_RANGE: &str = "<|5,100|>";
+#[derive(Debug, Deserialize)]
+struct FrameEmbeddingEntity {
+    embedding: Option<Vec<f32>>,
+}
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct VectorSearchEntity {
@@ -23,26 +64,86 @@ pub(crate) struct VectorSearchEntity {
     pub distance: f32,
 }
 
-fn vector_query_statement(table: &str, vector_column: &str, range: &str) -> String {
-    format!(
+/// 直接计算距离时使用的函数，和向量索引的距离类型一致
+pub(super) fn vector_distance(table: &str, column: &str) -> String {
+    match VECTOR_INDEXES
+        .iter()
+        .find(|(t, c, _, _)| *t == table && *c == column)
+    {
+        Some((_, _, "COSINE", _)) => {
+            format!("1 - vector::similarity::cosine({column}, $vector_value)")
+        }
+        _ => format!("vector::distance::euclidean({column}, $vector_value)"),
+    }
+}
+
+/// 返回查询语句和 SELECT 语句的位置
+/// - 限定了素材的时候不走 HNSW 索引，直接计算这些素材里的记录的距离
+///   索引是在整个库里取最近邻再过滤，素材少的时候大部分结果都会被过滤掉
+/// - 其他过滤条件也是在最近邻里再筛选，有过滤条件时多取一些最近邻
+fn vector_query_statement(
+    table: &str,
+    vector_column: &str,
+    candidates: usize,
+    filter: &ContentQueryFilter,
+) -> (String, usize) {
+    let where_clause = filter.to_where_clause(table);
+    if filter.file_identifiers.is_some() {
+        let statement = format!(
+            r#"
+LET $assets = (SELECT VALUE <-with[0].in FROM payload WHERE file_identifier IN ${file_identifiers});
+LET $records = array::union(
+    array::flatten((SELECT VALUE ->contains->?->contains->{table} FROM $assets)),
+    (SELECT VALUE id FROM $assets WHERE record::tb(id) = '{table}')
+);
+SELECT
+    id,
+    {distance} AS distance
+FROM $records
+WHERE {vector_column} != NONE{filter}
+ORDER BY distance
+LIMIT {limit};
+"#,
+            file_identifiers = FILTER_FILE_IDENTIFIERS_VAR,
+            table = table,
+            distance = vector_distance(table, vector_column),
+            vector_column = vector_column,
+            filter = where_clause,
+            limit = candidates
+        );
+        // 前两条语句是 LET
+        return (statement, 2);
+    }
+
+    let neighbors = if where_clause.is_empty() {
+        candidates
+    } else {
+        candidates * FILTERED_VECTOR_NEIGHBORS_RATIO
+    };
+    let statement = format!(
         r#"
 SELECT
     id,
     vector::distance::knn() AS distance
 FROM {table}
-WHERE {vector_column} {range} $vector_value
+WHERE {vector_column} {range} $vector_value{filter}
 ORDER BY distance
 LIMIT {limit};
 "#,
         table = table,
         vector_column = vector_column,
-        range = range,
-        limit = VECTOR_QUERY_LIMIT
-    )
+        range = vector_range(neighbors),
+        filter = where_clause,
+        limit = candidates
+    );
+    (statement, 0)
 }
 
 /// 组装 (table, column) 的元组数组，给后面使用，只搜索 Image 和 Text 基础对象，然后再回溯关联的对象
-fn vector_search_columns() -> Vec<(&'static str, &'static str, &'static VectorSearchType)> {
+/// 不在 filter 的 sources 里的字段会被跳过
+pub(super) fn vector_search_columns(
+    filter: &ContentQueryFilter,
+) -> Vec<(&'static str, &'static str, &'static VectorSearchType)> {
     let params = vec![
         (
             ImageModel::table(),
@@ -68,6 +169,7 @@ fn vector_search_columns() -> Vec<(&'static str, &'static str, &'static VectorSe
             .collect::<Vec<(&str, &str, &VectorSearchType)>>()
     })
     .flatten()
+    .filter(|(table, _, vector_type)| filter.allows_column(table, Some(*vector_type)))
     .collect::<Vec<(&str, &str, &VectorSearchType)>>();
     params
 }
@@ -76,34 +178,109 @@ impl DB {
     /// 🔍 vector search
     ///
     /// if not vision_vector, please input text_vector
+    /// candidates 是每个字段的最近邻数量，见 `vector_candidates`
     pub async fn vector_search(
         &self,
         text_embedding: Vec<f32>,
         vision_embedding: Vec<f32>,
+        filter: &ContentQueryFilter,
+        candidates: usize,
     ) -> anyhow::Result<Vec<VectorSearchResult>> {
         if text_embedding.is_empty() || vision_embedding.is_empty() {
             anyhow::bail!("data is empty in vector search");
         }
 
-        // 组装 (table, column, vector_value) 的元组数组，给后面使用
-        let params = vector_search_columns();
-        let futures = params.into_iter().map(|(table, column, vector_type)| {
-            let (vector_value, range) = match vector_type {
-                VectorSearchType::Text => (text_embedding.clone(), TEXT_VECTOR_RANGE),
-                VectorSearchType::Vision => (vision_embedding.clone(), VISION_VECTOR_RANGE),
-            };
-            let query_statement = vector_query_statement(table, column, range);
+        // 组装 (table, column, vector_type, vector_value, candidates) 的元组数组，给后面使用
+        let params = vector_search_columns(filter)
+            .into_iter()
+            .map(|(table, column, vector_type)| {
+                let vector_value = match vector_type {
+                    VectorSearchType::Text => text_embedding.clone(),
+                    VectorSearchType::Vision => vision_embedding.clone(),
+                };
+                (table, column, vector_type, vector_value, candidates)
+            })
+            .collect::<Vec<_>>();
+
+        self._vector_search(params, filter).await
+    }
+
+    /// 🔍 以图搜图，只搜索图像向量
+    pub async fn image_vector_search(
+        &self,
+        vision_embedding: Vec<f32>,
+        filter: &ContentQueryFilter,
+        candidates: usize,
+    ) -> anyhow::Result<Vec<VectorSearchResult>> {
+        if vision_embedding.is_empty() {
+            anyhow::bail!("data is empty in image vector search");
+        }
+
+        let params = vector_search_columns(filter)
+            .into_iter()
+            .filter(|(_, _, vector_type)| **vector_type == VectorSearchType::Vision)
+            .map(|(table, column, vector_type)| {
+                (
+                    table,
+                    column,
+                    vector_type,
+                    vision_embedding.clone(),
+                    candidates.max(IMAGE_QUERY_MIN_CANDIDATES),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self._vector_search(params, filter).await
+    }
+
+    /// 获取视频中最接近 timestamp（毫秒）的 image_frame 的图像向量，视频还没有索引时返回 None
+    pub async fn video_frame_embedding(
+        &self,
+        file_identifier: &str,
+        timestamp: i64,
+    ) -> anyhow::Result<Option<Vec<f32>>> {
+        let mut res = self
+            .client
+            .query(VIDEO_FRAME_EMBEDDING_SQL)
+            .bind(("file_identifier", file_identifier.to_string()))
+            .bind(("timestamp", timestamp))
+            .await?;
+        check_db_error_from_resp!(res).map_err(|errors_map| {
+            tracing::error!("video_frame_embedding errors: {errors_map:?}");
+            anyhow::anyhow!("video_frame_embedding errors: {errors_map:?}")
+        })?;
+        // 第一条语句是 LET
+        let res: Vec<FrameEmbeddingEntity> = res.take(1)?;
+        Ok(res
+            .into_iter()
+            .next()
+            .and_then(|entity| entity.embedding)
+            .filter(|embedding| !embedding.is_empty()))
+    }
+
+    async fn _vector_search(
+        &self,
+        params: Vec<(&str, &str, &VectorSearchType, Vec<f32>, usize)>,
+        filter: &ContentQueryFilter,
+    ) -> anyhow::Result<Vec<VectorSearchResult>> {
+        let futures = params.into_iter().map(|param| {
+            let (table, column, vector_type, vector_value, candidates) = param;
+            let (query_statement, select_index) =
+                vector_query_statement(table, column, candidates, filter);
             async move {
-                let mut res = self
+                let mut query = self
                     .client
                     .query(query_statement)
-                    .bind(("vector_value", vector_value))
-                    .await?;
+                    .bind(("vector_value", vector_value));
+                for (name, value) in filter.bind_values() {
+                    query = query.bind((name, value));
+                }
+                let mut res = query.await?;
                 check_db_error_from_resp!(res).map_err(|errors_map| {
                     tracing::error!("vector_search errors: {errors_map:?}");
                     anyhow::anyhow!("vector_search errors: {errors_map:?}")
                 })?;
-                let res: Vec<VectorSearchEntity> = res.take(0)?;
+                let res: Vec<VectorSearchEntity> = res.take(select_index)?;
                 Ok::<_, anyhow::Error>(
                     res.iter()
                         .map(|d| VectorSearchResult {
@@ -127,3 +304,112 @@ impl DB {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{vector_candidates, vector_range};
+    use crate::{
+        db::shared::test::{
+            fake_file_identifier, fake_image_model, gen_image_vector, gen_text_vector, setup,
+        },
+        query::{
+            model::{SearchModel, TextSearchModel, TextToken},
+            ContentQueryFilter, ContentQueryMode,
+        },
+    };
+    use test_log::test;
+
+    #[test]
+    fn test_vector_candidates() {
+        assert_eq!(vector_candidates(0, 20), 100);
+        assert_eq!(vector_candidates(80, 20), 100);
+        assert_eq!(vector_candidates(100, 20), 200);
+        assert_eq!(vector_candidates(usize::MAX, 20), 1000);
+        assert_eq!(vector_range(50), "<|50,100|>");
+        assert_eq!(vector_range(300), "<|300,300|>");
+    }
+
+    #[test(tokio::test)]
+    async fn test_vector_search_second_page() {
+        let db = setup(None).await;
+        let file_identifiers = (0..60).map(|_| fake_file_identifier()).collect::<Vec<_>>();
+        for file_identifier in file_identifiers.iter() {
+            db.insert_image(file_identifier.clone(), (fake_image_model(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        let search_model = SearchModel::Text(TextSearchModel {
+            data: "sunrise".to_string(),
+            tokens: TextToken(vec!["sunrise".to_string()]),
+            text_embedding: gen_text_vector(),
+            vision_embedding: gen_image_vector(),
+            mode: ContentQueryMode::Vector,
+            variants: vec![],
+        });
+        // 只有向量搜索的时候，第二页也要有结果
+        let page = db
+            .search(search_model, false, 20, 20, &ContentQueryFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(page.results.len(), 20);
+        assert!(page.total >= 60);
+
+        for file_identifier in file_identifiers.iter() {
+            db.delete_by_file_identifier(file_identifier).await.unwrap();
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn test_vector_search_file_identifiers() {
+        let db = setup(None).await;
+        let file_identifiers = (0..60).map(|_| fake_file_identifier()).collect::<Vec<_>>();
+        for file_identifier in file_identifiers.iter() {
+            db.insert_image(file_identifier.clone(), (fake_image_model(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        // 限定的素材不在整个库的最近邻里时也能搜到
+        let filter = ContentQueryFilter {
+            file_identifiers: Some(file_identifiers[..3].to_vec()),
+            ..Default::default()
+        };
+        let results = db
+            .vector_search(gen_text_vector(), gen_image_vector(), &filter, 100)
+            .await
+            .unwrap();
+        // 每张图片有描述的文本向量和图像向量两个字段
+        assert_eq!(results.len(), 6);
+        let page = db
+            .search(
+                SearchModel::Text(TextSearchModel {
+                    data: "sunrise".to_string(),
+                    tokens: TextToken(vec!["sunrise".to_string()]),
+                    text_embedding: gen_text_vector(),
+                    vision_embedding: gen_image_vector(),
+                    mode: ContentQueryMode::Vector,
+                    variants: vec![],
+                }),
+                false,
+                0,
+                20,
+                &filter,
+            )
+            .await
+            .unwrap();
+        let mut found = page
+            .results
+            .iter()
+            .map(|v| v.file_identifier.clone())
+            .collect::<Vec<_>>();
+        found.sort();
+        let mut expected = file_identifiers[..3].to_vec();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        for file_identifier in file_identifiers.iter() {
+            db.delete_by_file_identifier(file_identifier).await.unwrap();
+        }
+    }
+}
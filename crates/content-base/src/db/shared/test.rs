@@ -1,13 +1,15 @@
 use crate::db::model::{
     audio::{AudioFrameModel, AudioModel},
+    chapter::ChapterFrameModel,
     document::DocumentModel,
     image::ImageModel,
+    ocr::OcrFrameModel,
     page::PageModel,
     text::TextModel,
     video::{ImageFrameModel, VideoModel},
     web_page::WebPageModel,
 };
-use crate::db::DB;
+use crate::db::{EmbeddingSchema, DB};
 use fake::faker::internet::en::Username;
 use fake::faker::lorem::en::Sentence;
 use fake::Fake;
@@ -28,6 +30,15 @@ pub async fn setup(path: Option<&Path>) -> DB {
     #[cfg(feature = "remote-db")]
     let db = DB::new().await.unwrap();
 
+    db.sync_vector_indexes(&EmbeddingSchema {
+        text_model: "test-text-embedding".to_string(),
+        text_dim: 1024,
+        multi_modal_model: "test-multi-modal-embedding".to_string(),
+        multi_modal_dim: 512,
+    })
+    .await
+    .unwrap();
+
     db
 }
 
@@ -63,6 +74,7 @@ pub fn fake_image_model() -> ImageModel {
         caption: Sentence(5..10).fake(),
         embedding: gen_image_vector(),
         caption_embedding: gen_text_vector(),
+        objects: vec![],
     }
 }
 
@@ -77,6 +89,28 @@ pub fn fake_audio_frame_model() -> (AudioFrameModel, Vec<TextModel>) {
     )
 }
 
+pub fn fake_chapter_frame_model() -> (ChapterFrameModel, Vec<TextModel>) {
+    (
+        ChapterFrameModel {
+            id: None,
+            start_timestamp: (1..10).fake::<i64>(),
+            end_timestamp: (10..20).fake::<i64>(),
+        },
+        vec![fake_text_model()],
+    )
+}
+
+pub fn fake_ocr_frame_model() -> (OcrFrameModel, Vec<TextModel>) {
+    (
+        OcrFrameModel {
+            id: None,
+            start_timestamp: (1..10).fake::<i64>(),
+            end_timestamp: (10..20).fake::<i64>(),
+        },
+        vec![fake_text_model()],
+    )
+}
+
 pub fn fake_audio_model() -> (AudioModel, Vec<(AudioFrameModel, Vec<TextModel>)>) {
     (
         AudioModel { id: None },
@@ -121,11 +155,32 @@ pub fn fake_video_model() -> (
     VideoModel,
     Vec<(ImageFrameModel, Vec<ImageModel>)>,
     Vec<(AudioFrameModel, Vec<TextModel>)>,
+    Vec<(OcrFrameModel, Vec<TextModel>)>,
+    Vec<(ChapterFrameModel, Vec<TextModel>)>,
 ) {
     (
         VideoModel { id: None },
         (1..10).map(|_| fake_image_frame_model()).collect(),
         (1..10).map(|_| fake_audio_frame_model()).collect(),
+        vec![],
+        vec![],
+    )
+}
+
+pub fn fake_video_model_with_ocr() -> (
+    VideoModel,
+    Vec<(ImageFrameModel, Vec<ImageModel>)>,
+    Vec<(AudioFrameModel, Vec<TextModel>)>,
+    Vec<(OcrFrameModel, Vec<TextModel>)>,
+    Vec<(ChapterFrameModel, Vec<TextModel>)>,
+) {
+    let (video, image_frames, audio_frames, _, chapter_frames) = fake_video_model();
+    (
+        video,
+        image_frames,
+        audio_frames,
+        (1..5).map(|_| fake_ocr_frame_model()).collect(),
+        chapter_frames,
     )
 }
 
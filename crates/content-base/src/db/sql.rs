@@ -10,16 +10,19 @@ DEFINE FIELD IF NOT EXISTS url ON TABLE payload TYPE option<string>;
 DEFINE TABLE IF NOT EXISTS text;
 -- 定义 "text" 表的字段
 DEFINE FIELD IF NOT EXISTS content ON TABLE text TYPE string;
-DEFINE FIELD IF NOT EXISTS embedding ON TABLE text TYPE array;
+-- 切换 embedding 模型以后向量会被清空，所以向量字段都是 option
+DEFINE FIELD IF NOT EXISTS embedding ON TABLE text TYPE option<array>;
 
 
 -- 创建 "image" 表
 DEFINE TABLE IF NOT EXISTS image;
 -- 定义 "image" 表的字段
 -- image vector
-DEFINE FIELD IF NOT EXISTS embedding ON TABLE image TYPE array;
+DEFINE FIELD IF NOT EXISTS embedding ON TABLE image TYPE option<array>;
 DEFINE FIELD IF NOT EXISTS caption ON TABLE image TYPE string;
-DEFINE FIELD IF NOT EXISTS caption_embedding ON TABLE image TYPE array;
+DEFINE FIELD IF NOT EXISTS caption_embedding ON TABLE image TYPE option<array>;
+-- 物体检测的类别，旧数据没有这个字段
+DEFINE FIELD IF NOT EXISTS objects ON TABLE image TYPE option<array<string>>;
 
 
 -- 创建 "image frame" 表
@@ -36,6 +39,20 @@ DEFINE FIELD IF NOT EXISTS start_timestamp ON audio_frame TYPE number;
 DEFINE FIELD IF NOT EXISTS end_timestamp ON audio_frame TYPE number;
 
 
+-- 创建 "ocr frame" 表
+DEFINE TABLE IF NOT EXISTS ocr_frame;
+-- 定义 "ocr frame" 表的字段
+DEFINE FIELD IF NOT EXISTS start_timestamp ON ocr_frame TYPE number;
+DEFINE FIELD IF NOT EXISTS end_timestamp ON ocr_frame TYPE number;
+
+
+-- 创建 "chapter frame" 表
+DEFINE TABLE IF NOT EXISTS chapter_frame;
+-- 定义 "chapter frame" 表的字段
+DEFINE FIELD IF NOT EXISTS start_timestamp ON chapter_frame TYPE number;
+DEFINE FIELD IF NOT EXISTS end_timestamp ON chapter_frame TYPE number;
+
+
 -- 创建 "audio" 表
 DEFINE TABLE IF NOT EXISTS audio;
 -- 定义 "audio" 表的字段
@@ -67,10 +84,31 @@ DEFINE TABLE IF NOT EXISTS document;
 -- 无，只有 relate 和 with 关系
 
 
--- 定义向量索引
-DEFINE INDEX IF NOT EXISTS idx_text_embedding_hnsw_d1024 ON text FIELDS embedding HNSW DIMENSION 1024 DIST EUCLIDEAN;
-DEFINE INDEX IF NOT EXISTS idx_image_embedding_hnsw_d512 ON image FIELDS embedding HNSW DIMENSION 512 DIST COSINE;
-DEFINE INDEX IF NOT EXISTS idx_image_caption_embedding_hnsw_d1024 ON image FIELDS caption_embedding HNSW DIMENSION 1024 DIST EUCLIDEAN;
+-- 创建 "meta" 表
+DEFINE TABLE IF NOT EXISTS meta;
+-- 保存 library 级别的信息，比如 meta:embedding_schema 记录了生成向量索引时的 embedding 模型
+
+
+-- 创建 "term" 表，搜索补全用的词表，见 db::op::term
+DEFINE TABLE IF NOT EXISTS term;
+DEFINE FIELD IF NOT EXISTS term ON TABLE term TYPE string;
+-- 包含这个词的素材数量
+DEFINE FIELD IF NOT EXISTS asset_count ON TABLE term TYPE int;
+DEFINE INDEX IF NOT EXISTS idx_term_term ON TABLE term FIELDS term UNIQUE;
+
+
+-- 创建 "asset_term" 表，记录每个素材包含哪些词，id 是 file_identifier
+DEFINE TABLE IF NOT EXISTS asset_term;
+DEFINE FIELD IF NOT EXISTS terms ON TABLE asset_term TYPE array<string>;
+
+
+-- 创建 "asset_tag" 表，LLM 生成的素材标签，id 是 file_identifier，见 db::op::tag
+DEFINE TABLE IF NOT EXISTS asset_tag;
+DEFINE FIELD IF NOT EXISTS file_identifier ON TABLE asset_tag TYPE string;
+DEFINE FIELD IF NOT EXISTS tags ON TABLE asset_tag TYPE array<string>;
+
+
+-- 向量索引的维度取决于 embedding 模型，不在这里定义，见 VECTOR_INDEXES 和 DB::sync_vector_indexes
 
 
 -- 定义分词器
@@ -85,3 +123,30 @@ DEFINE ANALYZER IF NOT EXISTS mixed_analyzer TOKENIZERS blank, class, punct FILT
 DEFINE INDEX IF NOT EXISTS mixed_index_text_content ON text FIELDS content SEARCH ANALYZER mixed_analyzer BM25 HIGHLIGHTS;
 DEFINE INDEX IF NOT EXISTS mixed_index_image_caption ON image FIELDS caption SEARCH ANALYZER mixed_analyzer BM25 HIGHLIGHTS;
 "#;
+
+/// 向量索引使用的 embedding 模型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum VectorIndexEmbedding {
+    /// text embedding 模型
+    Text,
+    /// multi modal embedding 模型
+    MultiModal,
+}
+
+/// 所有的向量索引 (table, column, distance, embedding)
+/// 索引名字会带上维度，比如 idx_text_embedding_hnsw_d1024，维度变化的时候需要删掉旧的索引
+pub(crate) const VECTOR_INDEXES: [(&str, &str, &str, VectorIndexEmbedding); 3] = [
+    ("text", "embedding", "EUCLIDEAN", VectorIndexEmbedding::Text),
+    (
+        "image",
+        "embedding",
+        "COSINE",
+        VectorIndexEmbedding::MultiModal,
+    ),
+    (
+        "image",
+        "caption_embedding",
+        "EUCLIDEAN",
+        VectorIndexEmbedding::Text,
+    ),
+];
@@ -35,6 +35,8 @@ impl ContentBase {
     pub async fn delete(&self, payload: DeletePayload) -> anyhow::Result<()> {
         self.delete_search_indexes(&payload).await?;
         self.delete_artifacts(&payload).await?;
+        self.near_duplicate_cache
+            .invalidate(&payload.file_identifier);
         Ok(())
     }
 
@@ -48,6 +50,7 @@ impl ContentBase {
             .try_write()?
             .delete_by_file_identifier(&payload.file_identifier)
             .await?;
+        self.search_result_cache.clear();
         info!(
             "Deleted file_identifier: {} in surrealdb",
             payload.file_identifier
@@ -68,7 +71,7 @@ impl ContentBase {
             file_full_path_on_disk: PathBuf::new(), // this filed is not used in delete
         };
 
-        let tasks = Self::get_content_processing_tasks(task_record.metadata());
+        let tasks = Self::get_content_processing_tasks(&self.ctx, task_record.metadata());
         for (task, _) in tasks {
             delete_task(&file_info, &task, &self.ctx, payload.keep_completed_tasks).await;
         }
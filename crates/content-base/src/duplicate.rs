@@ -0,0 +1,479 @@
+use crate::ContentBase;
+use content_base_context::ContentBaseCtx;
+use content_base_task::{
+    image::{
+        embedding::ImageEmbeddingTask,
+        perceptual_hash::{hash_similarity, ImagePerceptualHashTask},
+    },
+    video::{
+        frame::VideoFrameTask, frame_embedding::VideoFrameEmbeddingTask,
+        perceptual_hash::VideoPerceptualHashTask,
+    },
+    TaskRecord,
+};
+use content_metadata::{ContentMetadata, ContentType};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+/// 默认的相似度阈值，embedding 和 perceptual hash 的综合相似度超过这个值才算重复
+pub const DEFAULT_NEAR_DUPLICATE_THRESHOLD: f32 = 0.9;
+/// 视频最多取多少帧计算平均 embedding 和比较 hash，均匀采样
+const MAX_VIDEO_SAMPLE_FRAMES: usize = 32;
+
+pub struct NearDuplicatePayload {
+    file_identifiers: Vec<String>,
+    threshold: f32,
+}
+
+impl NearDuplicatePayload {
+    pub fn new(file_identifiers: &[String]) -> Self {
+        Self {
+            file_identifiers: file_identifiers.to_vec(),
+            threshold: DEFAULT_NEAR_DUPLICATE_THRESHOLD,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// 素材和阈值都一样的时候结果也一样，素材的顺序不影响结果
+    fn cache_key(&self) -> u64 {
+        let mut file_identifiers = self.file_identifiers.iter().collect::<Vec<_>>();
+        file_identifiers.sort();
+        let mut hasher = DefaultHasher::new();
+        file_identifiers.hash(&mut hasher);
+        self.threshold.to_bits().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NearDuplicatePair {
+    pub file_identifiers: (String, String),
+    pub similarity: f32,
+    pub embedding_similarity: f32,
+    /// 有一个素材没有 perceptual hash 时为 None，这时只用 embedding 的相似度
+    pub hash_similarity: Option<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NearDuplicateCluster {
+    pub content_type: ContentType,
+    pub file_identifiers: Vec<String>,
+    /// 超过阈值的素材对，一个 cluster 里不一定每两个素材都超过阈值
+    pub pairs: Vec<NearDuplicatePair>,
+    /// pairs 的平均相似度
+    pub similarity: f32,
+}
+
+/// 用来比较的素材特征
+/// - embedding: 图片的 CLIP embedding，或者视频采样帧 embedding 的平均值，已经归一化
+/// - hashes: 图片的 dHash，或者视频采样帧的 dHash
+#[derive(Clone, Debug)]
+struct AssetSignature {
+    file_identifier: String,
+    content_type: ContentType,
+    embedding: Vec<f32>,
+    hashes: Vec<u64>,
+}
+
+/// 近似重复检测的缓存
+/// - signatures: 每个素材的特征，读取 embedding 和 hash 需要读很多文件，素材的任务重新执行或者删除以后失效
+/// - clusters: 最近一次的检测结果，素材、阈值和特征都没变的时候直接返回，不用再两两比较
+#[derive(Clone, Default)]
+pub(crate) struct NearDuplicateCache {
+    signatures: Arc<Mutex<HashMap<String, AssetSignature>>>,
+    clusters: Arc<Mutex<Option<(u64, Vec<NearDuplicateCluster>)>>>,
+}
+
+impl NearDuplicateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn signature(&self, file_identifier: &str) -> Option<AssetSignature> {
+        self.signatures.lock().ok()?.get(file_identifier).cloned()
+    }
+
+    fn put_signature(&self, signature: AssetSignature) {
+        if let Ok(mut signatures) = self.signatures.lock() {
+            signatures.insert(signature.file_identifier.clone(), signature);
+        }
+    }
+
+    fn clusters(&self, key: u64) -> Option<Vec<NearDuplicateCluster>> {
+        match self.clusters.lock().ok()?.as_ref() {
+            Some((cached_key, clusters)) if *cached_key == key => Some(clusters.clone()),
+            _ => None,
+        }
+    }
+
+    fn put_clusters(&self, key: u64, clusters: Vec<NearDuplicateCluster>) {
+        if let Ok(mut cached) = self.clusters.lock() {
+            *cached = Some((key, clusters));
+        }
+    }
+
+    /// 素材的任务执行完或者素材被删除以后调用
+    pub fn invalidate(&self, file_identifier: &str) {
+        if let Ok(mut signatures) = self.signatures.lock() {
+            signatures.remove(file_identifier);
+        }
+        if let Ok(mut cached) = self.clusters.lock() {
+            *cached = None;
+        }
+    }
+
+    /// 切换模型以后所有素材的 embedding 都会变
+    pub fn clear(&self) {
+        if let Ok(mut signatures) = self.signatures.lock() {
+            signatures.clear();
+        }
+        if let Ok(mut cached) = self.clusters.lock() {
+            *cached = None;
+        }
+    }
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// 均匀采样，保留第一个
+fn sample_evenly<T: Clone>(items: &[T], max: usize) -> Vec<T> {
+    if items.len() <= max {
+        return items.to_vec();
+    }
+    (0..max)
+        .map(|i| items[i * items.len() / max].clone())
+        .collect()
+}
+
+/// 两组 hash 的相似度，对较短的一组里的每一个 hash 找另一组里最相似的，取平均
+/// 视频的帧数不一样（比如剪掉了片头）时也能比较
+fn hashes_similarity(a: &[u64], b: &[u64]) -> Option<f32> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let total = short
+        .iter()
+        .map(|x| {
+            long.iter()
+                .map(|y| hash_similarity(*x, *y))
+                .fold(0.0f32, f32::max)
+        })
+        .sum::<f32>();
+    Some(total / short.len() as f32)
+}
+
+fn compare_signatures(a: &AssetSignature, b: &AssetSignature) -> Option<NearDuplicatePair> {
+    if a.content_type != b.content_type || a.embedding.len() != b.embedding.len() {
+        return None;
+    }
+    let embedding_similarity = a
+        .embedding
+        .iter()
+        .zip(b.embedding.iter())
+        .map(|(x, y)| x * y)
+        .sum::<f32>();
+    let hash_similarity = hashes_similarity(&a.hashes, &b.hashes);
+    let similarity = match hash_similarity {
+        Some(hash_similarity) => (embedding_similarity + hash_similarity) / 2.0,
+        None => embedding_similarity,
+    };
+    Some(NearDuplicatePair {
+        file_identifiers: (a.file_identifier.clone(), b.file_identifier.clone()),
+        similarity,
+        embedding_similarity,
+        hash_similarity,
+    })
+}
+
+fn find_root(parents: &mut Vec<usize>, i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+    root
+}
+
+/// 两两比较，相似度超过阈值的素材用并查集合并成 cluster
+/// 复杂度是 O(n^2)，n 是同类型素材的数量，embedding 已经归一化，每次比较只是一次点积
+fn cluster_signatures(signatures: &[AssetSignature], threshold: f32) -> Vec<NearDuplicateCluster> {
+    let mut parents = (0..signatures.len()).collect::<Vec<_>>();
+    let mut pairs = vec![];
+    for i in 0..signatures.len() {
+        for j in (i + 1)..signatures.len() {
+            let Some(pair) = compare_signatures(&signatures[i], &signatures[j]) else {
+                continue;
+            };
+            if pair.similarity < threshold {
+                continue;
+            }
+            let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+            if root_i != root_j {
+                parents[root_j] = root_i;
+            }
+            pairs.push((i, pair));
+        }
+    }
+
+    let mut clusters: HashMap<usize, NearDuplicateCluster> = HashMap::new();
+    for (i, pair) in pairs {
+        let root = find_root(&mut parents, i);
+        let cluster = clusters
+            .entry(root)
+            .or_insert_with(|| NearDuplicateCluster {
+                content_type: signatures[i].content_type,
+                file_identifiers: vec![],
+                pairs: vec![],
+                similarity: 0.0,
+            });
+        for file_identifier in [&pair.file_identifiers.0, &pair.file_identifiers.1] {
+            if !cluster.file_identifiers.contains(file_identifier) {
+                cluster.file_identifiers.push(file_identifier.clone());
+            }
+        }
+        cluster.pairs.push(pair);
+    }
+
+    let mut clusters = clusters
+        .into_values()
+        .map(|mut cluster| {
+            cluster.similarity = cluster.pairs.iter().map(|v| v.similarity).sum::<f32>()
+                / cluster.pairs.len() as f32;
+            cluster
+        })
+        .collect::<Vec<_>>();
+    // 素材多的 cluster 在前，一样多的相似度高的在前
+    clusters.sort_by(|a, b| {
+        b.file_identifiers
+            .len()
+            .cmp(&a.file_identifiers.len())
+            .then(b.similarity.total_cmp(&a.similarity))
+    });
+    clusters
+}
+
+async fn image_signature(
+    ctx: &ContentBaseCtx,
+    file_identifier: &str,
+) -> anyhow::Result<AssetSignature> {
+    let embedding = ImageEmbeddingTask
+        .embedding_content(file_identifier, ctx)
+        .await?;
+    // hash 任务是后加的，之前导入的图片没有 hash，只用 embedding 比较
+    let hashes = ImagePerceptualHashTask
+        .hash_content(file_identifier, ctx)
+        .await
+        .map(|hash| vec![hash])
+        .unwrap_or_default();
+    Ok(AssetSignature {
+        file_identifier: file_identifier.to_string(),
+        content_type: ContentType::Image,
+        embedding: normalize(embedding),
+        hashes,
+    })
+}
+
+async fn video_signature(
+    ctx: &ContentBaseCtx,
+    file_identifier: &str,
+) -> anyhow::Result<AssetSignature> {
+    let frames = VideoFrameTask.frame_content(file_identifier, ctx).await?;
+    let mut embedding: Vec<f32> = vec![];
+    let mut count = 0;
+    for frame in sample_evenly(&frames, MAX_VIDEO_SAMPLE_FRAMES) {
+        let Ok(frame_embedding) = VideoFrameEmbeddingTask
+            .frame_embedding_content(file_identifier, ctx, frame.timestamp)
+            .await
+        else {
+            continue;
+        };
+        if embedding.is_empty() {
+            embedding = vec![0.0; frame_embedding.len()];
+        }
+        if frame_embedding.len() != embedding.len() {
+            continue;
+        }
+        // 每一帧先归一化，避免某一帧的 embedding 模长太大
+        for (v, x) in embedding.iter_mut().zip(normalize(frame_embedding)) {
+            *v += x;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        anyhow::bail!("no frame embedding found for {}", file_identifier);
+    }
+
+    let hashes = VideoPerceptualHashTask
+        .hash_content(file_identifier, ctx)
+        .await
+        .map(|hashes| {
+            sample_evenly(&hashes, MAX_VIDEO_SAMPLE_FRAMES)
+                .into_iter()
+                .map(|v| v.hash)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AssetSignature {
+        file_identifier: file_identifier.to_string(),
+        content_type: ContentType::Video,
+        embedding: normalize(embedding),
+        hashes,
+    })
+}
+
+impl ContentBase {
+    /// 查找相似或重复的图片和视频
+    /// 还没有 embedding 的素材（任务没完成或者不是图片和视频）会被跳过
+    /// 素材的特征和检测结果都会缓存，两两比较在 blocking 线程里执行
+    pub async fn near_duplicates(
+        &self,
+        payload: NearDuplicatePayload,
+    ) -> anyhow::Result<Vec<NearDuplicateCluster>> {
+        let cache_key = payload.cache_key();
+        if let Some(clusters) = self.near_duplicate_cache.clusters(cache_key) {
+            return Ok(clusters);
+        }
+
+        let mut signatures = vec![];
+        for file_identifier in payload.file_identifiers.iter() {
+            if let Some(signature) = self.near_duplicate_cache.signature(file_identifier) {
+                signatures.push(signature);
+                continue;
+            }
+            let task_record = TaskRecord::from_content_base(file_identifier, &self.ctx).await;
+            let signature = match task_record.metadata() {
+                ContentMetadata::Image(_) => image_signature(&self.ctx, file_identifier).await,
+                ContentMetadata::Video(_) => video_signature(&self.ctx, file_identifier).await,
+                _ => continue,
+            };
+            match signature {
+                Ok(signature) => {
+                    self.near_duplicate_cache.put_signature(signature.clone());
+                    signatures.push(signature);
+                }
+                Err(e) => {
+                    tracing::debug!(
+                        "skip {} in near duplicate detection: {}",
+                        file_identifier,
+                        e
+                    );
+                }
+            }
+        }
+
+        let threshold = payload.threshold;
+        let clusters =
+            tokio::task::spawn_blocking(move || cluster_signatures(&signatures, threshold)).await?;
+        self.near_duplicate_cache
+            .put_clusters(cache_key, clusters.clone());
+
+        Ok(clusters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        cluster_signatures, hashes_similarity, normalize, sample_evenly, AssetSignature,
+        NearDuplicatePayload,
+    };
+    use content_metadata::ContentType;
+
+    #[test]
+    fn test_hashes_similarity() {
+        assert_eq!(hashes_similarity(&[], &[1]), None);
+        assert_eq!(hashes_similarity(&[0xff], &[0xff]), Some(1.0));
+        // 较短的一组里每个 hash 都能在另一组里找到一样的
+        assert_eq!(hashes_similarity(&[1, 2], &[3, 2, 1]), Some(1.0));
+        assert_eq!(sample_evenly(&[0, 1, 2, 3, 4, 5], 3), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_near_duplicate_cache_key() {
+        let a = NearDuplicatePayload::new(&["a".to_string(), "b".to_string()]);
+        let b = NearDuplicatePayload::new(&["b".to_string(), "a".to_string()]);
+        assert_eq!(a.cache_key(), b.cache_key());
+        assert_ne!(a.cache_key(), b.with_threshold(0.8).cache_key());
+    }
+
+    #[test]
+    fn test_cluster_signatures() {
+        let signatures = vec![
+            AssetSignature {
+                file_identifier: "a".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![1.0, 0.0, 0.0]),
+                hashes: vec![0xffff],
+            },
+            // 和 a 只是缩放过
+            AssetSignature {
+                file_identifier: "b".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![0.99, 0.05, 0.0]),
+                hashes: vec![0xfffe],
+            },
+            // 和 b 很像，和 a 没有直接超过阈值也会被合并到一个 cluster
+            AssetSignature {
+                file_identifier: "c".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![0.92, 0.39, 0.0]),
+                hashes: vec![0xfffe],
+            },
+            // embedding 很像但是 hash 完全不同
+            AssetSignature {
+                file_identifier: "d".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![0.98, 0.1, 0.0]),
+                hashes: vec![!0xffff],
+            },
+            // 类型不同不比较
+            AssetSignature {
+                file_identifier: "e".to_string(),
+                content_type: ContentType::Video,
+                embedding: normalize(vec![1.0, 0.0, 0.0]),
+                hashes: vec![0xffff],
+            },
+            AssetSignature {
+                file_identifier: "f".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![0.0, 0.0, 1.0]),
+                hashes: vec![],
+            },
+            AssetSignature {
+                file_identifier: "g".to_string(),
+                content_type: ContentType::Image,
+                embedding: normalize(vec![0.0, 0.01, 1.0]),
+                hashes: vec![],
+            },
+        ];
+        let clusters = cluster_signatures(&signatures, 0.96);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].file_identifiers, vec!["a", "b", "c"]);
+        assert!(!clusters[0]
+            .pairs
+            .iter()
+            .any(|v| v.file_identifiers == ("a".to_string(), "c".to_string())));
+        assert_eq!(clusters[1].file_identifiers, vec!["f", "g"]);
+        assert_eq!(clusters[1].pairs[0].hash_similarity, None);
+    }
+}
@@ -2,7 +2,9 @@ mod constant;
 mod core;
 pub mod db;
 pub mod delete;
+pub mod duplicate;
 pub mod query;
+mod segment;
 pub mod task;
 pub mod upsert;
 mod utils;
@@ -10,7 +12,9 @@ mod utils;
 use std::sync::Arc;
 
 use crate::db::DB;
-pub use content_base_context::ContentBaseCtx;
+use crate::duplicate::NearDuplicateCache;
+use crate::query::{QueryEmbeddingCache, QueryExpansionCache, SearchResultCache};
+pub use content_base_context::{tagging::TaggingConfig, ContentBaseCtx};
 use content_base_pool::TaskPool;
 pub use content_base_pool::{TaskNotification, TaskStatus};
 use tokio::sync::RwLock;
@@ -20,6 +24,10 @@ pub struct ContentBase {
     ctx: ContentBaseCtx,
     task_pool: TaskPool,
     surrealdb_client: Arc<RwLock<DB>>,
+    query_expansion_cache: QueryExpansionCache,
+    query_embedding_cache: QueryEmbeddingCache,
+    search_result_cache: SearchResultCache,
+    near_duplicate_cache: NearDuplicateCache,
 }
 
 #[cfg(test)]
@@ -0,0 +1,66 @@
+use super::payload::ContentQueryResult;
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+/// 缓存多少个 query 的排序结果，翻页时直接从缓存里取，不用再搜索和 rank 一遍
+const SEARCH_RESULT_CACHE_SIZE: usize = 32;
+/// 缓存多少个 query 的向量，扩展查询每次都是同样的几个，不用重复推理
+const QUERY_EMBEDDING_CACHE_SIZE: usize = 256;
+
+/// 文本搜索排好序的全部结果，key 由 query、过滤条件和排序参数组成
+/// 索引有变化（写入或者删除素材、切换模型）的时候需要调用 clear
+#[derive(Clone)]
+pub(crate) struct SearchResultCache(Arc<Mutex<LruCache<String, Vec<ContentQueryResult>>>>);
+
+impl SearchResultCache {
+    pub fn new() -> Self {
+        let size = NonZeroUsize::new(SEARCH_RESULT_CACHE_SIZE).expect("cache size is not zero");
+        Self(Arc::new(Mutex::new(LruCache::new(size))))
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<ContentQueryResult>> {
+        self.0.lock().ok()?.get(key).cloned()
+    }
+
+    pub fn put(&self, key: &str, results: Vec<ContentQueryResult>) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.put(key.to_string(), results);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.clear();
+        }
+    }
+}
+
+/// query 的文本向量和图像向量，切换模型的时候需要调用 clear
+#[derive(Clone)]
+pub(crate) struct QueryEmbeddingCache(Arc<Mutex<LruCache<String, (Vec<f32>, Vec<f32>)>>>);
+
+impl QueryEmbeddingCache {
+    pub fn new() -> Self {
+        let size = NonZeroUsize::new(QUERY_EMBEDDING_CACHE_SIZE).expect("cache size is not zero");
+        Self(Arc::new(Mutex::new(LruCache::new(size))))
+    }
+
+    pub fn get(&self, query: &str) -> Option<(Vec<f32>, Vec<f32>)> {
+        self.0.lock().ok()?.get(query).cloned()
+    }
+
+    pub fn put(&self, query: &str, embeddings: (Vec<f32>, Vec<f32>)) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.put(query.to_string(), embeddings);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.clear();
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use super::payload::{
+    audio::{AudioIndexMetadata, AudioSliceType},
+    image::ImageIndexMetadata,
+    raw_text::{RawTextChunkType, RawTextIndexMetadata},
+    video::{VideoIndexMetadata, VideoSliceType},
+    web_page::{WebPageChunkType, WebPageIndexMetadata},
+    ContentIndexMetadata,
+};
+use crate::ContentBase;
+use ai::Transcription;
+use content_base_task::{
+    audio::trans_chunk::{AudioTransChunkTask, AudioTranscriptChunkTrait},
+    image::description::ImageDescriptionTask,
+    raw_text::chunk::{DocumentChunkTrait, RawTextChunkTask},
+    video::{
+        frame::{VideoFrameTask, VIDEO_FRAME_SUMMARY_BATCH_SIZE},
+        frame_description::VideoFrameDescriptionTask,
+        trans_chunk::VideoTransChunkTask,
+    },
+    web_page::chunk::WebPageChunkTask,
+    TaskRecord,
+};
+use content_metadata::ContentMetadata;
+
+/// 扩展了相邻内容的片段
+#[derive(Debug, Clone)]
+pub struct ExpandedReference {
+    pub metadata: ContentIndexMetadata,
+    pub content: String,
+}
+
+/// 素材里的一段原始内容，metadata 表示这段内容在素材里的位置
+#[derive(Debug, Clone)]
+pub struct AssetContextSegment {
+    pub metadata: ContentIndexMetadata,
+    pub content: String,
+}
+
+impl ContentBase {
+    /// 把检索到的片段向前后各扩展 radius 个相邻的分段，用于给 LLM 提供更完整的上下文
+    /// - 音视频使用相邻的转录分段，返回的是转录原文，而不是索引里的转录总结
+    /// - 文档和网页使用相邻的 chunk
+    /// - 图片和视频画面没有相邻的文本，返回 None
+    pub async fn expand_reference(
+        &self,
+        file_identifier: &str,
+        metadata: &ContentIndexMetadata,
+        radius: usize,
+    ) -> anyhow::Result<Option<ExpandedReference>> {
+        let ctx = self.ctx();
+        let expanded = match metadata {
+            ContentIndexMetadata::Video(metadata) => match metadata.slice_type {
+                VideoSliceType::Audio => {
+                    let chunks = VideoTransChunkTask
+                        .chunk_content(file_identifier, ctx)
+                        .await?;
+                    expand_transcript(
+                        &chunks,
+                        metadata.start_timestamp,
+                        metadata.end_timestamp,
+                        radius,
+                    )
+                    .map(|(start_timestamp, end_timestamp, content)| {
+                        ExpandedReference {
+                            metadata: VideoIndexMetadata {
+                                slice_type: VideoSliceType::Audio,
+                                start_timestamp,
+                                end_timestamp,
+                            }
+                            .into(),
+                            content,
+                        }
+                    })
+                }
+                VideoSliceType::Visual => None,
+            },
+            ContentIndexMetadata::Audio(metadata) => {
+                let chunks = AudioTransChunkTask
+                    .chunk_content(file_identifier, ctx)
+                    .await?;
+                expand_transcript(
+                    &chunks,
+                    metadata.start_timestamp,
+                    metadata.end_timestamp,
+                    radius,
+                )
+                .map(|(start_timestamp, end_timestamp, content)| {
+                    ExpandedReference {
+                        metadata: AudioIndexMetadata {
+                            slice_type: metadata.slice_type.clone(),
+                            start_timestamp,
+                            end_timestamp,
+                        }
+                        .into(),
+                        content,
+                    }
+                })
+            }
+            ContentIndexMetadata::RawText(metadata) => {
+                let chunks = RawTextChunkTask.chunk_content(file_identifier, ctx).await?;
+                expand_chunks(&chunks, metadata.start_index, metadata.end_index, radius).map(
+                    |(start_index, end_index, content)| ExpandedReference {
+                        metadata: RawTextIndexMetadata {
+                            chunk_type: metadata.chunk_type.clone(),
+                            start_index,
+                            end_index,
+                        }
+                        .into(),
+                        content,
+                    },
+                )
+            }
+            ContentIndexMetadata::WebPage(metadata) => {
+                let chunks = WebPageChunkTask.chunk_content(file_identifier, ctx).await?;
+                expand_chunks(&chunks, metadata.start_index, metadata.end_index, radius).map(
+                    |(start_index, end_index, content)| ExpandedReference {
+                        metadata: WebPageIndexMetadata {
+                            chunk_type: metadata.chunk_type.clone(),
+                            start_index,
+                            end_index,
+                        }
+                        .into(),
+                        content,
+                    },
+                )
+            }
+            ContentIndexMetadata::Image(_) => None,
+        };
+
+        Ok(expanded)
+    }
+}
+
+impl ContentBase {
+    /// 素材里所有的原始内容，按在素材里的位置排序，用于针对单个素材的问答
+    /// - 视频包括转录分段和画面描述，音频包括转录分段
+    /// - 文档和网页是所有的 chunk
+    /// - 图片是图片描述
+    /// 还没有处理完的内容会被跳过，所有内容都没有的时候返回空数组
+    pub async fn asset_context(
+        &self,
+        file_identifier: &str,
+    ) -> anyhow::Result<Vec<AssetContextSegment>> {
+        let ctx = self.ctx();
+        let task_record = TaskRecord::from_content_base(file_identifier, ctx).await;
+
+        let mut segments = vec![];
+        match task_record.metadata() {
+            ContentMetadata::Video(metadata) => {
+                if metadata.audio.is_some() {
+                    let chunks = VideoTransChunkTask
+                        .chunk_content(file_identifier, ctx)
+                        .await
+                        .unwrap_or_default();
+                    segments.extend(chunks.into_iter().map(|chunk| {
+                        AssetContextSegment {
+                            metadata: VideoIndexMetadata {
+                                slice_type: VideoSliceType::Audio,
+                                start_timestamp: chunk.start_timestamp,
+                                end_timestamp: chunk.end_timestamp,
+                            }
+                            .into(),
+                            content: chunk.text,
+                        }
+                    }));
+                }
+                let frames = VideoFrameTask
+                    .frame_content(file_identifier, ctx)
+                    .await
+                    .unwrap_or_default();
+                for frames_chunk in frames.chunks(VIDEO_FRAME_SUMMARY_BATCH_SIZE) {
+                    let start_timestamp =
+                        frames_chunk.first().expect("first frame exists").timestamp;
+                    let end_timestamp = frames_chunk.last().expect("last frame exists").timestamp;
+                    let Ok(caption) = VideoFrameDescriptionTask
+                        .frame_description_content(
+                            file_identifier,
+                            ctx,
+                            start_timestamp,
+                            end_timestamp,
+                        )
+                        .await
+                    else {
+                        continue;
+                    };
+                    segments.push(AssetContextSegment {
+                        metadata: VideoIndexMetadata {
+                            slice_type: VideoSliceType::Visual,
+                            start_timestamp,
+                            end_timestamp,
+                        }
+                        .into(),
+                        content: caption,
+                    });
+                }
+                // 转录和画面描述按时间交错排列
+                segments.sort_by_key(|segment| segment.metadata.segment_range());
+            }
+            ContentMetadata::Audio(_) => {
+                let chunks = AudioTransChunkTask
+                    .chunk_content(file_identifier, ctx)
+                    .await
+                    .unwrap_or_default();
+                segments.extend(chunks.into_iter().map(|chunk| {
+                    AssetContextSegment {
+                        metadata: AudioIndexMetadata {
+                            slice_type: AudioSliceType::Transcript,
+                            start_timestamp: chunk.start_timestamp,
+                            end_timestamp: chunk.end_timestamp,
+                        }
+                        .into(),
+                        content: chunk.text,
+                    }
+                }));
+            }
+            ContentMetadata::RawText(_) => {
+                let chunks = RawTextChunkTask
+                    .chunk_content(file_identifier, ctx)
+                    .await
+                    .unwrap_or_default();
+                segments.extend(chunks.into_iter().enumerate().map(|(idx, chunk)| {
+                    AssetContextSegment {
+                        metadata: RawTextIndexMetadata {
+                            chunk_type: RawTextChunkType::Content,
+                            start_index: idx,
+                            end_index: idx,
+                        }
+                        .into(),
+                        content: chunk,
+                    }
+                }));
+            }
+            ContentMetadata::WebPage(_) => {
+                let chunks = WebPageChunkTask
+                    .chunk_content(file_identifier, ctx)
+                    .await
+                    .unwrap_or_default();
+                segments.extend(chunks.into_iter().enumerate().map(|(idx, chunk)| {
+                    AssetContextSegment {
+                        metadata: WebPageIndexMetadata {
+                            chunk_type: WebPageChunkType::Content,
+                            start_index: idx,
+                            end_index: idx,
+                        }
+                        .into(),
+                        content: chunk,
+                    }
+                }));
+            }
+            ContentMetadata::Image(_) => {
+                if let Ok(caption) = ImageDescriptionTask
+                    .description_content(file_identifier, ctx)
+                    .await
+                {
+                    segments.push(AssetContextSegment {
+                        metadata: ContentIndexMetadata::Image(ImageIndexMetadata { data: 0 }),
+                        content: caption,
+                    });
+                }
+            }
+            _ => {
+                anyhow::bail!("unsupported content type: {}", file_identifier);
+            }
+        }
+
+        Ok(segments
+            .into_iter()
+            .filter(|segment| !segment.content.trim().is_empty())
+            .collect())
+    }
+}
+
+/// 找到和 [start, end] 有重叠的转录分段，再向前后各扩展 radius 个分段
+fn expand_transcript(
+    chunks: &[Transcription],
+    start: i64,
+    end: i64,
+    radius: usize,
+) -> Option<(i64, i64, String)> {
+    let overlaps =
+        |chunk: &Transcription| chunk.start_timestamp < end && chunk.end_timestamp > start;
+    let first = chunks.iter().position(overlaps)?;
+    let last = chunks.iter().rposition(overlaps)?;
+    let from = first.saturating_sub(radius);
+    let to = (last + radius).min(chunks.len() - 1);
+    let content = chunks[from..=to]
+        .iter()
+        .map(|chunk| chunk.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some((
+        chunks[from].start_timestamp,
+        chunks[to].end_timestamp,
+        content,
+    ))
+}
+
+/// chunk 的 index 范围向前后各扩展 radius 个 chunk
+fn expand_chunks(
+    chunks: &[String],
+    start: usize,
+    end: usize,
+    radius: usize,
+) -> Option<(usize, usize, String)> {
+    if start > end || end >= chunks.len() {
+        return None;
+    }
+    let from = start.saturating_sub(radius);
+    let to = (end + radius).min(chunks.len() - 1);
+    Some((from, to, chunks[from..=to].join("\n")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expand_chunks, expand_transcript};
+    use ai::Transcription;
+
+    #[test]
+    fn test_expand_transcript() {
+        let chunks = (0..5)
+            .map(|i| Transcription {
+                start_timestamp: i * 1000,
+                end_timestamp: (i + 1) * 1000,
+                text: format!("t{}", i),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            expand_transcript(&chunks, 2000, 3000, 1),
+            Some((1000, 4000, "t1\nt2\nt3".to_string()))
+        );
+        // 在开头和结尾截断
+        assert_eq!(
+            expand_transcript(&chunks, 0, 1000, 2),
+            Some((0, 3000, "t0\nt1\nt2".to_string()))
+        );
+        assert_eq!(
+            expand_transcript(&chunks, 3500, 5000, 1),
+            Some((2000, 5000, "t2\nt3\nt4".to_string()))
+        );
+        assert_eq!(expand_transcript(&chunks, 6000, 7000, 1), None);
+    }
+
+    #[test]
+    fn test_expand_chunks() {
+        let chunks = (0..4).map(|i| format!("c{}", i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            expand_chunks(&chunks, 1, 1, 1),
+            Some((0, 2, "c0\nc1\nc2".to_string()))
+        );
+        assert_eq!(
+            expand_chunks(&chunks, 3, 3, 2),
+            Some((1, 3, "c1\nc2\nc3".to_string()))
+        );
+        assert_eq!(expand_chunks(&chunks, 4, 4, 1), None);
+    }
+}
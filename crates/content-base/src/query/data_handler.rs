@@ -1,38 +1,160 @@
-use super::{model::SearchModel, ContentQueryPayload};
+use super::{model::SearchModel, ContentImageQuery, ContentImageQueryPayload, ContentQueryPayload};
 use crate::{
-    constant::STOP_WORDS,
-    query::model::{TextSearchModel, TextToken},
+    constant::{CJK_STOP_WORDS, STOP_WORDS},
+    query::model::{ImageSearchModel, TextSearchModel, TextSearchVariant, TextToken},
+    segment::cut_words,
     utils::deduplicate,
     ContentBase,
 };
-use ai::TextEmbeddingModel;
+use ai::{MultiModalEmbeddingInput, TextEmbeddingModel};
+use content_base_task::{
+    image::embedding::ImageEmbeddingTask,
+    video::{frame::VideoFrameTask, frame_embedding::VideoFrameEmbeddingTask},
+    TaskRecord,
+};
+use content_metadata::ContentMetadata;
+use futures::future::try_join_all;
 use regex::Regex;
+use std::path::Path;
+use storage::Storage;
 
 impl ContentBase {
     /// 构造内部查询模型 SearchModel
     /// 1. 分词
     /// 2. query 文字 -> 文本向量 + 图片向量
+    /// 3. 如果设置了 expand_query，用 LLM 生成扩展查询，每个扩展查询同样分词和计算向量
     #[tracing::instrument(err(Debug), skip_all, fields(query=%payload.query))]
     pub async fn query_payload_to_model(
         &self,
         payload: &ContentQueryPayload,
     ) -> anyhow::Result<SearchModel> {
+        let TextSearchVariant {
+            data,
+            tokens,
+            text_embedding,
+            vision_embedding,
+        } = self.text_search_variant(&payload.query).await?;
+
+        let variants = if payload.expand_query {
+            let queries = self.expand_query(&payload.query).await;
+            try_join_all(queries.iter().map(|query| self.text_search_variant(query))).await?
+        } else {
+            vec![]
+        };
+
+        Ok(SearchModel::Text(TextSearchModel {
+            data,
+            tokens,
+            text_embedding,
+            vision_embedding,
+            mode: payload.mode,
+            variants,
+        }))
+    }
+
+    async fn text_search_variant(&self, query: &str) -> anyhow::Result<TextSearchVariant> {
+        let (text_model_embedding, clip_text_embedding) = self.query_embeddings(query).await?;
+        Ok(TextSearchVariant {
+            data: query.to_string(),
+            tokens: TextToken(self.tokenizer(query).await?),
+            text_embedding: text_model_embedding,
+            vision_embedding: clip_text_embedding,
+        })
+    }
+
+    /// query 的文本向量和图像向量，按 query 缓存，扩展查询和翻页时不用重新推理
+    async fn query_embeddings(&self, query: &str) -> anyhow::Result<(Vec<f32>, Vec<f32>)> {
+        if let Some(embeddings) = self.query_embedding_cache.get(query) {
+            return Ok(embeddings);
+        }
         let multi_modal_embedding: TextEmbeddingModel = self.ctx.multi_modal_embedding()?.0.into();
         let clip_text_embedding = multi_modal_embedding
-            .process_single(payload.query.clone())
+            .process_single(query.to_string())
             .await?;
         let text_model_embedding = self
             .ctx
             .text_embedding()?
             .0
-            .process_single(payload.query.clone())
+            .process_single(query.to_string())
             .await?;
-        Ok(SearchModel::Text(TextSearchModel {
-            data: payload.query.clone(),
-            tokens: TextToken(self.tokenizer(&payload.query).await?),
-            text_embedding: text_model_embedding,
-            vision_embedding: clip_text_embedding,
-        }))
+        let embeddings = (text_model_embedding, clip_text_embedding);
+        self.query_embedding_cache.put(query, embeddings.clone());
+        Ok(embeddings)
+    }
+
+    /// 构造以图搜图的查询模型 SearchModel
+    /// 已有素材优先使用任务生成的 embedding，没有的话再用 multi modal embedding 模型计算
+    pub async fn image_query_payload_to_model(
+        &self,
+        payload: &ContentImageQueryPayload,
+    ) -> anyhow::Result<SearchModel> {
+        let vision_embedding = match &payload.image {
+            ContentImageQuery::File(path) => self.image_file_embedding(path).await?,
+            ContentImageQuery::Asset {
+                file_identifier,
+                timestamp,
+            } => {
+                self.asset_image_embedding(file_identifier, *timestamp)
+                    .await?
+            }
+        };
+        Ok(SearchModel::Image(ImageSearchModel { vision_embedding }))
+    }
+
+    async fn image_file_embedding(&self, path: impl AsRef<Path>) -> anyhow::Result<Vec<f32>> {
+        let (model, _) = self.ctx.multi_modal_embedding()?;
+        model
+            .process_single(MultiModalEmbeddingInput::Image(path.as_ref().to_path_buf()))
+            .await
+    }
+
+    /// 获取素材的图像向量，视频取最接近 timestamp 的一帧，图片就是图片本身
+    pub(crate) async fn asset_image_embedding(
+        &self,
+        file_identifier: &str,
+        timestamp: Option<i64>,
+    ) -> anyhow::Result<Vec<f32>> {
+        let task_record = TaskRecord::from_content_base(file_identifier, &self.ctx).await;
+        match task_record.metadata() {
+            ContentMetadata::Image(_) => {
+                ImageEmbeddingTask
+                    .embedding_content(file_identifier, &self.ctx)
+                    .await
+            }
+            ContentMetadata::Video(_) => {
+                let timestamp = timestamp.unwrap_or(0);
+                let frames = VideoFrameTask
+                    .frame_content(file_identifier, &self.ctx)
+                    .await?;
+                let frame = frames
+                    .iter()
+                    .min_by_key(|frame| (frame.timestamp - timestamp).abs())
+                    .ok_or_else(|| anyhow::anyhow!("no frame found in {}", file_identifier))?;
+                match VideoFrameEmbeddingTask
+                    .frame_embedding_content(file_identifier, &self.ctx, frame.timestamp)
+                    .await
+                {
+                    Ok(embedding) => Ok(embedding),
+                    Err(e) => {
+                        tracing::warn!("frame embedding not found, compute it directly: {e}");
+                        let image_path = VideoFrameTask
+                            .get_absolute_path(frame.image_file.clone())
+                            .map_err(|e| {
+                                anyhow::anyhow!(
+                                    "Failed to get absolute path for frame image file {:?}: {:?}",
+                                    frame.image_file,
+                                    e
+                                )
+                            })?;
+                        self.image_file_embedding(image_path).await
+                    }
+                }
+            }
+            _ => anyhow::bail!(
+                "content type of {} is not supported for image search",
+                file_identifier
+            ),
+        }
     }
 
     pub async fn tokenizer(&self, data: &str) -> anyhow::Result<Vec<String>> {
@@ -50,8 +172,11 @@ impl ContentBase {
         // 移除所有标点符号
         let final_result = punctuation_pattern.replace_all(&cleaned_data, "");
 
+        // 中文没有空格，需要再用词典分词，和写入索引时的分词保持一致
         let tokens: Vec<String> = final_result
             .split_whitespace()
+            .flat_map(cut_words)
+            .filter(|s| !CJK_STOP_WORDS.contains(s))
             .map(|s| s.to_string())
             .collect();
 
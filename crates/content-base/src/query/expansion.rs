@@ -0,0 +1,175 @@
+use crate::ContentBase;
+use ai::llm::{LLMInferenceParams, LLMMessage};
+use lru::LruCache;
+use serde::Deserialize;
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+/// 缓存多少个 query 的扩展结果，同一个 query 翻页或者重复搜索时不再调用 LLM
+const QUERY_EXPANSION_CACHE_SIZE: usize = 256;
+/// 最多使用多少个扩展查询，每个扩展查询都要计算 embedding 并搜索一遍
+const MAX_QUERY_VARIANTS: usize = 4;
+/// 同义词最多使用几个，剩下的位置留给翻译和画面描述
+const MAX_SYNONYMS: usize = 2;
+
+const QUERY_EXPANSION_PROMPT: &str = r#"You are a search query rewriter for a media library that contains videos, audios, images and documents in both Chinese and English.
+Rewrite the user's search query to improve recall.
+
+Respond with a JSON object only, with the following fields:
+- "synonyms": up to 3 short queries with the same meaning in the same language as the input, using synonyms or closely related words
+- "translation": the query translated into English if it is in Chinese, or into Chinese otherwise
+- "visual": a short English sentence describing what a picture or video frame matching the query would look like
+
+Example:
+Input: 海边日落
+Output: {"synonyms": ["海滩黄昏", "海上落日"], "translation": "sunset at the seaside", "visual": "a photo of the sun setting over the ocean with an orange sky"}
+
+Additional Rules:
+- Do not add any explanation or markdown, just the JSON object
+- Keep each query short, no more than 20 words"#;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub(crate) struct QueryExpansion {
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    #[serde(default)]
+    pub translation: Option<String>,
+    #[serde(default)]
+    pub visual: Option<String>,
+}
+
+impl QueryExpansion {
+    /// 扩展出来的查询，去掉空的和重复的，以及和原始查询一样的
+    /// 翻译和画面描述优先，然后是同义词
+    pub fn queries(&self, query: &str) -> Vec<String> {
+        let mut seen = vec![query.trim().to_lowercase()];
+        self.translation
+            .iter()
+            .chain(self.visual.iter())
+            .chain(self.synonyms.iter().take(MAX_SYNONYMS))
+            .map(|v| v.trim().to_string())
+            .filter(|v| {
+                let key = v.to_lowercase();
+                if v.is_empty() || seen.contains(&key) {
+                    return false;
+                }
+                seen.push(key);
+                true
+            })
+            .take(MAX_QUERY_VARIANTS)
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct QueryExpansionCache(Arc<Mutex<LruCache<String, QueryExpansion>>>);
+
+impl QueryExpansionCache {
+    pub fn new() -> Self {
+        let size = NonZeroUsize::new(QUERY_EXPANSION_CACHE_SIZE).expect("cache size is not zero");
+        Self(Arc::new(Mutex::new(LruCache::new(size))))
+    }
+
+    fn get(&self, query: &str) -> Option<QueryExpansion> {
+        self.0.lock().ok()?.get(query).cloned()
+    }
+
+    fn put(&self, query: &str, expansion: QueryExpansion) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.put(query.to_string(), expansion);
+        }
+    }
+}
+
+/// LLM 有时会在 JSON 前后加上说明或者 markdown 代码块，只取第一个 { 到最后一个 } 之间的内容
+fn parse_query_expansion(response: &str) -> anyhow::Result<QueryExpansion> {
+    let start = response.find('{');
+    let end = response.rfind('}');
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => {
+            Ok(serde_json::from_str(&response[start..=end])?)
+        }
+        _ => anyhow::bail!("no json object found in response: {}", response),
+    }
+}
+
+impl ContentBase {
+    /// 用 LLM 生成 query 的扩展查询，结果按 query 缓存
+    /// LLM 出错时不扩展，也不缓存，扩展是可选的，不应该影响搜索
+    pub(super) async fn expand_query(&self, query: &str) -> Vec<String> {
+        let query = query.trim();
+        if let Some(expansion) = self.query_expansion_cache.get(query) {
+            return expansion.queries(query);
+        }
+        match self.request_query_expansion(query).await {
+            Ok(expansion) => {
+                tracing::debug!("query expansion of {}: {:?}", query, expansion);
+                let queries = expansion.queries(query);
+                self.query_expansion_cache.put(query, expansion);
+                queries
+            }
+            Err(e) => {
+                tracing::warn!("failed to expand query: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    async fn request_query_expansion(&self, query: &str) -> anyhow::Result<QueryExpansion> {
+        let (llm, _) = self.ctx.llm()?;
+        let mut response = llm
+            .process_single((
+                vec![
+                    LLMMessage::new_system(QUERY_EXPANSION_PROMPT),
+                    LLMMessage::new_user(query),
+                ],
+                LLMInferenceParams::default(),
+            ))
+            .await?;
+        let response = response.to_string().await?;
+        parse_query_expansion(&response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_query_expansion, QueryExpansion};
+
+    #[test]
+    fn test_parse_query_expansion() {
+        let expansion = parse_query_expansion(
+            "```json\n{\"synonyms\": [\"海滩黄昏\"], \"translation\": \"sunset at the seaside\"}\n```",
+        )
+        .unwrap();
+        assert_eq!(
+            expansion,
+            QueryExpansion {
+                synonyms: vec!["海滩黄昏".to_string()],
+                translation: Some("sunset at the seaside".to_string()),
+                visual: None,
+            }
+        );
+        assert!(parse_query_expansion("sorry, I can't help").is_err());
+    }
+
+    #[test]
+    fn test_query_expansion_queries() {
+        let expansion = QueryExpansion {
+            synonyms: vec![
+                "海边日落".to_string(),
+                "海滩黄昏".to_string(),
+                "".to_string(),
+                "海上落日".to_string(),
+            ],
+            translation: Some("Sunset at the seaside".to_string()),
+            visual: Some(" sunset at the seaside ".to_string()),
+        };
+        // 和原始查询一样的、空的、重复的都去掉，同义词最多两个
+        assert_eq!(
+            expansion.queries("海边日落"),
+            vec!["Sunset at the seaside".to_string(), "海滩黄昏".to_string()]
+        );
+    }
+}
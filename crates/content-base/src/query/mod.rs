@@ -1,25 +1,235 @@
+mod cache;
+pub mod context;
 mod data_handler;
+mod expansion;
 pub mod model;
+pub mod parser;
 pub mod payload;
-use crate::ContentBase;
+mod rerank;
+use crate::{
+    db::{
+        search::{apply_recency, group_results_by_asset, sort_by_score, vector_candidates},
+        TagCount, TermSuggestion,
+    },
+    ContentBase,
+};
+pub(crate) use cache::{QueryEmbeddingCache, SearchResultCache};
 use content_base_task::{
     audio::transcript::{AudioTranscriptTask, AudioTranscriptTrait},
     image::description::ImageDescriptionTask,
     raw_text::chunk::{DocumentChunkTrait, RawTextChunkTask},
     video::{frame_description::VideoFrameDescriptionTask, transcript::VideoTranscriptTask},
 };
+use content_metadata::ContentType;
+pub(crate) use expansion::QueryExpansionCache;
+use futures::future::BoxFuture;
+use model::{ImageSearchModel, SearchModel};
 use payload::{
     audio::AudioSliceType, raw_text::RawTextChunkType, video::VideoSliceType, ContentIndexMetadata,
-    ContentQueryResult,
+    ContentQueryGroupedPage, ContentQueryPage, ContentQueryResult,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::Arc,
 };
 
 const MAX_RETRIEVAL_COUNT: usize = 20;
+/// 每页最多返回多少条结果，调用方传入更大的 max_count 时会被截断
+const MAX_PAGE_SIZE: usize = 100;
+/// 推荐相似画面时，同一个视频里离当前画面太近的片段（前后毫秒数）不算推荐结果
+const RECOMMEND_EXCLUDE_DURATION: i64 = 10_000;
+
+/// 搜索过滤条件，会直接拼进 SurrealDB 的查询语句里，而不是在结果出来以后再筛选
+/// - 所有字段为 None 时不做任何过滤
+/// - 文件夹、时间、大小、时长这些信息不在 SurrealDB 里，需要调用方先筛选出 file_identifiers
+#[derive(Clone, Debug, Default)]
+pub struct ContentQueryFilter {
+    /// 只搜索这些类型的内容
+    pub content_types: Option<Vec<ContentType>>,
+    /// 只搜索这些文件，也就是 asset_object 的 hash
+    pub file_identifiers: Option<Vec<String>>,
+    /// 只搜索这些来源，None 表示搜索所有来源
+    pub sources: Option<Vec<ContentQuerySource>>,
+    /// 文本里必须包含这些短语，不区分大小写
+    pub phrases: Vec<String>,
+    /// 素材的文本里不能包含这些词，不区分大小写
+    /// 只要素材有一个片段包含就排除整个素材，搜索之前会转换成 excluded_file_identifiers
+    pub excluded: Vec<String>,
+    /// 不搜索这些文件
+    pub excluded_file_identifiers: Vec<String>,
+    /// 画面里必须同时有这些物体（COCO 类别名，比如 dog、car），只会命中 image 表
+    pub objects: Vec<String>,
+    /// 素材必须同时有这些标签，搜索之前会转换成 file_identifiers
+    pub tags: Vec<String>,
+}
+
+impl ContentQueryFilter {
+    /// 过滤条件的 hash，用在排序结果的缓存 key 里
+    /// 文件夹、时间等条件会转换成很长的 file_identifiers，直接拼进 key 里太大了，素材的顺序不影响结果
+    fn cache_key(&self) -> u64 {
+        let sorted = |v: &Vec<String>| {
+            let mut v = v.iter().collect::<Vec<_>>();
+            v.sort();
+            v
+        };
+        let mut hasher = DefaultHasher::new();
+        self.content_types
+            .as_ref()
+            .map(|v| v.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+            .hash(&mut hasher);
+        self.file_identifiers.as_ref().map(sorted).hash(&mut hasher);
+        self.sources.hash(&mut hasher);
+        self.phrases.hash(&mut hasher);
+        self.excluded.hash(&mut hasher);
+        sorted(&self.excluded_file_identifiers).hash(&mut hasher);
+        self.objects.hash(&mut hasher);
+        self.tags.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 搜索的来源，对应不同的表和字段
+/// text 表的记录按上一层对象区分来源，见 `text_parent_tables`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentQuerySource {
+    /// 音频和视频的语音转录，包括按转录生成的视频章节标题（text 表）
+    Transcript,
+    /// 文档和网页的文本（text 表）
+    Text,
+    /// 图片和视频画面上识别出来的文字（text 表）
+    Ocr,
+    /// 图片和视频画面的描述（image 表的 caption 和 caption_embedding）
+    Caption,
+    /// 图片和视频画面的图像向量（image 表的 embedding）
+    Vision,
+}
+
+/// 文本搜索的方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ContentQueryMode {
+    /// 全文搜索和向量搜索都使用，然后 rank
+    #[default]
+    Hybrid,
+    /// 只使用全文搜索
+    FullText,
+    /// 只使用向量搜索
+    Vector,
+}
+
+/// 排序时各个信号的权重，保存在 library 的设置里
+/// - full_text、text_vector、vision_vector 是召回在 rrf 融合时的权重，两种向量的权重一样时合成一路排序
+/// - transcript、caption 按召回的来源乘在对应那一路的 rrf 分数上，transcript 只包括音频和视频的转录，caption 不包括图像向量
+/// - recency 是新素材的加分，按创建时间衰减，0 表示不考虑创建时间
+#[cfg_attr(feature = "rspc", derive(specta::Type))]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RankWeights {
+    pub full_text: f32,
+    pub text_vector: f32,
+    pub vision_vector: f32,
+    pub transcript: f32,
+    pub caption: f32,
+    pub recency: f32,
+}
+
+impl Default for RankWeights {
+    fn default() -> Self {
+        Self {
+            full_text: 1.0,
+            text_vector: 1.0,
+            vision_vector: 1.0,
+            transcript: 1.0,
+            caption: 1.0,
+            recency: 0.0,
+        }
+    }
+}
+
+/// 查询素材的创建时间（毫秒时间戳），参数是搜索命中的 file_identifier，返回值的 key 是 file_identifier
+/// 创建时间不在 SurrealDB 里，由调用方提供
+pub type AssetCreatedAtLoader = Arc<
+    dyn Fn(Vec<String>) -> BoxFuture<'static, anyhow::Result<HashMap<String, i64>>> + Send + Sync,
+>;
+
+/// 排序相关的参数
+#[derive(Clone, Default)]
+pub struct ContentQueryRankOptions {
+    pub weights: RankWeights,
+    /// 只有 weights.recency 大于 0 时才需要，只会查询搜索命中的素材，没有创建时间的素材不加分
+    pub asset_created_at: Option<AssetCreatedAtLoader>,
+    /// 是否在结果里返回每个信号的分数明细
+    pub explain: bool,
+}
+
+/// 查询素材用来排序的属性值，参数是搜索命中的 file_identifier，返回值的 key 是 file_identifier
+pub type AssetSortValuesLoader = Arc<
+    dyn Fn(Vec<String>) -> BoxFuture<'static, anyhow::Result<HashMap<String, f64>>> + Send + Sync,
+>;
+
+/// 按素材的属性排序，而不是按相关度
+/// 属性（时长、分辨率等）不在 SurrealDB 里，由调用方提供，只会查询搜索命中的素材
+#[derive(Clone)]
+pub struct ContentQuerySort {
+    pub values: AssetSortValuesLoader,
+    pub descending: bool,
+}
+
+impl ContentQuerySort {
+    /// 查询 items 里所有素材的属性值，然后排序
+    pub(crate) async fn sort<T>(
+        &self,
+        items: &mut [T],
+        file_identifier: impl Fn(&T) -> &str,
+    ) -> anyhow::Result<()> {
+        let file_identifiers = items
+            .iter()
+            .map(|v| file_identifier(v).to_string())
+            .collect::<HashSet<_>>();
+        let values = (self.values)(file_identifiers.into_iter().collect()).await?;
+        sort_by_values(items, &values, self.descending, file_identifier);
+        Ok(())
+    }
+}
+
+/// 稳定排序，值相同时保持原来按分数的顺序，没有值的素材排在最后
+fn sort_by_values<T>(
+    items: &mut [T],
+    values: &HashMap<String, f64>,
+    descending: bool,
+    file_identifier: impl Fn(&T) -> &str,
+) {
+    items.sort_by(|a, b| {
+        let a = values.get(file_identifier(a));
+        let b = values.get(file_identifier(b));
+        match (a, b) {
+            (Some(a), Some(b)) if descending => b.total_cmp(a),
+            (Some(a), Some(b)) => a.total_cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+    });
+}
 
 pub struct ContentQueryPayload {
     pub query: String,
+    /// 每页的数量
     pub max_count: Option<usize>,
+    /// 跳过前面多少条结果，用于分页
+    /// 同一个 query 的排序是稳定的，所以可以直接用 offset 翻页
+    pub offset: usize,
     pub with_hit_reason: bool,
     pub with_reference_content: bool,
+    pub filter: ContentQueryFilter,
+    pub mode: ContentQueryMode,
+    pub rank: ContentQueryRankOptions,
+    /// 用 LLM 生成同义词、翻译和画面描述，和原始查询一起搜索，需要设置 LLM 模型
+    pub expand_query: bool,
+    /// None 表示按相关度排序
+    pub sort: Option<ContentQuerySort>,
 }
 
 impl Default for ContentQueryPayload {
@@ -27,8 +237,73 @@ impl Default for ContentQueryPayload {
         Self {
             query: String::new(),
             max_count: None,
+            offset: 0,
             with_hit_reason: true,
             with_reference_content: true,
+            filter: ContentQueryFilter::default(),
+            mode: ContentQueryMode::default(),
+            rank: ContentQueryRankOptions::default(),
+            expand_query: false,
+            sort: None,
+        }
+    }
+}
+
+impl ContentQueryPayload {
+    /// 每页的数量，不能超过 MAX_PAGE_SIZE
+    fn page_size(&self) -> usize {
+        self.max_count
+            .unwrap_or(MAX_RETRIEVAL_COUNT)
+            .min(MAX_PAGE_SIZE)
+    }
+
+    /// 排序结果的缓存 key，包括所有影响搜索和排序的参数，不包括按属性排序
+    /// 分页只通过向量搜索的候选数量影响结果，同一段里的翻页共用缓存
+    fn cache_key(&self, vector_candidates: usize) -> String {
+        format!(
+            "{}|{:?}|{:x}|{:?}|{}|{}|{}",
+            self.query.trim(),
+            self.mode,
+            self.filter.cache_key(),
+            self.rank.weights,
+            self.rank.explain,
+            self.expand_query,
+            vector_candidates
+        )
+    }
+}
+
+/// 以图搜图的图片来源
+pub enum ContentImageQuery {
+    /// 本地的图片文件，比如用户上传的图片
+    File(PathBuf),
+    /// 库里已有的素材，如果是视频，会使用最接近 timestamp（毫秒）的一帧
+    Asset {
+        file_identifier: String,
+        timestamp: Option<i64>,
+    },
+}
+
+pub struct ContentImageQueryPayload {
+    pub image: ContentImageQuery,
+    pub max_count: Option<usize>,
+    pub offset: usize,
+    pub filter: ContentQueryFilter,
+}
+
+pub struct RecommendVideoFramePayload {
+    pub file_identifier: String,
+    /// 当前画面的时间戳，毫秒
+    pub timestamp: i64,
+    pub max_count: Option<usize>,
+}
+
+impl RecommendVideoFramePayload {
+    pub fn new(file_identifier: &str, timestamp: i64) -> Self {
+        Self {
+            file_identifier: file_identifier.to_string(),
+            timestamp,
+            max_count: None,
         }
     }
 }
@@ -39,19 +314,16 @@ impl ContentBase {
     ///     2. 将上述结果进行 rank
     ///     3. 对上述 rank 的结果进行向上回溯
     ///     4. 填充 payload 信息
-    #[tracing::instrument(err(Debug), skip_all, fields(query=%payload.query))]
-    pub async fn query(
-        &self,
-        payload: ContentQueryPayload,
-    ) -> anyhow::Result<Vec<ContentQueryResult>> {
-        let search_model = self.query_payload_to_model(&payload).await?;
-        let max_count = payload.max_count.unwrap_or(MAX_RETRIEVAL_COUNT);
-
-        let query_results = self
-            .surrealdb_client
-            .try_read()?
-            .search(search_model, true, max_count)
-            .await?;
+    ///     5. 如果设置了 rerank 模型，对排在前面的结果进行二次排序
+    ///     6. 按照 offset 和 max_count 分页
+    #[tracing::instrument(err(Debug), skip_all, fields(query=%payload.query, offset=%payload.offset))]
+    pub async fn query(&self, payload: ContentQueryPayload) -> anyhow::Result<ContentQueryPage> {
+        let mut query_results = self.text_query_results(&payload).await?;
+        let max_count = payload.page_size();
+        if let Some(sort) = &payload.sort {
+            sort.sort(&mut query_results, |v| &v.file_identifier)
+                .await?;
+        }
 
         // if payload.with_reference_content {
         //     for query_result in query_results.iter_mut() {
@@ -60,9 +332,246 @@ impl ContentBase {
         //     }
         // }
 
+        let total = query_results.len();
+        let results = query_results
+            .into_iter()
+            .skip(payload.offset)
+            .take(max_count)
+            .collect();
+
+        Ok(ContentQueryPage { results, total })
+    }
+
+    /// 按素材分组的文本搜索，流程和 query 一样，最后把同一个素材的片段合并成一条
+    /// offset 和 max_count 按素材计算
+    #[tracing::instrument(err(Debug), skip_all, fields(query=%payload.query, offset=%payload.offset))]
+    pub async fn query_grouped(
+        &self,
+        payload: ContentQueryPayload,
+    ) -> anyhow::Result<ContentQueryGroupedPage> {
+        let query_results = self.text_query_results(&payload).await?;
+        let max_count = payload.page_size();
+
+        let mut grouped_results = group_results_by_asset(query_results);
+        if let Some(sort) = &payload.sort {
+            sort.sort(&mut grouped_results, |v| &v.file_identifier)
+                .await?;
+        }
+        let total = grouped_results.len();
+        let results = grouped_results
+            .into_iter()
+            .skip(payload.offset)
+            .take(max_count)
+            .collect();
+
+        Ok(ContentQueryGroupedPage { results, total })
+    }
+
+    /// 文本搜索的全部结果，已经排好序，还没有分页
+    /// 排序结果按 query 缓存，翻页时不再重新搜索
+    async fn text_query_results(
+        &self,
+        payload: &ContentQueryPayload,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        // query 里只有过滤条件或者排除的词，没有需要搜索的内容
+        if payload.query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let vector_candidates = vector_candidates(payload.offset, payload.page_size());
+        let cache_key = payload.cache_key(vector_candidates);
+        if let Some(query_results) = self.search_result_cache.get(&cache_key) {
+            return Ok(query_results);
+        }
+
+        let search_model = self.query_payload_to_model(payload).await?;
+        let mut query_results = self
+            .surrealdb_client
+            .try_read()?
+            .search_results(
+                search_model,
+                true,
+                &payload.filter,
+                &payload.rank,
+                vector_candidates,
+            )
+            .await?;
+        if let (true, Some(load)) = (
+            payload.rank.weights.recency > 0.0,
+            &payload.rank.asset_created_at,
+        ) {
+            let file_identifiers = query_results
+                .iter()
+                .map(|v| v.file_identifier.clone())
+                .collect::<HashSet<_>>();
+            let asset_created_at = load(file_identifiers.into_iter().collect()).await?;
+            apply_recency(
+                &mut query_results,
+                payload.rank.weights.recency,
+                &asset_created_at,
+            );
+            sort_by_score(&mut query_results);
+        }
+        // 二次排序比较慢，缓存的是二次排序以后的结果，翻页时不会重新推理
+        self.rerank(&payload.query, &mut query_results).await;
+        self.search_result_cache
+            .put(&cache_key, query_results.clone());
+
         Ok(query_results)
     }
 
+    /// 以图搜图
+    /// 图片转换成 multi modal embedding 以后，只在图像向量里搜索，后面的流程和文本搜索一样
+    #[tracing::instrument(err(Debug), skip_all, fields(offset=%payload.offset))]
+    pub async fn query_by_image(
+        &self,
+        payload: ContentImageQueryPayload,
+    ) -> anyhow::Result<ContentQueryPage> {
+        let search_model = self.image_query_payload_to_model(&payload).await?;
+        let max_count = payload
+            .max_count
+            .unwrap_or(MAX_RETRIEVAL_COUNT)
+            .min(MAX_PAGE_SIZE);
+
+        let mut query_results = self
+            .surrealdb_client
+            .try_read()?
+            .search_results(
+                search_model,
+                false,
+                &payload.filter,
+                &ContentQueryRankOptions::default(),
+                vector_candidates(payload.offset, max_count),
+            )
+            .await?;
+        // 用库里的素材搜索时，素材自己一定是最相似的，不作为结果返回
+        if let ContentImageQuery::Asset {
+            file_identifier, ..
+        } = &payload.image
+        {
+            query_results.retain(|result| &result.file_identifier != file_identifier);
+        }
+
+        let total = query_results.len();
+        let results = query_results
+            .into_iter()
+            .skip(payload.offset)
+            .take(max_count)
+            .collect();
+
+        Ok(ContentQueryPage { results, total })
+    }
+
+    /// 在一个素材（视频、音频、文档、网页）里搜索，返回全部命中的片段
+    /// 结果按时间戳或者 chunk 的位置排序，而不是按分数，方便在时间轴上标记
+    #[tracing::instrument(err(Debug), skip_all, fields(file_identifier=%file_identifier, query=%query))]
+    pub async fn query_in_asset(
+        &self,
+        file_identifier: &str,
+        query: &str,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let payload = ContentQueryPayload {
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let SearchModel::Text(text) = self.query_payload_to_model(&payload).await? else {
+            anyhow::bail!("unexpected search model for text query");
+        };
+        self.surrealdb_client
+            .try_read()?
+            .search_in_asset(text, file_identifier)
+            .await
+    }
+
+    /// 搜索补全，按前缀匹配已索引的转录文本和画面描述里的词
+    pub async fn suggest_terms(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<TermSuggestion>> {
+        self.surrealdb_client
+            .try_read()?
+            .suggest_terms(prefix, limit)
+            .await
+    }
+
+    /// 素材的关键词标签，还没有生成标签的时候返回空
+    pub async fn asset_tags(&self, file_identifier: &str) -> anyhow::Result<Vec<String>> {
+        self.surrealdb_client
+            .try_read()?
+            .asset_tags(file_identifier)
+            .await
+    }
+
+    /// 素材库里所有的标签和对应的素材数量
+    pub async fn list_tags(&self) -> anyhow::Result<Vec<TagCount>> {
+        self.surrealdb_client.try_read()?.list_tags().await
+    }
+
+    /// 推荐和视频某一帧画面相似的片段
+    /// 1. 取最接近 timestamp 的 image_frame 的图像向量
+    /// 2. 在视频的图像向量里搜索，合并相邻的帧
+    /// 3. 去掉同一个视频里离当前画面太近的片段
+    #[tracing::instrument(err(Debug), skip_all, fields(file_identifier=%payload.file_identifier, timestamp=%payload.timestamp))]
+    pub async fn recommend_video_frame(
+        &self,
+        payload: RecommendVideoFramePayload,
+    ) -> anyhow::Result<Vec<ContentQueryResult>> {
+        let surrealdb_client = self.surrealdb_client.try_read()?;
+        let vision_embedding = match surrealdb_client
+            .video_frame_embedding(&payload.file_identifier, payload.timestamp)
+            .await?
+        {
+            Some(embedding) => embedding,
+            None => {
+                self.asset_image_embedding(&payload.file_identifier, Some(payload.timestamp))
+                    .await?
+            }
+        };
+        let filter = ContentQueryFilter {
+            content_types: Some(vec![ContentType::Video]),
+            ..Default::default()
+        };
+        let max_count = payload
+            .max_count
+            .unwrap_or(MAX_RETRIEVAL_COUNT)
+            .min(MAX_PAGE_SIZE);
+
+        // 需要先排除掉当前画面附近的片段再截断，所以这里取全部结果
+        let page = surrealdb_client
+            .search(
+                SearchModel::Image(ImageSearchModel { vision_embedding }),
+                false,
+                0,
+                usize::MAX,
+                &filter,
+            )
+            .await?;
+
+        let exclude_start = payload.timestamp - RECOMMEND_EXCLUDE_DURATION;
+        let exclude_end = payload.timestamp + RECOMMEND_EXCLUDE_DURATION;
+        let results = page
+            .results
+            .into_iter()
+            .filter(|result| {
+                if result.file_identifier != payload.file_identifier {
+                    return true;
+                }
+                match result.metadata.segment_range() {
+                    Some((start, end)) => end < exclude_start || start > exclude_end,
+                    None => false,
+                }
+            })
+            .take(max_count)
+            .collect();
+
+        Ok(results)
+    }
+
     async fn _reference_content(
         &self,
         query_result: &ContentQueryResult,
@@ -139,3 +648,48 @@ impl ContentBase {
         Ok(reference_content)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{sort_by_values, ContentQueryFilter};
+    use content_metadata::ContentType;
+
+    #[test]
+    fn test_sort_by_values() {
+        let values = [("a", 10.0), ("b", 30.0), ("c", 10.0)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        // 原来的顺序是按分数排的，值相同的 c 和 a 保持原来的顺序，没有值的 d 排在最后
+        let mut items = vec!["d", "c", "b", "a"];
+        sort_by_values(&mut items, &values, false, |v| *v);
+        assert_eq!(items, vec!["c", "a", "b", "d"]);
+
+        let mut items = vec!["d", "c", "b", "a"];
+        sort_by_values(&mut items, &values, true, |v| *v);
+        assert_eq!(items, vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn test_filter_cache_key() {
+        let filter = |file_identifiers: &[&str]| ContentQueryFilter {
+            content_types: Some(vec![ContentType::Video]),
+            file_identifiers: Some(file_identifiers.iter().map(|v| v.to_string()).collect()),
+            ..Default::default()
+        };
+        // 素材的顺序不影响 key，素材不同时 key 也不同
+        assert_eq!(
+            filter(&["a", "b"]).cache_key(),
+            filter(&["b", "a"]).cache_key()
+        );
+        assert_ne!(filter(&["a", "b"]).cache_key(), filter(&["a"]).cache_key());
+        assert_ne!(
+            filter(&[]).cache_key(),
+            ContentQueryFilter {
+                content_types: Some(vec![ContentType::Video]),
+                ..Default::default()
+            }
+            .cache_key()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::ContentQueryMode;
 use crate::db::model::id::ID;
 
 pub struct TextToken(pub Vec<String>);
@@ -9,14 +10,20 @@ pub struct TextSearchModel {
     pub text_embedding: Vec<f32>,
     /// 用于查询图像向量
     pub vision_embedding: Vec<f32>,
+    pub mode: ContentQueryMode,
+    /// LLM 扩展出来的查询（同义词、另一种语言、画面描述），和原始查询一样搜索，结果一起 rank
+    pub variants: Vec<TextSearchVariant>,
 }
 
-pub struct ImageSearchModel {
-    pub prompt: String,
-    pub prompt_search_model: TextSearchModel,
-    /// 用于查询文本向量
+pub struct TextSearchVariant {
+    pub data: String,
+    pub tokens: TextToken,
     pub text_embedding: Vec<f32>,
-    /// 用于查询图像向量
+    pub vision_embedding: Vec<f32>,
+}
+
+pub struct ImageSearchModel {
+    /// 图片的 multi modal embedding，用于查询图像向量
     pub vision_embedding: Vec<f32>,
 }
 
@@ -0,0 +1,531 @@
+//! 搜索语法
+//!
+//! 在普通文本之外支持以下写法，可以混在一起使用：
+//! - `"product launch"` 短语，文本里必须包含完整的短语
+//! - `-draft` 或者 `-"rough cut"` 排除包含这个词或短语的结果
+//! - `type:video|image` 内容类型，可选 video、audio、image、document、webpage
+//! - `in:/campaigns/2024/` 文件夹
+//! - `before:2024-06-01`、`after:2024-01-01` 素材的创建时间
+//! - `duration:>60s`、`duration:<=5m`、`duration:30s..2m` 音视频时长，单位可以是 s、m、h，默认是秒
+//!   范围都包括边界，`>` 和 `>=` 一样，`<` 和 `<=` 一样
+//! - `orientation:portrait`、`orientation:landscape`、`orientation:square` 画面方向，也可以写 vertical、horizontal
+//! - `res:4k`、`res:1080p`、`res:720` 最低分辨率，按短边计算，竖屏的 4K 视频也算 4K
+//! - `camera:canon` 拍摄照片的相机厂商或型号
+//! - `audio:yes`、`audio:no` 视频是否有音轨
+//! - `object:dog|car` 画面里必须同时有这些物体，可以写多次，多个词的类别用引号或者下划线，比如 `object:traffic_light`
+//! - `tag:outdoor|product_demo` 素材必须同时有这些标签，写法和 object 一样
+//! - `source:transcript|text|ocr|caption|vision` 搜索的来源，text 是文档和网页的文本，ocr 是画面上的文字
+//! - `mode:fulltext` 或者 `mode:vector` 只使用全文搜索或者向量搜索
+//!
+//! 不认识的 qualifier 或者格式不对的值会当作普通文本处理。
+//! 文件夹、时间、时长和画面参数不在 SurrealDB 里，需要调用方转换成 file_identifiers，见 [`ParsedQuery::apply_to_filter`]。
+
+use super::{ContentQueryFilter, ContentQueryMode, ContentQuerySource};
+use chrono::NaiveDate;
+use content_metadata::ContentType;
+use serde::{Deserialize, Serialize};
+
+/// 时长范围，单位是秒
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DurationRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// 画面方向，按宽高比判断
+#[cfg_attr(feature = "rspc", derive(specta::Type))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaOrientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// 普通的词
+    pub terms: Vec<String>,
+    /// 引号里的短语
+    pub phrases: Vec<String>,
+    /// 需要排除的词或短语
+    pub excluded: Vec<String>,
+    pub content_types: Option<Vec<ContentType>>,
+    pub materialized_path: Option<String>,
+    pub before: Option<NaiveDate>,
+    pub after: Option<NaiveDate>,
+    pub duration: Option<DurationRange>,
+    pub orientation: Option<MediaOrientation>,
+    /// 最低分辨率，短边的像素数
+    pub resolution: Option<u32>,
+    pub camera: Option<String>,
+    pub has_audio: Option<bool>,
+    pub sources: Option<Vec<ContentQuerySource>>,
+    /// 物体检测的类别，小写
+    pub objects: Vec<String>,
+    /// 素材的标签，小写
+    pub tags: Vec<String>,
+    pub mode: ContentQueryMode,
+}
+
+impl ParsedQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut chars = input.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+
+            let negated = c == '-';
+            if negated {
+                chars.next();
+                match chars.peek() {
+                    // 单独的 - 当作普通文本
+                    None => {
+                        parsed.terms.push("-".to_string());
+                        break;
+                    }
+                    Some(c) if c.is_whitespace() => {
+                        parsed.terms.push("-".to_string());
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                let phrase = read_until(&mut chars, |c| c == '"');
+                chars.next();
+                let phrase = phrase.trim().to_string();
+                if phrase.is_empty() {
+                    continue;
+                }
+                if negated {
+                    parsed.excluded.push(phrase);
+                } else {
+                    parsed.phrases.push(phrase);
+                }
+                continue;
+            }
+
+            let mut word = read_until(&mut chars, |c| c.is_whitespace() || c == '"');
+            if negated {
+                parsed.excluded.push(word);
+                continue;
+            }
+
+            if let Some((key, value)) = word.split_once(':') {
+                let key = key.to_lowercase();
+                let mut value = value.to_string();
+                // qualifier 的值可以用引号，比如 in:"/my folder/"
+                if value.is_empty() && chars.peek() == Some(&'"') {
+                    chars.next();
+                    value = read_until(&mut chars, |c| c == '"');
+                    chars.next();
+                    word = format!("{}:\"{}\"", key, value);
+                }
+                if parsed.apply_qualifier(&key, &value) {
+                    continue;
+                }
+            }
+            parsed.terms.push(word);
+        }
+
+        parsed
+    }
+
+    /// 返回 false 表示不是合法的 qualifier
+    fn apply_qualifier(&mut self, key: &str, value: &str) -> bool {
+        let value = value.trim();
+        if value.is_empty() {
+            return false;
+        }
+        match key {
+            "type" => match split_values(value, parse_content_type) {
+                Some(content_types) => self.content_types = Some(content_types),
+                None => return false,
+            },
+            "in" => {
+                let mut path = value.to_string();
+                if !path.starts_with('/') {
+                    path.insert(0, '/');
+                }
+                // materialized_path 以 / 结尾，补上 / 避免 /a 匹配到 /ab/
+                if !path.ends_with('/') {
+                    path.push('/');
+                }
+                self.materialized_path = Some(path);
+            }
+            "before" => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                Ok(date) => self.before = Some(date),
+                Err(_) => return false,
+            },
+            "after" => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                Ok(date) => self.after = Some(date),
+                Err(_) => return false,
+            },
+            "duration" => match parse_duration_range(value) {
+                Some(duration) => self.duration = Some(duration),
+                None => return false,
+            },
+            "orientation" => match parse_orientation(&value.to_lowercase()) {
+                Some(orientation) => self.orientation = Some(orientation),
+                None => return false,
+            },
+            "res" | "resolution" => match parse_resolution(&value.to_lowercase()) {
+                Some(resolution) => self.resolution = Some(resolution),
+                None => return false,
+            },
+            "camera" => self.camera = Some(value.to_string()),
+            "audio" => match value.to_lowercase().as_str() {
+                "yes" | "true" => self.has_audio = Some(true),
+                "no" | "false" | "none" => self.has_audio = Some(false),
+                _ => return false,
+            },
+            "object" | "objects" => {
+                for object in value.split(['|', ',']) {
+                    let object = object.trim().replace('_', " ").to_lowercase();
+                    if !object.is_empty() && !self.objects.contains(&object) {
+                        self.objects.push(object);
+                    }
+                }
+            }
+            "tag" | "tags" => {
+                for tag in value.split(['|', ',']) {
+                    let tag = tag.trim().replace('_', " ").to_lowercase();
+                    if !tag.is_empty() && !self.tags.contains(&tag) {
+                        self.tags.push(tag);
+                    }
+                }
+            }
+            "source" => match split_values(value, parse_source) {
+                Some(sources) => self.sources = Some(sources),
+                None => return false,
+            },
+            "mode" => match value.to_lowercase().as_str() {
+                "fulltext" | "text" => self.mode = ContentQueryMode::FullText,
+                "vector" | "semantic" => self.mode = ContentQueryMode::Vector,
+                "hybrid" => self.mode = ContentQueryMode::Hybrid,
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// 用于分词和计算向量的文本，包括普通的词和短语
+    pub fn text(&self) -> String {
+        self.terms
+            .iter()
+            .chain(self.phrases.iter())
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// 把短语、排除的词、物体、标签、内容类型和来源合并到 filter 里
+    /// 内容类型和 filter 里已有的取交集
+    pub fn apply_to_filter(&self, filter: &mut ContentQueryFilter) {
+        if let Some(content_types) = &self.content_types {
+            filter.content_types = Some(match filter.content_types.take() {
+                Some(existing) => existing
+                    .into_iter()
+                    .filter(|t| content_types.contains(t))
+                    .collect(),
+                None => content_types.clone(),
+            });
+        }
+        if let Some(sources) = &self.sources {
+            filter.sources = Some(sources.clone());
+        }
+        filter.phrases.extend(self.phrases.iter().cloned());
+        filter.excluded.extend(self.excluded.iter().cloned());
+        filter.objects.extend(self.objects.iter().cloned());
+        filter.tags.extend(self.tags.iter().cloned());
+    }
+}
+
+fn read_until(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    stop: impl Fn(char) -> bool,
+) -> String {
+    let mut result = String::new();
+    while let Some(&c) = chars.peek() {
+        if stop(c) {
+            break;
+        }
+        result.push(c);
+        chars.next();
+    }
+    result
+}
+
+/// 多个值用 | 或者 , 分隔，有一个不合法就返回 None
+fn split_values<T: PartialEq>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    let mut result = vec![];
+    for v in value.split(['|', ',']).filter(|v| !v.is_empty()) {
+        let v = parse(&v.to_lowercase())?;
+        if !result.contains(&v) {
+            result.push(v);
+        }
+    }
+    if result.is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+fn parse_content_type(value: &str) -> Option<ContentType> {
+    match value {
+        "video" => Some(ContentType::Video),
+        "audio" => Some(ContentType::Audio),
+        "image" => Some(ContentType::Image),
+        "document" | "doc" | "text" => Some(ContentType::RawText),
+        "webpage" | "web" => Some(ContentType::WebPage),
+        _ => None,
+    }
+}
+
+fn parse_source(value: &str) -> Option<ContentQuerySource> {
+    match value {
+        "transcript" => Some(ContentQuerySource::Transcript),
+        "text" => Some(ContentQuerySource::Text),
+        "ocr" => Some(ContentQuerySource::Ocr),
+        "caption" => Some(ContentQuerySource::Caption),
+        "vision" => Some(ContentQuerySource::Vision),
+        _ => None,
+    }
+}
+
+fn parse_orientation(value: &str) -> Option<MediaOrientation> {
+    match value {
+        "landscape" | "horizontal" => Some(MediaOrientation::Landscape),
+        "portrait" | "vertical" => Some(MediaOrientation::Portrait),
+        "square" => Some(MediaOrientation::Square),
+        _ => None,
+    }
+}
+
+/// 4k、1080p、720，返回短边的像素数
+fn parse_resolution(value: &str) -> Option<u32> {
+    match value {
+        "8k" => Some(4320),
+        "4k" | "uhd" => Some(2160),
+        "2k" | "qhd" => Some(1440),
+        "hd" | "fhd" => Some(1080),
+        _ => value.strip_suffix('p').unwrap_or(value).parse().ok(),
+    }
+}
+
+/// 60、60s、1.5m、2h
+fn parse_duration_seconds(value: &str) -> Option<f64> {
+    let value = value.trim().to_lowercase();
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => value.split_at(index),
+        None => (value.as_str(), "s"),
+    };
+    let number = number.parse::<f64>().ok()?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "s" | "sec" => number,
+        "m" | "min" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return None,
+    };
+    Some(seconds)
+}
+
+/// >60s、>=60s、<5m、<=5m、=60s、30s..2m
+fn parse_duration_range(value: &str) -> Option<DurationRange> {
+    if let Some((min, max)) = value.split_once("..") {
+        return Some(DurationRange {
+            min: parse_duration_seconds(min),
+            max: parse_duration_seconds(max),
+        })
+        .filter(|range| range.min.is_some() || range.max.is_some());
+    }
+    // 时长是浮点数，> 和 >= 不做区分，都包括边界，见模块文档
+    let range = if let Some(v) = value.strip_prefix(">=").or(value.strip_prefix('>')) {
+        DurationRange {
+            min: Some(parse_duration_seconds(v)?),
+            max: None,
+        }
+    } else if let Some(v) = value.strip_prefix("<=").or(value.strip_prefix('<')) {
+        DurationRange {
+            min: None,
+            max: Some(parse_duration_seconds(v)?),
+        }
+    } else {
+        let v = parse_duration_seconds(value.strip_prefix('=').unwrap_or(value))?;
+        DurationRange {
+            min: Some(v),
+            max: Some(v),
+        }
+    };
+    Some(range)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DurationRange, MediaOrientation, ParsedQuery};
+    use crate::query::{ContentQueryFilter, ContentQueryMode, ContentQuerySource};
+    use chrono::NaiveDate;
+    use content_metadata::ContentType;
+
+    #[test]
+    fn test_parse_bare_text() {
+        let parsed = ParsedQuery::parse("a man riding a bike");
+        assert_eq!(parsed.text(), "a man riding a bike");
+        assert_eq!(
+            parsed,
+            ParsedQuery {
+                terms: vec!["a", "man", "riding", "a", "bike"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_phrases_and_exclusions() {
+        let parsed = ParsedQuery::parse(r#"keynote "product launch" -draft -"rough cut""#);
+        assert_eq!(parsed.terms, vec!["keynote"]);
+        assert_eq!(parsed.phrases, vec!["product launch"]);
+        assert_eq!(parsed.excluded, vec!["draft", "rough cut"]);
+        assert_eq!(parsed.text(), "keynote product launch");
+    }
+
+    #[test]
+    fn test_parse_qualifiers() {
+        let parsed = ParsedQuery::parse(
+            "type:video|audio in:/campaigns/2024 before:2024-06-01 duration:>60s source:transcript|caption mode:fulltext launch",
+        );
+        assert_eq!(parsed.terms, vec!["launch"]);
+        assert_eq!(
+            parsed.content_types,
+            Some(vec![ContentType::Video, ContentType::Audio])
+        );
+        assert_eq!(
+            parsed.materialized_path.as_deref(),
+            Some("/campaigns/2024/")
+        );
+        assert_eq!(parsed.before, NaiveDate::from_ymd_opt(2024, 6, 1));
+        assert_eq!(
+            parsed.duration,
+            Some(DurationRange {
+                min: Some(60.0),
+                max: None
+            })
+        );
+        assert_eq!(
+            parsed.sources,
+            Some(vec![
+                ContentQuerySource::Transcript,
+                ContentQuerySource::Caption
+            ])
+        );
+        assert_eq!(parsed.mode, ContentQueryMode::FullText);
+    }
+
+    #[test]
+    fn test_parse_invalid_qualifiers() {
+        // 不认识的 qualifier 和格式不对的值当作普通文本
+        let parsed = ParsedQuery::parse("time:10:30 type:movie before:yesterday");
+        assert_eq!(
+            parsed.terms,
+            vec!["time:10:30", "type:movie", "before:yesterday"]
+        );
+        assert_eq!(parsed.content_types, None);
+        assert_eq!(parsed.before, None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let parsed = ParsedQuery::parse("duration:30s..2m");
+        assert_eq!(
+            parsed.duration,
+            Some(DurationRange {
+                min: Some(30.0),
+                max: Some(120.0)
+            })
+        );
+        let parsed = ParsedQuery::parse("duration:<=1.5h");
+        assert_eq!(
+            parsed.duration,
+            Some(DurationRange {
+                min: None,
+                max: Some(5400.0)
+            })
+        );
+        // > 和 >= 一样包括边界
+        assert_eq!(
+            ParsedQuery::parse("duration:>60s").duration,
+            ParsedQuery::parse("duration:>=60s").duration
+        );
+    }
+
+    #[test]
+    fn test_parse_media_qualifiers() {
+        let parsed = ParsedQuery::parse("res:4k orientation:vertical audio:no camera:Canon sunset");
+        assert_eq!(parsed.terms, vec!["sunset"]);
+        assert_eq!(parsed.resolution, Some(2160));
+        assert_eq!(parsed.orientation, Some(MediaOrientation::Portrait));
+        assert_eq!(parsed.has_audio, Some(false));
+        assert_eq!(parsed.camera.as_deref(), Some("Canon"));
+
+        let parsed = ParsedQuery::parse("res:720p orientation:diagonal");
+        assert_eq!(parsed.resolution, Some(720));
+        assert_eq!(parsed.orientation, None);
+        assert_eq!(parsed.terms, vec!["orientation:diagonal"]);
+    }
+
+    #[test]
+    fn test_apply_to_filter() {
+        let parsed = ParsedQuery::parse(r#"type:video|image "launch event" -draft"#);
+        let mut filter = ContentQueryFilter {
+            content_types: Some(vec![ContentType::Video, ContentType::Audio]),
+            ..Default::default()
+        };
+        parsed.apply_to_filter(&mut filter);
+        assert_eq!(filter.content_types, Some(vec![ContentType::Video]));
+        assert_eq!(filter.phrases, vec!["launch event"]);
+        assert_eq!(filter.excluded, vec!["draft"]);
+    }
+
+    #[test]
+    fn test_parse_objects() {
+        let parsed =
+            ParsedQuery::parse(r#"object:Dog|car object:traffic_light object:"fire hydrant""#);
+        assert!(parsed.terms.is_empty());
+        assert_eq!(
+            parsed.objects,
+            vec!["dog", "car", "traffic light", "fire hydrant"]
+        );
+        assert_eq!(parsed.text(), "");
+
+        let parsed = ParsedQuery::parse("object:dog running on the beach");
+        assert_eq!(parsed.text(), "running on the beach");
+        let mut filter = ContentQueryFilter::default();
+        parsed.apply_to_filter(&mut filter);
+        assert_eq!(filter.objects, vec!["dog"]);
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let parsed = ParsedQuery::parse(r#"tag:Outdoor|product_demo tag:"female speaker" launch"#);
+        assert_eq!(
+            parsed.tags,
+            vec!["outdoor", "product demo", "female speaker"]
+        );
+        assert_eq!(parsed.text(), "launch");
+        let mut filter = ContentQueryFilter::default();
+        parsed.apply_to_filter(&mut filter);
+        assert_eq!(filter.tags, parsed.tags);
+    }
+}
@@ -1,14 +1,14 @@
 use super::ContentIndexMetadata;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AudioSliceType {
     Transcript, // 语音转写，目前暂时只有这一个
 }
 
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioIndexMetadata {
     pub slice_type: AudioSliceType,
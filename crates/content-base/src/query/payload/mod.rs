@@ -8,7 +8,7 @@ use self::{
     audio::AudioIndexMetadata, image::ImageIndexMetadata, raw_text::RawTextIndexMetadata,
     video::VideoIndexMetadata, web_page::WebPageIndexMetadata,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // ContentIndexMetadata uses tagged variant serialization
 // When serialized to JSON, variants will include a "content_type" field indicating the variant type
@@ -18,7 +18,7 @@ use serde::Serialize;
 // }
 // 这个对象的数据在 expand_hit_result 函数中被计算出来
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "contentType")]
 pub enum ContentIndexMetadata {
     Video(VideoIndexMetadata),
@@ -28,6 +28,27 @@ pub enum ContentIndexMetadata {
     WebPage(WebPageIndexMetadata),
 }
 
+impl ContentIndexMetadata {
+    /// 片段的起止位置，音视频是时间戳（毫秒），文本是 chunk 的 index，图片没有片段
+    pub fn segment_range(&self) -> Option<(i64, i64)> {
+        match self {
+            ContentIndexMetadata::Video(metadata) => {
+                Some((metadata.start_timestamp, metadata.end_timestamp))
+            }
+            ContentIndexMetadata::Audio(metadata) => {
+                Some((metadata.start_timestamp, metadata.end_timestamp))
+            }
+            ContentIndexMetadata::RawText(metadata) => {
+                Some((metadata.start_index as i64, metadata.end_index as i64))
+            }
+            ContentIndexMetadata::WebPage(metadata) => {
+                Some((metadata.start_index as i64, metadata.end_index as i64))
+            }
+            ContentIndexMetadata::Image(_) => None,
+        }
+    }
+}
+
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
 #[derive(Debug, Clone, Serialize)]
 #[cfg_attr(feature = "rspc", serde(tag = "reason", content = "text"))]
@@ -39,9 +60,42 @@ pub enum ContentQueryHitReason {
     SemanticTranscriptMatch(String), // 命中的语义音频文本
     SemanticCaptionMatch(String),    // 命中的语义画面描述
     VisionMatch,                     // 命中的语义视觉内容
+    OcrMatch(String),                // 命中的图片或者视频画面上的文字
+    ChapterMatch(String),            // 命中的视频章节标题
 }
 
-#[derive(Debug, Serialize)]
+/// 某一路召回里的名次和原始分数，rank 从 0 开始
+#[cfg_attr(feature = "rspc", derive(specta::Type))]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentQuerySignalExplain {
+    pub rank: u32,
+    /// 全文搜索是 BM25 分数，向量搜索是 distance
+    pub value: f32,
+    pub weight: f32,
+}
+
+/// 一个结果的分数明细，用于调试排序
+#[cfg_attr(feature = "rspc", derive(specta::Type))]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentQueryExplain {
+    pub full_text: Option<ContentQuerySignalExplain>,
+    /// 每个分词的 BM25 分数
+    pub full_text_tokens: Vec<(String, f32)>,
+    pub text_vector: Option<ContentQuerySignalExplain>,
+    pub vision_vector: Option<ContentQuerySignalExplain>,
+    /// 加权 rrf 融合以后的分数
+    pub fused_score: f32,
+    /// transcript 或者 caption 的权重
+    pub source_weight: f32,
+    /// 新素材的加分比例，最终分数会乘以 1 + recency_boost
+    pub recency_boost: f32,
+    pub rerank_score: Option<f32>,
+    pub final_score: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ContentQueryResult {
     pub file_identifier: String,
     pub score: f32,
@@ -49,6 +103,59 @@ pub struct ContentQueryResult {
     pub hit_reason: Option<ContentQueryHitReason>, // 命中的索引内容
     pub reference_content: Option<String>,         // 根据 metadata 提取出来的内容片段
     pub search_hint: String,
+    /// 只有设置了 explain 才有
+    pub explain: Option<ContentQueryExplain>,
+}
+
+/// 一页搜索结果
+#[derive(Debug, Serialize)]
+pub struct ContentQueryPage {
+    pub results: Vec<ContentQueryResult>,
+    /// 命中结果的总数
+    /// 全文搜索和向量搜索召回的候选数量是有上限的，所以这只是一个估计值
+    pub total: usize,
+}
+
+/// 素材里命中的一个片段
+#[derive(Debug, Serialize)]
+pub struct ContentQueryHit {
+    pub score: f32,
+    pub metadata: ContentIndexMetadata,
+    pub hit_reason: Option<ContentQueryHitReason>,
+    pub reference_content: Option<String>,
+    pub search_hint: String,
+    pub explain: Option<ContentQueryExplain>,
+}
+
+impl From<ContentQueryResult> for ContentQueryHit {
+    fn from(value: ContentQueryResult) -> Self {
+        Self {
+            score: value.score,
+            metadata: value.metadata,
+            hit_reason: value.hit_reason,
+            reference_content: value.reference_content,
+            search_hint: value.search_hint,
+            explain: value.explain,
+        }
+    }
+}
+
+/// 按素材分组的搜索结果，一个素材只有一条
+#[derive(Debug, Serialize)]
+pub struct ContentQueryGroupedResult {
+    pub file_identifier: String,
+    /// 所有命中片段的综合分数
+    pub score: f32,
+    /// 命中的片段，按照在素材里的位置排序
+    pub hits: Vec<ContentQueryHit>,
+}
+
+/// 一页按素材分组的搜索结果
+#[derive(Debug, Serialize)]
+pub struct ContentQueryGroupedPage {
+    pub results: Vec<ContentQueryGroupedResult>,
+    /// 命中素材的总数，同样是估计值
+    pub total: usize,
 }
 
 // #[derive(Debug, Serialize)]
@@ -1,15 +1,15 @@
 use super::ContentIndexMetadata;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VideoSliceType {
     Visual, // 画面切片
     Audio,  // 语音切片
 }
 
 #[cfg_attr(feature = "rspc", derive(specta::Type))]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoIndexMetadata {
     pub slice_type: VideoSliceType,
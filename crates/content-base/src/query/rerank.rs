@@ -0,0 +1,145 @@
+use super::payload::ContentQueryResult;
+use crate::ContentBase;
+
+/// 只对排在前面的结果做二次排序，cross-encoder 每一条都要推理一次，比较慢
+const RERANK_TOP_N: usize = 50;
+
+impl ContentBase {
+    /// 用 cross-encoder 对前 RERANK_TOP_N 条结果的 reference_content 和 query 重新打分并排序
+    /// - 只调整前 N 条之间的顺序，分数沿用原来前 N 条的分数（从高到低重新分配），这样和后面的结果仍然是有序的
+    /// - 模型出错时保持原来的顺序，二次排序是可选的，不应该影响搜索
+    pub(super) async fn rerank(&self, query: &str, query_results: &mut Vec<ContentQueryResult>) {
+        let Ok((text_rerank, _)) = self.ctx().text_rerank() else {
+            return;
+        };
+        let top_n = query_results.len().min(RERANK_TOP_N);
+        if top_n < 2 {
+            return;
+        }
+
+        let items = query_results[..top_n]
+            .iter()
+            .map(|result| {
+                (
+                    query.to_string(),
+                    result.reference_content.clone().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let scores = match text_rerank.process(items).await {
+            Ok(scores) => scores,
+            Err(e) => {
+                tracing::warn!("failed to rerank: {}", e);
+                return;
+            }
+        };
+        // 没有文本或者单条出错的结果排在最后
+        let scores = scores
+            .into_iter()
+            .zip(query_results[..top_n].iter())
+            .map(|(score, result)| match score {
+                Ok(score) if result.reference_content.is_some() => score,
+                _ => f32::MIN,
+            })
+            .collect::<Vec<_>>();
+
+        rerank_by_scores(query_results, scores);
+    }
+}
+
+/// 按照 scores 重新排列前 scores.len() 条结果
+fn rerank_by_scores(query_results: &mut Vec<ContentQueryResult>, scores: Vec<f32>) {
+    let top_n = scores.len();
+    let original_scores = query_results[..top_n]
+        .iter()
+        .map(|result| result.score)
+        .collect::<Vec<_>>();
+
+    let mut reranked = query_results.drain(..top_n).zip(scores).collect::<Vec<_>>();
+    // 稳定排序，分数相同时保持原来的顺序
+    reranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let reranked = reranked
+        .into_iter()
+        .zip(original_scores)
+        .map(|((mut result, rerank_score), score)| {
+            result.score = score;
+            if let Some(explain) = result.explain.as_mut() {
+                explain.rerank_score = (rerank_score > f32::MIN).then_some(rerank_score);
+                explain.final_score = score;
+            }
+            if rerank_score > f32::MIN {
+                if !result.search_hint.is_empty() {
+                    result.search_hint.push_str("; ");
+                }
+                result
+                    .search_hint
+                    .push_str(&format!("rerank.score:{}", rerank_score));
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    query_results.splice(0..0, reranked);
+}
+
+#[cfg(test)]
+mod test {
+    use super::rerank_by_scores;
+    use crate::query::payload::{
+        image::ImageIndexMetadata, ContentIndexMetadata, ContentQueryResult,
+    };
+
+    #[test]
+    fn test_rerank_by_scores() {
+        let mut results = vec![
+            ContentQueryResult {
+                file_identifier: "a".to_string(),
+                score: 0.9,
+                metadata: ContentIndexMetadata::Image(ImageIndexMetadata { data: 0 }),
+                hit_reason: None,
+                reference_content: Some("a".to_string()),
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "b".to_string(),
+                score: 0.8,
+                metadata: ContentIndexMetadata::Image(ImageIndexMetadata { data: 0 }),
+                hit_reason: None,
+                reference_content: Some("b".to_string()),
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "c".to_string(),
+                score: 0.7,
+                metadata: ContentIndexMetadata::Image(ImageIndexMetadata { data: 0 }),
+                hit_reason: None,
+                reference_content: Some("c".to_string()),
+                search_hint: "".to_string(),
+                explain: None,
+            },
+            ContentQueryResult {
+                file_identifier: "d".to_string(),
+                score: 0.1,
+                metadata: ContentIndexMetadata::Image(ImageIndexMetadata { data: 0 }),
+                hit_reason: None,
+                reference_content: Some("d".to_string()),
+                search_hint: "".to_string(),
+                explain: None,
+            },
+        ];
+        rerank_by_scores(&mut results, vec![0.2, 0.95, f32::MIN]);
+
+        let ids = results
+            .iter()
+            .map(|r| r.file_identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["b", "a", "c", "d"]);
+        let scores = results.iter().map(|r| r.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![0.9, 0.8, 0.7, 0.1]);
+        assert_eq!(results[0].search_hint, "rerank.score:0.95");
+        assert_eq!(results[2].search_hint, "");
+    }
+}
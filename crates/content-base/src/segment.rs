@@ -0,0 +1,111 @@
+//! 中文分词
+//!
+//! SurrealDB 的 mixed_analyzer 只按空格、字符类别和标点切分，一整句中文会被当成一个词，BM25 基本命中不了。
+//! 这里用 jieba 做基于词典的分词：
+//! - 写入 `text.content` 和 `image.caption` 之前，用 [`segment`] 在中文词之间插入 [`SEGMENT_SEPARATOR`]
+//! - 查询的时候用同样的方式切分 query，保证两边的词是一致的
+//! - 从数据库读出来的文本（包括 `search::highlight` 的结果）需要用 [`desegment`] 还原成原文
+//!
+//! 分词之前写入的数据会在打开 library 的时候重新分词，见 `DB::migrate_text_segmentation`。
+
+use jieba_rs::Jieba;
+use std::sync::OnceLock;
+
+/// 分词插入的分隔符，U+2008 (PUNCTUATION SPACE)
+/// 它属于 whitespace，会被 analyzer 的 blank tokenizer 切开；正常文本里几乎不会出现，去掉它就能还原原文
+pub(crate) const SEGMENT_SEPARATOR: char = '\u{2008}';
+
+fn jieba() -> &'static Jieba {
+    static JIEBA: OnceLock<Jieba> = OnceLock::new();
+    JIEBA.get_or_init(Jieba::new)
+}
+
+/// CJK 统一表意文字，包括扩展 A 和兼容表意文字
+pub(crate) fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// 把文本切成词，不包含空白
+pub(crate) fn cut_words(text: &str) -> Vec<&str> {
+    jieba()
+        .cut(text, true)
+        .into_iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// 在中文词的边界插入分隔符，不是中文的部分保持不变
+pub(crate) fn segment(text: &str) -> String {
+    // 原文里如果本来就有分隔符，先换成普通空格，否则 desegment 的时候会被删掉
+    let text = text.replace(SEGMENT_SEPARATOR, " ");
+    if !text.chars().any(is_han) {
+        return text;
+    }
+
+    let mut result = String::with_capacity(text.len() * 2);
+    let mut last_char: Option<char> = None;
+    for word in jieba().cut(&text, true) {
+        let Some(first_char) = word.chars().next() else {
+            continue;
+        };
+        if let Some(last_char) = last_char {
+            if !last_char.is_whitespace()
+                && !first_char.is_whitespace()
+                && (is_han(last_char) || is_han(first_char))
+            {
+                result.push(SEGMENT_SEPARATOR);
+            }
+        }
+        result.push_str(word);
+        last_char = word.chars().last();
+    }
+    result
+}
+
+/// 去掉 segment 插入的分隔符，还原原文，高亮标记不受影响
+pub(crate) fn desegment(text: &str) -> String {
+    text.replace(SEGMENT_SEPARATOR, "")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cut_words, desegment, segment, SEGMENT_SEPARATOR};
+
+    #[test]
+    fn test_segment_roundtrip() {
+        let texts = [
+            "我们今天讨论一下视频剪辑的流程",
+            "GenDAM 是一个 AI 驱动的素材管理工具，支持 video 和 audio。",
+            "hello world",
+            "",
+        ];
+        for text in texts {
+            assert_eq!(desegment(&segment(text)), text);
+        }
+    }
+
+    #[test]
+    fn test_segment_chinese() {
+        let segmented = segment("我们讨论视频剪辑");
+        let words = segmented.split(SEGMENT_SEPARATOR).collect::<Vec<_>>();
+        assert!(words.len() > 1);
+        assert!(words.contains(&"视频"));
+
+        // 英文部分不插入分隔符
+        assert_eq!(segment("hello world"), "hello world");
+    }
+
+    #[test]
+    fn test_desegment_highlight() {
+        let segmented = segment("讨论视频剪辑");
+        let highlighted = segmented.replace("视频", "<b>视频</b>");
+        assert_eq!(desegment(&highlighted), "讨论<b>视频</b>剪辑");
+    }
+
+    #[test]
+    fn test_cut_words() {
+        let words = cut_words("视频 剪辑 editing");
+        assert_eq!(words, vec!["视频", "剪辑", "editing"]);
+    }
+}
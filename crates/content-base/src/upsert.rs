@@ -1,8 +1,10 @@
 use crate::db::{
     model::{
         audio::{AudioFrameModel, AudioModel},
+        chapter::ChapterFrameModel,
         document::DocumentModel,
         image::ImageModel,
+        ocr::OcrFrameModel,
         page::PageModel,
         text::TextModel,
         video::{ImageFrameModel, VideoModel},
@@ -15,29 +17,45 @@ use content_base_context::ContentBaseCtx;
 use content_base_pool::{TaskNotification, TaskStatus};
 use content_base_task::{
     audio::{
+        tags::AudioTagsTask,
         trans_chunk::{AudioTransChunkTask, AudioTranscriptChunkTrait},
         trans_chunk_sum::{AudioTransChunkSumTask, AudioTransChunkSumTrait},
         trans_chunk_sum_embed::{AudioTransChunkSumEmbedTask, AudioTransChunkSumEmbedTrait},
     },
     image::{
-        desc_embed::ImageDescEmbedTask, description::ImageDescriptionTask,
+        desc_embed::ImageDescEmbedTask,
+        description::ImageDescriptionTask,
         embedding::ImageEmbeddingTask,
+        object_detection::{DetectedObject, ImageObjectDetectionTask},
+        ocr::ImageOcrTask,
+        ocr_embed::ImageOcrEmbedTask,
+        tags::ImageTagsTask,
     },
     raw_text::{
         chunk::{DocumentChunkTrait, RawTextChunkTask},
         chunk_sum_embed::{DocumentChunkSumEmbedTrait, RawTextChunkSumEmbedTask},
+        tags::RawTextTagsTask,
     },
+    tags::TagsTrait,
     video::{
+        chapter::VideoChapterTask,
+        chapter_embed::VideoChapterEmbedTask,
         frame::{VideoFrameTask, VIDEO_FRAME_SUMMARY_BATCH_SIZE},
         frame_desc_embed::VideoFrameDescEmbedTask,
         frame_description::VideoFrameDescriptionTask,
         frame_embedding::VideoFrameEmbeddingTask,
+        frame_object_detection::VideoFrameObjectDetectionTask,
+        frame_ocr::VideoFrameOcrTask,
+        frame_ocr_embed::VideoFrameOcrEmbedTask,
+        tags::{VideoTagsTask, VideoVisualTagsTask},
         trans_chunk::VideoTransChunkTask,
         trans_chunk_sum::VideoTransChunkSumTask,
         trans_chunk_sum_embed::VideoTransChunkSumEmbedTask,
     },
-    web_page::{chunk::WebPageChunkTask, chunk_sum_embed::WebPageChunkSumEmbedTask},
-    FileInfo, TaskRecord,
+    web_page::{
+        chunk::WebPageChunkTask, chunk_sum_embed::WebPageChunkSumEmbedTask, tags::WebPageTagsTask,
+    },
+    ContentTaskType, FileInfo, TaskRecord,
 };
 use content_metadata::{
     audio::AudioMetadata, image::ImageMetadata, raw_text::RawTextMetadata, video::VideoMetadata,
@@ -107,7 +125,7 @@ impl ContentBase {
             file_full_path_on_disk: payload.file_full_path_on_disk.clone(),
         };
 
-        let tasks = Self::get_content_processing_tasks(&payload.metadata);
+        let tasks = Self::get_content_processing_tasks(&self.ctx, &payload.metadata);
         let mut unfinished_tasks = std::collections::HashSet::new();
         for (task_type, _) in tasks.iter() {
             // ContentTaskType 实现了 to_string 和 Eq, 可以 clone 了以后用于 HashSet
@@ -144,6 +162,8 @@ impl ContentBase {
         tokio::spawn({
             let ctx = self.ctx.clone();
             let surrealdb_client = self.surrealdb_client.clone();
+            let search_result_cache = self.search_result_cache.clone();
+            let near_duplicate_cache = self.near_duplicate_cache.clone();
             let file_identifier = file_info.file_identifier.to_string();
             // 对 task notification 做进一步处理
             async move {
@@ -168,6 +188,8 @@ impl ContentBase {
                             surrealdb_client.clone(),
                         )
                         .await;
+                        search_result_cache.clear();
+                        near_duplicate_cache.invalidate(&file_identifier);
                     }
                 }
             }
@@ -185,6 +207,7 @@ async fn task_post_process(
     metadata: &ContentMetadata,
     surrealdb_client: Arc<RwLock<DB>>,
 ) -> anyhow::Result<()> {
+    let tags_surrealdb_client = surrealdb_client.clone();
     match metadata {
         ContentMetadata::Video(metadata) => {
             // 但如果 video 没有音频，则直接跳过 TransChunkSumEmbed
@@ -216,6 +239,8 @@ async fn task_post_process(
         }
         _ => {}
     };
+    // 写入索引的时候会先删除素材已有的标签，所以标签要在索引之后保存
+    upsert_tags_to_surrealdb(ctx, file_identifier, metadata, tags_surrealdb_client).await?;
     Ok(())
 }
 
@@ -226,6 +251,92 @@ fn warn_and_skip(msg: &'static str) -> impl FnOnce(anyhow::Error) -> anyhow::Err
     }
 }
 
+#[tracing::instrument(skip_all)]
+async fn upsert_tags_to_surrealdb(
+    ctx: &ContentBaseCtx,
+    file_identifier: &str,
+    metadata: &ContentMetadata,
+    surrealdb_client: Arc<RwLock<DB>>,
+) -> anyhow::Result<()> {
+    let task_type: ContentTaskType = match metadata {
+        ContentMetadata::Video(metadata) => {
+            if metadata.audio.is_some() {
+                VideoTagsTask.into()
+            } else {
+                VideoVisualTagsTask.into()
+            }
+        }
+        ContentMetadata::Audio(_) => AudioTagsTask.into(),
+        ContentMetadata::Image(_) => ImageTagsTask.into(),
+        ContentMetadata::RawText(_) => RawTextTagsTask.into(),
+        ContentMetadata::WebPage(_) => WebPageTagsTask.into(),
+        _ => return Ok(()),
+    };
+    // 标签任务还没有完成过的时候没有输出可读，不需要保存也不需要警告
+    let task_record = TaskRecord::from_content_base(file_identifier, ctx).await;
+    if !task_record
+        .task_list(&task_type)
+        .is_some_and(|runs| runs.iter().any(|run| run.is_completed()))
+    {
+        return Ok(());
+    }
+    let tags = match metadata {
+        ContentMetadata::Video(metadata) => {
+            if metadata.audio.is_some() {
+                VideoTagsTask.tags_content(file_identifier, ctx).await
+            } else {
+                VideoVisualTagsTask.tags_content(file_identifier, ctx).await
+            }
+        }
+        ContentMetadata::Audio(_) => AudioTagsTask.tags_content(file_identifier, ctx).await,
+        ContentMetadata::Image(_) => ImageTagsTask.tags_content(file_identifier, ctx).await,
+        ContentMetadata::RawText(_) => RawTextTagsTask.tags_content(file_identifier, ctx).await,
+        ContentMetadata::WebPage(_) => WebPageTagsTask.tags_content(file_identifier, ctx).await,
+        _ => return Ok(()),
+    };
+    // 没有生成标签的时候不影响其他索引
+    let Ok(tags) = tags.map_err(warn_and_skip("tags")) else {
+        return Ok(());
+    };
+    surrealdb_client
+        .try_write()?
+        .set_tags(file_identifier, tags)
+        .await?;
+    Ok(())
+}
+
+/// 去重以后的物体类别，保持第一次出现的顺序
+fn unique_object_labels<'a>(objects: impl Iterator<Item = &'a DetectedObject>) -> Vec<String> {
+    let mut labels: Vec<String> = vec![];
+    for object in objects {
+        if !labels.contains(&object.label) {
+            labels.push(object.label.clone());
+        }
+    }
+    labels
+}
+
+/// 一段 OCR 识别到的文字，一个 ocr_frame 下面只有一个 text
+fn ocr_frame(
+    start_timestamp: i64,
+    end_timestamp: i64,
+    content: String,
+    embedding: Vec<f32>,
+) -> (OcrFrameModel, Vec<TextModel>) {
+    (
+        OcrFrameModel {
+            id: None,
+            start_timestamp,
+            end_timestamp,
+        },
+        vec![TextModel {
+            id: None,
+            content,
+            embedding,
+        }],
+    )
+}
+
 #[tracing::instrument(skip_all)]
 async fn upsert_audio_index_to_surrealdb(
     ctx: &ContentBaseCtx,
@@ -344,6 +455,16 @@ async fn upsert_video_index_to_surrealdb(
             .await
             .map_err(warn_and_skip("video image frames"))?;
         // tracing::debug!("video frames: {frames:?}");
+        // 物体检测是可选的，没有配置模型或者还没有结果的时候不索引物体
+        let frame_objects = if ctx.object_detection().is_ok() {
+            VideoFrameObjectDetectionTask
+                .frame_objects_content(file_identifier, ctx)
+                .await
+                .unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let frame_objects = &frame_objects;
         let future = frames
             .chunks(VIDEO_FRAME_SUMMARY_BATCH_SIZE)
             .into_iter()
@@ -381,11 +502,21 @@ async fn upsert_video_index_to_surrealdb(
                         start_timestamp,
                         end_timestamp,
                     };
+                    // 片段里所有帧检测到的物体都算这个片段的
+                    let objects = unique_object_labels(
+                        frame_objects
+                            .iter()
+                            .filter(|v| {
+                                v.timestamp >= start_timestamp && v.timestamp <= end_timestamp
+                            })
+                            .flat_map(|v| v.objects.iter()),
+                    );
                     let images = vec![ImageModel {
                         id: None,
                         caption,
                         embedding,
                         caption_embedding,
+                        objects,
                     }];
                     Result::<(ImageFrameModel, Vec<ImageModel>), anyhow::Error>::Ok((
                         image_frame,
@@ -397,11 +528,80 @@ async fn upsert_video_index_to_surrealdb(
         try_join_all(future).await?
     };
 
+    // OCR 是可选的，没有配置模型或者还没有结果的时候不索引画面上的文字
+    // 没有 embedding 的片段（比如识别结果是空的）也跳过，text 表的向量索引要求 embedding 不为空
+    let ocr_segments = if ctx.ocr().is_ok() {
+        VideoFrameOcrTask
+            .ocr_segments_content(file_identifier, ctx)
+            .await
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    let mut ocr_frames = vec![];
+    for segment in ocr_segments {
+        let Ok(embedding) = VideoFrameOcrEmbedTask
+            .frame_ocr_embed_content(
+                file_identifier,
+                ctx,
+                segment.start_timestamp,
+                segment.end_timestamp,
+            )
+            .await
+        else {
+            continue;
+        };
+        ocr_frames.push(ocr_frame(
+            segment.start_timestamp,
+            segment.end_timestamp,
+            segment.text,
+            embedding,
+        ));
+    }
+
+    // 章节只有有音频的视频才有，还没有结果的时候不索引章节标题
+    let chapters = VideoChapterTask
+        .chapters_content(file_identifier, ctx)
+        .await
+        .unwrap_or_default();
+    let mut chapter_frames = vec![];
+    for chapter in chapters {
+        let Ok(embedding) = VideoChapterEmbedTask
+            .chapter_embed_content(
+                file_identifier,
+                ctx,
+                chapter.start_timestamp,
+                chapter.end_timestamp,
+            )
+            .await
+        else {
+            continue;
+        };
+        chapter_frames.push((
+            ChapterFrameModel {
+                id: None,
+                start_timestamp: chapter.start_timestamp,
+                end_timestamp: chapter.end_timestamp,
+            },
+            vec![TextModel {
+                id: None,
+                content: chapter.title,
+                embedding,
+            }],
+        ));
+    }
+
     surrealdb_client
         .try_write()?
         .insert_video(
             file_identifier.to_string(),
-            (VideoModel { id: None }, image_frames, audio_frames),
+            (
+                VideoModel { id: None },
+                image_frames,
+                audio_frames,
+                ocr_frames,
+                chapter_frames,
+            ),
         )
         .await?;
     Ok(())
@@ -427,16 +627,49 @@ async fn upsert_image_index_to_surrealdb(
         .embedding_content(file_identifier, ctx)
         .await
         .map_err(warn_and_skip("image embedding"))?;
+    let objects = if ctx.object_detection().is_ok() {
+        ImageObjectDetectionTask
+            .objects_content(file_identifier, ctx)
+            .await
+            .map(|objects| unique_object_labels(objects.iter()))
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    // 图片上的文字作为一个时间戳是 0 的 ocr_frame
+    let ocr_text = if ctx.ocr().is_ok() {
+        ImageOcrTask
+            .ocr_text_content(file_identifier, ctx)
+            .await
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let ocr_frames = if ocr_text.is_empty() {
+        vec![]
+    } else {
+        match ImageOcrEmbedTask
+            .ocr_embed_content(file_identifier, ctx)
+            .await
+        {
+            Ok(ocr_embedding) => vec![ocr_frame(0, 0, ocr_text, ocr_embedding)],
+            _ => vec![],
+        }
+    };
     surrealdb_client
         .try_write()?
         .insert_image(
             file_identifier.to_string(),
-            ImageModel {
-                id: None,
-                caption,
-                embedding,
-                caption_embedding,
-            },
+            (
+                ImageModel {
+                    id: None,
+                    caption,
+                    embedding,
+                    caption_embedding,
+                    objects,
+                },
+                ocr_frames,
+            ),
         )
         .await?;
     Ok(())
@@ -1,5 +1,8 @@
 use std::path::Path;
-use content_metadata::{image::ImageMetadata, ContentMetadata};
+use content_metadata::{
+    image::{ImageExif, ImageMetadata},
+    ContentMetadata,
+};
 use image::ImageReader;
 
 pub(crate) fn get_image_metadata(file_path: impl AsRef<Path>) -> anyhow::Result<ContentMetadata> {
@@ -25,5 +28,33 @@ pub(crate) fn get_image_metadata(file_path: impl AsRef<Path>) -> anyhow::Result<
             _ => "Unknown",
         }
         .to_string(),
+        exif: get_image_exif(file_path),
     }))
 }
+
+/// 读取照片的 EXIF，只取搜索需要的字段，读取失败或者没有 EXIF 时返回 None
+pub fn get_image_exif(file_path: impl AsRef<Path>) -> Option<ImageExif> {
+    let file = std::fs::File::open(file_path.as_ref()).ok()?;
+    let mut reader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    let ascii_field = |tag: exif::Tag| {
+        let field = exif.get_field(tag, exif::In::PRIMARY)?;
+        match &field.value {
+            exif::Value::Ascii(values) => values
+                .first()
+                .map(|v| String::from_utf8_lossy(v).trim().to_string())
+                .filter(|v| !v.is_empty()),
+            _ => None,
+        }
+    };
+    let image_exif = ImageExif {
+        make: ascii_field(exif::Tag::Make),
+        model: ascii_field(exif::Tag::Model),
+        taken_at: ascii_field(exif::Tag::DateTimeOriginal).or(ascii_field(exif::Tag::DateTime)),
+    };
+    if image_exif.make.is_none() && image_exif.model.is_none() && image_exif.taken_at.is_none() {
+        None
+    } else {
+        Some(image_exif)
+    }
+}
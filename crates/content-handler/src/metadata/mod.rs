@@ -7,6 +7,7 @@ mod video;
 use audio::get_audio_metadata;
 use constants::{get_kind_from_extension, get_kind_from_mime, get_mime_from_extension};
 use content_metadata::{ContentMetadata, ContentType};
+pub use image::get_image_exif;
 use image::get_image_metadata;
 use raw_text::get_raw_text_metadata;
 use std::path::Path;
@@ -7,4 +7,20 @@ pub struct ImageMetadata {
     pub width: u32,
     pub height: u32,
     pub color: String,
+    /// 照片的 EXIF 信息，没有 EXIF 的图片为 None
+    /// 加上这个字段之前处理的图片没有这个字段，需要重新读取
+    #[serde(default)]
+    pub exif: Option<ImageExif>,
+}
+
+#[cfg_attr(feature = "rspc", derive(specta::Type))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageExif {
+    /// 相机厂商，比如 Canon
+    pub make: Option<String>,
+    /// 相机型号，比如 Canon EOS R5
+    pub model: Option<String>,
+    /// 拍摄时间，EXIF 里的原始格式，比如 2024:06:01 10:00:00，没有时区
+    pub taken_at: Option<String>,
 }
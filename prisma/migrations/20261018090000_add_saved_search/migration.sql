@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "SavedSearch" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "query" TEXT NOT NULL DEFAULT '',
+    "filters" TEXT,
+    "imageAssetObjectHash" TEXT,
+    "pinnedMaterializedPath" TEXT,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL
+);
+
+-- CreateIndex
+CREATE INDEX "SavedSearch_pinnedMaterializedPath_idx" ON "SavedSearch"("pinnedMaterializedPath");
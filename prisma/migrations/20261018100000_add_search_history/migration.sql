@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "SearchHistory" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "query" TEXT NOT NULL,
+    "count" INTEGER NOT NULL DEFAULT 1,
+    "lastSearchedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "SearchHistory_query_key" ON "SearchHistory"("query");
+
+-- CreateIndex
+CREATE INDEX "SearchHistory_lastSearchedAt_idx" ON "SearchHistory"("lastSearchedAt");
@@ -0,0 +1,16 @@
+-- AlterTable
+ALTER TABLE "AssetObject" ADD COLUMN "aspectRatio" REAL;
+ALTER TABLE "AssetObject" ADD COLUMN "bitRate" INTEGER;
+ALTER TABLE "AssetObject" ADD COLUMN "cameraMake" TEXT;
+ALTER TABLE "AssetObject" ADD COLUMN "cameraModel" TEXT;
+ALTER TABLE "AssetObject" ADD COLUMN "duration" REAL;
+ALTER TABLE "AssetObject" ADD COLUMN "frameRate" REAL;
+ALTER TABLE "AssetObject" ADD COLUMN "hasAudio" BOOLEAN;
+ALTER TABLE "AssetObject" ADD COLUMN "height" INTEGER;
+ALTER TABLE "AssetObject" ADD COLUMN "width" INTEGER;
+
+-- CreateIndex
+CREATE INDEX "AssetObject_duration_idx" ON "AssetObject"("duration");
+
+-- CreateIndex
+CREATE INDEX "AssetObject_width_height_idx" ON "AssetObject"("width", "height");